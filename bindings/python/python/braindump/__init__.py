@@ -0,0 +1,5 @@
+"""An ergonomic, opinionated memory interface for AI agents."""
+
+from ._braindump import Embedder, InMemoryDB, MemoryEntry, MemoryManager
+
+__all__ = ["Embedder", "InMemoryDB", "MemoryEntry", "MemoryManager"]
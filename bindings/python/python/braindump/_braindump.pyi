@@ -0,0 +1,77 @@
+from typing import Callable, Optional
+
+class Embedder:
+    @staticmethod
+    def openai(model: str) -> "Embedder": ...
+    @staticmethod
+    def cohere(model: str) -> "Embedder": ...
+    @staticmethod
+    def gemini(model: str) -> "Embedder": ...
+    @staticmethod
+    def jina(model: str) -> "Embedder": ...
+    @staticmethod
+    def fastembed() -> "Embedder":
+        """Only available when built with the `fastembed` feature."""
+    @staticmethod
+    def from_callable(
+        callable: Callable[[str], list[float]],
+        dims: Optional[int] = None,
+        model_id: Optional[str] = None,
+    ) -> "Embedder": ...
+
+class InMemoryDB:
+    def __init__(self, dims: int) -> None: ...
+    @staticmethod
+    def load(path: str) -> "InMemoryDB": ...
+    def save(self, path: str) -> None: ...
+    def dims(self) -> int: ...
+
+class MemoryEntry:
+    def __init__(
+        self,
+        id: str,
+        content: str,
+        kind: str = "semantic",
+        importance: float = 0.5,
+        source_context: str = "",
+        confidence: str = "high",
+        metadata: list[tuple[str, str]] = ...,
+        agent_id: Optional[str] = None,
+        shared: bool = False,
+        created_at: Optional[int] = None,
+    ) -> None: ...
+    @property
+    def id(self) -> str: ...
+    @property
+    def content(self) -> str: ...
+    @property
+    def kind(self) -> str: ...
+    @property
+    def importance(self) -> float: ...
+    @property
+    def created_at(self) -> int: ...
+    @property
+    def last_accessed(self) -> int: ...
+    @property
+    def access_count(self) -> int: ...
+    @property
+    def source_context(self) -> str: ...
+    @property
+    def confidence(self) -> str: ...
+    @property
+    def metadata(self) -> list[tuple[str, str]]: ...
+    @property
+    def agent_id(self) -> Optional[str]: ...
+    @property
+    def shared(self) -> bool: ...
+    def to_json(self) -> str: ...
+    @staticmethod
+    def from_json(json: str) -> "MemoryEntry": ...
+
+class MemoryManager:
+    def __init__(self, embedder: Embedder, storage: InMemoryDB) -> None: ...
+    def store(self, entry: MemoryEntry) -> None: ...
+    def retrieve(self, query: str, limit: int = 5) -> list[MemoryEntry]: ...
+    def delete(self, id: str) -> None: ...
+    def count(self) -> int: ...
+    def save(self, path: str) -> None: ...
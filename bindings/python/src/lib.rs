@@ -0,0 +1,475 @@
+//! Python bindings for braindump, using `pyo3`.
+//!
+//! The bindings expose a [`MemoryManager`] over an [`InMemoryDB`], along with the embedding API adapters (and any Python callable as an embedder).
+//! Stores are saved and loaded using the same snapshot format as [`InMemoryDB::save_snapshot`], so Rust and Python agents can share the same memories on disk.
+//!
+//! Every method is blocking from Python's point of view: async work runs on a shared Tokio runtime, with the GIL released while it runs.
+
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+
+use braindump::{
+    embed::Embedder,
+    memory::{
+        Confidence, MemoryEntry, MemoryKind, MetadataEntry, Visibility, manager::MemoryManager,
+    },
+    vector_store::InMemoryDB,
+};
+use pyo3::{
+    exceptions::{PyRuntimeError, PyValueError},
+    prelude::*,
+};
+
+type Manager = MemoryManager<AnyEmbedder, InMemoryDB>;
+
+/// The runtime used to run async work (including HTTP requests made by the embedding API adapters).
+fn runtime() -> &'static tokio::runtime::Runtime {
+    static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("failed to start the Tokio runtime")
+    })
+}
+
+fn to_py_err(err: braindump::error::Error) -> PyErr {
+    PyRuntimeError::new_err(err.to_string())
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> PyResult<MutexGuard<'_, T>> {
+    mutex
+        .lock()
+        .map_err(|_| PyRuntimeError::new_err("Mutex was poisoned"))
+}
+
+/// Any of the embedders that can be created from Python.
+#[derive(Clone)]
+enum AnyEmbedder {
+    OpenAI(Arc<braindump::openai::OpenAIEmbedder>),
+    Cohere(Arc<braindump::cohere::CohereEmbedder>),
+    Gemini(Arc<braindump::gemini::GeminiEmbedder>),
+    Jina(Arc<braindump::jina::JinaEmbedder>),
+    #[cfg(feature = "fastembed")]
+    Fastembed(Arc<braindump::fastembed::FastembedTextEmbedder>),
+    /// A Python callable taking a string and returning a list of floats.
+    Callable {
+        callable: Arc<Py<PyAny>>,
+        dims: Option<usize>,
+        model_id: Option<String>,
+    },
+}
+
+impl Embedder for AnyEmbedder {
+    async fn embed_text(&self, input: &str) -> Result<Vec<f32>, braindump::error::Error> {
+        match self {
+            Self::OpenAI(embedder) => embedder.embed_text(input).await,
+            Self::Cohere(embedder) => embedder.embed_text(input).await,
+            Self::Gemini(embedder) => embedder.embed_text(input).await,
+            Self::Jina(embedder) => embedder.embed_text(input).await,
+            #[cfg(feature = "fastembed")]
+            Self::Fastembed(embedder) => embedder.embed_text(input).await,
+            Self::Callable { callable, .. } => Python::attach(|py| {
+                callable
+                    .call1(py, (input,))
+                    .and_then(|embedding| embedding.extract::<Vec<f32>>(py))
+                    .map_err(|err| braindump::error::Error::Custom(err.to_string()))
+            }),
+        }
+    }
+
+    async fn embed_query(&self, input: &str) -> Result<Vec<f32>, braindump::error::Error> {
+        match self {
+            Self::Cohere(embedder) => embedder.embed_query(input).await,
+            Self::Gemini(embedder) => embedder.embed_query(input).await,
+            Self::Jina(embedder) => embedder.embed_query(input).await,
+            _ => self.embed_text(input).await,
+        }
+    }
+
+    async fn embed_document(&self, input: &str) -> Result<Vec<f32>, braindump::error::Error> {
+        match self {
+            Self::Cohere(embedder) => embedder.embed_document(input).await,
+            Self::Gemini(embedder) => embedder.embed_document(input).await,
+            Self::Jina(embedder) => embedder.embed_document(input).await,
+            _ => self.embed_text(input).await,
+        }
+    }
+
+    fn dims(&self) -> Option<usize> {
+        match self {
+            Self::OpenAI(embedder) => embedder.dims(),
+            Self::Cohere(embedder) => embedder.dims(),
+            Self::Gemini(embedder) => embedder.dims(),
+            Self::Jina(embedder) => embedder.dims(),
+            #[cfg(feature = "fastembed")]
+            Self::Fastembed(embedder) => Embedder::dims(embedder.as_ref()),
+            Self::Callable { dims, .. } => *dims,
+        }
+    }
+
+    fn model_id(&self) -> Option<String> {
+        match self {
+            Self::OpenAI(embedder) => embedder.model_id(),
+            Self::Cohere(embedder) => embedder.model_id(),
+            Self::Gemini(embedder) => embedder.model_id(),
+            Self::Jina(embedder) => embedder.model_id(),
+            #[cfg(feature = "fastembed")]
+            Self::Fastembed(embedder) => embedder.model_id(),
+            Self::Callable { model_id, .. } => model_id.clone(),
+        }
+    }
+}
+
+/// An embedder, created using one of the static methods (eg, `Embedder.openai("text-embedding-3-small")`).
+/// API keys are read from the environment, the same as the `from_env` constructors in Rust.
+#[pyclass(name = "Embedder", frozen)]
+struct PyEmbedder(AnyEmbedder);
+
+#[pymethods]
+impl PyEmbedder {
+    #[staticmethod]
+    fn openai(model: String) -> PyResult<Self> {
+        let embedder = braindump::openai::OpenAIEmbedder::from_env(model).map_err(to_py_err)?;
+        Ok(Self(AnyEmbedder::OpenAI(Arc::new(embedder))))
+    }
+
+    #[staticmethod]
+    fn cohere(model: String) -> PyResult<Self> {
+        let embedder = braindump::cohere::CohereEmbedder::from_env(model).map_err(to_py_err)?;
+        Ok(Self(AnyEmbedder::Cohere(Arc::new(embedder))))
+    }
+
+    #[staticmethod]
+    fn gemini(model: String) -> PyResult<Self> {
+        let embedder = braindump::gemini::GeminiEmbedder::from_env(model).map_err(to_py_err)?;
+        Ok(Self(AnyEmbedder::Gemini(Arc::new(embedder))))
+    }
+
+    #[staticmethod]
+    fn jina(model: String) -> PyResult<Self> {
+        let embedder = braindump::jina::JinaEmbedder::from_env(model).map_err(to_py_err)?;
+        Ok(Self(AnyEmbedder::Jina(Arc::new(embedder))))
+    }
+
+    /// Uses the default `fastembed` model (downloading it if needed).
+    #[cfg(feature = "fastembed")]
+    #[staticmethod]
+    fn fastembed() -> PyResult<Self> {
+        let embedder = braindump::fastembed::FastembedTextEmbedder::try_new(Default::default())
+            .map_err(to_py_err)?;
+        Ok(Self(AnyEmbedder::Fastembed(Arc::new(embedder))))
+    }
+
+    /// Uses a Python callable (taking a string and returning a list of floats) as an embedder.
+    /// `model_id` identifies the embedding model behind the callable, so memories embedded by a different model are caught on retrieval.
+    #[staticmethod]
+    #[pyo3(signature = (callable, dims = None, model_id = None))]
+    fn from_callable(callable: Py<PyAny>, dims: Option<usize>, model_id: Option<String>) -> Self {
+        Self(AnyEmbedder::Callable {
+            callable: Arc::new(callable),
+            dims,
+            model_id,
+        })
+    }
+}
+
+/// An in-memory vector store. The store is moved into a `MemoryManager` when one is created from it.
+#[pyclass(name = "InMemoryDB", frozen)]
+struct PyInMemoryDB(Mutex<Option<InMemoryDB>>);
+
+#[pymethods]
+impl PyInMemoryDB {
+    #[new]
+    fn new(dims: usize) -> Self {
+        Self(Mutex::new(Some(InMemoryDB::new(dims))))
+    }
+
+    /// Loads a store from a snapshot file.
+    #[staticmethod]
+    fn load(path: String) -> PyResult<Self> {
+        let db = InMemoryDB::load_snapshot(path).map_err(to_py_err)?;
+        Ok(Self(Mutex::new(Some(db))))
+    }
+
+    /// Saves a snapshot of the store to a file.
+    fn save(&self, path: String) -> PyResult<()> {
+        lock(&self.0)?
+            .as_ref()
+            .ok_or_else(moved_db_error)?
+            .save_snapshot(path)
+            .map_err(to_py_err)
+    }
+
+    fn dims(&self) -> PyResult<usize> {
+        Ok(lock(&self.0)?.as_ref().ok_or_else(moved_db_error)?.dims())
+    }
+}
+
+fn moved_db_error() -> PyErr {
+    PyValueError::new_err("This InMemoryDB has been moved into a MemoryManager")
+}
+
+/// A memory entry.
+#[pyclass(name = "MemoryEntry")]
+#[derive(Clone)]
+struct PyMemoryEntry(MemoryEntry);
+
+#[pymethods]
+impl PyMemoryEntry {
+    #[new]
+    #[pyo3(signature = (
+        id,
+        content,
+        kind = "semantic",
+        importance = 0.5,
+        source_context = String::new(),
+        confidence = "high",
+        metadata = Vec::new(),
+        agent_id = None,
+        shared = false,
+        created_at = None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        id: String,
+        content: String,
+        kind: &str,
+        importance: f32,
+        source_context: String,
+        confidence: &str,
+        metadata: Vec<(String, String)>,
+        agent_id: Option<String>,
+        shared: bool,
+        created_at: Option<i64>,
+    ) -> PyResult<Self> {
+        let created_at = created_at.unwrap_or_else(|| chrono::Utc::now().timestamp());
+
+        Ok(Self(MemoryEntry {
+            id: id.into(),
+            content,
+            kind: parse_kind(kind)?,
+            importance,
+            created_at,
+            last_accessed: created_at,
+            access_count: 0,
+            source_context,
+            confidence: parse_confidence(confidence)?,
+            metadata: metadata
+                .into_iter()
+                .map(|(key, value)| MetadataEntry::new(key, value))
+                .collect(),
+            agent_id,
+            visibility: if shared {
+                Visibility::Shared
+            } else {
+                Visibility::Private
+            },
+            embedding_model: None,
+            image: None,
+            tags: Vec::new(),
+            strength: braindump::memory::strength::INITIAL_STRENGTH,
+        }))
+    }
+
+    #[getter]
+    fn id(&self) -> &str {
+        &self.0.id
+    }
+
+    #[getter]
+    fn content(&self) -> &str {
+        &self.0.content
+    }
+
+    #[getter]
+    fn kind(&self) -> &'static str {
+        match self.0.kind {
+            MemoryKind::Working => "working",
+            MemoryKind::Episodic => "episodic",
+            MemoryKind::Semantic => "semantic",
+            MemoryKind::Procedural => "procedural",
+        }
+    }
+
+    #[getter]
+    fn importance(&self) -> f32 {
+        self.0.importance
+    }
+
+    #[getter]
+    fn created_at(&self) -> i64 {
+        self.0.created_at
+    }
+
+    #[getter]
+    fn last_accessed(&self) -> i64 {
+        self.0.last_accessed
+    }
+
+    #[getter]
+    fn access_count(&self) -> u32 {
+        self.0.access_count
+    }
+
+    #[getter]
+    fn source_context(&self) -> &str {
+        &self.0.source_context
+    }
+
+    #[getter]
+    fn confidence(&self) -> &'static str {
+        match self.0.confidence {
+            Confidence::Low => "low",
+            Confidence::Medium => "medium",
+            Confidence::High => "high",
+        }
+    }
+
+    #[getter]
+    fn metadata(&self) -> Vec<(String, String)> {
+        self.0
+            .metadata
+            .iter()
+            .map(|entry| (entry.key().to_string(), entry.value().to_string()))
+            .collect()
+    }
+
+    #[getter]
+    fn agent_id(&self) -> Option<&str> {
+        self.0.agent_id.as_deref()
+    }
+
+    #[getter]
+    fn shared(&self) -> bool {
+        self.0.visibility == Visibility::Shared
+    }
+
+    /// Serializes the memory to JSON, in the same format used by snapshots.
+    fn to_json(&self) -> PyResult<String> {
+        serde_json::to_string(&self.0).map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<Self> {
+        serde_json::from_str(json)
+            .map(Self)
+            .map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "MemoryEntry(id={:?}, content={:?})",
+            self.0.id, self.0.content
+        )
+    }
+}
+
+fn parse_kind(kind: &str) -> PyResult<MemoryKind> {
+    match kind.to_lowercase().as_str() {
+        "working" => Ok(MemoryKind::Working),
+        "episodic" => Ok(MemoryKind::Episodic),
+        "semantic" => Ok(MemoryKind::Semantic),
+        "procedural" => Ok(MemoryKind::Procedural),
+        _ => Err(PyValueError::new_err(format!(
+            "Unknown memory kind `{kind}` (expected working, episodic, semantic or procedural)"
+        ))),
+    }
+}
+
+fn parse_confidence(confidence: &str) -> PyResult<Confidence> {
+    match confidence.to_lowercase().as_str() {
+        "low" => Ok(Confidence::Low),
+        "medium" => Ok(Confidence::Medium),
+        "high" => Ok(Confidence::High),
+        _ => Err(PyValueError::new_err(format!(
+            "Unknown confidence `{confidence}` (expected low, medium or high)"
+        ))),
+    }
+}
+
+/// A memory manager, storing memories in an `InMemoryDB`.
+#[pyclass(name = "MemoryManager", frozen)]
+struct PyMemoryManager(Mutex<Manager>);
+
+impl PyMemoryManager {
+    /// Runs an async operation against the manager on the shared runtime, with the GIL released.
+    fn run<T, F>(&self, py: Python<'_>, op: F) -> PyResult<T>
+    where
+        T: Send,
+        F: AsyncFnOnce(&mut Manager) -> Result<T, braindump::error::Error> + Send,
+    {
+        py.detach(|| {
+            let mut manager = lock(&self.0)?;
+            runtime().block_on(op(&mut manager)).map_err(to_py_err)
+        })
+    }
+}
+
+#[pymethods]
+impl PyMemoryManager {
+    /// Creates a memory manager. The store is moved into the manager, so it can't be used directly afterwards.
+    #[new]
+    fn new(embedder: &PyEmbedder, storage: &PyInMemoryDB) -> PyResult<Self> {
+        let db = lock(&storage.0)?.take().ok_or_else(moved_db_error)?;
+        let manager = MemoryManager::builder()
+            .embedder(embedder.0.clone())
+            .storage(db)
+            .build()
+            .map_err(to_py_err)?;
+
+        Ok(Self(Mutex::new(manager)))
+    }
+
+    /// Stores a memory, embedding its content.
+    fn store(&self, py: Python<'_>, entry: PyMemoryEntry) -> PyResult<()> {
+        let entry = entry.0;
+        self.run(py, async |manager| {
+            manager.store(entry.content.clone(), entry).await
+        })
+    }
+
+    /// Retrieves the memories most relevant to a query.
+    #[pyo3(signature = (query, limit = 5))]
+    fn retrieve(
+        &self,
+        py: Python<'_>,
+        query: String,
+        limit: usize,
+    ) -> PyResult<Vec<PyMemoryEntry>> {
+        self.run(py, async |manager| {
+            let results = manager.retrieve(query, limit).await?;
+            Ok(results
+                .iter()
+                .map(|result| PyMemoryEntry(result.data_owned()))
+                .collect())
+        })
+    }
+
+    /// Deletes a memory.
+    fn delete(&self, py: Python<'_>, id: String) -> PyResult<()> {
+        self.run(py, async |manager| manager.delete(&id).await)
+    }
+
+    /// The number of stored memories.
+    fn count(&self, py: Python<'_>) -> PyResult<usize> {
+        self.run(py, async |manager| manager.count().await)
+    }
+
+    /// Saves a snapshot of the stored memories to a file, which can be loaded using `InMemoryDB.load` (or `InMemoryDB::load_snapshot` in Rust).
+    fn save(&self, py: Python<'_>, path: String) -> PyResult<()> {
+        self.run(py, async |manager| {
+            manager.flush().await?;
+            manager.storage().save_snapshot(path)
+        })
+    }
+}
+
+#[pymodule]
+fn _braindump(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyEmbedder>()?;
+    m.add_class::<PyInMemoryDB>()?;
+    m.add_class::<PyMemoryEntry>()?;
+    m.add_class::<PyMemoryManager>()?;
+
+    Ok(())
+}
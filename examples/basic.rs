@@ -1,6 +1,6 @@
 use braindump::{
     fastembed::FastembedTextEmbedder,
-    memory::{Confidence, MemoryEntry, MemoryKind, manager::MemoryManager},
+    memory::{Confidence, MemoryEntry, MemoryKind, Visibility, manager::MemoryManager},
     vector_store::InMemoryDB,
 };
 
@@ -8,7 +8,7 @@ use braindump::{
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Default model here should be `bge-small-en-v-1.5`
     // default size is 384 dims
-    let model = FastembedTextEmbedder::default();
+    let model = FastembedTextEmbedder::try_default()?;
     let storage = InMemoryDB::new(384);
 
     let mut memory = MemoryManager::builder()
@@ -19,7 +19,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let memory_contents = "User likes rabbits".to_string();
 
     let memory_entry = MemoryEntry {
-        id: "001".to_string(),
+        id: "001".into(),
         content: memory_contents.clone(),
         kind: MemoryKind::Episodic,
         importance: 0.8,
@@ -29,6 +29,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         confidence: Confidence::High,
         metadata: Vec::new(),
         source_context: "Generated for the purposes of testing".to_string(),
+        agent_id: None,
+        visibility: Visibility::Private,
+        embedding_model: None,
+        image: None,
+        tags: Vec::new(),
+        strength: braindump::memory::strength::INITIAL_STRENGTH,
     };
 
     memory.store(memory_contents, memory_entry).await?;
@@ -1,12 +1,9 @@
 use braindump::{
     embed::RigEmbedder,
-    memory::{generation::MemoryGenerator, manager::MemoryManager},
+    memory::{conversation::ConversationTurn, generation::MemoryGenerator, manager::MemoryManager},
     vector_store::InMemoryDB,
 };
-use rig::{
-    client::{EmbeddingsClient, ProviderClient},
-    parallel_internal,
-};
+use rig::client::{EmbeddingsClient, ProviderClient};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -24,12 +21,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("Memory manager initialised");
 
-    // Here we're using a Vec<str> for brevity
-    // however in a *real* application, you may use your message history
+    // Here we're building the conversation by hand for brevity
+    // however in a *real* application, you may use your message history (`rig` messages can be converted directly)
     let chat_history = vec![
-        "User: Can you help me write a Rust program?",
-        "Assistant: Of course! What would you like to write today?",
-        "User: Please help me write a simple web server using Axum.",
+        ConversationTurn::user("Can you help me write a Rust program?"),
+        ConversationTurn::assistant("Of course! What would you like to write today?"),
+        ConversationTurn::user("Please help me write a simple web server using Axum."),
     ];
 
     let ext =
@@ -37,7 +34,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut memory_gen = MemoryGenerator::new(ext);
 
     println!("Generating memories...");
-    let memories = memory_gen.generate_memory(chat_history).await;
+    let memories = memory_gen.generate_memory(chat_history).await?;
     println!("Memories generated: {memories:?}");
 
     for memory in memories {
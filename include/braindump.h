@@ -0,0 +1,72 @@
+/*
+ * C interface for braindump (the `capi` feature). See `src/capi.rs` for details.
+ *
+ * Strings are null-terminated UTF-8. Strings returned by the library must be freed with
+ * `braindump_string_free`. Memory entries are passed around as JSON.
+ */
+
+#ifndef BRAINDUMP_H
+#define BRAINDUMP_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum BraindumpStatus {
+    BRAINDUMP_OK = 0,
+    BRAINDUMP_NULL_POINTER = 1,
+    BRAINDUMP_INVALID_UTF8 = 2,
+    BRAINDUMP_INVALID_ARGUMENT = 3,
+    BRAINDUMP_NOT_FOUND = 4,
+    BRAINDUMP_EMBEDDING_FAILED = 5,
+    BRAINDUMP_INTERNAL = 6,
+} BraindumpStatus;
+
+typedef enum BraindumpMemoryKind {
+    BRAINDUMP_MEMORY_KIND_WORKING = 0,
+    BRAINDUMP_MEMORY_KIND_EPISODIC = 1,
+    BRAINDUMP_MEMORY_KIND_SEMANTIC = 2,
+    BRAINDUMP_MEMORY_KIND_PROCEDURAL = 3,
+} BraindumpMemoryKind;
+
+/* An opaque memory manager handle. */
+typedef struct BraindumpManager BraindumpManager;
+
+/*
+ * Embeds `text`, writing exactly `dims` floats to `out`. Returns 0 on success.
+ * Must be safe to call from any thread with the given user data.
+ */
+typedef int (*braindump_embed_fn)(void *user_data, const char *text, float *out, size_t dims);
+
+/* The message of the last error on the current thread, or NULL. Owned by the library. */
+const char *braindump_last_error(void);
+
+/*
+ * Returns NULL on failure. `model_id` identifies the embedding model behind the callback, so
+ * memories embedded by a different model are caught on retrieval. It may be NULL.
+ */
+BraindumpManager *braindump_manager_new(size_t dims, braindump_embed_fn embed, void *user_data,
+                                        const char *model_id);
+BraindumpManager *braindump_manager_load(const char *path, braindump_embed_fn embed, void *user_data,
+                                         const char *model_id);
+void braindump_manager_free(BraindumpManager *manager);
+
+BraindumpStatus braindump_store(BraindumpManager *manager, const char *id, const char *content,
+                                BraindumpMemoryKind kind, float importance);
+BraindumpStatus braindump_store_json(BraindumpManager *manager, const char *entry_json);
+/* Writes a JSON array of memory entries to `out_json`, to be freed with `braindump_string_free`. */
+BraindumpStatus braindump_retrieve(BraindumpManager *manager, const char *query, size_t limit,
+                                   char **out_json);
+BraindumpStatus braindump_delete(BraindumpManager *manager, const char *id);
+BraindumpStatus braindump_count(BraindumpManager *manager, size_t *out_count);
+BraindumpStatus braindump_save(BraindumpManager *manager, const char *path);
+
+void braindump_string_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* BRAINDUMP_H */
@@ -0,0 +1,276 @@
+//! A module for handling embeddings using [AWS Bedrock](https://docs.aws.amazon.com/bedrock/latest/userguide/embeddings.html) through the AWS SDK.
+//! Requests are authenticated with SigV4 using the standard AWS credential chain, so traffic never has to leave AWS.
+//! Ensure that you have the `bedrock` feature enabled.
+
+use aws_sdk_bedrockruntime::{Client, primitives::Blob};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+/// `amazon.titan-embed-text-v2:0`
+pub const TITAN_EMBED_TEXT_V2: &str = "amazon.titan-embed-text-v2:0";
+/// `amazon.titan-embed-text-v1`
+pub const TITAN_EMBED_TEXT_V1: &str = "amazon.titan-embed-text-v1";
+/// `cohere.embed-english-v3`
+pub const COHERE_EMBED_ENGLISH_V3: &str = "cohere.embed-english-v3";
+/// `cohere.embed-multilingual-v3`
+pub const COHERE_EMBED_MULTILINGUAL_V3: &str = "cohere.embed-multilingual-v3";
+
+/// The request format used by a Bedrock model. Inferred from the model ID, but can be overridden for inference profiles or custom model ARNs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BedrockModelFamily {
+    /// Amazon Titan text embedding models.
+    Titan,
+    /// Cohere embedding models hosted on Bedrock. Queries and documents are embedded asymmetrically.
+    Cohere,
+}
+
+impl BedrockModelFamily {
+    fn from_model_id(model_id: &str) -> Self {
+        if model_id.contains("cohere.") {
+            Self::Cohere
+        } else {
+            Self::Titan
+        }
+    }
+}
+
+/// A text embedder using AWS Bedrock, made compliant to work with the `Embedder` trait.
+pub struct BedrockEmbedder {
+    client: Client,
+    model_id: String,
+    family: BedrockModelFamily,
+    dimensions: Option<usize>,
+    normalize: Option<bool>,
+}
+
+impl BedrockEmbedder {
+    /// Creates a new instance of `BedrockEmbedder` from a pre-configured Bedrock runtime client and a model ID (see the model constants in this module).
+    pub fn new<S>(client: Client, model_id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        let model_id = model_id.into();
+
+        Self {
+            client,
+            family: BedrockModelFamily::from_model_id(&model_id),
+            model_id,
+            dimensions: None,
+            normalize: None,
+        }
+    }
+
+    /// Creates a new instance of `BedrockEmbedder`, loading the AWS configuration (region, credentials) from the environment.
+    pub async fn from_env<S>(model_id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        let config = aws_config::load_from_env().await;
+        Self::new(Client::new(&config), model_id)
+    }
+
+    /// Overrides the request format of the model. Useful for inference profiles or custom model ARNs, where the family can't be inferred from the model ID.
+    pub fn model_family(mut self, family: BedrockModelFamily) -> Self {
+        self.family = family;
+        self
+    }
+
+    /// Sets the number of output dimensions. Only supported by Titan Text Embeddings V2 (256, 512 or 1024).
+    pub fn dimensions(mut self, dimensions: usize) -> Self {
+        self.dimensions = Some(dimensions);
+        self
+    }
+
+    /// Sets whether or not embeddings should be normalized. Only supported by Titan Text Embeddings V2, which normalizes by default.
+    pub fn normalize(mut self, normalize: bool) -> Self {
+        self.normalize = Some(normalize);
+        self
+    }
+
+    async fn embed(&self, text: &str, input_type: &str) -> Result<Vec<f32>, crate::Error> {
+        match self.family {
+            BedrockModelFamily::Titan => {
+                let body = TitanRequest {
+                    input_text: text,
+                    dimensions: self.dimensions,
+                    normalize: self.normalize,
+                };
+
+                let response: TitanResponse = self.invoke(&body).await?;
+                Ok(response.embedding)
+            }
+            BedrockModelFamily::Cohere => {
+                let body = CohereRequest {
+                    texts: vec![text],
+                    input_type,
+                };
+
+                let response: CohereResponse = self.invoke(&body).await?;
+                response
+                    .embeddings
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| crate::Error::custom("Bedrock returned no embeddings"))
+            }
+        }
+    }
+
+    async fn invoke<Req, Res>(&self, body: &Req) -> Result<Res, crate::Error>
+    where
+        Req: Serialize,
+        Res: DeserializeOwned,
+    {
+        let body = serde_json::to_vec(body).map_err(bedrock_error)?;
+
+        let output = self
+            .client
+            .invoke_model()
+            .model_id(&self.model_id)
+            .content_type("application/json")
+            .accept("application/json")
+            .body(Blob::new(body))
+            .send()
+            .await
+            .map_err(bedrock_error)?;
+
+        serde_json::from_slice(output.body().as_ref()).map_err(bedrock_error)
+    }
+}
+
+impl crate::embed::Embedder for BedrockEmbedder {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "bedrock_embed_text", skip_all, fields(input_len = text.len()))
+    )]
+    async fn embed_text(&self, text: &str) -> Result<Vec<f32>, crate::Error> {
+        self.embed(text, "search_document").await
+    }
+
+    async fn embed_query(&self, text: &str) -> Result<Vec<f32>, crate::Error> {
+        self.embed(text, "search_query").await
+    }
+
+    async fn embed_document(&self, text: &str) -> Result<Vec<f32>, crate::Error> {
+        self.embed(text, "search_document").await
+    }
+
+    fn dims(&self) -> Option<usize> {
+        self.dimensions.or(match self.model_id.as_str() {
+            TITAN_EMBED_TEXT_V2 => Some(1024),
+            TITAN_EMBED_TEXT_V1 => Some(1536),
+            COHERE_EMBED_ENGLISH_V3 | COHERE_EMBED_MULTILINGUAL_V3 => Some(1024),
+            _ => None,
+        })
+    }
+
+    fn max_input_tokens(&self) -> Option<usize> {
+        match self.model_id.as_str() {
+            TITAN_EMBED_TEXT_V2 | TITAN_EMBED_TEXT_V1 => Some(8192),
+            COHERE_EMBED_ENGLISH_V3 | COHERE_EMBED_MULTILINGUAL_V3 => Some(512),
+            _ => None,
+        }
+    }
+
+    fn model_id(&self) -> Option<String> {
+        Some(match self.dimensions {
+            Some(dims) => format!("bedrock/{}/{dims}", self.model_id),
+            None => format!("bedrock/{}", self.model_id),
+        })
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TitanRequest<'a> {
+    input_text: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dimensions: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    normalize: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct TitanResponse {
+    embedding: Vec<f32>,
+}
+
+#[derive(Serialize)]
+struct CohereRequest<'a> {
+    texts: Vec<&'a str>,
+    input_type: &'a str,
+}
+
+#[derive(Deserialize)]
+struct CohereResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+fn bedrock_error<E>(err: E) -> crate::Error
+where
+    E: Into<crate::error::BoxedError>,
+{
+    crate::Error::wrap("Bedrock request failed", err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CohereRequest, CohereResponse, TitanRequest, TitanResponse};
+
+    #[test]
+    fn titan_requests_and_responses_match_the_api() {
+        let request = TitanRequest {
+            input_text: "User lives in Berlin",
+            dimensions: Some(512),
+            normalize: Some(true),
+        };
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "inputText": "User lives in Berlin",
+                "dimensions": 512,
+                "normalize": true
+            })
+        );
+
+        let request = TitanRequest {
+            dimensions: None,
+            normalize: None,
+            ..request
+        };
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({"inputText": "User lives in Berlin"})
+        );
+
+        let response: TitanResponse = serde_json::from_str(
+            r#"{"embedding": [0.0625, -0.0112, 0.0391], "inputTextTokenCount": 5, "embeddingsByType": {"float": [0.0625, -0.0112, 0.0391]}}"#,
+        )
+        .unwrap();
+        assert_eq!(response.embedding, [0.0625, -0.0112, 0.0391]);
+    }
+
+    #[test]
+    fn cohere_requests_and_responses_match_the_api() {
+        let request = CohereRequest {
+            texts: vec!["User lives in Berlin"],
+            input_type: "search_query",
+        };
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "texts": ["User lives in Berlin"],
+                "input_type": "search_query"
+            })
+        );
+
+        let response: CohereResponse = serde_json::from_str(
+            r#"{
+                "embeddings": [[0.016296387, -0.008354187]],
+                "id": "5b4b6f4e-6f1b-4b3e-9b5e-4c1b2a3d4e5f",
+                "response_type": "embeddings_floats",
+                "texts": ["user lives in berlin"]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(response.embeddings, [[0.016296387, -0.008354187]]);
+    }
+}
@@ -0,0 +1,184 @@
+//! A module for handling local embeddings using [`candle`](https://github.com/huggingface/candle), a pure-Rust ML framework.
+//! Ensure that you have the `candle` feature enabled. For GPU support, enable `candle-cuda` or `candle-metal`.
+//! Supports BERT-based sentence-transformer models (eg, `sentence-transformers/all-MiniLM-L6-v2` or `BAAI/bge-small-en-v1.5`).
+//! NOTE: This module is not WASM-friendly. Attempting to compile this module to `wasm` architecture will return an error.
+
+use std::path::Path;
+
+use candle_core::{Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config, DTYPE};
+use tokenizers::{Tokenizer, TruncationParams};
+
+pub use candle_core::Device as CandleDevice;
+
+/// A text embedder that runs sentence-transformer models locally using `candle`, made compliant to work with the `Embedder` trait.
+/// Embeddings are created by mean-pooling the last hidden state of the model, and are L2-normalized by default.
+pub struct CandleEmbedder {
+    model: BertModel,
+    tokenizer: Tokenizer,
+    dims: usize,
+    max_tokens: usize,
+    normalize: bool,
+    model_id: String,
+}
+
+impl CandleEmbedder {
+    /// Loads a model from local files: the model config (`config.json`), the tokenizer (`tokenizer.json`) and the model weights (`model.safetensors`).
+    pub fn from_files<P1, P2, P3>(
+        config: P1,
+        tokenizer: P2,
+        weights: P3,
+        device: &Device,
+    ) -> Result<Self, crate::Error>
+    where
+        P1: AsRef<Path>,
+        P2: AsRef<Path>,
+        P3: AsRef<Path>,
+    {
+        let config = std::fs::read_to_string(config).map_err(candle_error)?;
+        let config: Config = serde_json::from_str(&config).map_err(candle_error)?;
+
+        let mut tokenizer = Tokenizer::from_file(tokenizer).map_err(candle_error)?;
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: config.max_position_embeddings,
+                ..Default::default()
+            }))
+            .map_err(candle_error)?;
+
+        // SAFETY: The weights file is memory mapped, so it must not be modified while the model is in use.
+        let vb = unsafe {
+            VarBuilder::from_mmaped_safetensors(&[weights.as_ref()], DTYPE, device)
+                .map_err(candle_error)?
+        };
+        let model = BertModel::load(vb, &config).map_err(candle_error)?;
+
+        Ok(Self {
+            model,
+            tokenizer,
+            dims: config.hidden_size,
+            max_tokens: config.max_position_embeddings,
+            normalize: true,
+            model_id: format!("candle/{}", weights.as_ref().display()),
+        })
+    }
+
+    /// Downloads (or loads from the local Hugging Face cache) a model from the Hugging Face Hub by its repository ID.
+    /// The repository must contain `config.json`, `tokenizer.json` and `model.safetensors`.
+    pub fn from_pretrained(model_id: &str, device: &Device) -> Result<Self, crate::Error> {
+        let api = hf_hub::api::sync::Api::new().map_err(candle_error)?;
+        let repo = api.model(model_id.to_string());
+
+        let config = repo.get("config.json").map_err(candle_error)?;
+        let tokenizer = repo.get("tokenizer.json").map_err(candle_error)?;
+        let weights = repo.get("model.safetensors").map_err(candle_error)?;
+
+        let mut embedder = Self::from_files(config, tokenizer, weights, device)?;
+        embedder.model_id = format!("candle/{model_id}");
+
+        Ok(embedder)
+    }
+
+    /// Sets whether or not embeddings should be L2-normalized. Defaults to `true`.
+    pub fn normalize(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
+    /// The dimensions of the embeddings created by this model.
+    pub fn dims(&self) -> usize {
+        self.dims
+    }
+
+    fn embed(&self, text: &str) -> Result<Vec<f32>, candle_core::Error> {
+        let encoding = self
+            .tokenizer
+            .encode(text, true)
+            .map_err(candle_core::Error::wrap)?;
+
+        let device = &self.model.device;
+        let input_ids = Tensor::new(encoding.get_ids(), device)?.unsqueeze(0)?;
+        let type_ids = Tensor::new(encoding.get_type_ids(), device)?.unsqueeze(0)?;
+        let attention_mask = Tensor::new(encoding.get_attention_mask(), device)?.unsqueeze(0)?;
+
+        let hidden = self
+            .model
+            .forward(&input_ids, &type_ids, Some(&attention_mask))?;
+
+        mean_pool(&hidden, &attention_mask, self.normalize)?
+            .squeeze(0)?
+            .to_vec1::<f32>()
+    }
+}
+
+/// Mean-pools token embeddings of shape `(batch, tokens, hidden)` over non-padding tokens, optionally L2-normalizing the result.
+fn mean_pool(
+    hidden: &Tensor,
+    attention_mask: &Tensor,
+    normalize: bool,
+) -> Result<Tensor, candle_core::Error> {
+    let mask = attention_mask.to_dtype(hidden.dtype())?.unsqueeze(2)?;
+    let summed = hidden.broadcast_mul(&mask)?.sum(1)?;
+    let mut pooled = summed.broadcast_div(&mask.sum(1)?)?;
+
+    if normalize {
+        let norm = pooled.sqr()?.sum_keepdim(1)?.sqrt()?;
+        pooled = pooled.broadcast_div(&norm)?;
+    }
+
+    Ok(pooled)
+}
+
+impl crate::embed::Embedder for CandleEmbedder {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "candle_embed_text", skip_all, fields(input_len = text.len()))
+    )]
+    async fn embed_text(&self, text: &str) -> Result<Vec<f32>, crate::Error> {
+        self.embed(text).map_err(candle_error)
+    }
+
+    fn dims(&self) -> Option<usize> {
+        Some(self.dims)
+    }
+
+    fn max_input_tokens(&self) -> Option<usize> {
+        Some(self.max_tokens)
+    }
+
+    fn model_id(&self) -> Option<String> {
+        Some(self.model_id.clone())
+    }
+}
+
+fn candle_error<E>(err: E) -> crate::Error
+where
+    E: Into<crate::error::BoxedError>,
+{
+    crate::Error::wrap("Candle error", err)
+}
+
+#[cfg(test)]
+mod tests {
+    use candle_core::{Device, Tensor};
+
+    use super::mean_pool;
+
+    #[test]
+    fn padding_tokens_are_left_out_of_the_mean() {
+        let device = Device::Cpu;
+        let hidden = Tensor::new(&[[[1.0f32, 2.0], [3.0, 6.0], [100.0, 100.0]]], &device).unwrap();
+        let mask = Tensor::new(&[[1u32, 1, 0]], &device).unwrap();
+
+        let pooled = mean_pool(&hidden, &mask, false).unwrap();
+        assert_eq!(pooled.to_vec2::<f32>().unwrap(), [[2.0, 4.0]]);
+
+        let normalized = mean_pool(&hidden, &mask, true)
+            .unwrap()
+            .to_vec2::<f32>()
+            .unwrap();
+        let norm = normalized[0].iter().map(|x| x * x).sum::<f32>().sqrt();
+        assert!((norm - 1.0).abs() < 1e-6);
+    }
+}
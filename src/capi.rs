@@ -0,0 +1,556 @@
+//! A C interface over the memory manager, so that braindump can be embedded in C, C++ or Swift applications.
+//! The matching header is `include/braindump.h`.
+//!
+//! To get a library to link against, depend on `braindump` (with the `capi` feature) from a crate with a `staticlib` or `cdylib` crate type.
+//!
+//! Conventions:
+//! - Managers are opaque handles, created with [`braindump_manager_new`] (or [`braindump_manager_load`]) and freed with [`braindump_manager_free`].
+//! - Strings are null-terminated UTF-8. Strings returned by the library must be freed with [`braindump_string_free`].
+//! - Every fallible function returns a [`BraindumpStatus`]. The message of the last error on the current thread can be fetched with [`braindump_last_error`].
+//! - Memory entries are passed around as JSON, using the same representation as their `serde` implementations.
+//!
+//! Embeddings are created by a callback. As the manager may be used from any thread, the callback (and its user data) must be thread-safe.
+
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString, c_char, c_void},
+    panic::{AssertUnwindSafe, catch_unwind},
+    ptr,
+};
+
+use crate::{
+    embed::Embedder,
+    error::StorageError,
+    memory::{Confidence, MemoryEntry, MemoryKind, Visibility, manager::MemoryManager},
+    vector_store::InMemoryDB,
+};
+
+/// A status code returned by every fallible function.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BraindumpStatus {
+    Ok = 0,
+    NullPointer = 1,
+    InvalidUtf8 = 2,
+    InvalidArgument = 3,
+    NotFound = 4,
+    EmbeddingFailed = 5,
+    Internal = 6,
+}
+
+/// The kind of a memory stored using [`braindump_store`].
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BraindumpMemoryKind {
+    Working = 0,
+    Episodic = 1,
+    Semantic = 2,
+    Procedural = 3,
+}
+
+impl From<BraindumpMemoryKind> for MemoryKind {
+    fn from(kind: BraindumpMemoryKind) -> Self {
+        match kind {
+            BraindumpMemoryKind::Working => Self::Working,
+            BraindumpMemoryKind::Episodic => Self::Episodic,
+            BraindumpMemoryKind::Semantic => Self::Semantic,
+            BraindumpMemoryKind::Procedural => Self::Procedural,
+        }
+    }
+}
+
+/// A callback that embeds `text`, writing exactly `dims` floats to `out`. Returns 0 on success, or any other value on failure.
+pub type BraindumpEmbedFn = Option<
+    unsafe extern "C" fn(
+        user_data: *mut c_void,
+        text: *const c_char,
+        out: *mut f32,
+        dims: usize,
+    ) -> i32,
+>;
+
+/// An embedder that calls a C callback.
+struct CallbackEmbedder {
+    embed: unsafe extern "C" fn(*mut c_void, *const c_char, *mut f32, usize) -> i32,
+    user_data: *mut c_void,
+    dims: usize,
+    model_id: Option<String>,
+}
+
+// Safety: callers of `braindump_manager_new` promise that the callback and its user data are thread-safe.
+unsafe impl Send for CallbackEmbedder {}
+unsafe impl Sync for CallbackEmbedder {}
+
+impl Embedder for CallbackEmbedder {
+    async fn embed_text(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        let text = CString::new(input)
+            .map_err(|_| crate::Error::custom("Text to embed contains a null byte"))?;
+        let mut embedding = vec![0.0; self.dims];
+
+        // Safety: `text` is a valid C string and `embedding` has room for `dims` floats.
+        let code = unsafe {
+            (self.embed)(
+                self.user_data,
+                text.as_ptr(),
+                embedding.as_mut_ptr(),
+                self.dims,
+            )
+        };
+        if code != 0 {
+            Err(crate::Error::embedding(
+                &format!("Embedding callback failed with code {code}"),
+                false,
+            ))?
+        }
+
+        Ok(embedding)
+    }
+
+    fn dims(&self) -> Option<usize> {
+        Some(self.dims)
+    }
+
+    fn model_id(&self) -> Option<String> {
+        self.model_id.clone()
+    }
+}
+
+/// An opaque memory manager handle.
+pub struct BraindumpManager(MemoryManager<CallbackEmbedder, InMemoryDB>);
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// An error to be returned over the C interface.
+struct FfiError(BraindumpStatus, String);
+
+impl From<crate::Error> for FfiError {
+    fn from(err: crate::Error) -> Self {
+        let status = match &err {
+            crate::Error::Storage(StorageError::EmbeddingNotExists(_)) => BraindumpStatus::NotFound,
+            crate::Error::Storage(StorageError::MismatchedDimensions(..)) => {
+                BraindumpStatus::InvalidArgument
+            }
+            crate::Error::Embedding { .. } => BraindumpStatus::EmbeddingFailed,
+            _ => BraindumpStatus::Internal,
+        };
+
+        Self(status, err.to_string())
+    }
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', ""))
+        .expect("null bytes have been removed from the message");
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Runs the body of an FFI function, recording any error (or panic) so it doesn't unwind over the C boundary.
+fn guard<F>(body: F) -> BraindumpStatus
+where
+    F: FnOnce() -> Result<(), FfiError>,
+{
+    match catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(())) => BraindumpStatus::Ok,
+        Ok(Err(FfiError(status, message))) => {
+            set_last_error(message);
+            status
+        }
+        Err(_) => {
+            set_last_error("braindump panicked".to_string());
+            BraindumpStatus::Internal
+        }
+    }
+}
+
+/// Borrows a C string as a `&str`.
+///
+/// # Safety
+/// `ptr` must be null or point to a valid null-terminated string.
+unsafe fn to_str<'a>(ptr: *const c_char) -> Result<&'a str, FfiError> {
+    if ptr.is_null() {
+        Err(FfiError(
+            BraindumpStatus::NullPointer,
+            "Unexpected null pointer".to_string(),
+        ))?
+    }
+
+    // Safety: the caller guarantees `ptr` is a valid C string.
+    unsafe { CStr::from_ptr(ptr) }.to_str().map_err(|_| {
+        FfiError(
+            BraindumpStatus::InvalidUtf8,
+            "String is not valid UTF-8".to_string(),
+        )
+    })
+}
+
+/// Borrows a C string as a `&str`, treating a null pointer as `None`.
+///
+/// # Safety
+/// `ptr` must be null or point to a valid null-terminated string.
+unsafe fn to_optional_str<'a>(ptr: *const c_char) -> Result<Option<&'a str>, FfiError> {
+    if ptr.is_null() {
+        return Ok(None);
+    }
+
+    // Safety: the caller guarantees `ptr` is a valid C string.
+    unsafe { to_str(ptr) }.map(Some)
+}
+
+/// Borrows a manager handle.
+///
+/// # Safety
+/// `ptr` must be null or a handle returned by [`braindump_manager_new`] that hasn't been freed.
+unsafe fn to_manager<'a>(ptr: *mut BraindumpManager) -> Result<&'a mut BraindumpManager, FfiError> {
+    // Safety: the caller guarantees `ptr` is a valid handle (if it isn't null).
+    unsafe { ptr.as_mut() }.ok_or_else(|| {
+        FfiError(
+            BraindumpStatus::NullPointer,
+            "Unexpected null manager".to_string(),
+        )
+    })
+}
+
+fn new_manager(
+    db: InMemoryDB,
+    embed: BraindumpEmbedFn,
+    user_data: *mut c_void,
+    model_id: Option<&str>,
+) -> Result<*mut BraindumpManager, FfiError> {
+    let embed = embed.ok_or_else(|| {
+        FfiError(
+            BraindumpStatus::NullPointer,
+            "An embedding callback is required".to_string(),
+        )
+    })?;
+    let embedder = CallbackEmbedder {
+        embed,
+        user_data,
+        dims: db.dims(),
+        model_id: model_id.map(str::to_string),
+    };
+    let manager = MemoryManager::builder()
+        .embedder(embedder)
+        .storage(db)
+        .build()?;
+
+    Ok(Box::into_raw(Box::new(BraindumpManager(manager))))
+}
+
+/// Returns the message of the last error on the current thread, or null if there hasn't been one.
+/// The message is owned by the library, and stays valid until the next failing call on the same thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn braindump_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Creates a memory manager storing `dims`-dimension embeddings in memory. Returns null on failure.
+///
+/// `model_id` identifies the embedding model behind the callback (see [`Embedder::model_id`]), so memories embedded by a different model are caught when retrieving them.
+/// It can be null, in which case memories are left untagged.
+///
+/// # Safety
+/// `embed` must be safe to call from any thread with `user_data`, for as long as the manager lives. `model_id` must be null or a valid C string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn braindump_manager_new(
+    dims: usize,
+    embed: BraindumpEmbedFn,
+    user_data: *mut c_void,
+    model_id: *const c_char,
+) -> *mut BraindumpManager {
+    let mut manager = ptr::null_mut();
+    guard(|| {
+        // Safety: the caller guarantees `model_id` is null or a valid C string.
+        let model_id = unsafe { to_optional_str(model_id) }?;
+        manager = new_manager(InMemoryDB::new(dims), embed, user_data, model_id)?;
+        Ok(())
+    });
+
+    manager
+}
+
+/// Creates a memory manager from a snapshot file saved with [`braindump_save`]. Returns null on failure.
+/// `model_id` is the same as for [`braindump_manager_new`].
+///
+/// # Safety
+/// `path` must be a valid C string. `embed` must be safe to call from any thread with `user_data`, for as long as the manager lives. `model_id` must be null or a valid C string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn braindump_manager_load(
+    path: *const c_char,
+    embed: BraindumpEmbedFn,
+    user_data: *mut c_void,
+    model_id: *const c_char,
+) -> *mut BraindumpManager {
+    let mut manager = ptr::null_mut();
+    guard(|| {
+        // Safety: the caller guarantees the pointers are valid.
+        let (path, model_id) = unsafe { (to_str(path)?, to_optional_str(model_id)?) };
+        manager = new_manager(InMemoryDB::load_snapshot(path)?, embed, user_data, model_id)?;
+        Ok(())
+    });
+
+    manager
+}
+
+/// Frees a memory manager. Does nothing if `manager` is null.
+///
+/// # Safety
+/// `manager` must be null or a handle that hasn't already been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn braindump_manager_free(manager: *mut BraindumpManager) {
+    if !manager.is_null() {
+        // Safety: the caller guarantees `manager` came from `Box::into_raw` and hasn't been freed.
+        drop(unsafe { Box::from_raw(manager) });
+    }
+}
+
+/// Stores a memory with the given ID, content, kind and importance (between 0.0 and 1.0).
+///
+/// # Safety
+/// `manager` must be a valid handle, and `id` and `content` must be valid C strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn braindump_store(
+    manager: *mut BraindumpManager,
+    id: *const c_char,
+    content: *const c_char,
+    kind: BraindumpMemoryKind,
+    importance: f32,
+) -> BraindumpStatus {
+    guard(|| {
+        // Safety: the caller guarantees the pointers are valid.
+        let (manager, id, content) =
+            unsafe { (to_manager(manager)?, to_str(id)?, to_str(content)?) };
+        let now = chrono::Utc::now().timestamp();
+        let entry = MemoryEntry {
+            id: id.into(),
+            content: content.to_string(),
+            kind: kind.into(),
+            importance: importance.clamp(0.0, 1.0),
+            created_at: now,
+            last_accessed: now,
+            access_count: 0,
+            source_context: String::new(),
+            confidence: Confidence::High,
+            metadata: Vec::new(),
+            agent_id: None,
+            visibility: Visibility::default(),
+            embedding_model: None,
+            image: None,
+            tags: Vec::new(),
+            strength: crate::memory::strength::INITIAL_STRENGTH,
+        };
+
+        futures::executor::block_on(manager.0.store(content, entry))?;
+        Ok(())
+    })
+}
+
+/// Stores a memory given as a JSON memory entry.
+///
+/// # Safety
+/// `manager` must be a valid handle, and `entry_json` must be a valid C string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn braindump_store_json(
+    manager: *mut BraindumpManager,
+    entry_json: *const c_char,
+) -> BraindumpStatus {
+    guard(|| {
+        // Safety: the caller guarantees the pointers are valid.
+        let (manager, entry_json) = unsafe { (to_manager(manager)?, to_str(entry_json)?) };
+        let entry: MemoryEntry = serde_json::from_str(entry_json).map_err(|err| {
+            FfiError(
+                BraindumpStatus::InvalidArgument,
+                format!("Invalid memory entry: {err}"),
+            )
+        })?;
+
+        futures::executor::block_on(manager.0.store(entry.content.clone(), entry))?;
+        Ok(())
+    })
+}
+
+/// Retrieves up to `limit` memories relevant to a query, writing them to `out_json` as a JSON array of memory entries.
+/// The string written to `out_json` must be freed with [`braindump_string_free`].
+///
+/// # Safety
+/// `manager` must be a valid handle, `query` must be a valid C string and `out_json` must be a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn braindump_retrieve(
+    manager: *mut BraindumpManager,
+    query: *const c_char,
+    limit: usize,
+    out_json: *mut *mut c_char,
+) -> BraindumpStatus {
+    guard(|| {
+        // Safety: the caller guarantees the pointers are valid.
+        let (manager, query) = unsafe { (to_manager(manager)?, to_str(query)?) };
+        if out_json.is_null() {
+            Err(FfiError(
+                BraindumpStatus::NullPointer,
+                "Unexpected null output pointer".to_string(),
+            ))?
+        }
+
+        let results = futures::executor::block_on(manager.0.retrieve(query, limit))?;
+        let entries: Vec<MemoryEntry> = results.iter().map(|result| result.data_owned()).collect();
+        let json = serde_json::to_string(&entries)
+            .map_err(|err| FfiError(BraindumpStatus::Internal, err.to_string()))?;
+        let json = CString::new(json)
+            .map_err(|err| FfiError(BraindumpStatus::Internal, err.to_string()))?;
+
+        // Safety: `out_json` was checked to not be null.
+        unsafe { *out_json = json.into_raw() };
+        Ok(())
+    })
+}
+
+/// Deletes a memory. Returns [`BraindumpStatus::NotFound`] if there's no memory with the given ID.
+///
+/// # Safety
+/// `manager` must be a valid handle, and `id` must be a valid C string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn braindump_delete(
+    manager: *mut BraindumpManager,
+    id: *const c_char,
+) -> BraindumpStatus {
+    guard(|| {
+        // Safety: the caller guarantees the pointers are valid.
+        let (manager, id) = unsafe { (to_manager(manager)?, to_str(id)?) };
+        futures::executor::block_on(manager.0.delete(id))?;
+        Ok(())
+    })
+}
+
+/// Writes the number of stored memories to `out_count`.
+///
+/// # Safety
+/// `manager` must be a valid handle, and `out_count` must be a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn braindump_count(
+    manager: *mut BraindumpManager,
+    out_count: *mut usize,
+) -> BraindumpStatus {
+    guard(|| {
+        // Safety: the caller guarantees the pointer is valid.
+        let manager = unsafe { to_manager(manager)? };
+        let count = futures::executor::block_on(manager.0.count())?;
+        // Safety: the caller guarantees `out_count` is valid, and it's checked to not be null.
+        match unsafe { out_count.as_mut() } {
+            Some(out_count) => *out_count = count,
+            None => Err(FfiError(
+                BraindumpStatus::NullPointer,
+                "Unexpected null output pointer".to_string(),
+            ))?,
+        }
+        Ok(())
+    })
+}
+
+/// Saves a snapshot of the stored memories to a file, which can be loaded with [`braindump_manager_load`].
+///
+/// # Safety
+/// `manager` must be a valid handle, and `path` must be a valid C string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn braindump_save(
+    manager: *mut BraindumpManager,
+    path: *const c_char,
+) -> BraindumpStatus {
+    guard(|| {
+        // Safety: the caller guarantees the pointers are valid.
+        let (manager, path) = unsafe { (to_manager(manager)?, to_str(path)?) };
+        futures::executor::block_on(manager.0.flush())?;
+        manager.0.storage().save_snapshot(path)?;
+        Ok(())
+    })
+}
+
+/// Frees a string returned by the library. Does nothing if `string` is null.
+///
+/// # Safety
+/// `string` must be null or a string returned by the library that hasn't already been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn braindump_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        // Safety: the caller guarantees `string` came from `CString::into_raw` and hasn't been freed.
+        drop(unsafe { CString::from_raw(string) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{CStr, CString, c_char, c_void};
+
+    use super::*;
+
+    /// Counts occurrences of a handful of letters.
+    unsafe extern "C" fn letter_embed(
+        _user_data: *mut c_void,
+        text: *const c_char,
+        out: *mut f32,
+        dims: usize,
+    ) -> i32 {
+        let text = unsafe { CStr::from_ptr(text) }.to_str().unwrap();
+        let out = unsafe { std::slice::from_raw_parts_mut(out, dims) };
+        for (value, letter) in out.iter_mut().zip(['a', 'e', 'i', 'o']) {
+            *value = text.matches(letter).count() as f32 + 1.0;
+        }
+
+        0
+    }
+
+    #[test]
+    fn memories_can_be_managed_over_the_c_interface() {
+        unsafe {
+            let model_id = CString::new("letters/v1").unwrap();
+            let manager = braindump_manager_new(
+                4,
+                Some(letter_embed),
+                std::ptr::null_mut(),
+                model_id.as_ptr(),
+            );
+            assert!(!manager.is_null());
+
+            let (a, aaaa) = (CString::new("a").unwrap(), CString::new("aaaa").unwrap());
+            let status = braindump_store(
+                manager,
+                a.as_ptr(),
+                aaaa.as_ptr(),
+                BraindumpMemoryKind::Semantic,
+                0.5,
+            );
+            assert_eq!(status, BraindumpStatus::Ok);
+
+            let query = CString::new("aaa").unwrap();
+            let mut json = std::ptr::null_mut();
+            let status = braindump_retrieve(manager, query.as_ptr(), 1, &mut json);
+            assert_eq!(status, BraindumpStatus::Ok);
+            let entries: Vec<MemoryEntry> =
+                serde_json::from_str(CStr::from_ptr(json).to_str().unwrap()).unwrap();
+            assert_eq!(entries[0].id, "a");
+            assert_eq!(
+                entries[0].embedding_model.as_ref().unwrap().model,
+                "letters/v1"
+            );
+            braindump_string_free(json);
+
+            let missing = CString::new("missing").unwrap();
+            let status = braindump_delete(manager, missing.as_ptr());
+            assert_eq!(status, BraindumpStatus::NotFound);
+            assert!(!braindump_last_error().is_null());
+
+            let status = braindump_store_json(manager, query.as_ptr());
+            assert_eq!(status, BraindumpStatus::InvalidArgument);
+
+            let mut count = 0;
+            assert_eq!(braindump_count(manager, &mut count), BraindumpStatus::Ok);
+            assert_eq!(count, 1);
+
+            braindump_manager_free(manager);
+        }
+    }
+}
@@ -0,0 +1,299 @@
+//! A module for handling embeddings using the [Cohere](https://docs.cohere.com/reference/embed) embeddings API.
+//! Ensure that you have the `cohere` feature enabled.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    embed::QuantizedVector,
+    http::{api_key_from_env, post_json},
+};
+
+const COHERE_API_BASE_URL: &str = "https://api.cohere.com";
+
+/// `embed-v4.0`
+pub const EMBED_V4: &str = "embed-v4.0";
+/// `embed-english-v3.0`
+pub const EMBED_ENGLISH_V3: &str = "embed-english-v3.0";
+/// `embed-english-light-v3.0`
+pub const EMBED_ENGLISH_LIGHT_V3: &str = "embed-english-light-v3.0";
+/// `embed-multilingual-v3.0`
+pub const EMBED_MULTILINGUAL_V3: &str = "embed-multilingual-v3.0";
+/// `embed-multilingual-light-v3.0`
+pub const EMBED_MULTILINGUAL_LIGHT_V3: &str = "embed-multilingual-light-v3.0";
+
+/// The type of input being embedded. Cohere v3+ models embed queries and documents asymmetrically.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputType {
+    /// Used for search queries (ie, when retrieving memories).
+    SearchQuery,
+    /// Used for documents that get stored (ie, when storing memories).
+    #[default]
+    SearchDocument,
+    /// Used for text classification.
+    Classification,
+    /// Used for clustering.
+    Clustering,
+}
+
+/// A text embedder using the Cohere embeddings API, made compliant to work with the `Embedder` trait.
+/// Queries are embedded using [`InputType::SearchQuery`] and stored memories using [`InputType::SearchDocument`].
+pub struct CohereEmbedder {
+    client: reqwest::Client,
+    api_key: String,
+    model: String,
+    base_url: String,
+    default_input_type: InputType,
+    dimensions: Option<usize>,
+}
+
+impl CohereEmbedder {
+    /// Creates a new instance of `CohereEmbedder` using a given API key and model (see the model constants in this module).
+    pub fn new<S1, S2>(api_key: S1, model: S2) -> Self
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        Self {
+            client: reqwest::Client::new(),
+            api_key: api_key.into(),
+            model: model.into(),
+            base_url: COHERE_API_BASE_URL.to_string(),
+            default_input_type: InputType::default(),
+            dimensions: None,
+        }
+    }
+
+    /// Creates a new instance of `CohereEmbedder`, using the `COHERE_API_KEY` environment variable as the API key.
+    pub fn from_env<S>(model: S) -> Result<Self, crate::Error>
+    where
+        S: Into<String>,
+    {
+        Ok(Self::new(api_key_from_env("COHERE_API_KEY")?, model))
+    }
+
+    /// Sets the base URL of the API (eg, for proxies).
+    pub fn base_url<S>(mut self, base_url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Sets the input type used by [`crate::embed::Embedder::embed_text`]. Defaults to [`InputType::SearchDocument`].
+    pub fn default_input_type(mut self, input_type: InputType) -> Self {
+        self.default_input_type = input_type;
+        self
+    }
+
+    /// Sets the number of output dimensions. Only supported by Embed v4 (256, 512, 1024 or 1536).
+    /// For models that aren't in this module, this also declares the dimensions of their embeddings (see [`crate::embed::Embedder::dims`]).
+    pub fn dimensions(mut self, dims: usize) -> Self {
+        self.dimensions = Some(dims);
+        self
+    }
+
+    /// Use a pre-configured `reqwest` client.
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Embeds text using a given input type.
+    pub async fn embed_with_input_type(
+        &self,
+        text: &str,
+        input_type: InputType,
+    ) -> Result<Vec<f32>, crate::Error> {
+        self.embed_as(text, input_type, "float")
+            .await?
+            .float
+            .into_iter()
+            .next()
+            .ok_or_else(|| crate::Error::custom("Cohere returned no embeddings"))
+    }
+
+    /// Embeds text using a given input type, returning an int8-quantized embedding.
+    pub async fn embed_int8_with_input_type(
+        &self,
+        text: &str,
+        input_type: InputType,
+    ) -> Result<QuantizedVector, crate::Error> {
+        self.embed_as(text, input_type, "int8")
+            .await?
+            .int8
+            .into_iter()
+            .next()
+            // Cohere doesn't return quantization parameters, but cosine similarity doesn't depend on the scale
+            .map(|values| QuantizedVector::new(values, 1.0, 0))
+            .ok_or_else(|| crate::Error::custom("Cohere returned no embeddings"))
+    }
+
+    async fn embed_as(
+        &self,
+        text: &str,
+        input_type: InputType,
+        embedding_type: &str,
+    ) -> Result<EmbeddingsByType, crate::Error> {
+        let request = self
+            .client
+            .post(format!("{}/v2/embed", self.base_url))
+            .bearer_auth(&self.api_key);
+
+        let body = EmbedRequest {
+            model: &self.model,
+            texts: vec![text],
+            input_type,
+            embedding_types: vec![embedding_type],
+            output_dimension: self.dimensions,
+        };
+
+        let response: EmbedResponse = post_json(request, &body).await?;
+
+        Ok(response.embeddings)
+    }
+}
+
+impl crate::embed::Embedder for CohereEmbedder {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "cohere_embed_text", skip_all, fields(input_len = text.len()))
+    )]
+    async fn embed_text(&self, text: &str) -> Result<Vec<f32>, crate::Error> {
+        self.embed_with_input_type(text, self.default_input_type)
+            .await
+    }
+
+    async fn embed_query(&self, text: &str) -> Result<Vec<f32>, crate::Error> {
+        self.embed_with_input_type(text, InputType::SearchQuery)
+            .await
+    }
+
+    async fn embed_document(&self, text: &str) -> Result<Vec<f32>, crate::Error> {
+        self.embed_with_input_type(text, InputType::SearchDocument)
+            .await
+    }
+
+    fn dims(&self) -> Option<usize> {
+        self.dimensions.or(match self.model.as_str() {
+            EMBED_V4 => Some(1536),
+            EMBED_ENGLISH_V3 | EMBED_MULTILINGUAL_V3 => Some(1024),
+            EMBED_ENGLISH_LIGHT_V3 | EMBED_MULTILINGUAL_LIGHT_V3 => Some(384),
+            _ => None,
+        })
+    }
+
+    fn max_input_tokens(&self) -> Option<usize> {
+        match self.model.as_str() {
+            EMBED_V4 => Some(128_000),
+            EMBED_ENGLISH_V3
+            | EMBED_MULTILINGUAL_V3
+            | EMBED_ENGLISH_LIGHT_V3
+            | EMBED_MULTILINGUAL_LIGHT_V3 => Some(512),
+            _ => None,
+        }
+    }
+
+    fn model_id(&self) -> Option<String> {
+        Some(match self.dimensions {
+            Some(dims) => format!("cohere/{}/{dims}", self.model),
+            None => format!("cohere/{}", self.model),
+        })
+    }
+}
+
+/// Embeddings are returned as int8 by the API itself, so quantized embeddings are a quarter of the size over the wire.
+impl crate::embed::QuantizedEmbedder for CohereEmbedder {
+    async fn embed_query_quantized(&self, text: &str) -> Result<QuantizedVector, crate::Error> {
+        self.embed_int8_with_input_type(text, InputType::SearchQuery)
+            .await
+    }
+
+    async fn embed_document_quantized(&self, text: &str) -> Result<QuantizedVector, crate::Error> {
+        self.embed_int8_with_input_type(text, InputType::SearchDocument)
+            .await
+    }
+}
+
+#[derive(Serialize)]
+struct EmbedRequest<'a> {
+    model: &'a str,
+    texts: Vec<&'a str>,
+    input_type: InputType,
+    embedding_types: Vec<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output_dimension: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct EmbedResponse {
+    embeddings: EmbeddingsByType,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingsByType {
+    #[serde(default)]
+    float: Vec<Vec<f32>>,
+    #[serde(default)]
+    int8: Vec<Vec<i8>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EmbedRequest, EmbedResponse, InputType};
+
+    #[test]
+    fn requests_match_the_api() {
+        let request = EmbedRequest {
+            model: super::EMBED_ENGLISH_V3,
+            texts: vec!["User lives in Berlin"],
+            input_type: InputType::SearchQuery,
+            embedding_types: vec!["float"],
+            output_dimension: None,
+        };
+
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "model": "embed-english-v3.0",
+                "texts": ["User lives in Berlin"],
+                "input_type": "search_query",
+                "embedding_types": ["float"]
+            })
+        );
+
+        let request = EmbedRequest {
+            model: super::EMBED_V4,
+            output_dimension: Some(512),
+            ..request
+        };
+        assert_eq!(
+            serde_json::to_value(&request).unwrap()["output_dimension"],
+            512
+        );
+    }
+
+    #[test]
+    fn responses_are_parsed() {
+        let response: EmbedResponse = serde_json::from_str(
+            r#"{
+                "id": "da6e531f-54c6-4a73-bf92-f60566d8d753",
+                "embeddings": {"float": [[0.016296387, -0.008354187, -0.04699707]]},
+                "texts": ["User lives in Berlin"],
+                "meta": {"api_version": {"version": "2"}, "billed_units": {"input_tokens": 4}}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            response.embeddings.float,
+            [[0.016296387, -0.008354187, -0.04699707]]
+        );
+        assert!(response.embeddings.int8.is_empty());
+
+        let response: EmbedResponse =
+            serde_json::from_str(r#"{"id": "1", "embeddings": {"int8": [[12, -5, 127]]}}"#)
+                .unwrap();
+        assert_eq!(response.embeddings.int8, [[12, -5, 127]]);
+    }
+}
@@ -0,0 +1,276 @@
+//! Embedding memoization, keyed by a hash of the embedded content.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::embed::Embedder;
+
+type ContentHash = [u8; 32];
+
+/// An embedder wrapper that memoizes embeddings, keyed by a hash of the model ID and the embedded content.
+/// This avoids repeated (potentially paid) API calls when the same text gets embedded multiple times.
+///
+/// Embeddings are kept in an in-memory LRU cache and can optionally be persisted to disk, so that they survive restarts.
+/// Queries and documents are cached separately, as some models embed them asymmetrically.
+pub struct CachedEmbedder<E> {
+    inner: E,
+    cache: Mutex<LruCache>,
+    disk_cache_dir: Option<PathBuf>,
+}
+
+impl<E> CachedEmbedder<E>
+where
+    E: Embedder,
+{
+    /// Wraps an embedder with an in-memory cache holding up to `capacity` embeddings.
+    pub fn new(inner: E, capacity: usize) -> Self {
+        Self {
+            inner,
+            cache: Mutex::new(LruCache::new(capacity)),
+            disk_cache_dir: None,
+        }
+    }
+
+    /// Additionally persist embeddings to files in a given directory. The directory is created if it doesn't exist.
+    /// Embeddings that fall out of the in-memory cache are loaded back from disk instead of being re-embedded.
+    pub fn with_disk_cache<P>(mut self, dir: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        self.disk_cache_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Get a reference to the wrapped embedder.
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    /// Consumes the wrapper, returning the wrapped embedder.
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+
+    /// The number of embeddings currently held in memory.
+    pub fn len(&self) -> usize {
+        self.cache
+            .lock()
+            .map(|cache| cache.entries.len())
+            .unwrap_or(0)
+    }
+
+    /// Whether or not the in-memory cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Clears the in-memory cache. The disk cache (if any) is left untouched.
+    pub fn clear(&self) {
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.entries.clear();
+        }
+    }
+
+    async fn cached<F, Fut>(
+        &self,
+        input_kind: &str,
+        input: &str,
+        embed: F,
+    ) -> Result<Vec<f32>, crate::Error>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Vec<f32>, crate::Error>>,
+    {
+        let key = self.content_hash(input_kind, input);
+
+        if let Some(embedding) = self.lock()?.get(&key) {
+            return Ok(embedding);
+        }
+
+        if let Some(embedding) = self.read_from_disk(&key) {
+            self.lock()?.insert(key, embedding.clone());
+            return Ok(embedding);
+        }
+
+        let embedding = embed().await?;
+        self.write_to_disk(&key, &embedding);
+        self.lock()?.insert(key, embedding.clone());
+
+        Ok(embedding)
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, LruCache>, crate::Error> {
+        self.cache
+            .lock()
+            .map_err(|_| crate::Error::custom("Embedding cache mutex was poisoned"))
+    }
+
+    fn content_hash(&self, input_kind: &str, input: &str) -> ContentHash {
+        let mut hasher = Sha256::new();
+        // The type name is only a fallback for the cache key, so if it changes the worst case is a cache miss
+        let model_id = self
+            .inner
+            .model_id()
+            .unwrap_or_else(|| std::any::type_name::<E>().to_string());
+        hasher.update(model_id.as_bytes());
+        hasher.update([0]);
+        hasher.update(input_kind.as_bytes());
+        hasher.update([0]);
+        hasher.update(input.as_bytes());
+        hasher.finalize().into()
+    }
+
+    fn disk_path(&self, key: &ContentHash) -> Option<PathBuf> {
+        let hex: String = key.iter().map(|byte| format!("{byte:02x}")).collect();
+        self.disk_cache_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{hex}.bin")))
+    }
+
+    fn read_from_disk(&self, key: &ContentHash) -> Option<Vec<f32>> {
+        let bytes = std::fs::read(self.disk_path(key)?).ok()?;
+
+        let embedding = bytes
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect();
+
+        Some(embedding)
+    }
+
+    /// Failing to write to the disk cache isn't fatal, as the embedding is still cached in memory.
+    fn write_to_disk(&self, key: &ContentHash, embedding: &[f32]) {
+        let Some(path) = self.disk_path(key) else {
+            return;
+        };
+
+        let bytes: Vec<u8> = embedding.iter().flat_map(|x| x.to_le_bytes()).collect();
+        let res = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, bytes));
+
+        #[cfg(feature = "tracing")]
+        if let Err(err) = res {
+            tracing::warn!(path = %path.display(), error = %err, "failed to write embedding to disk cache");
+        }
+        #[cfg(not(feature = "tracing"))]
+        let _ = res;
+    }
+}
+
+impl<E> Embedder for CachedEmbedder<E>
+where
+    E: Embedder,
+{
+    async fn embed_text(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        self.cached("text", input, || self.inner.embed_text(input))
+            .await
+    }
+
+    async fn embed_query(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        self.cached("query", input, || self.inner.embed_query(input))
+            .await
+    }
+
+    async fn embed_document(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        self.cached("document", input, || self.inner.embed_document(input))
+            .await
+    }
+
+    fn dims(&self) -> Option<usize> {
+        self.inner.dims()
+    }
+
+    fn max_input_tokens(&self) -> Option<usize> {
+        self.inner.max_input_tokens()
+    }
+
+    fn model_id(&self) -> Option<String> {
+        self.inner.model_id()
+    }
+}
+
+/// A minimal least-recently-used cache. Eviction is a linear scan, which is negligible next to the cost of creating an embedding.
+struct LruCache {
+    capacity: usize,
+    entries: HashMap<ContentHash, (Vec<f32>, u64)>,
+    tick: u64,
+}
+
+impl LruCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            tick: 0,
+        }
+    }
+
+    fn get(&mut self, key: &ContentHash) -> Option<Vec<f32>> {
+        self.tick += 1;
+        let (embedding, last_used) = self.entries.get_mut(key)?;
+        *last_used = self.tick;
+
+        Some(embedding.clone())
+    }
+
+    fn insert(&mut self, key: ContentHash, embedding: Vec<f32>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&key) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| *key);
+
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+
+        self.tick += 1;
+        self.entries.insert(key, (embedding, self.tick));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct CountingEmbedder {
+        calls: AtomicUsize,
+    }
+
+    impl Embedder for CountingEmbedder {
+        async fn embed_text(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![input.len() as f32])
+        }
+    }
+
+    #[tokio::test]
+    async fn repeated_inputs_are_only_embedded_once() {
+        let embedder = CachedEmbedder::new(CountingEmbedder::default(), 1);
+
+        embedder.embed_text("hello").await.unwrap();
+        embedder.embed_text("hello").await.unwrap();
+        assert_eq!(embedder.inner().calls.load(Ordering::SeqCst), 1);
+
+        // Evicts "hello", as the cache only holds a single embedding
+        embedder.embed_text("goodbye").await.unwrap();
+        embedder.embed_text("hello").await.unwrap();
+        assert_eq!(embedder.inner().calls.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,385 @@
+//! Failover between embedders.
+
+use std::sync::{Mutex, OnceLock};
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::embed::Embedder;
+
+/// An embedder that tries a primary embedder, falling over to a fallback embedder on error.
+/// Chains of more than two embedders can be created with [`FallbackEmbedder::or`].
+///
+/// Every embedder in the chain must produce embeddings with identical dimensions: embeddings with unexpected dimensions are treated as failures.
+/// The expected dimensions can be set with [`FallbackEmbedder::expected_dims`], otherwise they are taken from the first successful embedding.
+/// Every embedder must also report the same model ID (eg, the same model served from a different provider or region), as stored memories are tagged with it.
+/// Chains mixing models are refused when they're created, as their embeddings wouldn't share an embedding space.
+///
+/// Once the primary embedder fails `failure_threshold` times in a row, it is skipped for the duration of the cooldown before being tried again.
+pub struct FallbackEmbedder<P, F> {
+    primary: P,
+    fallback: F,
+    primary_health: Mutex<EmbedderHealth>,
+    fallback_health: Mutex<EmbedderHealth>,
+    failure_threshold: u32,
+    cooldown: TimeDelta,
+    dims: OnceLock<usize>,
+}
+
+/// Health information for an embedder in a [`FallbackEmbedder`].
+#[derive(Clone, Debug, Default)]
+pub struct EmbedderHealth {
+    /// The number of failures since the last success.
+    pub consecutive_failures: u32,
+    /// The total number of successful embeddings.
+    pub total_successes: u64,
+    /// The total number of failed embeddings.
+    pub total_failures: u64,
+    /// The error message of the most recent failure.
+    pub last_error: Option<String>,
+    /// When the most recent failure occurred.
+    pub last_failure: Option<DateTime<Utc>>,
+}
+
+impl EmbedderHealth {
+    /// Whether or not the last attempt to use the embedder was successful.
+    pub fn is_healthy(&self) -> bool {
+        self.consecutive_failures == 0
+    }
+
+    fn is_available(&self, failure_threshold: u32, cooldown: TimeDelta) -> bool {
+        if self.consecutive_failures < failure_threshold {
+            return true;
+        }
+
+        self.last_failure
+            .is_none_or(|last_failure| Utc::now() - last_failure >= cooldown)
+    }
+
+    fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.total_successes += 1;
+    }
+
+    fn record_failure(&mut self, err: &crate::Error) {
+        self.consecutive_failures += 1;
+        self.total_failures += 1;
+        self.last_error = Some(err.to_string());
+        self.last_failure = Some(Utc::now());
+    }
+}
+
+#[derive(Clone, Copy)]
+enum EmbedInput {
+    Text,
+    Query,
+    Document,
+}
+
+impl<P, F> FallbackEmbedder<P, F>
+where
+    P: Embedder,
+    F: Embedder,
+{
+    /// Creates a new fallback embedder. By default, the primary embedder is skipped for 30 seconds after 3 consecutive failures.
+    /// Returns an error if the embedders report different model IDs.
+    pub fn new(primary: P, fallback: F) -> Result<Self, crate::Error> {
+        let (primary_model, fallback_model) = (primary.model_id(), fallback.model_id());
+        if primary_model != fallback_model {
+            return Err(crate::Error::Custom(format!(
+                "Fallback embedder serves model {}, but the primary embedder serves {}",
+                fallback_model.as_deref().unwrap_or("<unknown>"),
+                primary_model.as_deref().unwrap_or("<unknown>"),
+            )));
+        }
+
+        Ok(Self {
+            primary,
+            fallback,
+            primary_health: Mutex::new(EmbedderHealth::default()),
+            fallback_health: Mutex::new(EmbedderHealth::default()),
+            failure_threshold: 3,
+            cooldown: TimeDelta::seconds(30),
+            dims: OnceLock::new(),
+        })
+    }
+
+    /// Adds another embedder to the end of the chain.
+    /// Returns an error if the new embedder reports a different model ID from the rest of the chain.
+    pub fn or<F2>(self, fallback: F2) -> Result<FallbackEmbedder<Self, F2>, crate::Error>
+    where
+        F2: Embedder,
+    {
+        let dims = self.dims.get().copied();
+        let chain = FallbackEmbedder::new(self, fallback)?;
+        if let Some(dims) = dims {
+            let _ = chain.dims.set(dims);
+        }
+
+        Ok(chain)
+    }
+
+    /// Sets the expected dimensions of embeddings.
+    pub fn expected_dims(self, dims: usize) -> Self {
+        let _ = self.dims.set(dims);
+        self
+    }
+
+    /// Sets how many consecutive failures the primary embedder can have before it gets skipped. Defaults to 3.
+    pub fn failure_threshold(mut self, failure_threshold: u32) -> Self {
+        self.failure_threshold = failure_threshold.max(1);
+        self
+    }
+
+    /// Sets how long an unhealthy primary embedder gets skipped for. Defaults to 30 seconds.
+    pub fn cooldown(mut self, cooldown: std::time::Duration) -> Self {
+        self.cooldown = TimeDelta::from_std(cooldown).unwrap_or(TimeDelta::MAX);
+        self
+    }
+
+    /// Health information for the primary embedder.
+    pub fn primary_health(&self) -> EmbedderHealth {
+        lock_health(&self.primary_health)
+    }
+
+    /// Health information for the fallback embedder.
+    pub fn fallback_health(&self) -> EmbedderHealth {
+        lock_health(&self.fallback_health)
+    }
+
+    async fn embed(&self, input: &str, kind: EmbedInput) -> Result<Vec<f32>, crate::Error> {
+        let primary_available = self
+            .primary_health
+            .lock()
+            .map(|health| health.is_available(self.failure_threshold, self.cooldown))
+            .unwrap_or(true);
+
+        if primary_available {
+            match self
+                .attempt(&self.primary, &self.primary_health, input, kind)
+                .await
+            {
+                Ok(embedding) => return Ok(embedding),
+                Err(_err) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(error = %_err, "primary embedder failed, falling back");
+                }
+            }
+        }
+
+        self.attempt(&self.fallback, &self.fallback_health, input, kind)
+            .await
+    }
+
+    async fn attempt<T>(
+        &self,
+        embedder: &T,
+        health: &Mutex<EmbedderHealth>,
+        input: &str,
+        kind: EmbedInput,
+    ) -> Result<Vec<f32>, crate::Error>
+    where
+        T: Embedder,
+    {
+        let res = match kind {
+            EmbedInput::Text => embedder.embed_text(input).await,
+            EmbedInput::Query => embedder.embed_query(input).await,
+            EmbedInput::Document => embedder.embed_document(input).await,
+        }
+        .and_then(|embedding| {
+            let expected = *self.dims.get_or_init(|| embedding.len());
+            if embedding.len() == expected {
+                Ok(embedding)
+            } else {
+                Err(crate::Error::Custom(format!(
+                    "Embedder {} returned {} dimensions, expected {expected}",
+                    embedder
+                        .model_id()
+                        .unwrap_or_else(|| std::any::type_name::<T>().to_string()),
+                    embedding.len()
+                )))
+            }
+        });
+
+        if let Ok(mut health) = health.lock() {
+            match &res {
+                Ok(_) => health.record_success(),
+                Err(err) => health.record_failure(err),
+            }
+        }
+
+        res
+    }
+}
+
+impl<P, F> Embedder for FallbackEmbedder<P, F>
+where
+    P: Embedder,
+    F: Embedder,
+{
+    async fn embed_text(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        self.embed(input, EmbedInput::Text).await
+    }
+
+    async fn embed_query(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        self.embed(input, EmbedInput::Query).await
+    }
+
+    async fn embed_document(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        self.embed(input, EmbedInput::Document).await
+    }
+
+    fn dims(&self) -> Option<usize> {
+        self.dims
+            .get()
+            .copied()
+            .or_else(|| self.primary.dims())
+            .or_else(|| self.fallback.dims())
+    }
+
+    /// The smallest token limit in the chain, so that inputs fit every embedder.
+    fn max_input_tokens(&self) -> Option<usize> {
+        match (
+            self.primary.max_input_tokens(),
+            self.fallback.max_input_tokens(),
+        ) {
+            (Some(primary), Some(fallback)) => Some(primary.min(fallback)),
+            (primary, fallback) => primary.or(fallback),
+        }
+    }
+
+    /// The model ID shared by every embedder in the chain.
+    fn model_id(&self) -> Option<String> {
+        self.primary.model_id()
+    }
+}
+
+fn lock_health(health: &Mutex<EmbedderHealth>) -> EmbedderHealth {
+    health
+        .lock()
+        .map(|health| health.clone())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use super::*;
+
+    struct ScriptedEmbedder {
+        value: f32,
+        dims: usize,
+        model: Option<&'static str>,
+        failing: AtomicBool,
+        calls: AtomicUsize,
+    }
+
+    impl ScriptedEmbedder {
+        fn new(value: f32) -> Self {
+            Self {
+                value,
+                dims: 2,
+                model: Some("model"),
+                failing: AtomicBool::new(false),
+                calls: AtomicUsize::new(0),
+            }
+        }
+
+        fn failing(self) -> Self {
+            self.failing.store(true, Ordering::SeqCst);
+            self
+        }
+
+        fn calls(&self) -> usize {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    impl Embedder for ScriptedEmbedder {
+        async fn embed_text(&self, _input: &str) -> Result<Vec<f32>, crate::Error> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.failing.load(Ordering::SeqCst) {
+                return Err(crate::Error::custom("unavailable"));
+            }
+
+            Ok(vec![self.value; self.dims])
+        }
+
+        fn model_id(&self) -> Option<String> {
+            self.model.map(str::to_string)
+        }
+    }
+
+    #[tokio::test]
+    async fn failures_fall_over_to_the_fallback() {
+        let embedder = FallbackEmbedder::new(
+            ScriptedEmbedder::new(1.0).failing(),
+            ScriptedEmbedder::new(2.0),
+        )
+        .unwrap();
+
+        assert_eq!(embedder.embed_text("hello").await.unwrap(), [2.0, 2.0]);
+
+        let primary = embedder.primary_health();
+        assert!(!primary.is_healthy());
+        assert_eq!(primary.total_failures, 1);
+        assert_eq!(primary.last_error.as_deref(), Some("unavailable"));
+        assert_eq!(embedder.fallback_health().total_successes, 1);
+
+        // The primary embedder is used again once it recovers
+        embedder.primary.failing.store(false, Ordering::SeqCst);
+        assert_eq!(embedder.embed_text("hello").await.unwrap(), [1.0, 1.0]);
+        assert!(embedder.primary_health().is_healthy());
+        assert_eq!(embedder.fallback.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn unhealthy_primaries_are_skipped_until_the_cooldown_passes() {
+        let embedder = FallbackEmbedder::new(
+            ScriptedEmbedder::new(1.0).failing(),
+            ScriptedEmbedder::new(2.0),
+        )
+        .unwrap()
+        .failure_threshold(2);
+
+        for _ in 0..4 {
+            embedder.embed_text("hello").await.unwrap();
+        }
+        // The primary is skipped after reaching the failure threshold
+        assert_eq!(embedder.primary.calls(), 2);
+        assert_eq!(embedder.fallback.calls(), 4);
+
+        // Once the cooldown has passed, the primary gets tried again
+        let embedder = embedder.cooldown(Duration::ZERO);
+        embedder.primary.failing.store(false, Ordering::SeqCst);
+        assert_eq!(embedder.embed_text("hello").await.unwrap(), [1.0, 1.0]);
+        assert_eq!(embedder.primary.calls(), 3);
+    }
+
+    #[tokio::test]
+    async fn embeddings_with_unexpected_dimensions_are_failures() {
+        let mut fallback = ScriptedEmbedder::new(2.0);
+        fallback.dims = 3;
+        let embedder = FallbackEmbedder::new(ScriptedEmbedder::new(1.0).failing(), fallback)
+            .unwrap()
+            .expected_dims(2);
+
+        assert!(embedder.embed_text("hello").await.is_err());
+        assert_eq!(embedder.fallback_health().total_failures, 1);
+        assert_eq!(embedder.dims(), Some(2));
+    }
+
+    #[test]
+    fn embedders_serving_different_models_are_refused() {
+        let mut other = ScriptedEmbedder::new(2.0);
+        other.model = Some("other-model");
+        assert!(FallbackEmbedder::new(ScriptedEmbedder::new(1.0), other).is_err());
+
+        let mut untagged = ScriptedEmbedder::new(2.0);
+        untagged.model = None;
+        let chain =
+            FallbackEmbedder::new(ScriptedEmbedder::new(1.0), ScriptedEmbedder::new(2.0)).unwrap();
+        assert!(chain.or(untagged).is_err());
+    }
+}
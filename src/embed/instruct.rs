@@ -0,0 +1,158 @@
+//! Instruction-prefixed asymmetric embedding.
+
+use crate::embed::Embedder;
+
+/// An embedder wrapper that prepends instructions to queries and documents before embedding them.
+///
+/// Several model families are trained with these prefixes and perform noticeably worse without them, for example:
+/// - E5: `"query: "` and `"passage: "` (see [`InstructEmbedder::e5`])
+/// - BGE: `"Represent this sentence for searching relevant passages: "` for queries only (see [`InstructEmbedder::bge`])
+/// - GTE/Qwen: `"Instruct: <task>\nQuery: "` for queries only
+///
+/// [`crate::memory::manager::MemoryManager`] embeds stored memories using the document prefix, and retrieval queries using the query prefix.
+pub struct InstructEmbedder<E> {
+    inner: E,
+    query_prefix: String,
+    document_prefix: String,
+}
+
+impl<E> InstructEmbedder<E>
+where
+    E: Embedder,
+{
+    /// Wraps an embedder with no prefixes. Set them with [`InstructEmbedder::query_prefix`] and [`InstructEmbedder::document_prefix`].
+    pub fn new(inner: E) -> Self {
+        Self {
+            inner,
+            query_prefix: String::new(),
+            document_prefix: String::new(),
+        }
+    }
+
+    /// Wraps an embedder using the prefixes for E5 models.
+    pub fn e5(inner: E) -> Self {
+        Self::new(inner)
+            .query_prefix("query: ")
+            .document_prefix("passage: ")
+    }
+
+    /// Wraps an embedder using the query instruction for English BGE models. Documents aren't prefixed.
+    pub fn bge(inner: E) -> Self {
+        Self::new(inner).query_prefix("Represent this sentence for searching relevant passages: ")
+    }
+
+    /// Sets the prefix prepended to queries.
+    pub fn query_prefix<S>(mut self, prefix: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.query_prefix = prefix.into();
+        self
+    }
+
+    /// Sets the prefix prepended to documents.
+    pub fn document_prefix<S>(mut self, prefix: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.document_prefix = prefix.into();
+        self
+    }
+
+    /// Get a reference to the wrapped embedder.
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    /// Consumes the wrapper, returning the wrapped embedder.
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+}
+
+impl<E> Embedder for InstructEmbedder<E>
+where
+    E: Embedder,
+{
+    /// Embeds text as a document.
+    async fn embed_text(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        self.embed_document(input).await
+    }
+
+    async fn embed_query(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        let input = format!("{}{input}", self.query_prefix);
+        self.inner.embed_query(&input).await
+    }
+
+    async fn embed_document(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        let input = format!("{}{input}", self.document_prefix);
+        self.inner.embed_document(&input).await
+    }
+
+    fn dims(&self) -> Option<usize> {
+        self.inner.dims()
+    }
+
+    fn max_input_tokens(&self) -> Option<usize> {
+        self.inner.max_input_tokens()
+    }
+
+    fn model_id(&self) -> Option<String> {
+        self.inner.model_id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// Records every input it's given.
+    #[derive(Default)]
+    struct RecordInputs(Mutex<Vec<String>>);
+
+    impl Embedder for RecordInputs {
+        async fn embed_text(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+            self.0.lock().unwrap().push(input.to_string());
+
+            Ok(vec![1.0])
+        }
+    }
+
+    #[tokio::test]
+    async fn prefixes_are_applied_by_input_type() {
+        let embedder = InstructEmbedder::e5(RecordInputs::default());
+        embedder
+            .embed_query("where does the user live?")
+            .await
+            .unwrap();
+        embedder
+            .embed_document("User lives in Berlin")
+            .await
+            .unwrap();
+        embedder.embed_text("User has a cat").await.unwrap();
+
+        assert_eq!(
+            *embedder.inner().0.lock().unwrap(),
+            [
+                "query: where does the user live?",
+                "passage: User lives in Berlin",
+                "passage: User has a cat"
+            ]
+        );
+
+        // BGE only prefixes queries
+        let embedder = InstructEmbedder::bge(RecordInputs::default());
+        embedder.embed_query("cats").await.unwrap();
+        embedder.embed_document("User has a cat").await.unwrap();
+
+        assert_eq!(
+            embedder.into_inner().0.into_inner().unwrap(),
+            [
+                "Represent this sentence for searching relevant passages: cats",
+                "User has a cat"
+            ]
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use crate::wasm::{WasmCompatSend, WasmCompatSync};
+
+pub mod cached;
+pub mod fallback;
+pub mod instruct;
+pub mod multimodal;
+pub mod normalized;
+pub mod preprocess;
+pub mod quantized;
+pub mod sparse;
+#[cfg(feature = "tokenizers")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokenizers")))]
+pub mod token_limit;
+pub mod truncated;
+
+pub use cached::CachedEmbedder;
+pub use fallback::FallbackEmbedder;
+pub use instruct::InstructEmbedder;
+pub use multimodal::{ImageSource, MultimodalEmbedder};
+pub use normalized::NormalizedEmbedder;
+pub use preprocess::{PreprocessedEmbedder, Preprocessor, PreprocessorChain};
+pub use quantized::{QuantizedEmbedder, QuantizedVector};
+pub use sparse::{SparseEmbedder, SparseVector};
+#[cfg(feature = "tokenizers")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokenizers")))]
+pub use token_limit::TokenLimitEmbedder;
+pub use truncated::TruncatedEmbedder;
+
+#[cfg(feature = "rig")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rig")))]
+pub use rig::RigEmbedder;
+
+/// A trait for generically abstracting embeddings over different kinds of embedder types (whether local or managed models, or if you're using a pipeline).
+pub trait Embedder: WasmCompatSend + WasmCompatSync {
+    fn embed_text(
+        &self,
+        input: &str,
+    ) -> impl Future<Output = Result<Vec<f32>, crate::Error>> + WasmCompatSend;
+
+    /// Embeds a search query. Used by [`crate::memory::manager::MemoryManager`] when retrieving memories.
+    /// Defaults to [`Embedder::embed_text`]. Override this for models that embed queries and documents asymmetrically.
+    fn embed_query(
+        &self,
+        input: &str,
+    ) -> impl Future<Output = Result<Vec<f32>, crate::Error>> + WasmCompatSend {
+        self.embed_text(input)
+    }
+
+    /// Embeds a document to be stored. Used by [`crate::memory::manager::MemoryManager`] when storing memories.
+    /// Defaults to [`Embedder::embed_text`]. Override this for models that embed queries and documents asymmetrically.
+    fn embed_document(
+        &self,
+        input: &str,
+    ) -> impl Future<Output = Result<Vec<f32>, crate::Error>> + WasmCompatSend {
+        self.embed_text(input)
+    }
+
+    /// The dimensions of the embeddings created by this embedder, if known ahead of time.
+    /// Used by [`crate::memory::manager::MemoryManagerBuilder::build`] to catch dimension mismatches with the storage before anything is inserted. Defaults to `None`.
+    ///
+    /// This returns an `Option` because some embedders can't know their dimensions without embedding something, which this (synchronous) method can't do: eg, API embedders given a model name they don't recognise.
+    /// Every built-in embedder returns `Some` for the models it knows about, and API embedders can be told the dimensions of any other model (eg, `OpenAIEmbedder::dimensions`).
+    fn dims(&self) -> Option<usize> {
+        None
+    }
+
+    /// The maximum number of tokens the embedding model accepts per input, if known.
+    /// Used by [`TokenLimitEmbedder`] (with the `tokenizers` feature) to truncate inputs before they get embedded. Defaults to `None`.
+    fn max_input_tokens(&self) -> Option<usize> {
+        None
+    }
+
+    /// An identifier for the embedding model (and its version), if known.
+    /// This is recorded on every stored memory so that embeddings from different models don't get mixed together.
+    /// Memories embedded without a model identifier are untagged, and are never treated as mismatched. Defaults to `None`.
+    fn model_id(&self) -> Option<String> {
+        None
+    }
+}
+
+/// A no-op struct for the embedder type.
+/// Attempted usage will result in a `NoOp` error as the purpose of this type is essentially to assist with generic builder typing.
+pub struct EmbedderNotSet;
+
+impl Embedder for EmbedderNotSet {
+    async fn embed_text(&self, _: &str) -> Result<Vec<f32>, crate::Error> {
+        Err(crate::Error::NoOp)
+    }
+}
+
+#[cfg(feature = "rig")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rig")))]
+mod rig {
+    use super::Embedder;
+    use rig::{
+        embeddings::{EmbeddingError, EmbeddingModel},
+        http_client,
+    };
+
+    pub struct RigEmbedder<T>
+    where
+        T: EmbeddingModel,
+    {
+        inner: T,
+        model_id: Option<String>,
+    }
+
+    impl<T> RigEmbedder<T>
+    where
+        T: EmbeddingModel,
+    {
+        pub fn new(inner: T) -> Self {
+            Self {
+                inner,
+                model_id: None,
+            }
+        }
+
+        /// Sets the model identifier recorded on stored memories (eg, "text-embedding-3-small").
+        /// Rig embedding models don't expose their name, so memories are left untagged unless this is set.
+        pub fn with_model_id<S>(mut self, model_id: S) -> Self
+        where
+            S: Into<String>,
+        {
+            self.model_id = Some(model_id.into());
+            self
+        }
+    }
+
+    impl<T> Embedder for RigEmbedder<T>
+    where
+        T: EmbeddingModel,
+    {
+        #[cfg_attr(
+            feature = "tracing",
+            tracing::instrument(name = "rig_embed_text", skip_all, fields(input_len = input.len()))
+        )]
+        async fn embed_text(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+            let res = self
+                .inner
+                .embed_text(input)
+                .await?
+                .vec
+                .into_iter()
+                .map(|x| x as f32)
+                .collect();
+
+            Ok(res)
+        }
+
+        fn dims(&self) -> Option<usize> {
+            Some(self.inner.ndims())
+        }
+
+        fn model_id(&self) -> Option<String> {
+            self.model_id.clone()
+        }
+    }
+
+    impl From<EmbeddingError> for crate::Error {
+        fn from(err: EmbeddingError) -> Self {
+            let retryable = match &err {
+                EmbeddingError::HttpError(err) => match err {
+                    http_client::Error::InvalidStatusCode(status)
+                    | http_client::Error::InvalidStatusCodeWithMessage(status, _) => {
+                        status.as_u16() == 408 || status.as_u16() == 429 || status.is_server_error()
+                    }
+                    // Connection errors and timeouts
+                    http_client::Error::Instance(_) | http_client::Error::StreamEnded => true,
+                    _ => false,
+                },
+                // Providers return error responses (including rate limits) as a message, without the status code
+                EmbeddingError::ProviderError(message) => is_transient_message(message),
+                _ => false,
+            };
+
+            crate::Error::embedding(&err.to_string(), retryable)
+        }
+    }
+
+    fn is_transient_message(message: &str) -> bool {
+        let message = message.to_lowercase();
+
+        [
+            "rate limit",
+            "rate_limit",
+            "too many requests",
+            "overloaded",
+            "429",
+            "503",
+        ]
+        .iter()
+        .any(|pattern| message.contains(pattern))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use rig::embeddings::EmbeddingError;
+
+        #[test]
+        fn rate_limits_are_retryable() {
+            let err = crate::Error::from(EmbeddingError::ProviderError(
+                "Rate limit reached for text-embedding-3-small".to_string(),
+            ));
+            assert!(matches!(
+                err,
+                crate::Error::Embedding {
+                    retryable: true,
+                    ..
+                }
+            ));
+
+            let err = crate::Error::from(EmbeddingError::ResponseError(
+                "Response data length does not match input length".to_string(),
+            ));
+            assert!(matches!(
+                err,
+                crate::Error::Embedding {
+                    retryable: false,
+                    ..
+                }
+            ));
+        }
+    }
+}
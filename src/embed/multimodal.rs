@@ -0,0 +1,41 @@
+//! Image embeddings for multimodal memory.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{embed::Embedder, wasm::WasmCompatSend};
+
+/// An image that can be embedded and attached to a memory.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ImageSource {
+    /// A URI pointing at the image (eg, a file path, `file://` URI or `https://` URL).
+    Uri(String),
+    /// Raw (encoded) image data, eg the contents of a PNG or JPEG file.
+    Bytes(Vec<u8>),
+}
+
+impl ImageSource {
+    /// Returns the local file path of the image, if the image is a file path or `file://` URI.
+    pub fn as_path(&self) -> Option<&str> {
+        let Self::Uri(uri) = self else {
+            return None;
+        };
+
+        match uri.split_once("://") {
+            Some(("file", path)) => Some(path),
+            Some(_) => None,
+            None => Some(uri),
+        }
+    }
+}
+
+/// An embedder that can embed images into the same embedding space as text (eg, CLIP).
+///
+/// As text and images share an embedding space, text queries can recall image memories and vice versa.
+/// Memories containing images can be stored with [`crate::memory::manager::MemoryManager::store_image`].
+pub trait MultimodalEmbedder: Embedder {
+    /// Embeds an image.
+    fn embed_image(
+        &self,
+        image: &ImageSource,
+    ) -> impl Future<Output = Result<Vec<f32>, crate::Error>> + WasmCompatSend;
+}
@@ -0,0 +1,107 @@
+//! L2 normalization of embeddings.
+
+use crate::embed::Embedder;
+
+/// The tolerance used when checking whether an embedding is normalized.
+const NORM_TOLERANCE: f32 = 1e-3;
+
+/// An embedder wrapper that L2-normalizes embeddings (ie, scales them to unit length).
+///
+/// Several providers return unnormalized embeddings. Cosine similarity doesn't depend on the magnitude of embeddings, but dot-product based backends (and any scoring that mixes similarity with other signals) do.
+pub struct NormalizedEmbedder<E> {
+    inner: E,
+}
+
+impl<E> NormalizedEmbedder<E>
+where
+    E: Embedder,
+{
+    /// Wraps an embedder, normalizing its embeddings.
+    pub fn new(inner: E) -> Self {
+        Self { inner }
+    }
+
+    /// Get a reference to the wrapped embedder.
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    /// Consumes the wrapper, returning the wrapped embedder.
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+}
+
+impl<E> Embedder for NormalizedEmbedder<E>
+where
+    E: Embedder,
+{
+    async fn embed_text(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        Ok(normalize(self.inner.embed_text(input).await?))
+    }
+
+    async fn embed_query(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        Ok(normalize(self.inner.embed_query(input).await?))
+    }
+
+    async fn embed_document(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        Ok(normalize(self.inner.embed_document(input).await?))
+    }
+
+    fn dims(&self) -> Option<usize> {
+        self.inner.dims()
+    }
+
+    fn max_input_tokens(&self) -> Option<usize> {
+        self.inner.max_input_tokens()
+    }
+
+    fn model_id(&self) -> Option<String> {
+        self.inner.model_id()
+    }
+}
+
+/// Scales an embedding to unit length. Zero vectors are returned as-is.
+pub fn normalize(mut embedding: Vec<f32>) -> Vec<f32> {
+    let norm = l2_norm(&embedding);
+    if norm > 0.0 {
+        embedding.iter_mut().for_each(|x| *x /= norm);
+    }
+
+    embedding
+}
+
+/// Whether or not an embedding has unit length (within a small tolerance).
+pub fn is_normalized(embedding: &[f32]) -> bool {
+    (l2_norm(embedding) - 1.0).abs() <= NORM_TOLERANCE
+}
+
+fn l2_norm(embedding: &[f32]) -> f32 {
+    embedding.iter().map(|x| x * x).sum::<f32>().sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::LetterEmbedder;
+
+    #[tokio::test]
+    async fn embeddings_have_unit_length() {
+        let embedder = NormalizedEmbedder::new(LetterEmbedder);
+
+        for input in ["aaa", "hello world", ""] {
+            let embedding = embedder.embed_document(input).await.unwrap();
+            assert!(is_normalized(&embedding));
+        }
+        assert!(!is_normalized(
+            &LetterEmbedder.embed_text("aaa").await.unwrap()
+        ));
+    }
+
+    #[test]
+    fn zero_vectors_are_left_untouched() {
+        assert_eq!(normalize(vec![0.0, 0.0, 0.0]), [0.0, 0.0, 0.0]);
+        assert!(!is_normalized(&[0.0, 0.0, 0.0]));
+        assert_eq!(normalize(vec![3.0, 4.0]), [0.6, 0.8]);
+    }
+}
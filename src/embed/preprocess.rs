@@ -0,0 +1,217 @@
+//! Text preprocessing before embedding.
+
+use crate::{
+    embed::Embedder,
+    wasm::{WasmCompatSend, WasmCompatSync},
+};
+
+/// A step that cleans up text before it gets embedded.
+///
+/// Closures taking and returning a `String` can be used as preprocessors.
+pub trait Preprocessor: WasmCompatSend + WasmCompatSync {
+    fn process(&self, input: String) -> String;
+}
+
+impl<F> Preprocessor for F
+where
+    F: Fn(String) -> String + WasmCompatSend + WasmCompatSync,
+{
+    fn process(&self, input: String) -> String {
+        self(input)
+    }
+}
+
+/// Collapses runs of whitespace (including newlines) into single spaces, and trims the ends.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CollapseWhitespace;
+
+impl Preprocessor for CollapseWhitespace {
+    fn process(&self, input: String) -> String {
+        input.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// Lowercases text. Only useful for models that are case-sensitive but shouldn't be (eg, to match names regardless of casing).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Lowercase;
+
+impl Preprocessor for Lowercase {
+    fn process(&self, input: String) -> String {
+        input.to_lowercase()
+    }
+}
+
+/// Truncates text to a maximum number of characters.
+#[derive(Clone, Copy, Debug)]
+pub struct MaxLength(pub usize);
+
+impl Preprocessor for MaxLength {
+    fn process(&self, mut input: String) -> String {
+        if let Some((idx, _)) = input.char_indices().nth(self.0) {
+            input.truncate(idx);
+        }
+
+        input
+    }
+}
+
+/// Removes lines that only consist of boilerplate (compared case-insensitively, ignoring surrounding whitespace).
+/// The default phrases cover placeholders commonly found in exported chat logs.
+#[derive(Clone, Debug)]
+pub struct StripBoilerplate {
+    phrases: Vec<String>,
+}
+
+impl StripBoilerplate {
+    /// Strips lines matching any of the given phrases.
+    pub fn new<I, S>(phrases: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            phrases: phrases
+                .into_iter()
+                .map(|phrase| phrase.into().trim().to_lowercase())
+                .collect(),
+        }
+    }
+}
+
+impl Default for StripBoilerplate {
+    fn default() -> Self {
+        Self::new([
+            "<media omitted>",
+            "[image]",
+            "[attachment]",
+            "this message was deleted",
+            "(edited)",
+        ])
+    }
+}
+
+impl Preprocessor for StripBoilerplate {
+    fn process(&self, input: String) -> String {
+        input
+            .lines()
+            .filter(|line| {
+                let line = line.trim().to_lowercase();
+                !self.phrases.contains(&line)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// A chain of preprocessors, run in order.
+#[derive(Default)]
+pub struct PreprocessorChain {
+    steps: Vec<Box<dyn Preprocessor>>,
+}
+
+impl PreprocessorChain {
+    /// Creates an empty chain.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a preprocessor to the end of the chain.
+    pub fn then<P>(mut self, preprocessor: P) -> Self
+    where
+        P: Preprocessor + 'static,
+    {
+        self.steps.push(Box::new(preprocessor));
+        self
+    }
+}
+
+impl Preprocessor for PreprocessorChain {
+    fn process(&self, input: String) -> String {
+        self.steps
+            .iter()
+            .fold(input, |input, step| step.process(input))
+    }
+}
+
+/// An embedder wrapper that preprocesses text before embedding it.
+///
+/// Conversation logs are often full of formatting noise (eg, repeated newlines, chat export placeholders), which measurably hurts recall.
+pub struct PreprocessedEmbedder<E, P = PreprocessorChain> {
+    inner: E,
+    preprocessor: P,
+}
+
+impl<E, P> PreprocessedEmbedder<E, P>
+where
+    E: Embedder,
+    P: Preprocessor,
+{
+    /// Wraps an embedder with a preprocessor (eg, a [`PreprocessorChain`]).
+    pub fn new(inner: E, preprocessor: P) -> Self {
+        Self {
+            inner,
+            preprocessor,
+        }
+    }
+
+    /// Get a reference to the wrapped embedder.
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    /// Consumes the wrapper, returning the wrapped embedder.
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+}
+
+impl<E, P> Embedder for PreprocessedEmbedder<E, P>
+where
+    E: Embedder,
+    P: Preprocessor,
+{
+    async fn embed_text(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        let input = self.preprocessor.process(input.to_string());
+        self.inner.embed_text(&input).await
+    }
+
+    async fn embed_query(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        let input = self.preprocessor.process(input.to_string());
+        self.inner.embed_query(&input).await
+    }
+
+    async fn embed_document(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        let input = self.preprocessor.process(input.to_string());
+        self.inner.embed_document(&input).await
+    }
+
+    fn dims(&self) -> Option<usize> {
+        self.inner.dims()
+    }
+
+    fn max_input_tokens(&self) -> Option<usize> {
+        self.inner.max_input_tokens()
+    }
+
+    fn model_id(&self) -> Option<String> {
+        self.inner.model_id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chain_runs_steps_in_order() {
+        let chain = PreprocessorChain::new()
+            .then(StripBoilerplate::default())
+            .then(CollapseWhitespace)
+            .then(Lowercase)
+            .then(MaxLength(12));
+
+        let input = "User:   Hello\n<Media omitted>\n\n  THERE friend".to_string();
+
+        assert_eq!(chain.process(input), "user: hello ");
+    }
+}
@@ -0,0 +1,122 @@
+//! Int8-quantized embeddings, used to cut the transfer and memory costs of embeddings.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{embed::Embedder, wasm::WasmCompatSend};
+
+/// An int8-quantized embedding.
+/// Each value maps back to a float using `(value - zero_point) * scale`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QuantizedVector {
+    values: Vec<i8>,
+    scale: f32,
+    zero_point: i8,
+}
+
+impl QuantizedVector {
+    /// Creates a quantized vector from already-quantized values.
+    pub fn new(values: Vec<i8>, scale: f32, zero_point: i8) -> Self {
+        Self {
+            values,
+            scale,
+            zero_point,
+        }
+    }
+
+    /// Quantizes a float embedding, mapping its range of values onto the full int8 range.
+    pub fn quantize(embedding: &[f32]) -> Self {
+        // Always include zero in the range, so that it can be represented exactly
+        let min = embedding.iter().copied().fold(0.0, f32::min);
+        let max = embedding.iter().copied().fold(0.0, f32::max);
+
+        if max == min {
+            return Self::new(vec![0; embedding.len()], 1.0, 0);
+        }
+
+        let scale = (max - min) / 255.0;
+        let zero_point = (-128.0 - min / scale).round().clamp(-128.0, 127.0);
+
+        let values = embedding
+            .iter()
+            .map(|x| (x / scale + zero_point).round().clamp(-128.0, 127.0) as i8)
+            .collect();
+
+        Self::new(values, scale, zero_point as i8)
+    }
+
+    /// Converts the quantized vector back to a float embedding.
+    pub fn dequantize(&self) -> Vec<f32> {
+        self.values
+            .iter()
+            .map(|&x| (x as f32 - self.zero_point as f32) * self.scale)
+            .collect()
+    }
+
+    /// The quantized values.
+    pub fn values(&self) -> &[i8] {
+        &self.values
+    }
+
+    /// The size of a single quantization step.
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// The quantized value that represents zero.
+    pub fn zero_point(&self) -> i8 {
+        self.zero_point
+    }
+
+    /// The number of dimensions.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Whether or not the vector has no dimensions.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+/// An embedder that can return int8-quantized embeddings.
+///
+/// By default, float embeddings are quantized after being embedded. Embedders backed by APIs that support int8 output should override this, so that quantized embeddings are transferred end-to-end.
+/// Quantized embeddings can be stored and retrieved with [`crate::memory::manager::MemoryManager::store_quantized`] and [`crate::memory::manager::MemoryManager::retrieve_quantized`].
+pub trait QuantizedEmbedder: Embedder {
+    /// Embeds a search query as an int8-quantized embedding.
+    fn embed_query_quantized(
+        &self,
+        input: &str,
+    ) -> impl Future<Output = Result<QuantizedVector, crate::Error>> + WasmCompatSend {
+        async move {
+            let embedding = self.embed_query(input).await?;
+            Ok(QuantizedVector::quantize(&embedding))
+        }
+    }
+
+    /// Embeds a document to be stored as an int8-quantized embedding.
+    fn embed_document_quantized(
+        &self,
+        input: &str,
+    ) -> impl Future<Output = Result<QuantizedVector, crate::Error>> + WasmCompatSend {
+        async move {
+            let embedding = self.embed_document(input).await?;
+            Ok(QuantizedVector::quantize(&embedding))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::QuantizedVector;
+
+    #[test]
+    fn quantization_round_trips_within_one_step() {
+        let embedding = [0.5, -0.25, 0.0, 0.125, -1.0, 0.9];
+        let quantized = QuantizedVector::quantize(&embedding);
+
+        for (original, restored) in embedding.iter().zip(quantized.dequantize()) {
+            assert!((original - restored).abs() <= quantized.scale());
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Sparse embeddings (eg, SPLADE), used alongside dense embeddings for hybrid recall.
+
+use serde::{Deserialize, Serialize};
+
+use crate::wasm::{WasmCompatSend, WasmCompatSync};
+
+/// A sparse embedding: a list of (token) indices with non-zero weights.
+/// Indices are kept sorted so that dot products can be computed with a single merge pass.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SparseVector {
+    indices: Vec<u32>,
+    values: Vec<f32>,
+}
+
+impl SparseVector {
+    /// Creates a sparse vector from parallel lists of indices and values.
+    /// Pairs are sorted by index, and the values of duplicate indices are summed; if the lists are different lengths, the extra elements are ignored.
+    pub fn new(indices: Vec<u32>, values: Vec<f32>) -> Self {
+        let mut pairs: Vec<(u32, f32)> = indices.into_iter().zip(values).collect();
+        pairs.sort_by_key(|(index, _)| *index);
+
+        let mut merged: Vec<(u32, f32)> = Vec::with_capacity(pairs.len());
+        for (index, value) in pairs {
+            match merged.last_mut() {
+                Some((last, total)) if *last == index => *total += value,
+                _ => merged.push((index, value)),
+            }
+        }
+
+        let (indices, values) = merged.into_iter().unzip();
+
+        Self { indices, values }
+    }
+
+    /// The indices of non-zero weights.
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    /// The non-zero weights.
+    pub fn values(&self) -> &[f32] {
+        &self.values
+    }
+
+    /// The number of non-zero weights.
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Whether or not the vector has no non-zero weights.
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Computes the dot product between two sparse vectors.
+    pub fn dot(&self, other: &SparseVector) -> f32 {
+        let (mut i, mut j) = (0, 0);
+        let mut dot = 0.0;
+
+        while i < self.indices.len() && j < other.indices.len() {
+            match self.indices[i].cmp(&other.indices[j]) {
+                std::cmp::Ordering::Less => i += 1,
+                std::cmp::Ordering::Greater => j += 1,
+                std::cmp::Ordering::Equal => {
+                    dot += self.values[i] * other.values[j];
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+
+        dot
+    }
+}
+
+/// A trait for generically abstracting over sparse embedding models.
+pub trait SparseEmbedder: WasmCompatSend + WasmCompatSync {
+    fn embed_sparse(
+        &self,
+        input: &str,
+    ) -> impl Future<Output = Result<SparseVector, crate::Error>> + WasmCompatSend;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SparseVector;
+
+    #[test]
+    fn duplicate_indices_are_merged() {
+        let vector = SparseVector::new(vec![7, 2, 7, 4], vec![1.0, 2.0, 0.5, 3.0]);
+        assert_eq!(vector.indices(), [2, 4, 7]);
+        assert_eq!(vector.values(), [2.0, 3.0, 1.5]);
+
+        let other = SparseVector::new(vec![7, 5], vec![2.0, 1.0]);
+        assert_eq!(vector.dot(&other), 3.0);
+    }
+}
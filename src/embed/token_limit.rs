@@ -0,0 +1,167 @@
+//! Tokenizer-aware truncation of embedding inputs.
+//! Ensure that you have the `tokenizers` feature enabled.
+
+use tokenizers::{PostProcessor, Tokenizer};
+
+use crate::embed::Embedder;
+
+/// An embedder wrapper that truncates inputs to the embedding model's token limit before embedding them, using the model's own tokenizer.
+///
+/// Without this, over-long inputs get silently truncated by the provider (losing the end of a memory without any indication) or rejected outright.
+/// Inputs are cut on token boundaries, leaving room for any special tokens the tokenizer adds.
+pub struct TokenLimitEmbedder<E> {
+    inner: E,
+    tokenizer: Tokenizer,
+    max_tokens: usize,
+}
+
+impl<E> TokenLimitEmbedder<E>
+where
+    E: Embedder,
+{
+    /// Wraps an embedder, using its token limit (see [`Embedder::max_input_tokens`]).
+    /// Returns an error if the embedder doesn't declare a token limit; use [`TokenLimitEmbedder::with_max_tokens`] instead.
+    pub fn new(inner: E, tokenizer: Tokenizer) -> Result<Self, crate::Error> {
+        let Some(max_tokens) = inner.max_input_tokens() else {
+            return Err(crate::Error::Custom(format!(
+                "Embedder {} doesn't declare a token limit",
+                inner
+                    .model_id()
+                    .unwrap_or_else(|| std::any::type_name::<E>().to_string())
+            )));
+        };
+
+        Ok(Self::with_max_tokens(inner, tokenizer, max_tokens))
+    }
+
+    /// Wraps an embedder, truncating inputs to a given number of tokens.
+    pub fn with_max_tokens(inner: E, mut tokenizer: Tokenizer, max_tokens: usize) -> Self {
+        // Truncation is done by hand, so the tokenizer's own truncation can't hide over-long inputs
+        tokenizer.with_truncation(None).ok();
+
+        Self {
+            inner,
+            tokenizer,
+            max_tokens,
+        }
+    }
+
+    /// Loads the tokenizer from a file (`tokenizer.json`) and wraps an embedder, using its token limit.
+    pub fn from_file<P>(inner: E, tokenizer: P) -> Result<Self, crate::Error>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let tokenizer = Tokenizer::from_file(tokenizer).map_err(tokenizer_error)?;
+
+        Self::new(inner, tokenizer)
+    }
+
+    /// Get a reference to the wrapped embedder.
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    /// Consumes the wrapper, returning the wrapped embedder.
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+
+    /// Truncates text to the token limit. Text that is already within the limit is returned as-is.
+    pub fn truncate<'a>(&self, input: &'a str) -> Result<&'a str, crate::Error> {
+        let special_tokens = self
+            .tokenizer
+            .get_post_processor()
+            .map_or(0, |processor| processor.added_tokens(false));
+        let budget = self.max_tokens.saturating_sub(special_tokens);
+
+        let encoding = self
+            .tokenizer
+            .encode(input, false)
+            .map_err(tokenizer_error)?;
+        let Some(&(cutoff, _)) = encoding.get_offsets().get(budget) else {
+            return Ok(input);
+        };
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            tokens = encoding.len(),
+            max_tokens = self.max_tokens,
+            "truncated input to token limit"
+        );
+
+        let mut cutoff = cutoff.min(input.len());
+        while !input.is_char_boundary(cutoff) {
+            cutoff -= 1;
+        }
+
+        Ok(input[..cutoff].trim_end())
+    }
+}
+
+impl<E> Embedder for TokenLimitEmbedder<E>
+where
+    E: Embedder,
+{
+    async fn embed_text(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        self.inner.embed_text(self.truncate(input)?).await
+    }
+
+    async fn embed_query(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        self.inner.embed_query(self.truncate(input)?).await
+    }
+
+    async fn embed_document(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        self.inner.embed_document(self.truncate(input)?).await
+    }
+
+    fn dims(&self) -> Option<usize> {
+        self.inner.dims()
+    }
+
+    fn max_input_tokens(&self) -> Option<usize> {
+        Some(self.max_tokens)
+    }
+
+    fn model_id(&self) -> Option<String> {
+        self.inner.model_id()
+    }
+}
+
+fn tokenizer_error<E>(err: E) -> crate::Error
+where
+    E: Into<crate::error::BoxedError>,
+{
+    crate::Error::wrap("Tokenizer error", err)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use tokenizers::{
+        Tokenizer, models::wordlevel::WordLevel, pre_tokenizers::whitespace::Whitespace,
+    };
+
+    use super::TokenLimitEmbedder;
+    use crate::embed::EmbedderNotSet;
+
+    #[test]
+    fn inputs_are_cut_on_token_boundaries() {
+        let vocab: HashMap<String, u32> = [("[UNK]".to_string(), 0)].into();
+        let model = WordLevel::builder()
+            .vocab(vocab.into_iter().collect())
+            .unk_token("[UNK]".to_string())
+            .build()
+            .unwrap();
+        let mut tokenizer = Tokenizer::new(model);
+        tokenizer.with_pre_tokenizer(Some(Whitespace {}));
+
+        let embedder = TokenLimitEmbedder::with_max_tokens(EmbedderNotSet, tokenizer, 3);
+
+        assert_eq!(embedder.truncate("one two").unwrap(), "one two");
+        assert_eq!(
+            embedder.truncate("one two  three four five").unwrap(),
+            "one two  three"
+        );
+    }
+}
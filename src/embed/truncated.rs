@@ -0,0 +1,118 @@
+//! Matryoshka (MRL) dimension truncation.
+
+use crate::embed::{Embedder, normalized::normalize};
+
+/// An embedder wrapper that truncates embeddings to their first `dims` dimensions, then L2-normalizes them again.
+///
+/// This is only meaningful for models trained with Matryoshka Representation Learning (eg, OpenAI's `text-embedding-3` family, `nomic-embed-text-v1.5` or Gemini embedding models),
+/// which front-load information into the leading dimensions. Truncating a 1536-dimension embedding to 384 dimensions uses a quarter of the memory in exchange for some retrieval quality.
+pub struct TruncatedEmbedder<E> {
+    inner: E,
+    dims: usize,
+}
+
+impl<E> TruncatedEmbedder<E>
+where
+    E: Embedder,
+{
+    /// Wraps an embedder, truncating its embeddings to a given number of dimensions.
+    pub fn new(inner: E, dims: usize) -> Self {
+        Self { inner, dims }
+    }
+
+    /// The dimensions of the embeddings created by this embedder.
+    pub fn dims(&self) -> usize {
+        self.dims
+    }
+
+    /// Get a reference to the wrapped embedder.
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    /// Consumes the wrapper, returning the wrapped embedder.
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+
+    /// Checks that the truncated dimensions match the dimensions of a store (eg, [`crate::vector_store::InMemoryDB::dims`]).
+    pub fn validate_store_dims(&self, store_dims: usize) -> Result<(), crate::Error> {
+        if self.dims == store_dims {
+            Ok(())
+        } else {
+            Err(crate::Error::Custom(format!(
+                "Embeddings are truncated to {} dimensions, but the store expects {store_dims}",
+                self.dims
+            )))
+        }
+    }
+
+    fn truncate(&self, mut embedding: Vec<f32>) -> Result<Vec<f32>, crate::Error> {
+        if embedding.len() < self.dims {
+            return Err(crate::Error::Custom(format!(
+                "Cannot truncate an embedding with {} dimensions to {} dimensions",
+                embedding.len(),
+                self.dims
+            )));
+        }
+
+        embedding.truncate(self.dims);
+
+        Ok(normalize(embedding))
+    }
+}
+
+impl<E> Embedder for TruncatedEmbedder<E>
+where
+    E: Embedder,
+{
+    async fn embed_text(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        self.truncate(self.inner.embed_text(input).await?)
+    }
+
+    async fn embed_query(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        self.truncate(self.inner.embed_query(input).await?)
+    }
+
+    async fn embed_document(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        self.truncate(self.inner.embed_document(input).await?)
+    }
+
+    fn dims(&self) -> Option<usize> {
+        Some(self.dims)
+    }
+
+    fn max_input_tokens(&self) -> Option<usize> {
+        self.inner.max_input_tokens()
+    }
+
+    fn model_id(&self) -> Option<String> {
+        self.inner
+            .model_id()
+            .map(|model_id| format!("{model_id}/mrl-{}", self.dims))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{embed::normalized::is_normalized, test_util::LetterEmbedder};
+
+    #[tokio::test]
+    async fn embeddings_are_truncated_and_renormalized() {
+        let embedder = TruncatedEmbedder::new(LetterEmbedder, 2);
+
+        // "aaa" embeds to [4, 1, 1, 1]
+        let embedding = embedder.embed_text("aaa").await.unwrap();
+        assert_eq!(embedding.len(), 2);
+        assert!(is_normalized(&embedding));
+        assert!((embedding[0] / embedding[1] - 4.0).abs() < 1e-6);
+
+        assert_eq!(Embedder::dims(&embedder), Some(2));
+        assert!(embedder.validate_store_dims(2).is_ok());
+        assert!(embedder.validate_store_dims(4).is_err());
+
+        let embedder = TruncatedEmbedder::new(LetterEmbedder, 8);
+        assert!(embedder.embed_query("aaa").await.is_err());
+    }
+}
@@ -0,0 +1,486 @@
+//! Encryption at rest for memories, on top of any storage backend.
+//! Ensure that you have the `encryption` feature enabled.
+//!
+//! [`EncryptedStorage`] encrypts the content and source context of every memory (and optionally its embedding) with AES-256-GCM or XChaCha20-Poly1305 before handing it to the wrapped storage,
+//! and decrypts memories as they're read back. Memories stored before encryption was enabled are read back as-is.
+//!
+//! Metadata, tags and sparse embeddings are stored in plaintext so that the storage can still filter on them, so avoid putting sensitive data in them.
+
+use std::fmt;
+
+use aes_gcm::{
+    Aes256Gcm,
+    aead::{Aead, KeyInit},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chacha20poly1305::XChaCha20Poly1305;
+use rand::RngCore;
+
+use crate::{
+    embed::SparseVector,
+    memory::{MemoryEntry, MemoryId, MetadataEntry},
+    storage::{HealthStatus, HybridFusion, MetadataFilter, SearchResult, Storage},
+    vector_store::cosine_similarity,
+};
+
+/// Marks encrypted strings, so that plaintext memories stored before encryption was enabled can still be read.
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+
+/// The metadata key holding a memory's encrypted embedding, when embeddings are encrypted.
+const EMBEDDING_METADATA_KEY: &str = "braindump_encrypted_embedding";
+
+/// How many documents are decrypted at once when scanning for similar documents.
+const SCAN_BATCH_SIZE: usize = 100;
+
+/// A 256-bit key, along with the cipher it's used with.
+#[derive(Clone)]
+pub enum EncryptionKey {
+    Aes256Gcm([u8; 32]),
+    /// XChaCha20-Poly1305, which uses larger (192-bit) random nonces than AES-GCM, so is safer for encrypting very large numbers of memories with the same key.
+    XChaCha20Poly1305([u8; 32]),
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cipher = match self {
+            Self::Aes256Gcm(_) => "Aes256Gcm",
+            Self::XChaCha20Poly1305(_) => "XChaCha20Poly1305",
+        };
+
+        write!(f, "{cipher}([REDACTED])")
+    }
+}
+
+enum Cipher {
+    Aes256Gcm(Box<Aes256Gcm>),
+    XChaCha20Poly1305(Box<XChaCha20Poly1305>),
+}
+
+impl Cipher {
+    fn new(key: &EncryptionKey) -> Self {
+        match key {
+            EncryptionKey::Aes256Gcm(key) => Self::Aes256Gcm(Box::new(Aes256Gcm::new(key.into()))),
+            EncryptionKey::XChaCha20Poly1305(key) => {
+                Self::XChaCha20Poly1305(Box::new(XChaCha20Poly1305::new(key.into())))
+            }
+        }
+    }
+
+    /// Encrypts bytes using a random nonce, which is prepended to the ciphertext.
+    fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, crate::Error> {
+        let mut rng = rand::rng();
+        let (mut nonce, ciphertext) = match self {
+            Self::Aes256Gcm(cipher) => {
+                let mut nonce = [0u8; 12];
+                rng.fill_bytes(&mut nonce);
+                (nonce.to_vec(), cipher.encrypt(&nonce.into(), plaintext))
+            }
+            Self::XChaCha20Poly1305(cipher) => {
+                let mut nonce = [0u8; 24];
+                rng.fill_bytes(&mut nonce);
+                (nonce.to_vec(), cipher.encrypt(&nonce.into(), plaintext))
+            }
+        };
+        let ciphertext =
+            ciphertext.map_err(|_| crate::Error::custom("Failed to encrypt memory"))?;
+
+        nonce.extend(ciphertext);
+        Ok(nonce)
+    }
+
+    /// Decrypts bytes created by [`Cipher::encrypt`].
+    fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, crate::Error> {
+        let nonce_len = match self {
+            Self::Aes256Gcm(_) => 12,
+            Self::XChaCha20Poly1305(_) => 24,
+        };
+        if data.len() < nonce_len {
+            return Err(crate::Error::custom("Encrypted memory is malformed"));
+        }
+
+        let (nonce, ciphertext) = data.split_at(nonce_len);
+        let plaintext = match self {
+            Self::Aes256Gcm(cipher) => cipher.decrypt(nonce.into(), ciphertext),
+            Self::XChaCha20Poly1305(cipher) => cipher.decrypt(nonce.into(), ciphertext),
+        };
+
+        plaintext.map_err(|_| {
+            crate::Error::custom(
+                "Failed to decrypt memory. Check that the encryption key is correct",
+            )
+        })
+    }
+
+    fn encrypt_str(&self, plaintext: &[u8]) -> Result<String, crate::Error> {
+        Ok(format!(
+            "{ENCRYPTED_PREFIX}{}",
+            STANDARD.encode(self.encrypt(plaintext)?)
+        ))
+    }
+
+    /// Decrypts a string created by [`Cipher::encrypt_str`], or returns `None` if it isn't encrypted.
+    fn decrypt_str(&self, data: &str) -> Result<Option<Vec<u8>>, crate::Error> {
+        let Some(encoded) = data.strip_prefix(ENCRYPTED_PREFIX) else {
+            return Ok(None);
+        };
+
+        let data = STANDARD
+            .decode(encoded)
+            .map_err(|err| crate::Error::wrap("Encrypted memory is malformed", err))?;
+
+        self.decrypt(&data).map(Some)
+    }
+
+    fn decrypt_text(&self, data: &str) -> Result<String, crate::Error> {
+        match self.decrypt_str(data)? {
+            Some(plaintext) => String::from_utf8(plaintext)
+                .map_err(|err| crate::Error::wrap("Decrypted memory isn't valid UTF-8", err)),
+            None => Ok(data.to_string()),
+        }
+    }
+}
+
+/// A storage wrapper that encrypts memories before they're written to the wrapped storage, and decrypts them as they're read.
+///
+/// Embeddings are stored in plaintext by default, so that the wrapped storage can still search them. With [`EncryptedStorage::encrypt_embeddings`],
+/// embeddings are encrypted along with the memory and the wrapped storage only sees a placeholder, so every search decrypts and scores every stored memory (which is slow for large stores).
+pub struct EncryptedStorage<S> {
+    inner: S,
+    cipher: Cipher,
+    encrypt_embeddings: bool,
+}
+
+impl<S> EncryptedStorage<S>
+where
+    S: Storage,
+{
+    pub fn new(inner: S, key: EncryptionKey) -> Self {
+        Self {
+            inner,
+            cipher: Cipher::new(&key),
+            encrypt_embeddings: false,
+        }
+    }
+
+    /// Encrypts embeddings as well as memory contents, at the cost of slower searches. Defaults to `false`.
+    pub fn encrypt_embeddings(mut self, encrypt_embeddings: bool) -> Self {
+        self.encrypt_embeddings = encrypt_embeddings;
+        self
+    }
+
+    /// Consumes the wrapper, returning the wrapped storage.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn encrypt_payload(&self, mut entry: MemoryEntry) -> Result<MemoryEntry, crate::Error> {
+        entry.content = self.cipher.encrypt_str(entry.content.as_bytes())?;
+        entry.source_context = self.cipher.encrypt_str(entry.source_context.as_bytes())?;
+        entry
+            .metadata
+            .retain(|metadata| metadata.key() != EMBEDDING_METADATA_KEY);
+
+        Ok(entry)
+    }
+
+    fn encrypt_entry(
+        &self,
+        embedding: Vec<f32>,
+        entry: MemoryEntry,
+    ) -> Result<(Vec<f32>, MemoryEntry), crate::Error> {
+        let mut entry = self.encrypt_payload(entry)?;
+        if !self.encrypt_embeddings {
+            return Ok((embedding, entry));
+        }
+
+        let bytes: Vec<u8> = embedding.iter().flat_map(|x| x.to_le_bytes()).collect();
+        entry.metadata.push(MetadataEntry::new(
+            EMBEDDING_METADATA_KEY,
+            self.cipher.encrypt_str(&bytes)?,
+        ));
+
+        // A unit vector, so that storages requiring normalized embeddings accept it
+        let dims = embedding.len();
+        Ok((vec![1.0 / (dims as f32).sqrt(); dims], entry))
+    }
+
+    fn decrypt_result(&self, result: SearchResult) -> Result<SearchResult, crate::Error> {
+        let score = result.score();
+        let mut embedding = result.embedding_owned();
+        let mut entry = result.data_owned();
+
+        entry.content = self.cipher.decrypt_text(&entry.content)?;
+        entry.source_context = self.cipher.decrypt_text(&entry.source_context)?;
+        if let Some(idx) = entry
+            .metadata
+            .iter()
+            .position(|metadata| metadata.key() == EMBEDDING_METADATA_KEY)
+        {
+            let metadata = entry.metadata.remove(idx);
+            if let Some(bytes) = self.cipher.decrypt_str(metadata.value())? {
+                embedding = bytes
+                    .chunks_exact(4)
+                    .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                    .collect();
+            }
+        }
+
+        let result = SearchResult::new(embedding, entry);
+        Ok(match score {
+            Some(score) => result.with_score(score),
+            None => result,
+        })
+    }
+
+    fn decrypt_results(
+        &self,
+        results: Vec<SearchResult>,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        results
+            .into_iter()
+            .map(|result| self.decrypt_result(result))
+            .collect()
+    }
+
+    /// Searches by decrypting and scoring every stored document, for when the wrapped storage only holds placeholder embeddings.
+    async fn scan(
+        &self,
+        embedding: &[f32],
+        limit: usize,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        let mut best: Vec<(f32, SearchResult)> = Vec::new();
+        let mut offset = 0;
+        loop {
+            let batch = self.inner.get_oldest_page(offset, SCAN_BATCH_SIZE).await?;
+            if batch.is_empty() {
+                break;
+            }
+
+            offset += batch.len();
+            for result in batch {
+                let result = self.decrypt_result(result)?;
+                if filter.is_some_and(|filter| !filter.matches(result.data())) {
+                    continue;
+                }
+
+                best.push((cosine_similarity(embedding, result.embedding()), result));
+            }
+
+            best.sort_by(|a, b| b.0.total_cmp(&a.0));
+            best.truncate(limit);
+        }
+
+        Ok(best
+            .into_iter()
+            .map(|(score, result)| result.with_score(score))
+            .collect())
+    }
+}
+
+impl<S> Storage for EncryptedStorage<S>
+where
+    S: Storage,
+{
+    async fn insert(
+        &mut self,
+        embedding: Vec<f32>,
+        entry: MemoryEntry,
+    ) -> Result<(), crate::Error> {
+        let (embedding, entry) = self.encrypt_entry(embedding, entry)?;
+
+        self.inner.insert(embedding, entry).await
+    }
+
+    async fn insert_all(
+        &mut self,
+        entries: Vec<(Vec<f32>, MemoryEntry)>,
+    ) -> Result<(), crate::Error> {
+        let entries = entries
+            .into_iter()
+            .map(|(embedding, entry)| self.encrypt_entry(embedding, entry))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.inner.insert_all(entries).await
+    }
+
+    async fn search(
+        &self,
+        embedding: Vec<f32>,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        if self.encrypt_embeddings {
+            return self.scan(&embedding, limit, None).await;
+        }
+
+        self.decrypt_results(self.inner.search(embedding, limit).await?)
+    }
+
+    async fn search_by_id(&self, id: MemoryId) -> Result<SearchResult, crate::Error> {
+        self.decrypt_result(self.inner.search_by_id(id).await?)
+    }
+
+    async fn get_recent(&self, limit: usize) -> Result<Vec<SearchResult>, crate::Error> {
+        self.decrypt_results(self.inner.get_recent(limit).await?)
+    }
+
+    async fn delete(&mut self, id: MemoryId) -> Result<(), crate::Error> {
+        self.inner.delete(id).await
+    }
+
+    async fn delete_batch(&mut self, ids: Vec<MemoryId>) -> Result<(), crate::Error> {
+        self.inner.delete_batch(ids).await
+    }
+
+    async fn get_oldest(&self, limit: usize) -> Result<Vec<SearchResult>, crate::Error> {
+        self.decrypt_results(self.inner.get_oldest(limit).await?)
+    }
+
+    async fn get_oldest_page(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        self.decrypt_results(self.inner.get_oldest_page(offset, limit).await?)
+    }
+
+    async fn get_oldest_after(
+        &self,
+        created_at: i64,
+        id: MemoryId,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        self.decrypt_results(self.inner.get_oldest_after(created_at, id, limit).await?)
+    }
+
+    async fn get_between(&self, start: i64, end: i64) -> Result<Vec<SearchResult>, crate::Error> {
+        self.decrypt_results(self.inner.get_between(start, end).await?)
+    }
+
+    /// Updates the payload of a document. With encrypted embeddings, the stored embedding is kept as-is.
+    async fn update_payload_by_id(
+        &mut self,
+        id: MemoryId,
+        payload: MemoryEntry,
+    ) -> Result<(), crate::Error> {
+        let mut payload = self.encrypt_payload(payload)?;
+        if self.encrypt_embeddings {
+            let existing = self.inner.search_by_id(id.clone()).await?;
+            payload.metadata.extend(
+                existing
+                    .data()
+                    .metadata
+                    .iter()
+                    .filter(|metadata| metadata.key() == EMBEDDING_METADATA_KEY)
+                    .cloned(),
+            );
+        }
+
+        self.inner.update_payload_by_id(id, payload).await
+    }
+
+    async fn count(&self) -> Result<usize, crate::Error> {
+        self.inner.count().await
+    }
+
+    fn dims(&self) -> Option<usize> {
+        self.inner.dims()
+    }
+
+    fn requires_normalized_embeddings(&self) -> bool {
+        self.inner.requires_normalized_embeddings()
+    }
+
+    async fn insert_sparse(
+        &mut self,
+        id: MemoryId,
+        sparse: SparseVector,
+    ) -> Result<(), crate::Error> {
+        self.inner.insert_sparse(id, sparse).await
+    }
+
+    async fn search_hybrid(
+        &self,
+        embedding: Vec<f32>,
+        sparse: SparseVector,
+        limit: usize,
+        fusion: HybridFusion,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        if self.encrypt_embeddings {
+            return self.scan(&embedding, limit, None).await;
+        }
+
+        self.decrypt_results(
+            self.inner
+                .search_hybrid(embedding, sparse, limit, fusion)
+                .await?,
+        )
+    }
+
+    async fn search_filtered(
+        &self,
+        embedding: Vec<f32>,
+        limit: usize,
+        filter: MetadataFilter,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        if self.encrypt_embeddings {
+            return self.scan(&embedding, limit, Some(&filter)).await;
+        }
+
+        self.decrypt_results(self.inner.search_filtered(embedding, limit, filter).await?)
+    }
+
+    async fn health_check(&self) -> HealthStatus {
+        self.inner.health_check().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EncryptedStorage, EncryptionKey};
+    use crate::{memory::MemoryEntry, storage::Storage, test_util, vector_store::InMemoryDB};
+
+    fn entry(id: &str, content: &str) -> MemoryEntry {
+        MemoryEntry {
+            source_context: "private conversation".to_string(),
+            ..test_util::entry(id, content)
+        }
+    }
+
+    #[tokio::test]
+    async fn memories_are_encrypted_at_rest() {
+        for key in [
+            EncryptionKey::Aes256Gcm([7; 32]),
+            EncryptionKey::XChaCha20Poly1305([7; 32]),
+        ] {
+            let mut storage =
+                EncryptedStorage::new(InMemoryDB::new(2), key).encrypt_embeddings(true);
+            storage
+                .insert(vec![1.0, 0.0], entry("a", "User's PIN is 1234"))
+                .await
+                .unwrap();
+            storage
+                .insert(vec![0.0, 1.0], entry("b", "User likes rabbits"))
+                .await
+                .unwrap();
+
+            let raw = storage.inner.search_by_id("a".into()).await.unwrap();
+            assert!(!raw.data().content.contains("1234"));
+            assert!(!raw.data().source_context.contains("private"));
+            assert_ne!(raw.embedding(), [1.0, 0.0]);
+
+            let results = storage.search(vec![0.1, 0.9], 1).await.unwrap();
+            assert_eq!(results[0].data().content, "User likes rabbits");
+            assert_eq!(results[0].embedding(), [0.0, 1.0]);
+
+            let found = storage.search_by_id("a".into()).await.unwrap();
+            assert_eq!(found.data().content, "User's PIN is 1234");
+            assert_eq!(found.data().source_context, "private conversation");
+            assert!(found.data().metadata.is_empty());
+
+            let wrong_key =
+                EncryptedStorage::new(storage.into_inner(), EncryptionKey::Aes256Gcm([8; 32]));
+            assert!(wrong_key.search_by_id("a".into()).await.is_err());
+        }
+    }
+}
@@ -1,79 +1,227 @@
-use std::fmt::{self};
+use crate::memory::EmbeddingModelTag;
+
+/// An underlying error that caused a braindump error (eg, an HTTP, IO or (de)serialization error).
+pub type BoxedError = Box<dyn std::error::Error + Send + Sync>;
 
 /// Any kind of error.
-#[derive(Clone, Debug)]
+#[derive(Debug, thiserror::Error)]
 pub enum Error {
-    Build(BuildError),
-    Storage(StorageError),
+    #[error(transparent)]
+    Build(#[from] BuildError),
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+    #[error("{0}")]
     Custom(String),
+    /// An error caused by an underlying error, which can be inspected using [`std::error::Error::source`].
+    #[error("{context}")]
+    Wrapped { context: String, source: BoxedError },
+    /// An embedder failed to create an embedding (eg, because of a network error, or the provider rate limiting requests).
+    /// `retryable` is set when the same request may succeed if it's tried again later.
+    #[error("Embedding failed: {message}")]
+    Embedding { message: String, retryable: bool },
+    /// A model returned output that couldn't be parsed or failed validation (eg, a memory draft with empty content).
+    #[error("Invalid model output: {0}")]
+    InvalidOutput(String),
+    /// An operation didn't complete before its deadline.
+    #[error("Operation timed out after {0:?}")]
+    Timeout(std::time::Duration),
+    #[error("Type has no implementation")]
     NoOp,
 }
 
-impl std::error::Error for Error {}
+/// A broad classification of an [`Error`], for deciding how to handle it without matching on error messages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A component was misconfigured or missing when building it.
+    Config,
+    /// A memory with a given ID doesn't exist.
+    NotFound,
+    /// The input didn't match what a component expects (eg, mismatched dimensions or embedding models).
+    InvalidInput,
+    /// An embedder failed to create an embedding.
+    Embedding,
+    /// A model returned output that couldn't be parsed or failed validation.
+    InvalidOutput,
+    /// An operation didn't complete before its deadline.
+    Timeout,
+    /// An IO error (eg, when reading or writing a snapshot).
+    Io,
+    /// The operation isn't implemented by this type.
+    Unsupported,
+    /// Any other error.
+    Other,
+}
 
 impl Error {
+    /// Classifies the error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Build(_) => ErrorKind::Config,
+            Self::Storage(StorageError::EmbeddingNotExists(_)) => ErrorKind::NotFound,
+            Self::Storage(_) => ErrorKind::InvalidInput,
+            Self::Embedding { .. } => ErrorKind::Embedding,
+            Self::InvalidOutput(_) => ErrorKind::InvalidOutput,
+            Self::Timeout(_) => ErrorKind::Timeout,
+            Self::Wrapped { source, .. } if source.is::<std::io::Error>() => ErrorKind::Io,
+            Self::NoOp => ErrorKind::Unsupported,
+            Self::Custom(_) | Self::Wrapped { .. } => ErrorKind::Other,
+        }
+    }
+
+    /// Whether the operation that caused the error may succeed if it's tried again later (eg, after a rate limit, timeout or dropped connection).
+    /// Everything else is treated as permanent: retrying it will fail the same way.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Embedding { retryable, .. } => *retryable,
+            Self::Timeout(_) => true,
+            Self::Wrapped { source, .. } => is_transient(source.as_ref()),
+            _ => false,
+        }
+    }
+
     pub fn custom(input: &str) -> Self {
         Self::Custom(input.to_string())
     }
+
+    pub fn invalid_output(input: &str) -> Self {
+        Self::InvalidOutput(input.to_string())
+    }
+
+    /// Create an error where an embedder failed to create an embedding.
+    pub fn embedding(message: &str, retryable: bool) -> Self {
+        Self::Embedding {
+            message: message.to_string(),
+            retryable,
+        }
+    }
+
+    /// Create an error caused by an underlying error, with some context about what was being done (eg, "Failed to parse snapshot").
+    pub fn wrap<S, E>(context: S, source: E) -> Self
+    where
+        S: Into<String>,
+        E: Into<BoxedError>,
+    {
+        Self::Wrapped {
+            context: context.into(),
+            source: source.into(),
+        }
+    }
 }
 
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::Build(err) => write!(f, "{err}"),
-            Self::Storage(err) => write!(f, "{err}"),
-            Self::Custom(err) => write!(f, "{err}"),
-            Self::NoOp => write!(f, "Type has no implementation"),
+/// Checks an underlying error (and its sources) for transient failures.
+fn is_transient(err: &(dyn std::error::Error + 'static)) -> bool {
+    let mut current = Some(err);
+    while let Some(err) = current {
+        if let Some(err) = err.downcast_ref::<std::io::Error>() {
+            use std::io::ErrorKind;
+
+            if matches!(
+                err.kind(),
+                ErrorKind::TimedOut
+                    | ErrorKind::Interrupted
+                    | ErrorKind::WouldBlock
+                    | ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+                    | ErrorKind::ConnectionRefused
+            ) {
+                return true;
+            }
+        }
+
+        #[cfg(any(feature = "cohere", feature = "gemini", feature = "jina"))]
+        if let Some(err) = err.downcast_ref::<reqwest::Error>()
+            && crate::http::is_transient(err)
+        {
+            return true;
         }
+
+        current = err.source();
     }
+
+    false
 }
 
-impl From<BuildError> for Error {
-    fn from(value: BuildError) -> Self {
-        Self::Build(value)
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Self::wrap("IO error", value)
     }
 }
 
-impl From<StorageError> for Error {
-    fn from(value: StorageError) -> Self {
-        Self::Storage(value)
+impl From<serde_json::Error> for Error {
+    fn from(value: serde_json::Error) -> Self {
+        Self::wrap("JSON error", value)
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, thiserror::Error)]
 pub enum BuildError {
+    #[error("Embedder not found")]
     EmbedderNotFound,
+    #[error("Storage not found")]
     StorageNotFound,
+    #[error("Cache store not found. You need to add an InMemoryDB to your memory cache builder")]
+    CacheStoreNotFound,
+    #[error("The embedder creates {2}-dimension embeddings, but the {0} expects {1} dimensions")]
+    MismatchedDimensions(&'static str, usize, usize),
+    #[error(
+        "The hot cache expects {0}-dimension embeddings, but the storage expects {1} dimensions"
+    )]
+    MismatchedCacheDimensions(usize, usize),
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
+    #[error("Storage is unreachable: {0}")]
+    StorageUnreachable(String),
+    #[error("Embedder failed to embed a probe input: {0}")]
+    EmbedderUnreachable(String),
 }
 
-impl fmt::Display for BuildError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::EmbedderNotFound => write!(f, "Embedder not found"),
-            Self::StorageNotFound => write!(f, "Storage not found"),
-        }
-    }
-}
-
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, thiserror::Error)]
 pub enum StorageError {
+    #[error("Embedding with ID {0} doesn't exist in storage")]
     EmbeddingNotExists(String),
+    #[error("Mismatched dimensions when trying to store an embedding: {0}, {1}")]
     MismatchedDimensions(usize, usize),
+    #[error("Memory with ID {0} was embedded using {1}, but the current embedder is {2}")]
+    MismatchedEmbeddingModel(String, String, String),
+    #[error(
+        "Invalid memory ID {0:?}: IDs must be non-empty, at most 256 bytes and can't contain control characters"
+    )]
+    InvalidMemoryId(String),
+    #[error("Invalid importance {0}: importance must be between 0.0 and 1.0")]
+    InvalidImportance(f32),
+    #[error("Namespace {0:?} would exceed its quota with {1}")]
+    QuotaExceeded(String, String),
 }
 
-impl fmt::Display for StorageError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::EmbeddingNotExists(id) => {
-                write!(f, "Embedding with ID {id} doesn't exist in storage")
-            }
-            Self::MismatchedDimensions(store_dims, embed_dims) => {
-                write!(
-                    f,
-                    "Mismatched dimensions when trying to store an embedding: {store_dims}, {embed_dims}"
-                )
-            }
-        }
+impl BuildError {
+    /// Create an error where the dimensions of the embedder and a component (eg, the storage or the hot cache) do not match up.
+    pub fn mismatched_dimensions(
+        component: &'static str,
+        component_dims: usize,
+        embed_dims: usize,
+    ) -> Self {
+        Self::MismatchedDimensions(component, component_dims, embed_dims)
+    }
+
+    /// Create an error where the dimensions of the hot cache and the storage do not match up.
+    pub fn mismatched_cache_dimensions(cache_dims: usize, storage_dims: usize) -> Self {
+        Self::MismatchedCacheDimensions(cache_dims, storage_dims)
+    }
+
+    /// Create an error where a builder was given an invalid setting (eg, a memory limit of 0).
+    pub fn invalid_config(input: &str) -> Self {
+        Self::InvalidConfig(input.to_string())
+    }
+
+    /// Create an error where the storage failed a health check while building.
+    pub fn storage_unreachable(error: &str) -> Self {
+        Self::StorageUnreachable(error.to_string())
+    }
+
+    /// Create an error where the embedder failed to embed a probe input while building.
+    pub fn embedder_unreachable(error: &str) -> Self {
+        Self::EmbedderUnreachable(error.to_string())
     }
 }
 
@@ -87,4 +235,63 @@ impl StorageError {
     pub fn mismatched_dimensions(store_dims: usize, embed_dims: usize) -> Self {
         Self::MismatchedDimensions(store_dims, embed_dims)
     }
+
+    /// Create an error where a memory ID isn't valid (see [`crate::memory::MemoryId::new`]).
+    pub fn invalid_memory_id(id: &str) -> Self {
+        Self::InvalidMemoryId(id.to_string())
+    }
+
+    /// Create an error where a memory's importance isn't between 0.0 and 1.0 (see [`crate::memory::importance::ImportancePolicy`]).
+    pub fn invalid_importance(importance: f32) -> Self {
+        Self::InvalidImportance(importance)
+    }
+
+    /// Create an error where storing a memory would exceed the quota of its namespace (see [`crate::memory::quota::Quota`]).
+    pub fn quota_exceeded(namespace: &str, violation: &str) -> Self {
+        Self::QuotaExceeded(namespace.to_string(), violation.to_string())
+    }
+
+    /// Create an error where a memory was embedded using a different embedding model to the current one.
+    pub fn mismatched_embedding_model(
+        id: &str,
+        stored: &EmbeddingModelTag,
+        current: &EmbeddingModelTag,
+    ) -> Self {
+        Self::MismatchedEmbeddingModel(id.to_string(), stored.to_string(), current.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as _;
+
+    use super::{Error, ErrorKind, StorageError};
+    use crate::vector_store::InMemoryDB;
+
+    #[test]
+    fn underlying_errors_are_available_as_sources() {
+        let err = InMemoryDB::load_snapshot("/nonexistent/braindump/snapshot.json")
+            .err()
+            .unwrap();
+        assert!(err.to_string().starts_with("Failed to read snapshot"));
+
+        let source = err.source().unwrap();
+        let io = source.downcast_ref::<std::io::Error>().unwrap();
+        assert_eq!(io.kind(), std::io::ErrorKind::NotFound);
+        assert_eq!(err.kind(), ErrorKind::Io);
+        assert!(!err.is_retryable());
+    }
+
+    #[test]
+    fn errors_are_classified_as_retryable_or_permanent() {
+        assert!(Error::embedding("rate limited", true).is_retryable());
+        assert!(!Error::embedding("invalid API key", false).is_retryable());
+        assert!(Error::Timeout(std::time::Duration::from_secs(1)).is_retryable());
+        assert!(!Error::from(StorageError::embedding_not_exists("1")).is_retryable());
+
+        let reset = std::io::Error::from(std::io::ErrorKind::ConnectionReset);
+        let err = Error::wrap("Failed to send request", reset);
+        assert!(err.is_retryable());
+        assert_eq!(err.kind(), ErrorKind::Io);
+    }
 }
@@ -2,26 +2,104 @@
 //! Ensure that you have the `fastembed` feature enabled.
 //! NOTE: This module is not WASM-friendly. Attempting to compile this module to `wasm` architecture will return an error.
 
-use fastembed::TextEmbedding;
+use fastembed::{
+    EmbeddingModel, ImageEmbedding, ImageEmbeddingModel, ImageInitOptions, SparseTextEmbedding,
+    TextEmbedding, TextInitOptions,
+};
 use std::sync::Arc;
 use std::sync::Mutex;
 
+pub use fastembed::EmbeddingModel as FastembedModel;
+pub use fastembed::ImageEmbeddingModel as FastembedImageModel;
+
 /// A text embedder using `fastembed-rs`, made compliant to work with the `Embedder` trait.
 /// Under the hood, `std::sync::Arc` and `std::sync::Mutex` are used due to `fastembed::TextEmbedding` requiring `&mut self` to embed.
-pub struct FastembedTextEmbedder(Arc<Mutex<TextEmbedding>>);
+pub struct FastembedTextEmbedder {
+    model: Arc<Mutex<TextEmbedding>>,
+    model_name: Option<EmbeddingModel>,
+    dims: Option<usize>,
+    max_tokens: Option<usize>,
+    batch_size: Option<usize>,
+}
 
 impl Default for FastembedTextEmbedder {
+    /// Loads the default `fastembed` model.
+    ///
+    /// # Panics
+    /// Panics if the model fails to load. Use [`FastembedTextEmbedder::try_default`] to handle the error instead.
     fn default() -> Self {
-        let model = TextEmbedding::try_new(Default::default()).unwrap();
-
-        Self(Arc::new(Mutex::new(model)))
+        Self::try_default().expect("failed to load the default fastembed model")
     }
 }
 
 impl FastembedTextEmbedder {
-    /// Creates a new instance of `FastembedTextEmbedder`.
-    pub fn new(embedder: TextEmbedding) -> Self {
-        Self(Arc::new(Mutex::new(embedder)))
+    /// Creates a new instance of `FastembedTextEmbedder` from an already-loaded model.
+    /// As the model can't be inspected, its dimensions are found by embedding a short probe input. Prefer [`FastembedTextEmbedder::try_new`] or [`FastembedTextEmbedder::try_with_options`] where possible.
+    pub fn new(mut embedder: TextEmbedding) -> Self {
+        // If probing fails, embedding will fail in the same way later on
+        let dims = embedder
+            .embed(["dims"], None)
+            .ok()
+            .and_then(|embeddings| embeddings.first().map(Vec::len));
+
+        Self {
+            model: Arc::new(Mutex::new(embedder)),
+            model_name: None,
+            dims,
+            max_tokens: None,
+            batch_size: None,
+        }
+    }
+
+    /// Loads (downloading if required) the default `fastembed` model.
+    pub fn try_default() -> Result<Self, crate::Error> {
+        Self::try_new(EmbeddingModel::default())
+    }
+
+    /// Loads (downloading if required) a given model with the default initialization options.
+    pub fn try_new(model: EmbeddingModel) -> Result<Self, crate::Error> {
+        Self::try_with_options(TextInitOptions::new(model))
+    }
+
+    /// Loads (downloading if required) a model using the given initialization options (eg, cache directory, execution providers or max length).
+    pub fn try_with_options(options: TextInitOptions) -> Result<Self, crate::Error> {
+        let model_name = options.model_name.clone();
+        let max_tokens = options.max_length;
+        let dims = TextEmbedding::get_model_info(&model_name)
+            .map_err(fastembed_error)?
+            .dim;
+        let model = TextEmbedding::try_new(options).map_err(fastembed_error)?;
+
+        Ok(Self {
+            model: Arc::new(Mutex::new(model)),
+            model_name: Some(model_name),
+            dims: Some(dims),
+            max_tokens: Some(max_tokens),
+            batch_size: None,
+        })
+    }
+
+    /// Sets the batch size used by [`FastembedTextEmbedder::embed_batch`]. Defaults to `fastembed`'s default batch size.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = Some(batch_size);
+        self
+    }
+
+    /// The dimensions of the embeddings created by this model. This is only `None` if the model was created with [`FastembedTextEmbedder::new`] and failed to embed the probe input.
+    pub fn dims(&self) -> Option<usize> {
+        self.dims
+    }
+
+    /// Embeds multiple texts at once. This is considerably faster than embedding texts one by one.
+    pub fn embed_batch<S>(&self, texts: &[S]) -> Result<Vec<Vec<f32>>, crate::Error>
+    where
+        S: AsRef<str> + Send + Sync,
+    {
+        self.model
+            .lock()
+            .map_err(|_| poisoned_error())?
+            .embed(texts, self.batch_size)
+            .map_err(fastembed_error)
     }
 }
 
@@ -32,9 +110,216 @@ impl From<TextEmbedding> for FastembedTextEmbedder {
 }
 
 impl crate::embed::Embedder for FastembedTextEmbedder {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "fastembed_embed_text", skip_all, fields(input_len = text.len()))
+    )]
     async fn embed_text(&self, text: &str) -> Result<Vec<f32>, crate::Error> {
-        let embedding = self.0.lock().unwrap().embed(vec![text], None).unwrap();
+        self.embed_batch(&[text])?
+            .pop()
+            .ok_or_else(no_embeddings_error)
+    }
+
+    fn dims(&self) -> Option<usize> {
+        self.dims
+    }
+
+    fn max_input_tokens(&self) -> Option<usize> {
+        self.max_tokens
+    }
+
+    fn model_id(&self) -> Option<String> {
+        self.model_name
+            .as_ref()
+            .map(|model| format!("fastembed/{model}"))
+    }
+}
+
+fn fastembed_error<E>(err: E) -> crate::Error
+where
+    E: Into<crate::error::BoxedError>,
+{
+    crate::Error::wrap("fastembed error", err)
+}
+
+/// A previous embedding call panicked while holding the model's lock.
+fn poisoned_error() -> crate::Error {
+    crate::Error::embedding("fastembed model mutex was poisoned", false)
+}
+
+fn no_embeddings_error() -> crate::Error {
+    crate::Error::embedding("fastembed returned no embeddings", false)
+}
+
+/// A sparse text embedder (eg, SPLADE) using `fastembed-rs`, made compliant to work with the `SparseEmbedder` trait.
+/// Used alongside a dense embedder for hybrid retrieval (see [`crate::memory::manager::MemoryManager::retrieve_hybrid`]).
+pub struct FastembedSparseEmbedder(Arc<Mutex<SparseTextEmbedding>>);
+
+impl Default for FastembedSparseEmbedder {
+    /// Loads the default `fastembed` sparse model.
+    ///
+    /// # Panics
+    /// Panics if the model fails to load. Use [`FastembedSparseEmbedder::try_default`] to handle the error instead.
+    fn default() -> Self {
+        Self::try_default().expect("failed to load the default fastembed sparse model")
+    }
+}
+
+impl FastembedSparseEmbedder {
+    /// Creates a new instance of `FastembedSparseEmbedder`.
+    pub fn new(embedder: SparseTextEmbedding) -> Self {
+        Self(Arc::new(Mutex::new(embedder)))
+    }
+
+    /// Loads (downloading if required) the default `fastembed` sparse model.
+    pub fn try_default() -> Result<Self, crate::Error> {
+        let model = SparseTextEmbedding::try_new(Default::default()).map_err(fastembed_error)?;
+
+        Ok(Self::new(model))
+    }
+}
+
+impl From<SparseTextEmbedding> for FastembedSparseEmbedder {
+    fn from(embedder: SparseTextEmbedding) -> Self {
+        Self::new(embedder)
+    }
+}
+
+impl crate::embed::SparseEmbedder for FastembedSparseEmbedder {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "fastembed_embed_sparse", skip_all, fields(input_len = text.len()))
+    )]
+    async fn embed_sparse(&self, text: &str) -> Result<crate::embed::SparseVector, crate::Error> {
+        let mut embeddings = self
+            .0
+            .lock()
+            .map_err(|_| poisoned_error())?
+            .embed(vec![text], None)
+            .map_err(fastembed_error)?;
+
+        let Some(embedding) = embeddings.pop() else {
+            return Err(no_embeddings_error());
+        };
+
+        let indices = embedding.indices.into_iter().map(|x| x as u32).collect();
+
+        Ok(crate::embed::SparseVector::new(indices, embedding.values))
+    }
+}
+
+/// A multimodal embedder using `fastembed-rs` image models, made compliant to work with the `MultimodalEmbedder` trait.
+/// Images are embedded with an image model, and text with a paired text model that shares its embedding space (eg, CLIP), so that text queries can recall images.
+pub struct FastembedImageEmbedder {
+    model: Arc<Mutex<ImageEmbedding>>,
+    model_name: ImageEmbeddingModel,
+    text: FastembedTextEmbedder,
+}
+
+impl FastembedImageEmbedder {
+    /// Creates a new instance of `FastembedImageEmbedder` from an already-loaded image model and a text embedder.
+    /// The text embedder must embed into the same space as the image model.
+    pub fn new(
+        model: ImageEmbedding,
+        model_name: ImageEmbeddingModel,
+        text: FastembedTextEmbedder,
+    ) -> Self {
+        Self {
+            model: Arc::new(Mutex::new(model)),
+            model_name,
+            text,
+        }
+    }
+
+    /// Loads (downloading if required) a given image model and its paired text model.
+    pub fn try_new(
+        model: ImageEmbeddingModel,
+        text_model: EmbeddingModel,
+    ) -> Result<Self, crate::Error> {
+        let image = ImageEmbedding::try_new(ImageInitOptions::new(model.clone()))
+            .map_err(fastembed_error)?;
+        let text = FastembedTextEmbedder::try_new(text_model)?;
+
+        Ok(Self::new(image, model, text))
+    }
+
+    /// Loads (downloading if required) the CLIP ViT-B/32 image and text models.
+    pub fn try_clip() -> Result<Self, crate::Error> {
+        Self::try_new(ImageEmbeddingModel::ClipVitB32, EmbeddingModel::ClipVitB32)
+    }
+
+    /// Loads (downloading if required) the Nomic Embed v1.5 vision and text models.
+    pub fn try_nomic() -> Result<Self, crate::Error> {
+        Self::try_new(
+            ImageEmbeddingModel::NomicEmbedVisionV15,
+            EmbeddingModel::NomicEmbedTextV15,
+        )
+    }
+}
+
+impl crate::embed::Embedder for FastembedImageEmbedder {
+    async fn embed_text(&self, text: &str) -> Result<Vec<f32>, crate::Error> {
+        self.text.embed_text(text).await
+    }
+
+    fn dims(&self) -> Option<usize> {
+        Some(ImageEmbedding::get_model_info(&self.model_name).dim)
+    }
+
+    fn max_input_tokens(&self) -> Option<usize> {
+        self.text.max_input_tokens()
+    }
+
+    fn model_id(&self) -> Option<String> {
+        Some(format!("fastembed/{}", self.model_name))
+    }
+}
+
+impl crate::embed::MultimodalEmbedder for FastembedImageEmbedder {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "fastembed_embed_image", skip_all)
+    )]
+    async fn embed_image(
+        &self,
+        image: &crate::embed::ImageSource,
+    ) -> Result<Vec<f32>, crate::Error> {
+        let mut model = self.model.lock().map_err(|_| poisoned_error())?;
+
+        let mut embeddings = match image {
+            crate::embed::ImageSource::Bytes(bytes) => model.embed_bytes(&[bytes.as_slice()], None),
+            crate::embed::ImageSource::Uri(_) => {
+                let Some(path) = image.as_path() else {
+                    return Err(crate::Error::custom(
+                        "fastembed can only embed local images. Download remote images and embed them as bytes instead",
+                    ));
+                };
+                model.embed([path], None)
+            }
+        }
+        .map_err(fastembed_error)?;
+
+        embeddings.pop().ok_or_else(no_embeddings_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fastembed_error, no_embeddings_error, poisoned_error};
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn failures_are_typed_errors() {
+        for err in [poisoned_error(), no_embeddings_error()] {
+            assert_eq!(err.kind(), ErrorKind::Embedding);
+            assert!(!err.is_retryable());
+        }
 
-        Ok(embedding.first().cloned().unwrap())
+        let err = fastembed_error("model file is corrupt");
+        assert_eq!(err.kind(), ErrorKind::Other);
+        assert_eq!(
+            std::error::Error::source(&err).unwrap().to_string(),
+            "model file is corrupt"
+        );
     }
 }
@@ -0,0 +1,251 @@
+//! A module for handling embeddings using the [Google Gemini](https://ai.google.dev/gemini-api/docs/embeddings) embeddings API.
+//! Ensure that you have the `gemini` feature enabled.
+
+use serde::{Deserialize, Serialize};
+
+use crate::http::{api_key_from_env, post_json};
+
+const GEMINI_API_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+/// `text-embedding-004`
+pub const TEXT_EMBEDDING_004: &str = "text-embedding-004";
+/// `gemini-embedding-001`
+pub const GEMINI_EMBEDDING_001: &str = "gemini-embedding-001";
+
+/// The task that embeddings are being created for. Gemini optimises embeddings for the given task.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TaskType {
+    /// Used for search queries (ie, when retrieving memories).
+    RetrievalQuery,
+    /// Used for documents that get stored (ie, when storing memories).
+    #[default]
+    RetrievalDocument,
+    /// Used for semantic textual similarity.
+    SemanticSimilarity,
+    /// Used for text classification.
+    Classification,
+    /// Used for clustering.
+    Clustering,
+    /// Used for questions in a question answering system.
+    QuestionAnswering,
+    /// Used for fact verification.
+    FactVerification,
+    /// Used for code retrieval queries.
+    CodeRetrievalQuery,
+}
+
+/// A text embedder using the Gemini embeddings API, made compliant to work with the `Embedder` trait.
+/// Queries are embedded using [`TaskType::RetrievalQuery`] and stored memories using [`TaskType::RetrievalDocument`].
+pub struct GeminiEmbedder {
+    client: reqwest::Client,
+    api_key: String,
+    model: String,
+    base_url: String,
+    default_task_type: TaskType,
+    output_dimensionality: Option<usize>,
+}
+
+impl GeminiEmbedder {
+    /// Creates a new instance of `GeminiEmbedder` using a given API key and model (see the model constants in this module).
+    pub fn new<S1, S2>(api_key: S1, model: S2) -> Self
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        Self {
+            client: reqwest::Client::new(),
+            api_key: api_key.into(),
+            model: model.into(),
+            base_url: GEMINI_API_BASE_URL.to_string(),
+            default_task_type: TaskType::default(),
+            output_dimensionality: None,
+        }
+    }
+
+    /// Creates a new instance of `GeminiEmbedder`, using the `GEMINI_API_KEY` environment variable as the API key.
+    pub fn from_env<S>(model: S) -> Result<Self, crate::Error>
+    where
+        S: Into<String>,
+    {
+        Ok(Self::new(api_key_from_env("GEMINI_API_KEY")?, model))
+    }
+
+    /// Sets the base URL of the API (eg, for proxies).
+    pub fn base_url<S>(mut self, base_url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Sets the task type used by [`crate::embed::Embedder::embed_text`]. Defaults to [`TaskType::RetrievalDocument`].
+    pub fn default_task_type(mut self, task_type: TaskType) -> Self {
+        self.default_task_type = task_type;
+        self
+    }
+
+    /// Truncates the output embeddings to a given number of dimensions. Only supported by newer models.
+    pub fn output_dimensionality(mut self, dims: usize) -> Self {
+        self.output_dimensionality = Some(dims);
+        self
+    }
+
+    /// Use a pre-configured `reqwest` client.
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Embeds text using a given task type.
+    pub async fn embed_with_task_type(
+        &self,
+        text: &str,
+        task_type: TaskType,
+    ) -> Result<Vec<f32>, crate::Error> {
+        let model = self.model.trim_start_matches("models/");
+        let request = self
+            .client
+            .post(format!("{}/models/{model}:embedContent", self.base_url))
+            .header("x-goog-api-key", &self.api_key);
+
+        let body = EmbedContentRequest {
+            model: format!("models/{model}"),
+            content: Content {
+                parts: vec![Part { text }],
+            },
+            task_type,
+            output_dimensionality: self.output_dimensionality,
+        };
+
+        let response: EmbedContentResponse = post_json(request, &body).await?;
+
+        Ok(response.embedding.values)
+    }
+}
+
+impl crate::embed::Embedder for GeminiEmbedder {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "gemini_embed_text", skip_all, fields(input_len = text.len()))
+    )]
+    async fn embed_text(&self, text: &str) -> Result<Vec<f32>, crate::Error> {
+        self.embed_with_task_type(text, self.default_task_type)
+            .await
+    }
+
+    async fn embed_query(&self, text: &str) -> Result<Vec<f32>, crate::Error> {
+        self.embed_with_task_type(text, TaskType::RetrievalQuery)
+            .await
+    }
+
+    async fn embed_document(&self, text: &str) -> Result<Vec<f32>, crate::Error> {
+        self.embed_with_task_type(text, TaskType::RetrievalDocument)
+            .await
+    }
+
+    fn dims(&self) -> Option<usize> {
+        self.output_dimensionality
+            .or(match self.model.trim_start_matches("models/") {
+                TEXT_EMBEDDING_004 => Some(768),
+                GEMINI_EMBEDDING_001 => Some(3072),
+                _ => None,
+            })
+    }
+
+    fn max_input_tokens(&self) -> Option<usize> {
+        match self.model.trim_start_matches("models/") {
+            TEXT_EMBEDDING_004 | GEMINI_EMBEDDING_001 => Some(2048),
+            _ => None,
+        }
+    }
+
+    fn model_id(&self) -> Option<String> {
+        Some(match self.output_dimensionality {
+            Some(dims) => format!("gemini/{}/{dims}", self.model),
+            None => format!("gemini/{}", self.model),
+        })
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EmbedContentRequest<'a> {
+    model: String,
+    content: Content<'a>,
+    task_type: TaskType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output_dimensionality: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct Content<'a> {
+    parts: Vec<Part<'a>>,
+}
+
+#[derive(Serialize)]
+struct Part<'a> {
+    text: &'a str,
+}
+
+#[derive(Deserialize)]
+struct EmbedContentResponse {
+    embedding: ContentEmbedding,
+}
+
+#[derive(Deserialize)]
+struct ContentEmbedding {
+    values: Vec<f32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Content, EmbedContentRequest, EmbedContentResponse, Part, TaskType};
+
+    #[test]
+    fn requests_match_the_api() {
+        let request = EmbedContentRequest {
+            model: "models/text-embedding-004".to_string(),
+            content: Content {
+                parts: vec![Part {
+                    text: "User lives in Berlin",
+                }],
+            },
+            task_type: TaskType::RetrievalQuery,
+            output_dimensionality: Some(256),
+        };
+
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "model": "models/text-embedding-004",
+                "content": {"parts": [{"text": "User lives in Berlin"}]},
+                "taskType": "RETRIEVAL_QUERY",
+                "outputDimensionality": 256
+            })
+        );
+
+        let request = EmbedContentRequest {
+            output_dimensionality: None,
+            task_type: TaskType::RetrievalDocument,
+            ..request
+        };
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["taskType"], "RETRIEVAL_DOCUMENT");
+        assert!(json.get("outputDimensionality").is_none());
+    }
+
+    #[test]
+    fn responses_are_parsed() {
+        let response: EmbedContentResponse = serde_json::from_str(
+            r#"{"embedding": {"values": [0.013168523, -0.008711934, -0.046782676]}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            response.embedding.values,
+            [0.013168523, -0.008711934, -0.046782676]
+        );
+    }
+}
@@ -0,0 +1,384 @@
+//! A gRPC memory service using `tonic`, so that agents written in any language can share a central braindump service.
+//!
+//! The service is defined in `proto/braindump/v1/memory.proto` (regenerate the bindings with `just protogen` after changing it). The generated server and client live in [`proto`]: use [`service`] to serve a memory manager,
+//! and [`proto::memory_service_client::MemoryServiceClient`] to talk to one. Retrieved memories are streamed back one message at a time, in order of relevance.
+//!
+//! ```rust,ignore
+//! use std::sync::Arc;
+//! use futures::lock::Mutex;
+//!
+//! let manager = Arc::new(Mutex::new(manager));
+//! tonic::transport::Server::builder()
+//!     .add_service(braindump::grpc::service(manager))
+//!     .serve("0.0.0.0:50051".parse()?)
+//!     .await?;
+//! ```
+
+use std::pin::Pin;
+
+use futures::Stream;
+use tonic::{Request, Response, Status};
+
+use crate::{
+    embed::{Embedder, ImageSource},
+    error::StorageError,
+    memory::{
+        Confidence, EmbeddingModelTag, MemoryEntry, MemoryKind, MetadataEntry, Visibility,
+        cache::CacheStats,
+        manager::{CacheMode, RetrieveOptions, SharedMemoryManager},
+        strength::INITIAL_STRENGTH,
+    },
+    storage::Storage,
+};
+
+/// Types generated from the protobuf definitions.
+#[allow(clippy::all, missing_docs)]
+pub mod proto {
+    include!("braindump.v1.rs");
+}
+
+use proto::memory_service_server::{MemoryService, MemoryServiceServer};
+
+/// The default number of memories returned by `Retrieve`, if the request doesn't set a limit.
+const DEFAULT_RETRIEVE_LIMIT: usize = 5;
+
+/// Creates a gRPC service exposing a memory manager. The manager is shared, so it can still be used outside of the service.
+pub fn service<E, S>(
+    manager: SharedMemoryManager<E, S>,
+) -> MemoryServiceServer<GrpcMemoryService<E, S>>
+where
+    E: Embedder + 'static,
+    S: Storage + 'static,
+{
+    MemoryServiceServer::new(GrpcMemoryService::new(manager))
+}
+
+/// An implementation of the generated [`MemoryService`] trait, backed by a memory manager.
+pub struct GrpcMemoryService<E, S>
+where
+    E: Embedder,
+    S: Storage,
+{
+    manager: SharedMemoryManager<E, S>,
+}
+
+impl<E, S> GrpcMemoryService<E, S>
+where
+    E: Embedder,
+    S: Storage,
+{
+    pub fn new(manager: SharedMemoryManager<E, S>) -> Self {
+        Self { manager }
+    }
+}
+
+type MemoryStream = Pin<Box<dyn Stream<Item = Result<proto::Memory, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl<E, S> MemoryService for GrpcMemoryService<E, S>
+where
+    E: Embedder + 'static,
+    S: Storage + 'static,
+{
+    type RetrieveStream = MemoryStream;
+
+    async fn store(
+        &self,
+        request: Request<proto::StoreRequest>,
+    ) -> Result<Response<proto::StoreResponse>, Status> {
+        let memory = request
+            .into_inner()
+            .memory
+            .ok_or_else(|| Status::invalid_argument("A memory is required"))?;
+        let entry = MemoryEntry::try_from(memory)?;
+        let id = entry.id.clone();
+        let content = entry.content.clone();
+
+        self.manager
+            .lock()
+            .await
+            .store(content, entry)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(proto::StoreResponse { id: id.into() }))
+    }
+
+    async fn retrieve(
+        &self,
+        request: Request<proto::RetrieveRequest>,
+    ) -> Result<Response<Self::RetrieveStream>, Status> {
+        let request = request.into_inner();
+        let cache_mode = match request.cache_mode() {
+            proto::CacheMode::Unspecified | proto::CacheMode::Default => CacheMode::Default,
+            proto::CacheMode::Bypass => CacheMode::Bypass,
+            proto::CacheMode::CacheOnly => CacheMode::CacheOnly,
+        };
+        let limit = request
+            .limit
+            .map_or(DEFAULT_RETRIEVE_LIMIT, |limit| limit as usize);
+
+        let results = self
+            .manager
+            .lock()
+            .await
+            .retrieve_with_options(request.query, limit, RetrieveOptions { cache_mode })
+            .await
+            .map_err(to_status)?;
+
+        let memories: Vec<Result<proto::Memory, Status>> = results
+            .iter()
+            .map(|result| Ok(result.data_owned().into()))
+            .collect();
+
+        Ok(Response::new(Box::pin(futures::stream::iter(memories))))
+    }
+
+    async fn delete(
+        &self,
+        request: Request<proto::DeleteRequest>,
+    ) -> Result<Response<proto::DeleteResponse>, Status> {
+        self.manager
+            .lock()
+            .await
+            .delete(&request.into_inner().id)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(proto::DeleteResponse {}))
+    }
+
+    async fn stats(
+        &self,
+        _request: Request<proto::StatsRequest>,
+    ) -> Result<Response<proto::StatsResponse>, Status> {
+        let manager = self.manager.lock().await;
+
+        Ok(Response::new(proto::StatsResponse {
+            memories: manager.count().await.map_err(to_status)? as u64,
+            pending_writes: manager.pending_writes() as u64,
+            cache: manager.cache_stats().map(proto::CacheStats::from),
+        }))
+    }
+}
+
+fn to_status(error: crate::Error) -> Status {
+    match &error {
+        crate::Error::Storage(StorageError::EmbeddingNotExists(_)) => {
+            Status::not_found(error.to_string())
+        }
+        crate::Error::Storage(
+            StorageError::MismatchedDimensions(..)
+            | StorageError::InvalidMemoryId(_)
+            | StorageError::InvalidImportance(_),
+        ) => Status::invalid_argument(error.to_string()),
+        crate::Error::Storage(StorageError::QuotaExceeded(..)) => {
+            Status::resource_exhausted(error.to_string())
+        }
+        err if err.is_retryable() => Status::unavailable(error.to_string()),
+        _ => Status::internal(error.to_string()),
+    }
+}
+
+impl From<MemoryEntry> for proto::Memory {
+    fn from(entry: MemoryEntry) -> Self {
+        let kind = match entry.kind {
+            MemoryKind::Working => proto::MemoryKind::Working,
+            MemoryKind::Episodic => proto::MemoryKind::Episodic,
+            MemoryKind::Semantic => proto::MemoryKind::Semantic,
+            MemoryKind::Procedural => proto::MemoryKind::Procedural,
+        };
+        let confidence = match entry.confidence {
+            Confidence::Low => proto::Confidence::Low,
+            Confidence::Medium => proto::Confidence::Medium,
+            Confidence::High => proto::Confidence::High,
+        };
+        let visibility = match entry.visibility {
+            Visibility::Private => proto::Visibility::Private,
+            Visibility::Shared => proto::Visibility::Shared,
+        };
+
+        Self {
+            id: entry.id.into(),
+            content: entry.content,
+            kind: kind.into(),
+            importance: entry.importance,
+            created_at: entry.created_at,
+            last_accessed: entry.last_accessed,
+            access_count: entry.access_count,
+            source_context: entry.source_context,
+            confidence: confidence.into(),
+            metadata: entry
+                .metadata
+                .iter()
+                .map(|entry| proto::MetadataEntry {
+                    key: entry.key().to_string(),
+                    value: entry.value().to_string(),
+                })
+                .collect(),
+            agent_id: entry.agent_id,
+            visibility: visibility.into(),
+            embedding_model: entry.embedding_model.map(|tag| proto::EmbeddingModelTag {
+                model: tag.model,
+                dims: tag.dims as u64,
+            }),
+            image: entry.image.map(|image| proto::ImageSource {
+                source: Some(match image {
+                    ImageSource::Uri(uri) => proto::image_source::Source::Uri(uri),
+                    ImageSource::Bytes(bytes) => proto::image_source::Source::Data(bytes),
+                }),
+            }),
+            tags: entry.tags,
+            strength: entry.strength,
+        }
+    }
+}
+
+impl TryFrom<proto::Memory> for MemoryEntry {
+    type Error = Status;
+
+    fn try_from(memory: proto::Memory) -> Result<Self, Self::Error> {
+        let kind = match memory.kind() {
+            proto::MemoryKind::Unspecified => Err(Status::invalid_argument(
+                "The kind of the memory must be specified",
+            ))?,
+            proto::MemoryKind::Working => MemoryKind::Working,
+            proto::MemoryKind::Episodic => MemoryKind::Episodic,
+            proto::MemoryKind::Semantic => MemoryKind::Semantic,
+            proto::MemoryKind::Procedural => MemoryKind::Procedural,
+        };
+        let confidence = match memory.confidence() {
+            proto::Confidence::Unspecified => Err(Status::invalid_argument(
+                "The confidence of the memory must be specified",
+            ))?,
+            proto::Confidence::Low => Confidence::Low,
+            proto::Confidence::Medium => Confidence::Medium,
+            proto::Confidence::High => Confidence::High,
+        };
+        let visibility = match memory.visibility() {
+            proto::Visibility::Unspecified | proto::Visibility::Private => Visibility::Private,
+            proto::Visibility::Shared => Visibility::Shared,
+        };
+
+        Ok(Self {
+            id: memory.id.into(),
+            content: memory.content,
+            kind,
+            importance: memory.importance,
+            created_at: memory.created_at,
+            last_accessed: memory.last_accessed,
+            access_count: memory.access_count,
+            source_context: memory.source_context,
+            confidence,
+            metadata: memory
+                .metadata
+                .into_iter()
+                .map(|entry| MetadataEntry::new(entry.key, entry.value))
+                .collect(),
+            agent_id: memory.agent_id,
+            visibility,
+            embedding_model: memory.embedding_model.map(|tag| EmbeddingModelTag {
+                model: tag.model,
+                dims: tag.dims as usize,
+            }),
+            image: memory
+                .image
+                .and_then(|image| image.source)
+                .map(|source| match source {
+                    proto::image_source::Source::Uri(uri) => ImageSource::Uri(uri),
+                    proto::image_source::Source::Data(bytes) => ImageSource::Bytes(bytes),
+                }),
+            tags: memory.tags,
+            // Older clients don't send a strength
+            strength: if memory.strength > 0.0 {
+                memory.strength
+            } else {
+                INITIAL_STRENGTH
+            },
+        })
+    }
+}
+
+impl From<&CacheStats> for proto::CacheStats {
+    fn from(stats: &CacheStats) -> Self {
+        Self {
+            hits: stats.hits(),
+            misses: stats.misses(),
+            inserts: stats.inserts(),
+            evictions: stats.evictions(),
+            size: stats.size() as u64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use futures::StreamExt;
+    use futures::lock::Mutex;
+    use tonic::{Code, Request};
+
+    use super::{GrpcMemoryService, proto, proto::memory_service_server::MemoryService};
+    use crate::{
+        memory::{MemoryEntry, Visibility, manager::MemoryManager},
+        test_util::{LetterEmbedder, entry},
+        vector_store::InMemoryDB,
+    };
+
+    #[tokio::test]
+    async fn memories_are_streamed_back_from_retrieval() {
+        let manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+        let service = GrpcMemoryService::new(Arc::new(Mutex::new(manager)));
+
+        for (id, content) in [("a", "aaaa"), ("o", "oooo")] {
+            let memory = MemoryEntry {
+                visibility: Visibility::Shared,
+                ..entry(id, content)
+            };
+            service
+                .store(Request::new(proto::StoreRequest {
+                    memory: Some(memory.into()),
+                }))
+                .await
+                .unwrap();
+        }
+
+        let stream = service
+            .retrieve(Request::new(proto::RetrieveRequest {
+                query: "aaa".to_string(),
+                limit: Some(1),
+                cache_mode: proto::CacheMode::Bypass.into(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let memories: Vec<proto::Memory> = stream.map(Result::unwrap).collect().await;
+        assert_eq!(memories.len(), 1);
+        assert_eq!(memories[0].id, "a");
+        assert_eq!(memories[0].visibility(), proto::Visibility::Shared);
+
+        let invalid = proto::Memory {
+            id: "b".to_string(),
+            ..Default::default()
+        };
+        let status = service
+            .store(Request::new(proto::StoreRequest {
+                memory: Some(invalid),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+
+        let stats = service
+            .stats(Request::new(proto::StatsRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(stats.memories, 2);
+    }
+}
@@ -0,0 +1,47 @@
+//! Shared HTTP helpers for embedding providers that are accessed over a REST API.
+
+use serde::{Serialize, de::DeserializeOwned};
+
+/// Sends a JSON `POST` request and deserializes the JSON response.
+/// Non-success status codes are returned as an error, including the response body.
+pub(crate) async fn post_json<Req, Res>(
+    request: reqwest::RequestBuilder,
+    body: &Req,
+) -> Result<Res, crate::Error>
+where
+    Req: Serialize,
+    Res: DeserializeOwned,
+{
+    let response = request
+        .json(body)
+        .send()
+        .await
+        .map_err(|err| crate::Error::wrap("HTTP request failed", err))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let text = response.text().await.unwrap_or_default();
+        // Rate limits, timeouts and server errors may succeed if they're tried again
+        let retryable =
+            status.as_u16() == 408 || status.as_u16() == 429 || status.is_server_error();
+        return Err(crate::Error::embedding(
+            &format!("Request failed with status {status}: {text}"),
+            retryable,
+        ));
+    }
+
+    response
+        .json()
+        .await
+        .map_err(|err| crate::Error::wrap("Failed to parse response", err))
+}
+
+/// Whether a request failed in a way that may succeed if it's sent again (eg, a timeout or a dropped connection).
+pub(crate) fn is_transient(err: &reqwest::Error) -> bool {
+    err.is_timeout() || err.is_connect()
+}
+
+/// Gets an API key from an environment variable.
+pub(crate) fn api_key_from_env(var: &str) -> Result<String, crate::Error> {
+    std::env::var(var).map_err(|_| crate::Error::Custom(format!("{var} is not set")))
+}
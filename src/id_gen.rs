@@ -19,6 +19,37 @@ impl IdGenerationStrategy for UuidV4Generator {
     }
 }
 
+/// A struct that generates [ULIDs](https://github.com/ulid/spec): lexicographically sortable IDs prefixed with a millisecond timestamp.
+/// IDs generated within the same millisecond are monotonically increasing, so sorting memories by ID also sorts them by creation time.
+#[cfg(feature = "ulid")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ulid")))]
+#[derive(Default)]
+pub struct UlidGenerator(ulid::Generator);
+
+#[cfg(feature = "ulid")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ulid")))]
+impl UlidGenerator {
+    /// Creates a new instance of `UlidGenerator`.
+    pub fn new() -> Self {
+        Self(ulid::Generator::new())
+    }
+}
+
+#[cfg(feature = "ulid")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ulid")))]
+impl IdGenerationStrategy for UlidGenerator {
+    /// If more than 2^80 IDs are generated within the same millisecond, this waits for the next millisecond rather than breaking monotonicity.
+    fn generate_id(&mut self) -> String {
+        loop {
+            match self.0.generate() {
+                Ok(id) => return id.to_string(),
+                // Spins rather than sleeping, as threads can't sleep on WASM
+                Err(ulid::MonotonicError::Overflow) => std::hint::spin_loop(),
+            }
+        }
+    }
+}
+
 /// A counter. Provides IDs as numbers starting from 1 by default.
 pub struct Counter(u64);
 
@@ -140,10 +171,22 @@ mod tests {
         let mut generator = MemoryIdGenerator::new();
         let id = generator.generate_id();
 
-        assert_eq!("mem-000001", &id);
+        assert_eq!("mem-000000001", &id);
 
         let id = generator.generate_id();
 
-        assert_eq!("mem-000002", &id);
+        assert_eq!("mem-000000002", &id);
+    }
+
+    #[cfg(feature = "ulid")]
+    #[test]
+    fn ulids_sort_in_generation_order() {
+        let mut generator = crate::id_gen::UlidGenerator::new();
+        let ids: Vec<String> = (0..100).map(|_| generator.generate_id()).collect();
+
+        let mut sorted = ids.clone();
+        sorted.sort();
+        assert_eq!(ids, sorted);
+        assert_eq!(ids[0].len(), 26);
     }
 }
@@ -0,0 +1,243 @@
+//! A module for handling embeddings using the [Jina AI](https://jina.ai/embeddings/) embeddings API.
+//! Ensure that you have the `jina` feature enabled.
+
+use serde::{Deserialize, Serialize};
+
+use crate::http::{api_key_from_env, post_json};
+
+const JINA_API_BASE_URL: &str = "https://api.jina.ai/v1";
+
+/// `jina-embeddings-v3`
+pub const JINA_EMBEDDINGS_V3: &str = "jina-embeddings-v3";
+
+/// The task that embeddings are being created for. `jina-embeddings-v3` selects a task-specific LoRA adapter for each task.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub enum Task {
+    /// Used for search queries (ie, when retrieving memories).
+    #[serde(rename = "retrieval.query")]
+    RetrievalQuery,
+    /// Used for documents that get stored (ie, when storing memories).
+    #[default]
+    #[serde(rename = "retrieval.passage")]
+    RetrievalPassage,
+    /// Used for clustering and reranking.
+    #[serde(rename = "separation")]
+    Separation,
+    /// Used for text classification.
+    #[serde(rename = "classification")]
+    Classification,
+    /// Used for semantic textual similarity.
+    #[serde(rename = "text-matching")]
+    TextMatching,
+}
+
+/// A text embedder using the Jina embeddings API, made compliant to work with the `Embedder` trait.
+/// Queries are embedded using [`Task::RetrievalQuery`] and stored memories using [`Task::RetrievalPassage`].
+pub struct JinaEmbedder {
+    client: reqwest::Client,
+    api_key: String,
+    model: String,
+    base_url: String,
+    default_task: Task,
+    dimensions: Option<usize>,
+    late_chunking: bool,
+}
+
+impl JinaEmbedder {
+    /// Creates a new instance of `JinaEmbedder` using a given API key and model (see the model constants in this module).
+    pub fn new<S1, S2>(api_key: S1, model: S2) -> Self
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        Self {
+            client: reqwest::Client::new(),
+            api_key: api_key.into(),
+            model: model.into(),
+            base_url: JINA_API_BASE_URL.to_string(),
+            default_task: Task::default(),
+            dimensions: None,
+            late_chunking: false,
+        }
+    }
+
+    /// Creates a new instance of `JinaEmbedder`, using the `JINA_API_KEY` environment variable as the API key.
+    pub fn from_env<S>(model: S) -> Result<Self, crate::Error>
+    where
+        S: Into<String>,
+    {
+        Ok(Self::new(api_key_from_env("JINA_API_KEY")?, model))
+    }
+
+    /// Sets the base URL of the API (eg, for proxies).
+    pub fn base_url<S>(mut self, base_url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Sets the task used by [`crate::embed::Embedder::embed_text`]. Defaults to [`Task::RetrievalPassage`].
+    pub fn default_task(mut self, task: Task) -> Self {
+        self.default_task = task;
+        self
+    }
+
+    /// Truncates the output embeddings to a given number of dimensions (Matryoshka representation).
+    pub fn dimensions(mut self, dims: usize) -> Self {
+        self.dimensions = Some(dims);
+        self
+    }
+
+    /// Enables late chunking: the whole input is encoded before being chunked and pooled, so long inputs (eg, conversation histories) keep their surrounding context.
+    pub fn late_chunking(mut self, late_chunking: bool) -> Self {
+        self.late_chunking = late_chunking;
+        self
+    }
+
+    /// Use a pre-configured `reqwest` client.
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Embeds text using a given task.
+    pub async fn embed_with_task(&self, text: &str, task: Task) -> Result<Vec<f32>, crate::Error> {
+        let request = self
+            .client
+            .post(format!("{}/embeddings", self.base_url))
+            .bearer_auth(&self.api_key);
+
+        let body = EmbeddingsRequest {
+            model: &self.model,
+            input: vec![text],
+            task,
+            dimensions: self.dimensions,
+            late_chunking: self.late_chunking,
+            embedding_type: "float",
+        };
+
+        let response: EmbeddingsResponse = post_json(request, &body).await?;
+
+        response
+            .data
+            .into_iter()
+            .next()
+            .map(|data| data.embedding)
+            .ok_or_else(|| crate::Error::custom("Jina returned no embeddings"))
+    }
+}
+
+impl crate::embed::Embedder for JinaEmbedder {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "jina_embed_text", skip_all, fields(input_len = text.len()))
+    )]
+    async fn embed_text(&self, text: &str) -> Result<Vec<f32>, crate::Error> {
+        self.embed_with_task(text, self.default_task).await
+    }
+
+    async fn embed_query(&self, text: &str) -> Result<Vec<f32>, crate::Error> {
+        self.embed_with_task(text, Task::RetrievalQuery).await
+    }
+
+    async fn embed_document(&self, text: &str) -> Result<Vec<f32>, crate::Error> {
+        self.embed_with_task(text, Task::RetrievalPassage).await
+    }
+
+    fn dims(&self) -> Option<usize> {
+        self.dimensions.or(match self.model.as_str() {
+            JINA_EMBEDDINGS_V3 => Some(1024),
+            _ => None,
+        })
+    }
+
+    fn max_input_tokens(&self) -> Option<usize> {
+        match self.model.as_str() {
+            JINA_EMBEDDINGS_V3 => Some(8192),
+            _ => None,
+        }
+    }
+
+    fn model_id(&self) -> Option<String> {
+        Some(match self.dimensions {
+            Some(dims) => format!("jina/{}/{dims}", self.model),
+            None => format!("jina/{}", self.model),
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct EmbeddingsRequest<'a> {
+    model: &'a str,
+    input: Vec<&'a str>,
+    task: Task,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dimensions: Option<usize>,
+    late_chunking: bool,
+    embedding_type: &'a str,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingsResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    embedding: Vec<f32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EmbeddingsRequest, EmbeddingsResponse, Task};
+
+    #[test]
+    fn requests_match_the_api() {
+        let request = EmbeddingsRequest {
+            model: super::JINA_EMBEDDINGS_V3,
+            input: vec!["User lives in Berlin"],
+            task: Task::RetrievalPassage,
+            dimensions: Some(256),
+            late_chunking: true,
+            embedding_type: "float",
+        };
+
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "model": "jina-embeddings-v3",
+                "input": ["User lives in Berlin"],
+                "task": "retrieval.passage",
+                "dimensions": 256,
+                "late_chunking": true,
+                "embedding_type": "float"
+            })
+        );
+
+        let request = EmbeddingsRequest {
+            task: Task::RetrievalQuery,
+            dimensions: None,
+            ..request
+        };
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["task"], "retrieval.query");
+        assert!(json.get("dimensions").is_none());
+    }
+
+    #[test]
+    fn responses_are_parsed() {
+        let response: EmbeddingsResponse = serde_json::from_str(
+            r#"{
+                "model": "jina-embeddings-v3",
+                "object": "list",
+                "usage": {"total_tokens": 6, "prompt_tokens": 6},
+                "data": [{"object": "embedding", "index": 0, "embedding": [0.05, -0.12, 0.08]}]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(response.data[0].embedding, [0.05, -0.12, 0.08]);
+    }
+}
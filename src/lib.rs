@@ -2,12 +2,71 @@ pub mod embed;
 pub mod error;
 pub mod id_gen;
 pub mod memory;
+pub mod metrics;
 pub mod storage;
 pub mod vector_store;
 pub mod wasm;
 
+#[cfg(feature = "bedrock")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bedrock")))]
+pub mod bedrock;
+
+#[cfg(feature = "capi")]
+#[cfg_attr(docsrs, doc(cfg(feature = "capi")))]
+pub mod capi;
+
+#[cfg(feature = "candle")]
+#[cfg_attr(docsrs, doc(cfg(feature = "candle")))]
+pub mod candle;
+
+#[cfg(feature = "cohere")]
+#[cfg_attr(docsrs, doc(cfg(feature = "cohere")))]
+pub mod cohere;
+
+#[cfg(feature = "encryption")]
+#[cfg_attr(docsrs, doc(cfg(feature = "encryption")))]
+pub mod encryption;
+
 #[cfg(feature = "fastembed")]
 #[cfg_attr(docsrs, doc(cfg(feature = "fastembed")))]
 pub mod fastembed;
 
+#[cfg(feature = "gemini")]
+#[cfg_attr(docsrs, doc(cfg(feature = "gemini")))]
+pub mod gemini;
+
+#[cfg(feature = "grpc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "grpc")))]
+pub mod grpc;
+
+#[cfg(feature = "jina")]
+#[cfg_attr(docsrs, doc(cfg(feature = "jina")))]
+pub mod jina;
+
+#[cfg(feature = "llama-cpp")]
+#[cfg_attr(docsrs, doc(cfg(feature = "llama-cpp")))]
+pub mod llama_cpp;
+
+#[cfg(feature = "openai")]
+#[cfg_attr(docsrs, doc(cfg(feature = "openai")))]
+pub mod openai;
+
+#[cfg(feature = "ort")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ort")))]
+pub mod ort;
+
+#[cfg(feature = "server")]
+#[cfg_attr(docsrs, doc(cfg(feature = "server")))]
+pub mod server;
+
+#[cfg(feature = "timeout")]
+#[cfg_attr(docsrs, doc(cfg(feature = "timeout")))]
+pub mod timeout;
+
+#[cfg(any(feature = "cohere", feature = "gemini", feature = "jina"))]
+mod http;
+
+#[cfg(test)]
+pub(crate) mod test_util;
+
 use error::Error;
@@ -0,0 +1,237 @@
+//! A module for handling local embeddings using GGUF models through [`llama.cpp`](https://github.com/ggml-org/llama.cpp) (via the `llama-cpp-2` bindings).
+//! This runs embedding models (eg, `nomic-embed-text` or `bge` models converted to GGUF) fully offline, optionally offloading layers to the GPU.
+//! Ensure that you have the `llama-cpp` feature enabled. For GPU support, enable `llama-cpp-cuda`, `llama-cpp-metal` or `llama-cpp-vulkan`.
+//! Building this feature compiles `llama.cpp` from source, so `cmake` and a C++ compiler are required.
+//! NOTE: This module is not WASM-friendly. Attempting to compile this module to `wasm` architecture will return an error.
+
+use std::num::NonZeroU32;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+use llama_cpp_2::context::params::LlamaContextParams;
+use llama_cpp_2::llama_backend::LlamaBackend;
+use llama_cpp_2::llama_batch::LlamaBatch;
+use llama_cpp_2::model::LlamaModel;
+use llama_cpp_2::model::params::LlamaModelParams;
+
+pub use llama_cpp_2::context::params::LlamaPoolingType as LlamaPooling;
+
+/// How many of the model's layers should be offloaded to the GPU.
+/// Offloading requires `llama.cpp` to be built with a GPU backend (see the `llama-cpp-*` features), otherwise the model runs on the CPU.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GpuOffload {
+    /// Run the model entirely on the CPU.
+    #[default]
+    None,
+    /// Offload the given number of layers.
+    Layers(u32),
+    /// Offload every layer of the model.
+    All,
+}
+
+/// A text embedder that runs GGUF embedding models locally using `llama.cpp`, made compliant to work with the `Embedder` trait.
+/// A fresh `llama.cpp` context sized to the input is created for each embedding. Under the hood, `std::sync::Mutex` is used as contexts can't be created from the same model in parallel.
+pub struct LlamaCppEmbedder {
+    model: LlamaModel,
+    context_lock: Mutex<()>,
+    pooling: LlamaPooling,
+    threads: Option<i32>,
+    dims: usize,
+    max_tokens: usize,
+    normalize: bool,
+    model_id: String,
+}
+
+impl LlamaCppEmbedder {
+    /// Loads a GGUF embedding model from a local file, offloading layers to the GPU as requested.
+    pub fn from_file<P>(model: P, offload: GpuOffload) -> Result<Self, crate::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let params = match offload {
+            GpuOffload::None => LlamaModelParams::default().with_n_gpu_layers(0),
+            GpuOffload::Layers(layers) => LlamaModelParams::default().with_n_gpu_layers(layers),
+            GpuOffload::All => LlamaModelParams::default().with_n_gpu_layers(u32::MAX),
+        };
+
+        Self::from_file_with_params(model, &params)
+    }
+
+    /// Loads a GGUF embedding model from a local file using pre-configured model parameters (eg, with a specific main GPU or split mode).
+    pub fn from_file_with_params<P>(
+        model: P,
+        params: &LlamaModelParams,
+    ) -> Result<Self, crate::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = model.as_ref();
+        if !path.exists() {
+            return Err(crate::Error::Custom(format!(
+                "GGUF model file not found: {}",
+                path.display()
+            )));
+        }
+
+        let model = LlamaModel::load_from_file(backend()?, path, params).map_err(llama_error)?;
+        let dims = usize::try_from(model.n_embd_out()).map_err(llama_error)?;
+        let max_tokens = usize::try_from(model.n_ctx_train()).map_err(llama_error)?;
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+
+        Ok(Self {
+            model,
+            context_lock: Mutex::new(()),
+            pooling: LlamaPooling::Unspecified,
+            threads: None,
+            dims,
+            max_tokens,
+            normalize: true,
+            model_id: format!("llama-cpp/{name}"),
+        })
+    }
+
+    /// Sets how token embeddings are pooled. Defaults to the pooling type declared by the model.
+    /// [`LlamaPooling::None`] and [`LlamaPooling::Rank`] don't produce sentence embeddings, so embedding will fail with either.
+    pub fn pooling(mut self, pooling: LlamaPooling) -> Self {
+        self.pooling = pooling;
+        self
+    }
+
+    /// Sets the number of CPU threads used to embed. Defaults to `llama.cpp`'s own default.
+    pub fn threads(mut self, threads: i32) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Sets whether or not embeddings should be L2-normalized. Defaults to `true`.
+    pub fn normalize(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
+    /// Sets the maximum number of tokens to embed. Longer inputs are truncated. Defaults to the context length the model was trained with.
+    pub fn max_length(mut self, max_length: usize) -> Self {
+        self.max_tokens = max_length;
+        self
+    }
+
+    /// Sets the model identifier recorded on stored memories. Defaults to `llama-cpp/<file name>`.
+    pub fn model_id<S>(mut self, model_id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.model_id = model_id.into();
+        self
+    }
+
+    /// The dimensions of the embeddings created by this model.
+    pub fn dims(&self) -> usize {
+        self.dims
+    }
+
+    fn embed(&self, text: &str) -> Result<Vec<f32>, crate::Error> {
+        let mut tokens = self.model.vocab().tokenize(text.as_bytes(), true, false);
+        tokens.truncate(self.max_tokens);
+        if tokens.is_empty() {
+            return Err(crate::Error::custom("Cannot embed an empty input"));
+        }
+
+        let len = tokens.len();
+        let len_u32 = u32::try_from(len).map_err(llama_error)?;
+        let mut params = LlamaContextParams::default()
+            .with_embeddings(true)
+            .with_n_ctx(NonZeroU32::new(len_u32))
+            .with_n_batch(len_u32)
+            .with_n_ubatch(len_u32)
+            .with_pooling_type(self.pooling);
+        if let Some(threads) = self.threads {
+            params = params.with_n_threads(threads).with_n_threads_batch(threads);
+        }
+
+        let _guard = self
+            .context_lock
+            .lock()
+            .map_err(|_| crate::Error::custom("llama.cpp context mutex was poisoned"))?;
+        let mut context = self
+            .model
+            .new_context(backend()?, params)
+            .map_err(llama_error)?;
+
+        let mut batch = LlamaBatch::new(len, 1);
+        batch.add_sequence(&tokens, 0, false).map_err(llama_error)?;
+        context.decode(&mut batch).map_err(llama_error)?;
+
+        let mut embedding = context.embeddings_seq_ith(0).map_err(llama_error)?.to_vec();
+        if self.normalize {
+            normalize(&mut embedding);
+        }
+
+        Ok(embedding)
+    }
+}
+
+impl crate::embed::Embedder for LlamaCppEmbedder {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "llama_cpp_embed_text", skip_all, fields(input_len = text.len()))
+    )]
+    async fn embed_text(&self, text: &str) -> Result<Vec<f32>, crate::Error> {
+        self.embed(text)
+    }
+
+    fn dims(&self) -> Option<usize> {
+        Some(self.dims)
+    }
+
+    fn max_input_tokens(&self) -> Option<usize> {
+        Some(self.max_tokens)
+    }
+
+    fn model_id(&self) -> Option<String> {
+        Some(self.model_id.clone())
+    }
+}
+
+/// The `llama.cpp` backend can only be initialized once per process, so it's shared between every embedder.
+fn backend() -> Result<&'static LlamaBackend, crate::Error> {
+    static BACKEND: OnceLock<Result<LlamaBackend, String>> = OnceLock::new();
+
+    BACKEND
+        .get_or_init(|| LlamaBackend::init().map_err(|err| err.to_string()))
+        .as_ref()
+        .map_err(|err| crate::Error::Custom(format!("Failed to initialize llama.cpp: {err}")))
+}
+
+/// L2-normalizes an embedding in place, leaving zero vectors untouched.
+fn normalize(embedding: &mut [f32]) {
+    let norm = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        embedding.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+fn llama_error<E>(err: E) -> crate::Error
+where
+    E: Into<crate::error::BoxedError>,
+{
+    crate::Error::wrap("llama.cpp error", err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::normalize;
+
+    #[test]
+    fn embeddings_are_normalized() {
+        let mut embedding = [3.0, 4.0];
+        normalize(&mut embedding);
+        assert_eq!(embedding, [0.6, 0.8]);
+
+        let mut zero = [0.0, 0.0];
+        normalize(&mut zero);
+        assert_eq!(zero, [0.0, 0.0]);
+    }
+}
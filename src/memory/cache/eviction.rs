@@ -0,0 +1,231 @@
+//! Eviction policies, deciding which memories get evicted from a [`super::MemoryCache`] once it's full.
+
+use std::time::Duration;
+
+use crate::{
+    memory::{MemoryEntry, MemoryId, strength},
+    wasm::{WasmCompatSend, WasmCompatSync},
+};
+
+/// A cached memory being considered for eviction.
+#[derive(Clone, Copy, Debug)]
+pub struct EvictionCandidate<'a> {
+    pub entry: &'a MemoryEntry,
+    /// Whenever the memory was inserted into the cache (as a Unix timestamp).
+    pub inserted_at: i64,
+}
+
+/// A trait for deciding which memories get evicted from a [`super::MemoryCache`].
+/// Configured using [`super::MemoryCacheBuilder::eviction_policy`].
+pub trait EvictionPolicy: WasmCompatSend + WasmCompatSync {
+    /// Scores a cached memory - the lower the score, the sooner it gets evicted.
+    fn score(&self, candidate: &EvictionCandidate, now: i64) -> f64;
+
+    /// Whether or not a cached memory has expired, in which case it gets evicted regardless of its score. Defaults to `false`.
+    fn is_expired(&self, _candidate: &EvictionCandidate, _now: i64) -> bool {
+        false
+    }
+
+    /// How many cached memories are randomly sampled when looking for memories to evict, trading accuracy for speed with large caches.
+    /// Defaults to `None`, which considers every cached memory.
+    fn sample_size(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Picks which cached memories to evict out of a set of candidates (paired with whether they've already expired):
+/// every expired memory, plus up to `count` of the lowest scoring memories.
+pub(crate) fn pick_evictions<'a, I>(
+    policy: &dyn EvictionPolicy,
+    candidates: I,
+    count: usize,
+    now: i64,
+) -> Vec<MemoryId>
+where
+    I: IntoIterator<Item = (EvictionCandidate<'a>, bool)>,
+{
+    let mut expired = Vec::new();
+    let mut scored = Vec::new();
+    for (candidate, is_expired) in candidates {
+        if is_expired || policy.is_expired(&candidate, now) {
+            expired.push(candidate.entry.id.clone());
+        } else {
+            scored.push((policy.score(&candidate, now), candidate.entry.id.clone()));
+        }
+    }
+
+    scored.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let remaining = count.saturating_sub(expired.len());
+
+    expired
+        .into_iter()
+        .chain(scored.into_iter().take(remaining).map(|(_, id)| id))
+        .collect()
+}
+
+/// Evicts the least recently accessed memories first.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Lru;
+
+impl EvictionPolicy for Lru {
+    fn score(&self, candidate: &EvictionCandidate, _now: i64) -> f64 {
+        candidate.entry.last_accessed as f64
+    }
+}
+
+/// Evicts the least frequently accessed memories first, breaking ties by evicting the least recently accessed memory.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Lfu;
+
+impl EvictionPolicy for Lfu {
+    fn score(&self, candidate: &EvictionCandidate, now: i64) -> f64 {
+        let recency = (now - candidate.entry.last_accessed).max(0) as f64;
+
+        candidate.entry.access_count as f64 + 1.0 / (1.0 + recency)
+    }
+}
+
+/// Evicts the memories most likely to have faded first, following the spaced-repetition strength model (see [`strength`]).
+/// Memories that keep being useful grow stronger and outlast memories that were only used once, while permanent memories are evicted last.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StrengthDecay;
+
+impl EvictionPolicy for StrengthDecay {
+    fn score(&self, candidate: &EvictionCandidate, now: i64) -> f64 {
+        let entry = candidate.entry;
+
+        strength::retention(entry.strength, now - entry.last_accessed) as f64
+    }
+}
+
+/// Evicts memories that were inserted into the cache longer than the TTL ago, and otherwise evicts the oldest inserted memories first.
+#[derive(Clone, Copy, Debug)]
+pub struct TtlEviction {
+    ttl: Duration,
+}
+
+impl TtlEviction {
+    pub fn new(ttl: Duration) -> Self {
+        Self { ttl }
+    }
+}
+
+impl EvictionPolicy for TtlEviction {
+    fn score(&self, candidate: &EvictionCandidate, _now: i64) -> f64 {
+        candidate.inserted_at as f64
+    }
+
+    fn is_expired(&self, candidate: &EvictionCandidate, now: i64) -> bool {
+        now - candidate.inserted_at >= super::ttl_secs(self.ttl)
+    }
+}
+
+/// The weighting [`ScoreSampling`] scores cached memories with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EvictionWeights {
+    /// Added to the score for each time a memory has been accessed.
+    pub frequency: f64,
+    /// Multiplied by the importance of a memory (between 0.0 and 1.0) and added to the score.
+    pub importance: f64,
+    /// Subtracted from the score for each second since a memory was last accessed.
+    pub recency: f64,
+}
+
+impl Default for EvictionWeights {
+    fn default() -> Self {
+        Self {
+            frequency: 1000.0,
+            importance: 10_000.0,
+            recency: 1.0,
+        }
+    }
+}
+
+/// Samples cached memories at random and evicts the lowest scoring ones, scoring memories by access frequency, importance and recency.
+/// This is the default eviction policy.
+#[derive(Clone, Copy, Debug)]
+pub struct ScoreSampling {
+    sample_size: usize,
+    weights: EvictionWeights,
+}
+
+impl Default for ScoreSampling {
+    fn default() -> Self {
+        Self {
+            sample_size: 100,
+            weights: EvictionWeights::default(),
+        }
+    }
+}
+
+impl ScoreSampling {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how many cached memories are sampled when looking for memories to evict. Defaults to 100.
+    pub fn sample_size(mut self, size: usize) -> Self {
+        self.sample_size = size.max(1);
+        self
+    }
+
+    /// Sets how access frequency, importance and recency are weighted when scoring memories.
+    pub fn weights(mut self, weights: EvictionWeights) -> Self {
+        self.weights = weights;
+        self
+    }
+}
+
+impl EvictionPolicy for ScoreSampling {
+    fn score(&self, candidate: &EvictionCandidate, now: i64) -> f64 {
+        let entry = candidate.entry;
+        let recency = (now - entry.last_accessed) as f64;
+
+        self.weights.frequency * entry.access_count as f64
+            + self.weights.importance * entry.importance as f64
+            - self.weights.recency * recency
+    }
+
+    fn sample_size(&self) -> Option<usize> {
+        Some(self.sample_size)
+    }
+}
+
+/// Scores cached memories using a function (the lower the score, the sooner a memory gets evicted), given a memory and the current Unix timestamp.
+/// Like [`ScoreSampling`], cached memories are sampled at random when looking for memories to evict.
+/// Configured using [`super::MemoryCacheBuilder::eviction_score`].
+pub struct ScoreFn<F> {
+    score: F,
+    sample_size: usize,
+}
+
+impl<F> ScoreFn<F>
+where
+    F: Fn(&MemoryEntry, i64) -> f64,
+{
+    pub fn new(score: F) -> Self {
+        Self {
+            score,
+            sample_size: 100,
+        }
+    }
+
+    /// Sets how many cached memories are sampled when looking for memories to evict. Defaults to 100.
+    pub fn sample_size(mut self, size: usize) -> Self {
+        self.sample_size = size.max(1);
+        self
+    }
+}
+
+impl<F> EvictionPolicy for ScoreFn<F>
+where
+    F: Fn(&MemoryEntry, i64) -> f64 + WasmCompatSend + WasmCompatSync,
+{
+    fn score(&self, candidate: &EvictionCandidate, now: i64) -> f64 {
+        (self.score)(candidate.entry, now)
+    }
+
+    fn sample_size(&self) -> Option<usize> {
+        Some(self.sample_size)
+    }
+}
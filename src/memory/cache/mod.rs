@@ -0,0 +1,1227 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{BuildError, StorageError},
+    memory::{MemoryEntry, MemoryId, MemoryKind},
+    metrics,
+    storage::{SearchResult, Storage},
+    vector_store::{InMemoryDB, InMemoryDBSnapshot, cosine_similarity, read_json, write_json},
+    wasm::{WasmCompatSend, WasmCompatSync},
+};
+
+pub mod eviction;
+mod sharded;
+mod stats;
+
+pub use sharded::ShardedMemoryCache;
+pub(crate) use stats::Stopwatch;
+pub use stats::{CacheStats, LatencyHistogram};
+
+use eviction::{EvictionCandidate, EvictionPolicy, ScoreSampling, pick_evictions};
+
+/// The max memory limit of a [`MemoryCache`] unless configured otherwise.
+const DEFAULT_MEMORY_LIMIT: u32 = 500;
+
+/// A function deciding the TTL of a memory when it gets inserted into the cache (see [`MemoryCacheBuilder::ttl_by`]).
+type TtlFn = Box<dyn Fn(&MemoryEntry) -> Option<Duration> + Send + Sync>;
+
+/// A memory cache.
+/// Uses [`crate::vector_store::InMemoryDB`] internally.
+pub struct MemoryCache {
+    pub store: InMemoryDB,
+    cache_stats: CacheStats,
+    max_memory_limit: u32,
+    query_cache: QueryCache,
+    miss_cache: MissCache,
+    eviction_policy: Box<dyn EvictionPolicy>,
+    default_ttl: Option<Duration>,
+    ttl_fn: Option<TtlFn>,
+    slots: HashMap<MemoryId, CacheSlot>,
+    write_policy: WritePolicy,
+    partitions: HashMap<MemoryKind, CachePartition>,
+}
+
+/// How memories stored through a [`crate::memory::manager::MemoryManager`] with a hot cache reach the backing storage.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WritePolicy {
+    /// Memories are written to the backing storage straight away (and to the hot cache, if they should be cached).
+    #[default]
+    WriteThrough,
+    /// Memories are written to the hot cache straight away, while writes to the backing storage are buffered and flushed once `batch_size` writes are pending.
+    /// Pending writes are flushed when the manager is dropped, but prefer flushing them with [`crate::memory::manager::MemoryManager::close`] so errors can be handled.
+    WriteBack { batch_size: usize },
+}
+
+/// Whether memories of a given kind get cached (see [`CachePartition`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheAdmission {
+    Always,
+    Never,
+    /// Cache memories that are important (with an importance above 0.5) or have been accessed before.
+    WhenImportant,
+}
+
+impl CacheAdmission {
+    /// Whether a memory gets cached under this admission policy.
+    pub fn admits(&self, entry: &MemoryEntry) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Never => false,
+            Self::WhenImportant => entry.importance > 0.5 || entry.access_count > 0,
+        }
+    }
+}
+
+/// A partition of the hot cache, holding the cached memories of a single [`MemoryKind`].
+/// Configured using [`MemoryCacheBuilder::partition`].
+///
+/// By default, semantic and procedural memories are always cached, episodic memories are cached when they're important and working memories are never cached.
+/// Partitions have no size limit of their own by default, sharing the max memory limit of the cache.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CachePartition {
+    pub admission: CacheAdmission,
+    /// The most memories of this kind that the cache holds at once, evicting memories of the same kind to make room for new ones.
+    pub max_memory_limit: Option<u32>,
+}
+
+impl CachePartition {
+    pub fn new(admission: CacheAdmission) -> Self {
+        Self {
+            admission,
+            max_memory_limit: None,
+        }
+    }
+
+    /// The default partition for a given kind of memory.
+    pub fn default_for(kind: MemoryKind) -> Self {
+        let admission = match kind {
+            MemoryKind::Working => CacheAdmission::Never,
+            MemoryKind::Semantic | MemoryKind::Procedural => CacheAdmission::Always,
+            MemoryKind::Episodic => CacheAdmission::WhenImportant,
+        };
+
+        Self::new(admission)
+    }
+
+    /// Sets the most memories of this kind that the cache holds at once.
+    pub fn max_memory_limit(mut self, limit: u32) -> Self {
+        self.max_memory_limit = Some(limit);
+        self
+    }
+}
+
+/// When a cached memory was inserted, and when it expires (as Unix timestamps).
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+struct CacheSlot {
+    inserted_at: i64,
+    expires_at: Option<i64>,
+}
+
+impl CacheSlot {
+    fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
+}
+
+/// A TTL in whole seconds (the resolution of cache timestamps), rounded up so that sub-second TTLs don't expire straight away.
+fn ttl_secs(ttl: Duration) -> i64 {
+    i64::try_from(ttl.as_millis().div_ceil(1000)).unwrap_or(i64::MAX)
+}
+
+impl MemoryCache {
+    /// Creates a new instance of [`MemoryCache`].
+    /// NOTE: The max memory limit by using this method is set to 500. If you'd like to change it, please use the builder.
+    pub fn new(store: InMemoryDB) -> Self {
+        Self {
+            store,
+            cache_stats: CacheStats::new(),
+            max_memory_limit: DEFAULT_MEMORY_LIMIT,
+            query_cache: QueryCache::disabled(),
+            miss_cache: MissCache::disabled(),
+            eviction_policy: Box::new(ScoreSampling::default()),
+            default_ttl: None,
+            ttl_fn: None,
+            slots: HashMap::new(),
+            write_policy: WritePolicy::default(),
+            partitions: HashMap::new(),
+        }
+    }
+
+    /// Creates an empty builder instance for this struct
+    pub fn builder() -> MemoryCacheBuilder {
+        MemoryCacheBuilder::default()
+    }
+
+    /// Get the cache stats.
+    pub fn stats(&self) -> &CacheStats {
+        &self.cache_stats
+    }
+
+    /// The max memory limit before automatic eviction of items to make way for new cached memories.
+    pub fn memory_limit(&self) -> u32 {
+        self.max_memory_limit
+    }
+
+    pub fn stats_mut(&mut self) -> &mut CacheStats {
+        &mut self.cache_stats
+    }
+
+    /// The partition holding memories of a given kind (see [`MemoryCacheBuilder::partition`]).
+    pub fn partition(&self, kind: MemoryKind) -> CachePartition {
+        self.partitions
+            .get(&kind)
+            .copied()
+            .unwrap_or_else(|| CachePartition::default_for(kind))
+    }
+
+    /// Whether a memory should be cached, according to the admission policy of its partition.
+    pub fn admits(&self, entry: &MemoryEntry) -> bool {
+        self.partition(entry.kind).admission.admits(entry)
+    }
+
+    /// How memories reach the backing storage (see [`MemoryCacheBuilder::write_policy`]).
+    pub fn write_policy(&self) -> WritePolicy {
+        self.write_policy
+    }
+
+    /// Looks up the results of a previous query whose embedding is similar enough to the given query embedding.
+    /// Returns `None` if query result caching is disabled, there is no suitable cached query or the cached results include an expired memory.
+    pub fn cached_query_results(
+        &self,
+        embedding: &[f32],
+        limit: usize,
+    ) -> Option<Vec<SearchResult>> {
+        let results = self.query_cache.get(embedding, limit)?;
+
+        let now = chrono::Utc::now().timestamp();
+        let has_expired = results.iter().any(|result| {
+            self.slots
+                .get(&result.data().id)
+                .is_some_and(|slot| slot.is_expired(now))
+        });
+
+        (!has_expired).then_some(results)
+    }
+
+    /// Searches the cached memories, lazily evicting any expired memories that would have been returned.
+    pub async fn search(
+        &mut self,
+        embedding: Vec<f32>,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        let mut results = self.store.search(embedding, limit).await?;
+
+        let now = chrono::Utc::now().timestamp();
+        let expired: Vec<MemoryId> = results
+            .iter()
+            .map(|result| &result.data().id)
+            .filter(|id| self.slots.get(*id).is_some_and(|slot| slot.is_expired(now)))
+            .cloned()
+            .collect();
+
+        if !expired.is_empty() {
+            results.retain(|result| !expired.contains(&result.data().id));
+            for id in &expired {
+                self.remove(id).await?;
+            }
+            self.invalidate_query_cache();
+            self.record_evictions(expired.len());
+
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                evicted = expired.len(),
+                "lazily evicted expired memories from hot cache"
+            );
+        }
+
+        Ok(results)
+    }
+
+    /// Caches the results of a query, evicting the oldest cached query if the query cache is full.
+    pub fn cache_query_results(
+        &mut self,
+        embedding: Vec<f32>,
+        limit: usize,
+        results: Vec<SearchResult>,
+    ) {
+        self.query_cache.insert(embedding, limit, results);
+    }
+
+    /// Whether a query similar enough to the given query embedding recently had no acceptable results (see [`MemoryCacheBuilder::negative_cache_ttl`]).
+    /// Always returns `false` if negative caching is disabled.
+    pub fn is_known_miss(&self, embedding: &[f32]) -> bool {
+        self.miss_cache
+            .contains(embedding, chrono::Utc::now().timestamp())
+    }
+
+    /// Caches a query as a miss if none of its results are acceptable, so that similar queries can skip searching until the miss expires.
+    /// Returns whether the query was cached as a miss, which is always `false` if negative caching is disabled.
+    pub fn cache_miss(&mut self, embedding: Vec<f32>, results: &[SearchResult]) -> bool {
+        if !self.miss_cache.is_miss(&embedding, results) {
+            return false;
+        }
+
+        self.miss_cache
+            .insert(embedding, chrono::Utc::now().timestamp())
+    }
+
+    /// Clears all cached query results and misses. This should be called whenever the underlying memories change.
+    pub fn invalidate_query_cache(&mut self) {
+        self.query_cache.clear();
+        self.miss_cache.clear();
+    }
+
+    /// Inserts a memory into the cache, evicting memories first if the cache is full.
+    /// The memory expires after the TTL configured on the builder (see [`MemoryCacheBuilder::ttl`] and [`MemoryCacheBuilder::ttl_by`]), if any.
+    pub async fn insert(
+        &mut self,
+        embedding: Vec<f32>,
+        entry: MemoryEntry,
+    ) -> Result<(), crate::Error> {
+        let ttl = self
+            .ttl_fn
+            .as_ref()
+            .and_then(|ttl_fn| ttl_fn(&entry))
+            .or(self.default_ttl);
+
+        self.insert_with_ttl(embedding, entry, ttl).await
+    }
+
+    /// Inserts a memory into the cache with a specific TTL (or no TTL), overriding the TTL configured on the builder.
+    pub async fn insert_with_ttl(
+        &mut self,
+        embedding: Vec<f32>,
+        entry: MemoryEntry,
+        ttl: Option<Duration>,
+    ) -> Result<(), crate::Error> {
+        let stopwatch = Stopwatch::start();
+        if let Some(limit) = self.partition(entry.kind).max_memory_limit {
+            let count = self
+                .store
+                .entries()
+                .filter(|cached| cached.kind == entry.kind && cached.id != entry.id)
+                .count();
+            if count >= limit as usize {
+                self.evict(count + 1 - limit as usize, Some(entry.kind))
+                    .await?;
+            }
+        }
+        // Replacing a cached memory doesn't grow the cache, so it doesn't need to make room
+        let count = self.store.len() - usize::from(self.store.contains(&entry.id));
+        if count >= self.max_memory_limit as usize {
+            self.evict_from_cache(count + 1 - self.max_memory_limit as usize)
+                .await?;
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        let slot = CacheSlot {
+            inserted_at: now,
+            expires_at: ttl.map(|ttl| now.saturating_add(ttl_secs(ttl))),
+        };
+        self.slots.insert(entry.id.clone(), slot);
+        self.store.insert(embedding, entry).await?;
+
+        let size = self.store.len();
+        self.cache_stats.add_insert();
+        self.cache_stats.set_size(size);
+        metrics::record_cache_insert(size);
+        stopwatch.record(self.cache_stats.insert_latency_mut(), "insert");
+
+        Ok(())
+    }
+
+    /// Removes a memory from the cache, if the cache holds it.
+    pub async fn remove(&mut self, id: &str) -> Result<(), crate::Error> {
+        if self.store.contains(id) {
+            self.store.delete(id.into()).await?;
+        }
+        self.slots.remove(id);
+        self.cache_stats.set_size(self.store.len());
+
+        Ok(())
+    }
+
+    /// Evicts every expired memory from the cache, returning how many memories were evicted.
+    /// Expired memories are also evicted lazily when they're accessed, so this only needs to be called periodically to free up space.
+    pub async fn sweep_expired(&mut self) -> Result<usize, crate::Error> {
+        let now = chrono::Utc::now().timestamp();
+        let expired: Vec<MemoryId> = self
+            .slots
+            .iter()
+            .filter(|(_, slot)| slot.is_expired(now))
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for id in &expired {
+            self.remove(id).await?;
+        }
+        if !expired.is_empty() {
+            self.invalidate_query_cache();
+        }
+        self.record_evictions(expired.len());
+
+        #[cfg(feature = "tracing")]
+        if expired.is_empty() {
+            tracing::debug!("swept hot cache, no memories had expired");
+        } else {
+            tracing::info!(
+                evicted = expired.len(),
+                "swept expired memories from hot cache"
+            );
+        }
+
+        Ok(expired.len())
+    }
+
+    /// Saves the cached memories (along with when they expire) and the cache stats to a file, so that a restarted process can resume with a warm cache using [`MemoryCache::restore_snapshot`].
+    pub fn save_snapshot<P>(&self, path: P) -> Result<(), crate::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let snapshot = CacheSnapshot {
+            store: self.store.snapshot(),
+            slots: self.slots.clone(),
+            stats: self.cache_stats.clone(),
+        };
+
+        write_json(path.as_ref(), &snapshot)
+    }
+
+    /// Replaces the cached memories and the cache stats with a snapshot saved by [`MemoryCache::save_snapshot`], returning how many memories were restored.
+    /// The cache keeps its own configuration (eg, the memory limit and eviction policy). Memories that expired since the snapshot was saved are skipped,
+    /// and memories are evicted if the snapshot holds more memories than the cache can.
+    pub async fn restore_snapshot<P>(&mut self, path: P) -> Result<usize, crate::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let CacheSnapshot {
+            mut store,
+            mut slots,
+            stats,
+        } = read_json(path.as_ref())?;
+        if store.dims != self.store.dims() {
+            Err(StorageError::mismatched_dimensions(
+                self.store.dims(),
+                store.dims,
+            ))?
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        #[cfg(feature = "tracing")]
+        let saved = store.entries.len();
+        store.entries.retain(|snapshot| {
+            !slots
+                .get(&snapshot.entry.id)
+                .is_some_and(|slot| slot.is_expired(now))
+        });
+        #[cfg(feature = "tracing")]
+        let skipped = saved - store.entries.len();
+        slots.retain(|id, _| {
+            store
+                .entries
+                .iter()
+                .any(|snapshot| &snapshot.entry.id == id)
+        });
+
+        self.store = InMemoryDB::from_snapshot(store)?;
+        self.slots = slots;
+        self.cache_stats = stats;
+        self.cache_stats.set_size(self.store.len());
+        self.invalidate_query_cache();
+
+        let limit = self.max_memory_limit as usize;
+        if self.store.len() > limit {
+            self.evict_from_cache(self.store.len() - limit).await?;
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            restored = self.store.len(),
+            skipped_expired = skipped,
+            "restored hot cache from snapshot"
+        );
+
+        Ok(self.store.len())
+    }
+
+    /// Replaces the cached memories with the contents of another store (eg, an empty store after the embedder has changed).
+    pub(crate) fn replace_store(&mut self, store: InMemoryDB) {
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            discarded = self.store.len(),
+            dims = store.dims(),
+            "replaced hot cache store"
+        );
+
+        self.store = store;
+        self.slots.clear();
+        self.cache_stats.set_size(self.store.len());
+        self.invalidate_query_cache();
+    }
+
+    /// Evicts up to `count` memories, picked by the eviction policy (see [`MemoryCacheBuilder::eviction_policy`]).
+    /// Memories that the eviction policy considers expired are always evicted, even if that means evicting more than `count` memories.
+    pub async fn evict_from_cache(&mut self, count: usize) -> Result<(), crate::Error> {
+        self.evict(count, None).await
+    }
+
+    /// Evicts up to `count` memories of a given kind (or of any kind), picked by the eviction policy.
+    async fn evict(&mut self, count: usize, kind: Option<MemoryKind>) -> Result<(), crate::Error> {
+        let stopwatch = Stopwatch::start();
+        let now = chrono::Utc::now().timestamp();
+        let in_partition = |entry: &MemoryEntry| kind.is_none_or(|kind| entry.kind == kind);
+        let entries = match self.eviction_policy.sample_size() {
+            Some(size) => self.store.random_sample(size, in_partition),
+            None => self
+                .store
+                .entries()
+                .filter(|entry| in_partition(entry))
+                .collect(),
+        };
+
+        let candidates = entries.into_iter().map(|entry| {
+            let slot = self.slots.get(&entry.id);
+            let candidate = EvictionCandidate {
+                entry,
+                inserted_at: slot.map_or(entry.created_at, |slot| slot.inserted_at),
+            };
+
+            (candidate, slot.is_some_and(|slot| slot.is_expired(now)))
+        });
+        let to_evict = pick_evictions(self.eviction_policy.as_ref(), candidates, count, now);
+
+        for id in &to_evict {
+            self.store.delete(id.to_owned()).await?;
+            self.slots.remove(id);
+        }
+
+        #[cfg(feature = "tracing")]
+        if !to_evict.is_empty() {
+            // Can be more than requested, as expired memories are always evicted
+            tracing::debug!(
+                evicted = to_evict.len(),
+                requested = count,
+                ?kind,
+                ids = ?to_evict,
+                "evicted memories from hot cache"
+            );
+        }
+
+        self.record_evictions(to_evict.len());
+        stopwatch.record(self.cache_stats.eviction_latency_mut(), "evict");
+
+        Ok(())
+    }
+
+    fn record_evictions(&mut self, count: usize) {
+        self.cache_stats.add_evictions(count);
+        self.cache_stats.set_size(self.store.len());
+        metrics::record_evictions(count);
+        metrics::record_cache_size(self.store.len());
+    }
+}
+
+/// The contents of a [`MemoryCache`] saved by [`MemoryCache::save_snapshot`].
+#[derive(Deserialize, Serialize)]
+struct CacheSnapshot {
+    store: InMemoryDBSnapshot,
+    slots: HashMap<MemoryId, CacheSlot>,
+    stats: CacheStats,
+}
+
+/// A bounded cache of recent query embeddings along with their results.
+struct QueryCache {
+    entries: VecDeque<CachedQuery>,
+    capacity: usize,
+    similarity_threshold: f32,
+}
+
+struct CachedQuery {
+    embedding: Vec<f32>,
+    limit: usize,
+    results: Vec<SearchResult>,
+}
+
+impl QueryCache {
+    fn new(capacity: usize, similarity_threshold: f32) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+            similarity_threshold,
+        }
+    }
+
+    fn disabled() -> Self {
+        Self::new(0, 1.0)
+    }
+
+    fn get(&self, embedding: &[f32], limit: usize) -> Option<Vec<SearchResult>> {
+        self.entries
+            .iter()
+            .filter(|cached| cached.limit >= limit && cached.embedding.len() == embedding.len())
+            .map(|cached| (cosine_similarity(embedding, &cached.embedding), cached))
+            .filter(|(similarity, _)| *similarity >= self.similarity_threshold)
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, cached)| cached.results.iter().take(limit).cloned().collect())
+    }
+
+    fn insert(&mut self, embedding: Vec<f32>, limit: usize, results: Vec<SearchResult>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+
+        self.entries.push_back(CachedQuery {
+            embedding,
+            limit,
+            results,
+        });
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// A bounded cache of recent query embeddings that had no acceptable results, along with when they expire.
+struct MissCache {
+    entries: VecDeque<CachedMiss>,
+    capacity: usize,
+    /// `None` if negative caching is disabled.
+    ttl: Option<Duration>,
+    similarity_threshold: f32,
+    min_similarity: Option<f32>,
+}
+
+struct CachedMiss {
+    embedding: Vec<f32>,
+    expires_at: i64,
+}
+
+impl MissCache {
+    fn disabled() -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity: 0,
+            ttl: None,
+            similarity_threshold: 1.0,
+            min_similarity: None,
+        }
+    }
+
+    /// Whether a query had no acceptable results: either no results at all, or none at least as similar to the query as the minimum similarity.
+    fn is_miss(&self, embedding: &[f32], results: &[SearchResult]) -> bool {
+        match self.min_similarity {
+            Some(min_similarity) => results
+                .iter()
+                .all(|result| cosine_similarity(embedding, result.embedding()) < min_similarity),
+            None => results.is_empty(),
+        }
+    }
+
+    fn contains(&self, embedding: &[f32], now: i64) -> bool {
+        self.entries.iter().any(|miss| {
+            now < miss.expires_at
+                && miss.embedding.len() == embedding.len()
+                && cosine_similarity(embedding, &miss.embedding) >= self.similarity_threshold
+        })
+    }
+
+    fn insert(&mut self, embedding: Vec<f32>, now: i64) -> bool {
+        let Some(ttl) = self.ttl else {
+            return false;
+        };
+        if self.capacity == 0 {
+            return false;
+        }
+
+        self.entries.retain(|miss| now < miss.expires_at);
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+
+        self.entries.push_back(CachedMiss {
+            embedding,
+            expires_at: now.saturating_add(ttl_secs(ttl)),
+        });
+
+        true
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryCacheBuilder {
+    pub store: Option<InMemoryDB>,
+    max_memory_limit: Option<u32>,
+    query_cache_size: Option<usize>,
+    query_similarity_threshold: Option<f32>,
+    negative_cache_ttl: Option<Duration>,
+    negative_cache_size: Option<usize>,
+    miss_similarity_threshold: Option<f32>,
+    eviction_policy: Option<Box<dyn EvictionPolicy>>,
+    ttl: Option<Duration>,
+    ttl_fn: Option<TtlFn>,
+    write_policy: Option<WritePolicy>,
+    partitions: HashMap<MemoryKind, CachePartition>,
+}
+
+impl MemoryCacheBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Configures the cache builder to use a pre-existing in-memory database.
+    pub fn store(mut self, store: InMemoryDB) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Configures manual max memory limit. Defaults to 500, and must be at least 1.
+    pub fn max_memory_limit(mut self, limit: u32) -> Self {
+        self.max_memory_limit = Some(limit);
+        self
+    }
+
+    /// Enables semantic query result caching, keeping the results of up to `size` recent queries.
+    /// Queries whose embeddings are similar enough to a cached query (see [`MemoryCacheBuilder::query_similarity_threshold`]) are served from the cache.
+    pub fn query_cache_size(mut self, size: usize) -> Self {
+        self.query_cache_size = Some(size);
+        self
+    }
+
+    /// Sets the minimum similarity (between 0.0 and 1.0, using the same scale as search) for a query to be served from the query cache. Defaults to 0.98.
+    pub fn query_similarity_threshold(mut self, threshold: f32) -> Self {
+        self.query_similarity_threshold = Some(threshold);
+        self
+    }
+
+    /// Enables negative caching: queries with no acceptable results (see [`MemoryCacheBuilder::miss_similarity_threshold`]) are remembered for the given TTL,
+    /// so that repeating a similar query skips searching the backing storage and returns no results.
+    /// Cached misses are cleared whenever memories get stored or removed. Queries count as similar using [`MemoryCacheBuilder::query_similarity_threshold`].
+    pub fn negative_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.negative_cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Sets how many recent misses are kept by the negative cache. Defaults to 100.
+    pub fn negative_cache_size(mut self, size: usize) -> Self {
+        self.negative_cache_size = Some(size);
+        self
+    }
+
+    /// Sets the similarity (between 0.0 and 1.0, using the same scale as search) a result must have to the query for the query not to count as a miss.
+    /// By default, only queries with no results at all count as misses.
+    pub fn miss_similarity_threshold(mut self, threshold: f32) -> Self {
+        self.miss_similarity_threshold = Some(threshold);
+        self
+    }
+
+    /// Sets the policy used to pick which memories get evicted once the cache is full (eg, [`eviction::Lru`], [`eviction::Lfu`], [`eviction::StrengthDecay`] or [`eviction::TtlEviction`]).
+    /// Defaults to [`ScoreSampling`].
+    pub fn eviction_policy<P>(mut self, policy: P) -> Self
+    where
+        P: EvictionPolicy + 'static,
+    {
+        self.eviction_policy = Some(Box::new(policy));
+        self
+    }
+
+    /// Sets the function used to score cached memories once the cache is full (the lower the score, the sooner a memory gets evicted), given a memory and the current Unix timestamp.
+    /// This is shorthand for using [`eviction::ScoreFn`] as the eviction policy. To only tune the default weighting, use [`ScoreSampling::weights`].
+    pub fn eviction_score<F>(self, score: F) -> Self
+    where
+        F: Fn(&MemoryEntry, i64) -> f64 + WasmCompatSend + WasmCompatSync + 'static,
+    {
+        self.eviction_policy(eviction::ScoreFn::new(score))
+    }
+
+    /// Sets a TTL for every cached memory, after which it's evicted lazily (when it would be returned from a search) or by [`MemoryCache::sweep_expired`].
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Sets the TTL of each cached memory using a function (eg, a short TTL for episodic memories), falling back to [`MemoryCacheBuilder::ttl`] when it returns `None`.
+    pub fn ttl_by<F>(mut self, ttl_fn: F) -> Self
+    where
+        F: Fn(&MemoryEntry) -> Option<Duration> + Send + Sync + 'static,
+    {
+        self.ttl_fn = Some(Box::new(ttl_fn));
+        self
+    }
+
+    /// Configures the partition holding memories of a given kind (eg, never caching working memories, or capping how many episodic memories are cached).
+    /// Kinds without a configured partition use [`CachePartition::default_for`].
+    pub fn partition(mut self, kind: MemoryKind, partition: CachePartition) -> Self {
+        self.partitions.insert(kind, partition);
+        self
+    }
+
+    /// Sets how memories reach the backing storage. Defaults to [`WritePolicy::WriteThrough`].
+    pub fn write_policy(mut self, policy: WritePolicy) -> Self {
+        self.write_policy = Some(policy);
+        self
+    }
+
+    /// Build a [`ShardedMemoryCache`] with a given number of shards, which can be used from multiple tasks (or threads) at once.
+    /// The cached memories start out as the contents of the store. Only the max memory limit (split evenly between shards) and the eviction policy apply to sharded caches.
+    /// Returns an error if no store was provided, the max memory limit is 0 or any other option (TTLs, partitions, the query and negative caches or a write policy) was set.
+    pub fn build_sharded(self, shard_count: usize) -> Result<ShardedMemoryCache, crate::Error> {
+        let Some(store) = self.store else {
+            return Err(BuildError::CacheStoreNotFound)?;
+        };
+        let max_memory_limit = validate_memory_limit(self.max_memory_limit)?;
+
+        let unsupported = [
+            ("TTLs", self.ttl.is_some() || self.ttl_fn.is_some()),
+            ("partitions", !self.partitions.is_empty()),
+            (
+                "a query cache",
+                self.query_cache_size.is_some() || self.query_similarity_threshold.is_some(),
+            ),
+            (
+                "a negative cache",
+                self.negative_cache_ttl.is_some()
+                    || self.negative_cache_size.is_some()
+                    || self.miss_similarity_threshold.is_some(),
+            ),
+            ("a write policy", self.write_policy.is_some()),
+        ];
+        if let Some((option, _)) = unsupported.iter().find(|(_, set)| *set) {
+            return Err(BuildError::invalid_config(&format!(
+                "Sharded memory caches don't support {option}"
+            )))?;
+        }
+
+        let cache = ShardedMemoryCache::new(
+            store.dims(),
+            shard_count,
+            max_memory_limit,
+            self.eviction_policy
+                .unwrap_or_else(|| Box::new(ScoreSampling::default())),
+        );
+        for snapshot in store.snapshot().entries {
+            cache.insert(snapshot.embedding, snapshot.entry)?;
+        }
+
+        Ok(cache)
+    }
+
+    /// Build the [`MemoryCache`]. The max memory limit defaults to 500.
+    /// Returns an error if no store was provided, the max memory limit is 0 or a write-back batch size is 0.
+    pub fn build(self) -> Result<MemoryCache, crate::Error> {
+        let Some(store) = self.store else {
+            return Err(BuildError::CacheStoreNotFound)?;
+        };
+
+        let max_memory_limit = validate_memory_limit(self.max_memory_limit)?;
+        if self
+            .partitions
+            .values()
+            .any(|partition| partition.max_memory_limit == Some(0))
+        {
+            return Err(BuildError::invalid_config(
+                "The max memory limit of a memory cache partition must be at least 1",
+            ))?;
+        }
+        if let Some(WritePolicy::WriteBack { batch_size: 0 }) = self.write_policy {
+            return Err(BuildError::invalid_config(
+                "The batch size of a write-back memory cache must be at least 1",
+            ))?;
+        }
+
+        let query_similarity_threshold = self.query_similarity_threshold.unwrap_or(0.98);
+        let query_cache = QueryCache::new(
+            self.query_cache_size.unwrap_or_default(),
+            query_similarity_threshold,
+        );
+        let miss_cache = MissCache {
+            entries: VecDeque::new(),
+            capacity: self.negative_cache_size.unwrap_or(100),
+            ttl: self.negative_cache_ttl,
+            similarity_threshold: query_similarity_threshold,
+            min_similarity: self.miss_similarity_threshold,
+        };
+
+        let res = MemoryCache {
+            store,
+            max_memory_limit,
+            cache_stats: CacheStats::new(),
+            query_cache,
+            miss_cache,
+            eviction_policy: self
+                .eviction_policy
+                .unwrap_or_else(|| Box::new(ScoreSampling::default())),
+            default_ttl: self.ttl,
+            ttl_fn: self.ttl_fn,
+            slots: HashMap::new(),
+            write_policy: self.write_policy.unwrap_or_default(),
+            partitions: self.partitions,
+        };
+
+        Ok(res)
+    }
+}
+
+/// Returns the max memory limit of a cache (defaulting to 500), or an error if it's 0.
+fn validate_memory_limit(limit: Option<u32>) -> Result<u32, crate::Error> {
+    let limit = limit.unwrap_or(DEFAULT_MEMORY_LIMIT);
+    if limit == 0 {
+        Err(BuildError::invalid_config(
+            "The max memory limit of a memory cache must be at least 1",
+        ))?
+    }
+
+    Ok(limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{CacheAdmission, CachePartition, MemoryCache, eviction::Lru};
+    use crate::{
+        memory::{MemoryEntry, MemoryKind},
+        storage::SearchResult,
+        test_util,
+        vector_store::InMemoryDB,
+    };
+
+    fn entry(id: &str, last_accessed: i64) -> MemoryEntry {
+        MemoryEntry {
+            last_accessed,
+            ..test_util::entry(id, id)
+        }
+    }
+
+    #[tokio::test]
+    async fn lru_evicts_least_recently_accessed() {
+        let mut cache = MemoryCache::builder()
+            .store(InMemoryDB::new(2))
+            .max_memory_limit(2)
+            .eviction_policy(Lru)
+            .build()
+            .unwrap();
+
+        cache
+            .insert(vec![1.0, 0.0], entry("stale", 100))
+            .await
+            .unwrap();
+        cache
+            .insert(vec![0.0, 1.0], entry("fresh", 300))
+            .await
+            .unwrap();
+        cache
+            .insert(vec![1.0, 1.0], entry("new", 200))
+            .await
+            .unwrap();
+
+        assert!(!cache.store.contains("stale"));
+        assert!(cache.store.contains("fresh"));
+        assert!(cache.store.contains("new"));
+
+        let stats = cache.stats();
+        assert_eq!(stats.inserts(), 3);
+        assert_eq!(stats.evictions(), 1);
+        assert_eq!(stats.size(), 2);
+        assert_eq!(stats.insert_latency().count(), 3);
+        assert!(stats.insert_latency().percentile(0.99).is_some());
+
+        // Replacing a cached memory doesn't evict anything
+        cache
+            .insert(vec![0.0, 1.0], entry("fresh", 400))
+            .await
+            .unwrap();
+        assert_eq!(cache.stats().evictions(), 1);
+        assert_eq!(cache.store.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn custom_eviction_score_is_used() {
+        let mut cache = MemoryCache::builder()
+            .store(InMemoryDB::new(2))
+            .max_memory_limit(2)
+            .eviction_score(|entry, _now| entry.importance as f64)
+            .build()
+            .unwrap();
+
+        let mut vital = entry("vital", 100);
+        vital.importance = 0.9;
+        let mut trivial = entry("trivial", 300);
+        trivial.importance = 0.1;
+        cache.insert(vec![1.0, 0.0], vital).await.unwrap();
+        cache.insert(vec![0.0, 1.0], trivial).await.unwrap();
+        cache
+            .insert(vec![1.0, 1.0], entry("new", 200))
+            .await
+            .unwrap();
+
+        assert!(cache.store.contains("vital"));
+        assert!(!cache.store.contains("trivial"));
+    }
+
+    #[test]
+    fn similar_queries_are_served_from_the_query_cache() {
+        let mut cache = MemoryCache::builder()
+            .store(InMemoryDB::new(2))
+            .query_cache_size(1)
+            .query_similarity_threshold(0.95)
+            .build()
+            .unwrap();
+
+        let results = vec![SearchResult::new(vec![1.0, 0.0], entry("cached", 0))];
+        cache.cache_query_results(vec![1.0, 0.0], 1, results);
+
+        let hit = cache.cached_query_results(&[1.0, 0.05], 1).unwrap();
+        assert_eq!(hit[0].data().id, "cached");
+        assert!(cache.cached_query_results(&[0.0, 1.0], 1).is_none());
+        // The cached query can't answer a query for more results than it had
+        assert!(cache.cached_query_results(&[1.0, 0.0], 2).is_none());
+
+        cache.invalidate_query_cache();
+        assert!(cache.cached_query_results(&[1.0, 0.0], 1).is_none());
+    }
+
+    #[tokio::test]
+    async fn snapshots_restore_entries_and_stats() {
+        let path = std::env::temp_dir().join(format!(
+            "braindump-cache-snapshot-{}.json",
+            std::process::id()
+        ));
+        let mut cache = MemoryCache::builder()
+            .store(InMemoryDB::new(2))
+            .max_memory_limit(10)
+            .ttl_by(|entry| (entry.id == "working").then_some(Duration::ZERO))
+            .build()
+            .unwrap();
+        cache
+            .insert(vec![1.0, 0.0], entry("kept", 100))
+            .await
+            .unwrap();
+        cache
+            .insert(vec![0.0, 1.0], entry("working", 100))
+            .await
+            .unwrap();
+        cache.save_snapshot(&path).unwrap();
+
+        let mut restored = MemoryCache::new(InMemoryDB::new(2));
+        let count = restored.restore_snapshot(&path).await;
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(count.unwrap(), 1);
+        assert!(restored.store.contains("kept"));
+        assert!(!restored.store.contains("working"));
+        assert_eq!(restored.stats().inserts(), 2);
+        assert_eq!(restored.stats().size(), 1);
+    }
+
+    #[tokio::test]
+    async fn partitions_have_independent_limits() {
+        let mut cache = MemoryCache::builder()
+            .store(InMemoryDB::new(2))
+            .max_memory_limit(10)
+            .eviction_policy(Lru)
+            .partition(
+                MemoryKind::Episodic,
+                CachePartition::new(CacheAdmission::Always).max_memory_limit(1),
+            )
+            .build()
+            .unwrap();
+
+        let mut episode = entry("episode", 100);
+        episode.kind = MemoryKind::Episodic;
+        assert!(cache.admits(&episode));
+        cache.insert(vec![1.0, 0.0], episode).await.unwrap();
+        cache
+            .insert(vec![0.0, 1.0], entry("fact", 50))
+            .await
+            .unwrap();
+
+        let mut newer = entry("newer", 200);
+        newer.kind = MemoryKind::Episodic;
+        cache.insert(vec![1.0, 1.0], newer).await.unwrap();
+
+        assert!(!cache.store.contains("episode"));
+        assert!(cache.store.contains("newer"));
+        assert!(cache.store.contains("fact"));
+
+        let mut working = entry("working", 0);
+        working.kind = MemoryKind::Working;
+        assert!(!cache.admits(&working));
+    }
+
+    #[test]
+    fn sharded_cache_can_be_shared_between_threads() {
+        let cache = MemoryCache::builder()
+            .store(InMemoryDB::new(2))
+            .max_memory_limit(40)
+            .build_sharded(4)
+            .unwrap();
+
+        std::thread::scope(|scope| {
+            for thread in 0..4 {
+                let cache = &cache;
+                scope.spawn(move || {
+                    for idx in 0..5 {
+                        let id = format!("{thread}-{idx}");
+                        cache
+                            .insert(vec![1.0, thread as f32], entry(&id, 0))
+                            .unwrap();
+                    }
+                });
+            }
+        });
+
+        assert_eq!(cache.len(), 20);
+        assert!(cache.contains("3-4"));
+        assert_eq!(cache.get("0-0").unwrap().unwrap().data().id, "0-0");
+
+        let results = cache.search(&[0.0, 1.0], 5).unwrap();
+        assert_eq!(results.len(), 5);
+        assert!(
+            results
+                .iter()
+                .all(|result| result.data().id.starts_with('3'))
+        );
+
+        assert!(cache.remove("3-4").unwrap());
+        let stats = cache.stats();
+        assert_eq!(stats.inserts(), 20);
+        assert_eq!(stats.hits(), 1);
+        assert_eq!(stats.size(), 19);
+    }
+
+    #[test]
+    fn sharded_caches_reject_unsupported_options() {
+        let builder = || MemoryCache::builder().store(InMemoryDB::new(2));
+
+        assert!(builder().build_sharded(4).is_ok());
+        assert!(
+            builder()
+                .ttl(Duration::from_secs(60))
+                .build_sharded(4)
+                .is_err()
+        );
+        assert!(
+            builder()
+                .partition(
+                    MemoryKind::Working,
+                    CachePartition::new(CacheAdmission::Always)
+                )
+                .build_sharded(4)
+                .is_err()
+        );
+        assert!(builder().query_cache_size(10).build_sharded(4).is_err());
+        assert!(
+            builder()
+                .negative_cache_ttl(Duration::from_secs(60))
+                .build_sharded(4)
+                .is_err()
+        );
+        assert!(
+            builder()
+                .write_policy(super::WritePolicy::WriteBack { batch_size: 2 })
+                .build_sharded(4)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn build_validates_configuration() {
+        assert!(MemoryCache::builder().build().is_err());
+        assert!(
+            MemoryCache::builder()
+                .store(InMemoryDB::new(2))
+                .max_memory_limit(0)
+                .build()
+                .is_err()
+        );
+
+        let cache = MemoryCache::builder()
+            .store(InMemoryDB::new(2))
+            .build()
+            .unwrap();
+        assert_eq!(cache.memory_limit(), 500);
+    }
+
+    #[test]
+    fn misses_are_cached_until_invalidated() {
+        let mut cache = MemoryCache::builder()
+            .store(InMemoryDB::new(2))
+            .negative_cache_ttl(Duration::from_secs(60))
+            .miss_similarity_threshold(0.8)
+            .build()
+            .unwrap();
+
+        let unrelated = SearchResult::new(vec![0.0, 1.0], entry("unrelated", 0));
+        assert!(!cache.cache_miss(
+            vec![1.0, 0.0],
+            &[SearchResult::new(vec![1.0, 0.1], entry("related", 0))]
+        ));
+        assert!(cache.cache_miss(vec![1.0, 0.0], &[unrelated]));
+        assert!(cache.is_known_miss(&[1.0, 0.0]));
+        assert!(!cache.is_known_miss(&[0.0, 1.0]));
+
+        cache.invalidate_query_cache();
+        assert!(!cache.is_known_miss(&[1.0, 0.0]));
+    }
+
+    #[tokio::test]
+    async fn expired_memories_are_evicted() {
+        let mut cache = MemoryCache::builder()
+            .store(InMemoryDB::new(2))
+            .max_memory_limit(10)
+            .ttl_by(|entry| (entry.id == "working").then_some(Duration::ZERO))
+            .build()
+            .unwrap();
+
+        cache
+            .insert(vec![1.0, 0.0], entry("working", 0))
+            .await
+            .unwrap();
+        cache
+            .insert(vec![1.0, 0.1], entry("lasting", 0))
+            .await
+            .unwrap();
+        cache
+            .insert_with_ttl(vec![0.0, 1.0], entry("scratch", 0), Some(Duration::ZERO))
+            .await
+            .unwrap();
+
+        let results = cache.search(vec![1.0, 0.0], 2).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].data().id, "lasting");
+        assert!(!cache.store.contains("working"));
+
+        assert_eq!(cache.sweep_expired().await.unwrap(), 1);
+        assert!(!cache.store.contains("scratch"));
+    }
+
+    #[tokio::test]
+    async fn sub_second_ttls_round_up_to_a_second() {
+        let mut cache = MemoryCache::builder()
+            .store(InMemoryDB::new(2))
+            .max_memory_limit(10)
+            .ttl(Duration::from_millis(500))
+            .build()
+            .unwrap();
+
+        cache
+            .insert(vec![1.0, 0.0], entry("brief", 0))
+            .await
+            .unwrap();
+        let slot = cache.slots["brief"];
+        assert_eq!(slot.expires_at, Some(slot.inserted_at + 1));
+    }
+}
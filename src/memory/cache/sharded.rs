@@ -0,0 +1,271 @@
+//! A sharded memory cache, allowing lookups and inserts from multiple tasks (or threads) at once.
+
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::{
+    memory::{MemoryEntry, MemoryId},
+    metrics,
+    storage::SearchResult,
+    vector_store::InMemoryDB,
+};
+
+use super::{
+    CacheStats, Stopwatch,
+    eviction::{EvictionCandidate, EvictionPolicy, pick_evictions},
+};
+
+/// A memory cache split into shards, each behind its own lock, so that concurrent lookups and inserts only contend when they hit the same shard.
+/// Memories are assigned to shards by ID, and searches fan out over every shard.
+///
+/// Every method takes `&self`, so the cache can be shared (eg, in an `Arc`) without wrapping it in a mutex.
+/// Created using [`super::MemoryCacheBuilder::build_sharded`]. Sharded caches are standalone: [`crate::memory::manager::MemoryManager`] uses a [`super::MemoryCache`] as its hot cache.
+pub struct ShardedMemoryCache {
+    shards: Vec<Shard>,
+    /// The max memory limit of each shard.
+    shard_limit: usize,
+    eviction_policy: Box<dyn EvictionPolicy>,
+    /// Used to spread lookup stats over the shards, as every lookup touches every shard.
+    next_lookup: AtomicUsize,
+}
+
+struct Shard {
+    data: RwLock<ShardData>,
+    stats: Mutex<CacheStats>,
+}
+
+struct ShardData {
+    store: InMemoryDB,
+    /// Whenever each memory was inserted into the shard (as a Unix timestamp).
+    inserted_at: HashMap<MemoryId, i64>,
+}
+
+impl ShardedMemoryCache {
+    pub(crate) fn new(
+        dims: usize,
+        shard_count: usize,
+        max_memory_limit: u32,
+        eviction_policy: Box<dyn EvictionPolicy>,
+    ) -> Self {
+        let shard_count = shard_count.max(1);
+        let shards = (0..shard_count)
+            .map(|_| Shard {
+                data: RwLock::new(ShardData {
+                    store: InMemoryDB::new(dims),
+                    inserted_at: HashMap::new(),
+                }),
+                stats: Mutex::new(CacheStats::new()),
+            })
+            .collect();
+
+        Self {
+            shards,
+            shard_limit: (max_memory_limit as usize).div_ceil(shard_count),
+            eviction_policy,
+            next_lookup: AtomicUsize::new(0),
+        }
+    }
+
+    /// The number of shards.
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// The max memory limit before automatic eviction of items to make way for new cached memories.
+    /// The limit is split evenly between shards, so a shard may evict memories before the cache as a whole is full.
+    pub fn memory_limit(&self) -> usize {
+        self.shard_limit * self.shards.len()
+    }
+
+    /// The number of memories currently held in the cache.
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.data.read().map(|data| data.store.len()).unwrap_or(0))
+            .sum()
+    }
+
+    /// Whether or not the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether or not the cache holds a memory with the given ID.
+    pub fn contains(&self, id: &str) -> bool {
+        self.shard(id)
+            .data
+            .read()
+            .map(|data| data.store.contains(id))
+            .unwrap_or(false)
+    }
+
+    /// The stats of every shard, combined.
+    pub fn stats(&self) -> CacheStats {
+        let mut stats = CacheStats::new();
+        for shard in &self.shards {
+            if let Ok(shard_stats) = shard.stats.lock() {
+                stats.merge(&shard_stats);
+            }
+        }
+
+        stats
+    }
+
+    /// Gets a cached memory by ID.
+    pub fn get(&self, id: &str) -> Result<Option<SearchResult>, crate::Error> {
+        let data = read(self.shard(id))?;
+        let Some(entry) = data.store.payload(id) else {
+            return Ok(None);
+        };
+
+        Ok(Some(SearchResult::new(
+            data.store.fetch_embedding(id)?,
+            entry.clone(),
+        )))
+    }
+
+    /// Searches every shard, returning the most similar cached memories.
+    pub fn search(
+        &self,
+        embedding: &[f32],
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        let stopwatch = Stopwatch::start();
+        let mut scored = Vec::new();
+        for shard in &self.shards {
+            scored.extend(read(shard)?.store.search_scored(embedding, limit));
+        }
+
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored.truncate(limit);
+        let results: Vec<SearchResult> = scored.into_iter().map(|(_, result)| result).collect();
+
+        let idx = self.next_lookup.fetch_add(1, Ordering::Relaxed) % self.shards.len();
+        let mut stats = lock(&self.shards[idx])?;
+        if results.is_empty() {
+            stats.add_miss();
+        } else {
+            stats.add_hit();
+        }
+        stopwatch.record(stats.lookup_latency_mut(), "lookup");
+
+        Ok(results)
+    }
+
+    /// Inserts a memory into the cache, replacing any cached memory with the same ID and evicting memories from its shard first if the shard is full.
+    pub fn insert(&self, embedding: Vec<f32>, entry: MemoryEntry) -> Result<(), crate::Error> {
+        let stopwatch = Stopwatch::start();
+        let shard = self.shard(&entry.id);
+        let mut data = write(shard)?;
+
+        if data.store.contains(&entry.id) {
+            data.store.delete_entry(&entry.id)?;
+        }
+
+        let overflow = (data.store.len() + 1).saturating_sub(self.shard_limit);
+        let evicted = if overflow > 0 {
+            self.evict(&mut data, overflow)?
+        } else {
+            0
+        };
+
+        data.inserted_at
+            .insert(entry.id.clone(), chrono::Utc::now().timestamp());
+        data.store.insert_entry(embedding, entry)?;
+        let size = data.store.len();
+        drop(data);
+
+        let mut stats = lock(shard)?;
+        stats.add_insert();
+        stats.add_evictions(evicted);
+        stats.set_size(size);
+        metrics::record_cache_insert(self.len());
+        metrics::record_evictions(evicted);
+        stopwatch.record(stats.insert_latency_mut(), "insert");
+
+        Ok(())
+    }
+
+    /// Removes a memory from the cache, returning whether the cache held it.
+    pub fn remove(&self, id: &str) -> Result<bool, crate::Error> {
+        let shard = self.shard(id);
+        let mut data = write(shard)?;
+        if !data.store.contains(id) {
+            return Ok(false);
+        }
+
+        data.store.delete_entry(id)?;
+        data.inserted_at.remove(id);
+        let size = data.store.len();
+        drop(data);
+
+        lock(shard)?.set_size(size);
+
+        Ok(true)
+    }
+
+    /// Evicts up to `count` memories from a shard, picked by the eviction policy. Returns how many memories were evicted.
+    fn evict(&self, data: &mut ShardData, count: usize) -> Result<usize, crate::Error> {
+        let now = chrono::Utc::now().timestamp();
+        let entries = match self.eviction_policy.sample_size() {
+            Some(size) => data.store.random_sample(size, |_| true),
+            None => data.store.entries().collect(),
+        };
+
+        let candidates = entries.into_iter().map(|entry| {
+            let candidate = EvictionCandidate {
+                entry,
+                inserted_at: data
+                    .inserted_at
+                    .get(&entry.id)
+                    .copied()
+                    .unwrap_or(entry.created_at),
+            };
+
+            (candidate, false)
+        });
+        let to_evict = pick_evictions(self.eviction_policy.as_ref(), candidates, count, now);
+
+        for id in &to_evict {
+            data.store.delete_entry(id)?;
+            data.inserted_at.remove(id);
+        }
+
+        #[cfg(feature = "tracing")]
+        if !to_evict.is_empty() {
+            tracing::debug!(evicted = to_evict.len(), ids = ?to_evict, "evicted memories from cache shard");
+        }
+
+        Ok(to_evict.len())
+    }
+
+    fn shard(&self, id: &str) -> &Shard {
+        let mut hasher = DefaultHasher::new();
+        id.hash(&mut hasher);
+
+        &self.shards[hasher.finish() as usize % self.shards.len()]
+    }
+}
+
+fn read(shard: &Shard) -> Result<RwLockReadGuard<'_, ShardData>, crate::Error> {
+    shard
+        .data
+        .read()
+        .map_err(|_| crate::Error::custom("Memory cache shard lock was poisoned"))
+}
+
+fn write(shard: &Shard) -> Result<RwLockWriteGuard<'_, ShardData>, crate::Error> {
+    shard
+        .data
+        .write()
+        .map_err(|_| crate::Error::custom("Memory cache shard lock was poisoned"))
+}
+
+fn lock(shard: &Shard) -> Result<MutexGuard<'_, CacheStats>, crate::Error> {
+    shard
+        .stats
+        .lock()
+        .map_err(|_| crate::Error::custom("Memory cache shard stats mutex was poisoned"))
+}
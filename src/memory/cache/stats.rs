@@ -0,0 +1,307 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Statistics about a [`super::MemoryCache`], exposed by [`crate::memory::manager::MemoryManager::cache_stats`].
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct CacheStats {
+    hits: u32,
+    misses: u32,
+    inserts: u32,
+    evictions: u32,
+    size: usize,
+    lookup_latency: LatencyHistogram,
+    insert_latency: LatencyHistogram,
+    eviction_latency: LatencyHistogram,
+}
+
+impl CacheStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_hit(&mut self) {
+        self.hits += 1;
+    }
+
+    pub fn add_miss(&mut self) {
+        self.misses += 1;
+    }
+
+    pub(crate) fn add_insert(&mut self) {
+        self.inserts += 1;
+    }
+
+    pub(crate) fn add_evictions(&mut self, count: usize) {
+        self.evictions += count as u32;
+    }
+
+    pub(crate) fn set_size(&mut self, size: usize) {
+        self.size = size;
+    }
+
+    pub(crate) fn lookup_latency_mut(&mut self) -> &mut LatencyHistogram {
+        &mut self.lookup_latency
+    }
+
+    pub(crate) fn insert_latency_mut(&mut self) -> &mut LatencyHistogram {
+        &mut self.insert_latency
+    }
+
+    pub(crate) fn eviction_latency_mut(&mut self) -> &mut LatencyHistogram {
+        &mut self.eviction_latency
+    }
+
+    /// The number of cache hits.
+    pub fn hits(&self) -> u32 {
+        self.hits
+    }
+
+    /// The number of cache misses.
+    pub fn misses(&self) -> u32 {
+        self.misses
+    }
+
+    /// The ratio of hits to total lookups (between 0.0 and 1.0). Returns 0.0 if there have been no lookups.
+    pub fn hit_ratio(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            return 0.0;
+        }
+
+        self.hits as f64 / total as f64
+    }
+
+    /// The number of memories inserted into the cache.
+    pub fn inserts(&self) -> u32 {
+        self.inserts
+    }
+
+    /// The number of memories evicted from the cache (including expired memories).
+    pub fn evictions(&self) -> u32 {
+        self.evictions
+    }
+
+    /// The number of memories currently held in the cache.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The latency of cache lookups (including query result cache lookups).
+    pub fn lookup_latency(&self) -> &LatencyHistogram {
+        &self.lookup_latency
+    }
+
+    /// The latency of inserting memories into the cache (including any evictions needed to make room).
+    pub fn insert_latency(&self) -> &LatencyHistogram {
+        &self.insert_latency
+    }
+
+    /// The latency of evicting memories from the cache.
+    pub fn eviction_latency(&self) -> &LatencyHistogram {
+        &self.eviction_latency
+    }
+
+    /// Adds the counters and histograms of another set of stats (eg, from another cache shard) to these stats.
+    pub(crate) fn merge(&mut self, other: &CacheStats) {
+        self.hits += other.hits;
+        self.misses += other.misses;
+        self.inserts += other.inserts;
+        self.evictions += other.evictions;
+        self.size += other.size;
+        self.lookup_latency.merge(&other.lookup_latency);
+        self.insert_latency.merge(&other.insert_latency);
+        self.eviction_latency.merge(&other.eviction_latency);
+    }
+
+    /// Resets every counter and histogram. The size is kept, as it reflects the current contents of the cache.
+    pub fn reset(&mut self) {
+        *self = Self {
+            size: self.size,
+            ..Self::default()
+        };
+    }
+}
+
+/// The upper bounds of each latency histogram bucket (in microseconds). Latencies above the last bound fall into an overflow bucket.
+const BUCKET_BOUNDS_MICROS: [u64; 10] = [
+    1, 10, 50, 100, 500, 1_000, 5_000, 10_000, 100_000, 1_000_000,
+];
+
+/// A histogram of operation latencies, using fixed buckets from 1µs to 1s.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct LatencyHistogram {
+    buckets: [u64; BUCKET_BOUNDS_MICROS.len() + 1],
+    count: u64,
+    sum: Duration,
+    max: Duration,
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, latency: Duration) {
+        let micros = latency.as_micros();
+        let bucket = BUCKET_BOUNDS_MICROS
+            .iter()
+            .position(|bound| micros <= *bound as u128)
+            .unwrap_or(BUCKET_BOUNDS_MICROS.len());
+
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.sum += latency;
+        self.max = self.max.max(latency);
+    }
+
+    fn merge(&mut self, other: &LatencyHistogram) {
+        for (bucket, count) in self.buckets.iter_mut().zip(other.buckets) {
+            *bucket += count;
+        }
+        self.count += other.count;
+        self.sum += other.sum;
+        self.max = self.max.max(other.max);
+    }
+
+    /// The number of recorded latencies.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The mean latency, or `None` if nothing has been recorded.
+    pub fn mean(&self) -> Option<Duration> {
+        (self.count > 0)
+            .then(|| Duration::from_secs_f64(self.sum.as_secs_f64() / self.count as f64))
+    }
+
+    /// The highest recorded latency.
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// An estimate of the latency at a given quantile (eg, 0.99 for p99), using the upper bound of the bucket the quantile falls into.
+    /// Returns `None` if nothing has been recorded.
+    pub fn percentile(&self, quantile: f64) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let rank = ((quantile.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (idx, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let bound = BUCKET_BOUNDS_MICROS
+                    .get(idx)
+                    .map_or(self.max, |bound| Duration::from_micros(*bound));
+                return Some(bound.min(self.max));
+            }
+        }
+
+        Some(self.max)
+    }
+
+    /// The upper bound of each bucket (`None` for the overflow bucket), along with the number of latencies that fell into it.
+    pub fn buckets(&self) -> impl Iterator<Item = (Option<Duration>, u64)> + '_ {
+        BUCKET_BOUNDS_MICROS
+            .iter()
+            .map(|bound| Some(Duration::from_micros(*bound)))
+            .chain(std::iter::once(None))
+            .zip(self.buckets.iter().copied())
+    }
+}
+
+/// Measures the latency of a cache operation.
+/// `std::time::Instant` isn't available in WASM, so nothing is measured there.
+pub(crate) struct Stopwatch {
+    #[cfg(not(target_arch = "wasm32"))]
+    start: std::time::Instant,
+}
+
+impl Stopwatch {
+    pub(crate) fn start() -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            start: std::time::Instant::now(),
+        }
+    }
+
+    /// Records the time elapsed since the stopwatch was started into a histogram, as well as the cache latency metric for the given operation.
+    pub(crate) fn record(self, histogram: &mut LatencyHistogram, operation: &'static str) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let latency = self.start.elapsed();
+            histogram.record(latency);
+            crate::metrics::record_cache_latency(operation, latency);
+        }
+        #[cfg(target_arch = "wasm32")]
+        let _ = (histogram, operation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{CacheStats, LatencyHistogram};
+
+    #[test]
+    fn latencies_fall_into_buckets() {
+        let mut histogram = LatencyHistogram::default();
+        assert_eq!(histogram.mean(), None);
+        assert_eq!(histogram.percentile(0.5), None);
+
+        for micros in [5, 8, 40, 2_000_000] {
+            histogram.record(Duration::from_micros(micros));
+        }
+
+        assert_eq!(histogram.count(), 4);
+        assert_eq!(histogram.max(), Duration::from_secs(2));
+        assert_eq!(histogram.percentile(0.5), Some(Duration::from_micros(10)));
+        assert_eq!(histogram.percentile(0.75), Some(Duration::from_micros(50)));
+        // Latencies in the overflow bucket are estimated using the highest latency
+        assert_eq!(histogram.percentile(1.0), Some(Duration::from_secs(2)));
+
+        let buckets: Vec<_> = histogram
+            .buckets()
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        assert_eq!(
+            buckets,
+            [
+                (Some(Duration::from_micros(10)), 2),
+                (Some(Duration::from_micros(50)), 1),
+                (None, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn stats_are_merged_and_reset() {
+        let mut stats = CacheStats::new();
+        stats.add_hit();
+        stats.add_hit();
+        stats.add_hit();
+        stats.add_miss();
+        stats.add_insert();
+        stats.add_evictions(2);
+        stats.set_size(3);
+        stats.insert_latency_mut().record(Duration::from_micros(1));
+        assert_eq!(stats.hit_ratio(), 0.75);
+
+        let mut shard = CacheStats::new();
+        shard.add_miss();
+        shard.add_evictions(1);
+        shard.set_size(2);
+        shard.insert_latency_mut().record(Duration::from_micros(3));
+        stats.merge(&shard);
+
+        assert_eq!((stats.hits(), stats.misses()), (3, 2));
+        assert_eq!(stats.evictions(), 3);
+        assert_eq!(stats.size(), 5);
+        assert_eq!(stats.insert_latency().count(), 2);
+
+        stats.reset();
+        assert_eq!((stats.hits(), stats.misses(), stats.evictions()), (0, 0, 0));
+        assert_eq!(stats.hit_ratio(), 0.0);
+        assert_eq!(stats.insert_latency().count(), 0);
+        assert_eq!(stats.size(), 5);
+    }
+}
@@ -0,0 +1,311 @@
+//! Conversation turns, used as the input to memory generation.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Who a conversation turn came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+    /// The result of a tool call.
+    Tool,
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::System => write!(f, "System"),
+            Self::User => write!(f, "User"),
+            Self::Assistant => write!(f, "Assistant"),
+            Self::Tool => write!(f, "Tool"),
+        }
+    }
+}
+
+/// A single turn in a conversation.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ConversationTurn {
+    pub role: Role,
+    pub content: String,
+    /// Whenever the turn happened (as a Unix timestamp), if known.
+    pub timestamp: Option<i64>,
+}
+
+impl ConversationTurn {
+    pub fn new<S>(role: Role, content: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            role,
+            content: content.into(),
+            timestamp: None,
+        }
+    }
+
+    pub fn system<S>(content: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::new(Role::System, content)
+    }
+
+    pub fn user<S>(content: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::new(Role::User, content)
+    }
+
+    pub fn assistant<S>(content: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::new(Role::Assistant, content)
+    }
+
+    pub fn tool<S>(content: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::new(Role::Tool, content)
+    }
+
+    /// Sets when the turn happened (as a Unix timestamp).
+    pub fn with_timestamp(mut self, timestamp: i64) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+}
+
+impl fmt::Display for ConversationTurn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let timestamp = self
+            .timestamp
+            .and_then(|timestamp| chrono::DateTime::from_timestamp(timestamp, 0));
+
+        match timestamp {
+            Some(timestamp) => write!(
+                f,
+                "[{}] {}: {}",
+                timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
+                self.role,
+                self.content
+            ),
+            None => write!(f, "{}: {}", self.role, self.content),
+        }
+    }
+}
+
+impl From<&ConversationTurn> for ConversationTurn {
+    fn from(turn: &ConversationTurn) -> Self {
+        turn.clone()
+    }
+}
+
+/// A tool call made by an agent, along with its outcome. Tool calls convert into [`Role::Tool`] turns, so agent traces can be used as memory generation input
+/// (see [`crate::memory::generation::MemoryGenerator::generate_memory_from_tool_trace`]).
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ToolCallRecord {
+    /// The name of the tool.
+    pub tool: String,
+    /// The arguments the tool was called with.
+    pub arguments: serde_json::Value,
+    /// The output of the tool (or the error message, if the call failed).
+    pub output: String,
+    /// Whether or not the call succeeded.
+    pub succeeded: bool,
+    /// Whether or not the user approved the call, for tools that require approval.
+    pub approved: Option<bool>,
+    /// Whenever the call happened (as a Unix timestamp), if known.
+    pub timestamp: Option<i64>,
+}
+
+impl ToolCallRecord {
+    /// Records a successful tool call.
+    pub fn succeeded<S1, S2>(tool: S1, arguments: serde_json::Value, output: S2) -> Self
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        Self {
+            tool: tool.into(),
+            arguments,
+            output: output.into(),
+            succeeded: true,
+            approved: None,
+            timestamp: None,
+        }
+    }
+
+    /// Records a failed tool call.
+    pub fn failed<S1, S2>(tool: S1, arguments: serde_json::Value, error: S2) -> Self
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        Self {
+            succeeded: false,
+            ..Self::succeeded(tool, arguments, error)
+        }
+    }
+
+    /// Records whether or not the user approved the call.
+    pub fn approved(mut self, approved: bool) -> Self {
+        self.approved = Some(approved);
+        self
+    }
+
+    /// Sets when the call happened (as a Unix timestamp).
+    pub fn with_timestamp(mut self, timestamp: i64) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+}
+
+impl From<ToolCallRecord> for ConversationTurn {
+    fn from(call: ToolCallRecord) -> Self {
+        let outcome = if call.succeeded {
+            "succeeded"
+        } else {
+            "failed"
+        };
+        let approval = match call.approved {
+            Some(true) => " (approved by the user)",
+            Some(false) => " (rejected by the user)",
+            None => "",
+        };
+
+        let turn = ConversationTurn::tool(format!(
+            "`{}` called with {} {outcome}{approval}: {}",
+            call.tool, call.arguments, call.output
+        ));
+
+        match call.timestamp {
+            Some(timestamp) => turn.with_timestamp(timestamp),
+            None => turn,
+        }
+    }
+}
+
+/// Formats a conversation as a transcript, one turn per line (eg, `User: Hello!`). Used as the input to memory generators.
+pub fn format_conversation(turns: &[ConversationTurn]) -> String {
+    turns
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(feature = "rig")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rig")))]
+mod rig {
+    use rig::completion::message::{AssistantContent, Message, ToolResultContent, UserContent};
+
+    use super::{ConversationTurn, Role};
+
+    /// Converts a rig message into a conversation turn. Text, tool calls and tool results are kept, while other content (eg, images and reasoning) is skipped.
+    /// User messages that only consist of tool results are converted into [`Role::Tool`] turns.
+    impl From<Message> for ConversationTurn {
+        fn from(message: Message) -> Self {
+            match message {
+                Message::User { content } => {
+                    let only_tool_results = content
+                        .iter()
+                        .all(|content| matches!(content, UserContent::ToolResult(_)));
+
+                    let content = content
+                        .into_iter()
+                        .filter_map(|content| match content {
+                            UserContent::Text(text) => Some(text.text),
+                            UserContent::ToolResult(result) => Some(
+                                result
+                                    .content
+                                    .into_iter()
+                                    .filter_map(|content| match content {
+                                        ToolResultContent::Text(text) => Some(text.text),
+                                        ToolResultContent::Image(_) => None,
+                                    })
+                                    .collect::<Vec<_>>()
+                                    .join("\n"),
+                            ),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n");
+
+                    let role = if only_tool_results {
+                        Role::Tool
+                    } else {
+                        Role::User
+                    };
+
+                    ConversationTurn::new(role, content)
+                }
+                Message::Assistant { content, .. } => {
+                    let content = content
+                        .into_iter()
+                        .filter_map(|content| match content {
+                            AssistantContent::Text(text) => Some(text.text),
+                            AssistantContent::ToolCall(call) => Some(format!(
+                                "(called tool `{}` with arguments {})",
+                                call.function.name, call.function.arguments
+                            )),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n");
+
+                    ConversationTurn::assistant(content)
+                }
+            }
+        }
+    }
+
+    impl From<&Message> for ConversationTurn {
+        fn from(message: &Message) -> Self {
+            message.clone().into()
+        }
+    }
+    #[cfg(test)]
+    mod tests {
+        use rig::{
+            OneOrMany,
+            completion::message::{AssistantContent, Message},
+        };
+
+        use crate::memory::conversation::{ConversationTurn, Role};
+
+        #[test]
+        fn rig_messages_become_turns() {
+            let turn = ConversationTurn::from(Message::user("I live in Berlin"));
+            assert_eq!(turn, ConversationTurn::user("I live in Berlin"));
+
+            let turn = ConversationTurn::from(&Message::tool_result("call-1", "sunny"));
+            assert_eq!(turn, ConversationTurn::new(Role::Tool, "sunny"));
+
+            let message = Message::Assistant {
+                id: None,
+                content: OneOrMany::many([
+                    AssistantContent::text("Checking the weather"),
+                    AssistantContent::tool_call(
+                        "call-1",
+                        "weather",
+                        serde_json::json!({"city": "Berlin"}),
+                    ),
+                ])
+                .unwrap(),
+            };
+            assert_eq!(
+                ConversationTurn::from(message),
+                ConversationTurn::assistant(
+                    "Checking the weather\n(called tool `weather` with arguments {\"city\":\"Berlin\"})"
+                )
+            );
+        }
+    }
+}
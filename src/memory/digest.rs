@@ -0,0 +1,106 @@
+//! Episodic digesting (ie, rolling up old, low-importance memories into weekly or monthly digest memories).
+//! This keeps the number of stored memories bounded without losing the gist of what happened. See [`crate::memory::manager::MemoryManager::digest`].
+
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Days};
+use serde::{Deserialize, Serialize};
+
+use crate::memory::{MemoryEntry, MemoryId, MemoryKind, strength};
+
+/// The tag given to digest memories. Memories with this tag are never digested again.
+pub const DIGEST_TAG: &str = "digest";
+
+/// The period that memories are grouped by when digesting.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum DigestPeriod {
+    /// Calendar weeks (starting on Monday, in UTC).
+    #[default]
+    Weekly,
+    /// Calendar months (in UTC).
+    Monthly,
+}
+
+impl DigestPeriod {
+    /// The start of the period holding a timestamp (as a Unix timestamp), along with a label for it (eg, `week-2025-W14` or `month-2025-03`).
+    pub(crate) fn period_of(&self, timestamp: i64) -> (i64, String) {
+        let date = DateTime::from_timestamp(timestamp, 0)
+            .unwrap_or_default()
+            .date_naive();
+
+        let (start, label) = match self {
+            Self::Weekly => {
+                let offset = date.weekday().num_days_from_monday() as u64;
+                let start = date.checked_sub_days(Days::new(offset)).unwrap_or(date);
+                (start, date.format("week-%G-W%V").to_string())
+            }
+            Self::Monthly => {
+                let start = date.with_day(1).unwrap_or(date);
+                (start, date.format("month-%Y-%m").to_string())
+            }
+        };
+
+        (
+            start.and_time(Default::default()).and_utc().timestamp(),
+            label,
+        )
+    }
+}
+
+/// Which memories get digested, and how they're grouped.
+#[derive(Clone, Debug)]
+pub struct DigestPolicy {
+    /// Only memories older than this get digested. Defaults to 30 days.
+    pub min_age: Duration,
+    /// Only memories with an importance at or below this get digested. Defaults to 0.3.
+    pub max_importance: f32,
+    /// The kinds of memories that get digested. Defaults to episodic memories.
+    pub kinds: Vec<MemoryKind>,
+    pub period: DigestPeriod,
+    /// Periods with fewer memories than this are left alone. Defaults to 2.
+    pub min_memories: usize,
+}
+
+impl Default for DigestPolicy {
+    fn default() -> Self {
+        Self {
+            min_age: Duration::from_secs(30 * 24 * 60 * 60),
+            max_importance: 0.3,
+            kinds: vec![MemoryKind::Episodic],
+            period: DigestPeriod::Weekly,
+            min_memories: 2,
+        }
+    }
+}
+
+impl DigestPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether or not a memory should be digested, given the current time (as a Unix timestamp).
+    /// Memories that have become permanent through repeated use (see [`strength`]) are never digested.
+    pub fn should_digest(&self, entry: &MemoryEntry, now: i64) -> bool {
+        let age = now.saturating_sub(entry.created_at);
+
+        age >= self.min_age.as_secs() as i64
+            && entry.importance <= self.max_importance
+            && self.kinds.contains(&entry.kind)
+            && !entry.has_tag(DIGEST_TAG)
+            && !strength::is_permanent(entry.strength)
+    }
+}
+
+/// The ID of the digest memory for a period.
+pub(crate) fn digest_id(label: &str) -> MemoryId {
+    format!("digest-{label}").into()
+}
+
+/// The outcome of a digesting pass.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct DigestReport {
+    /// The IDs of every digest memory that was created (or updated).
+    pub digests: Vec<MemoryId>,
+    /// The number of memories that were rolled up into digests and deleted.
+    pub digested: usize,
+}
@@ -0,0 +1,228 @@
+//! Entities (eg, people, places and projects) mentioned in memories, allowing every memory about an entity to be looked up rather than only the most similar ones.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::memory::{MemoryEntry, MemoryId, MetadataEntry};
+
+/// The metadata key that memory entities are stored under.
+pub const ENTITY_METADATA_KEY: &str = "entity";
+
+/// The kind of an entity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize, JsonSchema)]
+pub enum EntityKind {
+    Person,
+    Place,
+    /// A company, team or other group of people.
+    Organization,
+    Project,
+    /// Anything else worth tracking (eg, a product, pet or event).
+    Other,
+}
+
+impl fmt::Display for EntityKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Person => write!(f, "Person"),
+            Self::Place => write!(f, "Place"),
+            Self::Organization => write!(f, "Organization"),
+            Self::Project => write!(f, "Project"),
+            Self::Other => write!(f, "Other"),
+        }
+    }
+}
+
+impl EntityKind {
+    fn parse(input: &str) -> Option<Self> {
+        match input {
+            "Person" => Some(Self::Person),
+            "Place" => Some(Self::Place),
+            "Organization" => Some(Self::Organization),
+            "Project" => Some(Self::Project),
+            "Other" => Some(Self::Other),
+            _ => None,
+        }
+    }
+}
+
+/// An entity mentioned in a memory.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct Entity {
+    /// The name of the entity, as written in the conversation (eg, `Acme Corp`).
+    pub name: String,
+    pub kind: EntityKind,
+}
+
+impl Entity {
+    pub fn new<S>(name: S, kind: EntityKind) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            kind,
+        }
+    }
+
+    /// The key used to link memories to this entity (see [`entity_key`]).
+    pub fn key(&self) -> String {
+        entity_key(&self.name)
+    }
+
+    /// Converts the entity into a metadata entry (eg, `entity: Organization: Acme Corp`).
+    pub fn to_metadata(&self) -> MetadataEntry {
+        MetadataEntry::new(ENTITY_METADATA_KEY, format!("{}: {}", self.kind, self.name))
+    }
+
+    /// Parses an entity from the value of an `entity` metadata entry.
+    pub(crate) fn from_metadata(value: &str) -> Option<Self> {
+        let (kind, name) = value.split_once(": ")?;
+
+        Some(Self::new(name, EntityKind::parse(kind)?))
+    }
+}
+
+/// Normalizes an entity name into a key, so that different spellings of the same name (eg, `Acme Corp.` and `acme corp`) link to the same entity.
+pub fn entity_key(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Everything known about an entity: its name, the other names it has been mentioned by and the memories that mention it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EntityRecord {
+    /// The key the entity is linked by (see [`entity_key`]).
+    pub key: String,
+    /// The name the entity was first mentioned by.
+    pub name: String,
+    pub kind: EntityKind,
+    /// Other names the entity has been mentioned by.
+    pub aliases: Vec<String>,
+    /// The IDs of every memory mentioning the entity.
+    pub memory_ids: Vec<MemoryId>,
+}
+
+/// An index of entity records, linking entities to the memories that mention them.
+/// [`crate::memory::manager::MemoryManager`] keeps an index up to date as memories get stored and removed.
+#[derive(Clone, Debug, Default)]
+pub struct EntityIndex {
+    records: HashMap<String, EntityRecord>,
+    /// Alias keys, mapped to the key of the entity they refer to.
+    aliases: HashMap<String, String>,
+}
+
+impl EntityIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Links a memory to every entity it mentions (see [`MemoryEntry::entities`]), creating entity records as needed.
+    pub fn link(&mut self, memory: &MemoryEntry) {
+        for entity in memory.entities() {
+            let key = self.resolve(&entity.key());
+            if key.is_empty() {
+                continue;
+            }
+
+            let record = self
+                .records
+                .entry(key.clone())
+                .or_insert_with(|| EntityRecord {
+                    key,
+                    name: entity.name.clone(),
+                    kind: entity.kind,
+                    aliases: Vec::new(),
+                    memory_ids: Vec::new(),
+                });
+
+            if record.name != entity.name && !record.aliases.contains(&entity.name) {
+                record.aliases.push(entity.name);
+            }
+            if !record.memory_ids.contains(&memory.id) {
+                record.memory_ids.push(memory.id.clone());
+            }
+        }
+    }
+
+    /// Unlinks a memory from every entity, removing entities that are no longer mentioned by any memory.
+    pub fn unlink(&mut self, memory_id: &str) {
+        self.records.retain(|_, record| {
+            record.memory_ids.retain(|id| id != memory_id);
+            !record.memory_ids.is_empty()
+        });
+    }
+
+    /// Treats an alias (eg, `Acme`) as another name for an entity (eg, `Acme Corp`), merging the alias' record into the entity's record if both exist.
+    /// Returns `false` if the entity doesn't exist.
+    pub fn add_alias(&mut self, name: &str, alias: &str) -> bool {
+        let key = self.resolve(&entity_key(name));
+        let alias_key = entity_key(alias);
+        if !self.records.contains_key(&key) || alias_key.is_empty() || alias_key == key {
+            return false;
+        }
+
+        let merged = self.records.remove(&alias_key);
+        // SAFETY: We've just checked that the record exists, and it can't be the one removed as the keys differ
+        let record = self.records.get_mut(&key).unwrap();
+        if !record.aliases.iter().any(|existing| existing == alias) {
+            record.aliases.push(alias.to_string());
+        }
+        if let Some(merged) = merged {
+            for id in merged.memory_ids {
+                if !record.memory_ids.contains(&id) {
+                    record.memory_ids.push(id);
+                }
+            }
+        }
+
+        for target in self.aliases.values_mut() {
+            if target == &alias_key {
+                *target = key.clone();
+            }
+        }
+        self.aliases.insert(alias_key, key);
+
+        true
+    }
+
+    /// Gets the record of an entity by name or alias.
+    pub fn get(&self, name: &str) -> Option<&EntityRecord> {
+        self.records.get(&self.resolve(&entity_key(name)))
+    }
+
+    /// Every entity record, in no particular order.
+    pub fn records(&self) -> impl Iterator<Item = &EntityRecord> {
+        self.records.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.records.clear();
+        self.aliases.clear();
+    }
+
+    /// Clears every entity record, keeping aliases.
+    pub(crate) fn clear_records(&mut self) {
+        self.records.clear();
+    }
+
+    fn resolve(&self, key: &str) -> String {
+        self.aliases
+            .get(key)
+            .cloned()
+            .unwrap_or_else(|| key.to_string())
+    }
+}
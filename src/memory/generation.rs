@@ -1,91 +1,681 @@
-use serde::Serialize;
-
 #[cfg(feature = "rig")]
 #[cfg_attr(docsrs, doc(cfg(feature = "rig")))]
 pub use rig::create_rig_memory_extractor;
 
+use std::marker::PhantomData;
+
+use futures::StreamExt;
+
 use crate::{
     id_gen::{IdGenerationStrategy, MemoryIdGenerator},
-    memory::{MemoryDraft, MemoryEntry},
+    memory::{
+        Confidence, MemoryDraft, MemoryDrafts, MemoryEntry, MemoryEntryFields, MetadataEntry,
+        conversation::{ConversationTurn, format_conversation},
+        importance::{
+            HeuristicImportanceScorer, ImportancePolicy, ImportanceRaterNotSet, ImportanceScorer,
+            RateImportance,
+        },
+        language::{LanguageDetector, language_instructions},
+        redact::Redactor,
+    },
+    storage::Storage,
     wasm::WasmCompatSend,
 };
 
 /// A simple trait to represent generating memories.
-pub trait MemoryGeneration {
-    fn generate(&self, input: &str) -> impl Future<Output = Vec<MemoryDraft>> + WasmCompatSend;
+/// Memories are generated as [`MemoryDraft`]s by default, but any draft type that converts into [`MemoryEntryFields`] can be used.
+pub trait MemoryGeneration<D = MemoryDraft> {
+    fn generate(
+        &self,
+        input: &str,
+    ) -> impl Future<Output = Result<Vec<D>, crate::Error>> + WasmCompatSend;
 }
 
-pub struct MemoryGenerator<IdGen, T>
+pub struct MemoryGenerator<IdGen, T, R = ImportanceRaterNotSet, D = MemoryDraft>
 where
-    T: MemoryGeneration,
+    T: MemoryGeneration<D>,
 {
     id_generator: IdGen,
     mem_generator: T,
+    _draft: PhantomData<fn() -> D>,
+    importance_scorer: Box<dyn ImportanceScorer>,
+    importance_rater: Option<R>,
+    redactor: Option<Box<dyn Redactor>>,
+    language_detector: Option<Box<dyn LanguageDetector>>,
+    min_confidence: Option<Confidence>,
+    min_importance: Option<f32>,
+    importance_policy: ImportancePolicy,
+    max_attempts: usize,
+    tags: Vec<String>,
+    stats: GenerationStats,
+}
+
+/// Counts of drafts produced and dropped by a [`MemoryGenerator`].
+#[derive(Clone, Debug, Default)]
+pub struct GenerationStats {
+    generated: u32,
+    dropped_low_confidence: u32,
+    dropped_low_importance: u32,
+    retries: u32,
+}
+
+impl GenerationStats {
+    /// The number of drafts that were turned into memory entries.
+    pub fn generated(&self) -> u32 {
+        self.generated
+    }
+
+    /// The number of drafts dropped for being below the minimum confidence.
+    pub fn dropped_low_confidence(&self) -> u32 {
+        self.dropped_low_confidence
+    }
+
+    /// The number of drafts dropped for being below the minimum importance.
+    pub fn dropped_low_importance(&self) -> u32 {
+        self.dropped_low_importance
+    }
+
+    /// The number of times generation was retried because of invalid output.
+    pub fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
 }
 
-impl<T> MemoryGenerator<MemoryIdGenerator, T>
+impl<T, D> MemoryGenerator<MemoryIdGenerator, T, ImportanceRaterNotSet, D>
 where
-    T: MemoryGeneration,
+    T: MemoryGeneration<D>,
+    D: Into<MemoryEntryFields>,
 {
     pub fn new(mem_generator: T) -> Self {
         Self {
             id_generator: MemoryIdGenerator::default(),
             mem_generator,
+            _draft: PhantomData,
+            importance_scorer: Box::new(HeuristicImportanceScorer::default()),
+            importance_rater: None,
+            redactor: None,
+            language_detector: None,
+            min_confidence: None,
+            min_importance: None,
+            importance_policy: ImportancePolicy::Reject,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            tags: Vec::new(),
+            stats: GenerationStats::default(),
         }
     }
 }
 
-impl<IdGen, T> MemoryGenerator<IdGen, T>
+impl<IdGen, T, R, D> MemoryGenerator<IdGen, T, R, D>
 where
     IdGen: IdGenerationStrategy,
-    T: MemoryGeneration,
+    T: MemoryGeneration<D>,
+    R: RateImportance,
+    D: Into<MemoryEntryFields>,
 {
+    /// Sets the scorer used for drafts that arrive without an importance value.
+    /// Defaults to [`HeuristicImportanceScorer`].
+    pub fn importance_scorer<S>(mut self, scorer: S) -> Self
+    where
+        S: ImportanceScorer + 'static,
+    {
+        self.importance_scorer = Box::new(scorer);
+        self
+    }
+
+    /// Rates the importance of every draft in a dedicated pass (eg, a second LLM call), replacing any importance given by the memory generator.
+    /// The rationale for each rating is stored in the memory's metadata (under `importance_rationale`).
+    pub fn importance_rater<R2>(self, rater: R2) -> MemoryGenerator<IdGen, T, R2, D>
+    where
+        R2: RateImportance,
+    {
+        MemoryGenerator {
+            id_generator: self.id_generator,
+            mem_generator: self.mem_generator,
+            _draft: PhantomData,
+            importance_scorer: self.importance_scorer,
+            importance_rater: Some(rater),
+            redactor: self.redactor,
+            language_detector: self.language_detector,
+            min_confidence: self.min_confidence,
+            min_importance: self.min_importance,
+            importance_policy: self.importance_policy,
+            max_attempts: self.max_attempts,
+            tags: self.tags,
+            stats: self.stats,
+        }
+    }
+
+    /// Redacts sensitive data (eg, with a [`crate::memory::redact::PiiRedactor`]) from the content and source context of every draft, before it gets scored or stored.
+    pub fn redactor<Red>(mut self, redactor: Red) -> Self
+    where
+        Red: Redactor + 'static,
+    {
+        self.redactor = Some(Box::new(redactor));
+        self
+    }
+
+    /// Detects the language of conversations (eg, with a [`crate::memory::language::WhatlangDetector`]), telling the memory generator to keep memories in that language.
+    /// Memories are tagged with the detected language code in a `language` metadata entry.
+    pub fn language_detector<L>(mut self, detector: L) -> Self
+    where
+        L: LanguageDetector + 'static,
+    {
+        self.language_detector = Some(Box::new(detector));
+        self
+    }
+
+    /// Drops drafts with a confidence below the given level (eg, [`Confidence::Medium`] drops low-confidence drafts).
+    pub fn min_confidence(mut self, confidence: Confidence) -> Self {
+        self.min_confidence = Some(confidence);
+        self
+    }
+
+    /// Drops drafts with an importance below the given value (between 0.0 and 1.0).
+    /// Drafts without an importance value are scored by the importance scorer first.
+    pub fn min_importance(mut self, importance: f32) -> Self {
+        self.min_importance = Some(importance);
+        self
+    }
+
+    /// Sets what happens with drafts that have an importance outside of 0.0 to 1.0 (eg, a memory generator rating a memory as 8 out of 10).
+    /// Defaults to [`ImportancePolicy::Reject`], which retries generation (see [`MemoryGenerator::max_attempts`]), while [`ImportancePolicy::Clamp`] clamps the importance instead.
+    pub fn importance_policy(mut self, policy: ImportancePolicy) -> Self {
+        self.importance_policy = policy;
+        self
+    }
+
+    /// Sets how many times memories are generated from an input before giving up, if the memory generator returns invalid output
+    /// (ie, output that can't be parsed, or drafts with empty content or an importance outside of 0.0 to 1.0).
+    /// Every retry tells the memory generator what was wrong with its previous output. Defaults to 3 attempts.
+    pub fn max_attempts(mut self, attempts: usize) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Tags every generated memory (eg, with the name of an import job or a session label), in addition to any tags given by the memory generator.
+    pub fn tags<I, Tag>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = Tag>,
+        Tag: Into<String>,
+    {
+        self.tags = tags.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Counts of drafts generated and dropped since the generator was created (or the stats were reset).
+    pub fn stats(&self) -> &GenerationStats {
+        &self.stats
+    }
+
+    pub fn stats_mut(&mut self) -> &mut GenerationStats {
+        &mut self.stats
+    }
+
     pub fn into_split(self) -> (IdGen, T) {
         (self.id_generator, self.mem_generator)
     }
 
-    pub async fn generate_memory<Input>(&mut self, memory: Input) -> Vec<MemoryEntry>
+    /// Generates memories from a conversation.
+    pub async fn generate_memory<I, Turn>(
+        &mut self,
+        conversation: I,
+    ) -> Result<Vec<MemoryEntry>, crate::Error>
     where
-        Input: Serialize,
+        I: IntoIterator<Item = Turn>,
+        Turn: Into<ConversationTurn>,
     {
-        let input = serde_json::to_string(&memory).unwrap();
+        let turns = collect_turns(conversation);
+        let input = format_conversation(&turns);
 
-        let drafts = self.mem_generator.generate(&input).await;
-        let created_at = chrono::Utc::now().timestamp();
+        self.generate_from_input(input, &turns).await
+    }
+
+    /// Generates memories from an agent's tool-use trace (eg, a conversation interleaved with [`crate::memory::conversation::ToolCallRecord`]s), rather than from a conversation with a user.
+    /// The memory generator is told to focus on what the agent learned: which tools and approaches succeeded or failed, facts about the environment and what the user approved of,
+    /// producing [`MemoryKind::Procedural`](crate::memory::MemoryKind::Procedural) and semantic memories.
+    pub async fn generate_memory_from_tool_trace<I, Turn>(
+        &mut self,
+        trace: I,
+    ) -> Result<Vec<MemoryEntry>, crate::Error>
+    where
+        I: IntoIterator<Item = Turn>,
+        Turn: Into<ConversationTurn>,
+    {
+        let turns = collect_turns(trace);
+        let input = format!(
+            "{TOOL_TRACE_INSTRUCTIONS}\n\n## Trace\n{}",
+            format_conversation(&turns)
+        );
+
+        self.generate_from_input(input, &turns).await
+    }
+
+    /// Generates memories, telling the memory generator about memories that already exist so that they don't get extracted again.
+    /// Memories that are updated or contradicted by the input may still be extracted.
+    pub async fn generate_memory_with_existing<I, Turn>(
+        &mut self,
+        conversation: I,
+        existing: &[MemoryEntry],
+    ) -> Result<Vec<MemoryEntry>, crate::Error>
+    where
+        I: IntoIterator<Item = Turn>,
+        Turn: Into<ConversationTurn>,
+    {
+        let turns = collect_turns(conversation);
+        let input = with_existing_memories(format_conversation(&turns), existing);
+
+        self.generate_from_input(input, &turns).await
+    }
+
+    /// Generates memories, telling the memory generator about the `limit` most recent memories in a store so that they don't get extracted again.
+    pub async fn generate_memory_with_store<I, Turn, S>(
+        &mut self,
+        conversation: I,
+        storage: &S,
+        limit: usize,
+    ) -> Result<Vec<MemoryEntry>, crate::Error>
+    where
+        I: IntoIterator<Item = Turn>,
+        Turn: Into<ConversationTurn>,
+        S: Storage,
+    {
+        let existing: Vec<MemoryEntry> = storage
+            .get_recent(limit)
+            .await?
+            .into_iter()
+            .map(|result| result.data_owned())
+            .collect();
+
+        self.generate_memory_with_existing(conversation, &existing)
+            .await
+    }
+
+    /// Generates memories from a formatted input, detecting the language of the conversation turns it was formatted from.
+    pub(crate) async fn generate_from_input(
+        &mut self,
+        input: String,
+        turns: &[ConversationTurn],
+    ) -> Result<Vec<MemoryEntry>, crate::Error> {
+        let drafted = self.draft(input, turns).await?;
 
-        drafts
+        Ok(self.finish(drafted))
+    }
+
+    /// Generates memories from several conversations concurrently (eg, to backfill memories from historical transcripts), running at most `parallelism` generations at once.
+    /// Results are returned in the same order as the conversations, so a failed conversation doesn't affect the others.
+    pub async fn generate_many<I, C, Turn>(
+        &mut self,
+        conversations: I,
+        parallelism: usize,
+    ) -> Vec<Result<Vec<MemoryEntry>, crate::Error>>
+    where
+        I: IntoIterator<Item = C>,
+        C: IntoIterator<Item = Turn>,
+        Turn: Into<ConversationTurn>,
+    {
+        let conversations: Vec<Vec<ConversationTurn>> =
+            conversations.into_iter().map(collect_turns).collect();
+
+        let this = &*self;
+        let drafted: Vec<Result<Drafted, crate::Error>> = futures::stream::iter(&conversations)
+            .map(|turns| this.draft(format_conversation(turns), turns))
+            .buffered(parallelism.max(1))
+            .collect()
+            .await;
+
+        drafted
             .into_iter()
-            .map(|draft| MemoryEntry {
-                id: self.id_generator.generate_id(),
+            .map(|drafted| drafted.map(|drafted| self.finish(drafted)))
+            .collect()
+    }
+
+    /// Generates drafts and runs them through the draft-level stages (confidence filtering, redaction and importance rating).
+    /// This doesn't touch the generator's state, so several inputs can be drafted concurrently.
+    async fn draft(
+        &self,
+        mut input: String,
+        turns: &[ConversationTurn],
+    ) -> Result<Drafted, crate::Error> {
+        let language = self.language_detector.as_ref().and_then(|detector| {
+            let sample: Vec<&str> = turns.iter().map(|turn| turn.content.as_str()).collect();
+            detector.detect(&sample.join("\n"))
+        });
+        if let Some(language) = &language {
+            input.insert_str(0, &language_instructions(language));
+        }
+
+        let mut retries = 0;
+        let mut drafts = loop {
+            let err = match self.mem_generator.generate(&input).await {
+                Ok(drafts) => {
+                    let drafts: Vec<MemoryEntryFields> =
+                        drafts.into_iter().map(Into::into).collect();
+                    match validate_drafts(&drafts, self.importance_policy) {
+                        Ok(()) => break drafts,
+                        Err(err) => err,
+                    }
+                }
+                Err(crate::Error::InvalidOutput(err)) => err,
+                Err(err) => return Err(err),
+            };
+
+            retries += 1;
+            if retries >= self.max_attempts {
+                return Err(crate::Error::InvalidOutput(err));
+            }
+
+            #[cfg(feature = "tracing")]
+            tracing::debug!(error = %err, attempt = retries, "retrying memory generation");
+
+            input.push_str(&format!(
+                "\n\n## Feedback\nA previous attempt at extracting memories from this input was invalid: {err}\nFix this in your response."
+            ));
+        };
+
+        let mut dropped_low_confidence = 0;
+        if let Some(min) = &self.min_confidence {
+            let count = drafts.len();
+            drafts.retain(|draft| &draft.confidence >= min);
+            dropped_low_confidence = (count - drafts.len()) as u32;
+        }
+
+        if let Some(redactor) = &self.redactor {
+            for draft in &mut drafts {
+                draft.content = redactor.redact(&draft.content);
+                draft.source_context = redactor.redact(&draft.source_context);
+            }
+        }
+
+        if let Some(rater) = &self.importance_rater
+            && !drafts.is_empty()
+        {
+            let ratings = rater.rate(&drafts).await?;
+            if ratings.len() != drafts.len() {
+                return Err(crate::Error::Custom(format!(
+                    "Importance rater returned {} ratings for {} drafts",
+                    ratings.len(),
+                    drafts.len()
+                )));
+            }
+
+            for (draft, rating) in drafts.iter_mut().zip(ratings) {
+                draft.importance = Some(rating.importance.clamp(0.0, 1.0));
+                draft
+                    .metadata
+                    .push(MetadataEntry::new("importance_rationale", rating.rationale));
+            }
+        }
+
+        if let Some(language) = &language {
+            for draft in &mut drafts {
+                draft
+                    .metadata
+                    .push(MetadataEntry::new("language", &language.code));
+            }
+        }
+
+        Ok(Drafted {
+            drafts,
+            dropped_low_confidence,
+            retries: retries as u32,
+        })
+    }
+
+    /// Turns drafts into memory entries, scoring their importance and assigning IDs.
+    fn finish(&mut self, drafted: Drafted) -> Vec<MemoryEntry> {
+        let Drafted {
+            drafts,
+            dropped_low_confidence,
+            retries,
+        } = drafted;
+        let created_at = chrono::Utc::now().timestamp();
+        self.stats.dropped_low_confidence += dropped_low_confidence;
+        self.stats.retries += retries;
+
+        let mut entries = Vec::with_capacity(drafts.len());
+        for draft in drafts {
+            let importance = draft
+                .importance
+                .unwrap_or_else(|| self.importance_scorer.score(&draft))
+                .clamp(0.0, 1.0);
+            if self.min_importance.is_some_and(|min| importance < min) {
+                self.stats.dropped_low_importance += 1;
+                continue;
+            }
+
+            let mut tags = draft.tags;
+            for tag in &self.tags {
+                if !tags.contains(tag) {
+                    tags.push(tag.clone());
+                }
+            }
+
+            self.stats.generated += 1;
+            entries.push(MemoryEntry {
+                id: self.id_generator.generate_id().into(),
+                importance,
                 kind: draft.kind,
                 content: draft.content,
-                importance: draft.importance,
                 created_at,
                 confidence: draft.confidence,
                 last_accessed: created_at,
                 access_count: 0,
                 source_context: draft.source_context,
                 metadata: draft.metadata,
-            })
-            .collect()
+                agent_id: draft.agent_id,
+                visibility: draft.visibility,
+                embedding_model: None,
+                image: None,
+                tags,
+                strength: crate::memory::strength::INITIAL_STRENGTH,
+            });
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            generated = entries.len(),
+            dropped_low_confidence = self.stats.dropped_low_confidence,
+            dropped_low_importance = self.stats.dropped_low_importance,
+            "generated memories"
+        );
+
+        entries
+    }
+}
+
+/// Drafts generated from a single input, before they get turned into memory entries.
+struct Drafted {
+    drafts: Vec<MemoryEntryFields>,
+    dropped_low_confidence: u32,
+    retries: u32,
+}
+
+/// The default number of attempts at generating valid memory drafts from an input.
+const DEFAULT_MAX_ATTEMPTS: usize = 3;
+
+/// Checks that drafts have non-empty content and a valid importance (if given) under the importance policy, describing every problem found.
+fn validate_drafts(drafts: &[MemoryEntryFields], policy: ImportancePolicy) -> Result<(), String> {
+    let mut problems = Vec::new();
+    for (idx, draft) in drafts.iter().enumerate() {
+        if draft.content.trim().is_empty() {
+            problems.push(format!("memory {idx} has empty content"));
+        }
+        if let Some(importance) = draft.importance
+            && policy.apply(importance).is_err()
+        {
+            problems.push(format!(
+                "memory {idx} has an importance of {importance}, which is outside of 0.0 to 1.0"
+            ));
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems.join("; "))
+    }
+}
+
+fn collect_turns<I, Turn>(conversation: I) -> Vec<ConversationTurn>
+where
+    I: IntoIterator<Item = Turn>,
+    Turn: Into<ConversationTurn>,
+{
+    conversation.into_iter().map(Into::into).collect()
+}
+
+/// Parses memory drafts out of a free-form LLM response, repairing common formatting issues.
+///
+/// This accepts a [`MemoryDrafts`] object or a bare array of drafts, optionally wrapped in a Markdown code block or surrounded by other text, with trailing commas removed.
+pub fn parse_memory_drafts(response: &str) -> Result<Vec<MemoryDraft>, crate::Error> {
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum Drafts {
+        Object(MemoryDrafts),
+        Array(Vec<MemoryDraft>),
+    }
+
+    parse_json_response::<Drafts>(response)
+        .map(|drafts| match drafts {
+            Drafts::Object(drafts) => drafts.memories,
+            Drafts::Array(drafts) => drafts,
+        })
+        .map_err(|err| crate::Error::InvalidOutput(format!("Failed to parse memory drafts: {err}")))
+}
+
+/// Parses JSON out of a free-form LLM response, skipping any surrounding text (eg, Markdown code blocks) and removing trailing commas.
+pub(crate) fn parse_json_response<T>(response: &str) -> Result<T, serde_json::Error>
+where
+    T: serde::de::DeserializeOwned,
+{
+    let trimmed = response.trim();
+    let start = trimmed.find(['{', '[']);
+    let end = trimmed.rfind(['}', ']']);
+    let json = match (start, end) {
+        (Some(start), Some(end)) if start < end => &trimmed[start..=end],
+        _ => trimmed,
+    };
+
+    serde_json::from_str(json).or_else(|_| serde_json::from_str(&remove_trailing_commas(json)))
+}
+
+/// Removes commas that directly precede a closing bracket (ignoring commas in strings).
+fn remove_trailing_commas(json: &str) -> String {
+    let mut out = String::with_capacity(json.len());
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for c in json.chars() {
+        if in_string {
+            escaped = !escaped && c == '\\';
+            in_string = escaped || c != '"';
+        } else if c == '"' {
+            in_string = true;
+        } else if c == '}' || c == ']' {
+            let len = out.trim_end().len();
+            if out[..len].ends_with(',') {
+                out.truncate(len - 1);
+            }
+        }
+
+        out.push(c);
+    }
+
+    out
+}
+
+/// Appends a list of already-known memories to a memory generation input.
+fn with_existing_memories(input: String, existing: &[MemoryEntry]) -> String {
+    if existing.is_empty() {
+        return input;
+    }
+
+    let mut input = format!(
+        "{input}\n\n## Existing memories\nThe following memories have already been extracted. Don't extract them again, unless the conversation updates or contradicts them:\n"
+    );
+    for memory in existing {
+        input.push_str(&format!("- {}\n", memory.content));
     }
+
+    input
 }
 
 #[cfg(feature = "rig")]
 #[cfg_attr(docsrs, doc(cfg(feature = "rig")))]
 mod rig {
-    use crate::memory::{MemoryDraft, generation::MemoryGeneration};
+    use crate::memory::{
+        MemoryDraft, MemoryDrafts,
+        generation::{MemoryGeneration, PREAMBLE, parse_memory_drafts},
+    };
+    use rig::agent::Agent;
     use rig::client::{Capabilities, Client, CompletionClient, Provider};
-    use rig::completion::CompletionModel;
-    use rig::extractor::Extractor;
+    use rig::completion::{CompletionModel, Prompt};
+    use rig::extractor::{ExtractionError, Extractor};
+    use schemars::JsonSchema;
+    use serde::{Deserialize, Serialize};
+
+    /// How many turns an agent gets to produce memories, allowing agents with tools to use them.
+    const AGENT_MAX_TURNS: usize = 5;
 
-    impl<T> MemoryGeneration for Extractor<T, MemoryDraft>
+    impl<T, D> MemoryGeneration<D> for Extractor<T, MemoryDrafts<D>>
     where
         T: CompletionModel,
+        D: JsonSchema + for<'a> Deserialize<'a> + Serialize + Send + Sync + 'static,
+    {
+        async fn generate(&self, input: &str) -> Result<Vec<D>, crate::Error> {
+            let drafts = self.extract(input).await.map_err(|err| match err {
+                ExtractionError::NoData | ExtractionError::DeserializationError(_) => {
+                    crate::Error::InvalidOutput(err.to_string())
+                }
+                ExtractionError::CompletionError(_) => {
+                    crate::Error::wrap("Completion request failed", err)
+                }
+            })?;
+
+            Ok(drafts.memories)
+        }
+    }
+
+    /// Memories are extracted by prompting the agent with the memory extraction instructions, then parsing (and repairing) JSON from its response.
+    /// If the response can't be parsed, the agent is asked to fix it once.
+    impl<M> MemoryGeneration for Agent<M>
+    where
+        M: CompletionModel,
     {
-        async fn generate(&self, input: &str) -> Vec<MemoryDraft> {
-            let draft = self.extract(input).await.unwrap();
-            vec![draft]
+        async fn generate(&self, input: &str) -> Result<Vec<MemoryDraft>, crate::Error> {
+            let schema =
+                serde_json::to_string(&schemars::schema_for!(MemoryDrafts)).unwrap_or_default();
+            let prompt = format!(
+                "{PREAMBLE}\n\nRespond with only a JSON object matching this JSON schema:\n{schema}\n\n## Conversation\n{input}"
+            );
+
+            let response = self
+                .prompt(prompt)
+                .multi_turn(AGENT_MAX_TURNS)
+                .await
+                .map_err(|err| crate::Error::wrap("Completion request failed", err))?;
+
+            match parse_memory_drafts(&response) {
+                Ok(drafts) => Ok(drafts),
+                Err(err) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(error = %err, "asking agent to repair memory drafts");
+
+                    let repair = format!(
+                        "Your response could not be parsed ({err}). Respond with only the corrected JSON object.\n\n{response}"
+                    );
+                    let response = self
+                        .prompt(repair)
+                        .await
+                        .map_err(|err| crate::Error::wrap("Completion request failed", err))?;
+
+                    parse_memory_drafts(&response)
+                }
+            }
         }
     }
 
@@ -95,7 +685,7 @@ mod rig {
         model_name: &str,
     ) -> Extractor<
         <rig::client::Client<Ext, HttpClient> as rig::client::CompletionClient>::CompletionModel,
-        MemoryDraft,
+        MemoryDrafts,
     >
     where
         Ext:
@@ -105,12 +695,28 @@ mod rig {
         Client<Ext, HttpClient>: CompletionClient,
     {
         client
-            .extractor::<MemoryDraft>(model_name)
+            .extractor::<MemoryDrafts>(model_name)
             .preamble(PREAMBLE)
             .build()
     }
+}
+
+/// Instructions prepended to the input when generating memories from an agent's tool-use trace.
+pub const TOOL_TRACE_INSTRUCTIONS: &str = r###"## Instructions
+The input is a trace of an AI agent's work, including the tools it called and their outcomes, rather than a conversation with a user.
+Extract what the agent learned that will help it act better in the future:
+
+- Procedures that worked (which tool to use for a task, with which arguments or in which order), using the `Procedural` kind
+- Tools or approaches that failed, and why (eg, missing permissions, rate limits, wrong argument formats), using the `Procedural` kind
+- Facts learned about the environment (eg, file locations, service endpoints, installed versions, account limits), using the `Semantic` kind
+- What the user approved or rejected (eg, "User doesn't allow deleting files without asking"), using the `Semantic` kind
 
-    const PREAMBLE: &str = r###"You are a memory extraction system designed to identify and extract important information about users from conversations. Your goal is to capture personal facts, preferences, and contextual information that will help provide better, more personalized interactions in the future.
+Don't extract one-off outputs that won't be useful again (eg, the contents of a search result), or anything the trace doesn't clearly show.
+Mark memories as "High" confidence only when the outcome is explicit in the trace."###;
+
+/// The system prompt used by the built-in LLM memory generators.
+#[cfg(any(feature = "rig", feature = "openai"))]
+pub(crate) const PREAMBLE: &str = r###"You are a memory extraction system designed to identify and extract important information about users from conversations. Your goal is to capture personal facts, preferences, and contextual information that will help provide better, more personalized interactions in the future.
 
     ## What to Extract
 
@@ -137,6 +743,9 @@ mod rig {
     - Important dates or deadlines
     - Previous decisions or commitments they've made
 
+    **Procedures:**
+    - How to accomplish recurring tasks for the user (tools, steps and settings that work), using the `Procedural` kind
+
     ## What NOT to Extract
 
     - Temporary states (current mood, "I'm tired today")
@@ -153,17 +762,30 @@ mod rig {
       "memories": [
         {
           "content": "Clear, concise statement of the memory",
-          "source_context": "Brief context of where this was mentioned"
+          "source_context": "Brief context of where this was mentioned",
+          "topics": ["one or more short topic tags"],
+          "entities": [{"name": "Name as written", "kind": "Person"}]
         }
       ]
     }
     ```
 
+    ## Topics
+
+    Tag every memory with one to three short, lowercase topics describing what it's about, so memories can be filtered by topic later.
+    Prefer these common topics where they fit: `work`, `family`, `relationships`, `health`, `preferences`, `hobbies`, `projects`, `education`, `finance`, `travel`, `home`, `tools`.
+    Only introduce a new topic if none of these fit, and keep it to a single word where possible.
+
+    ## Entities
+
+    List the specific people, places, organizations and projects each memory is about as entities, using the kinds `Person`, `Place`, `Organization`, `Project` or `Other`.
+    Use the full name as written in the conversation (eg, "Acme Corp" rather than "the company"), and the same name every time the same entity is mentioned. Don't list the user as an entity.
+
     ## Guidelines
 
     1. **Be specific and clear**: Write memories as clear, standalone statements that will make sense without the original conversation context
     2. **Use present tense**: Frame memories in present tense (e.g., "User is a software engineer" not "User said they are a software engineer")
-    3. **Avoid redundancy**: Don't extract information that's already been captured in previous memory extractions
+    3. **Avoid redundancy**: Don't extract information that's already been captured in previous memory extractions (listed under "Existing memories", if provided)
     4. **Prioritize actionable information**: Focus on information that will genuinely improve future interactions
     5. **Be conservative with confidence**: Only mark as "high" confidence if explicitly stated; use "medium" for inferred information; use "low" for uncertain interpretations
     6. **Respect privacy**: Be thoughtful about what personal information is truly useful to store
@@ -177,15 +799,19 @@ mod rig {
       "memories": [
         {
           "content": "User is a senior data scientist at a healthcare startup",
-          "source_context": "Mentioned while discussing work projects"
+          "source_context": "Mentioned while discussing work projects",
+          "topics": ["work"],
+          "entities": []
         },
         {
           "content": "User prefers code examples without excessive comments",
-          "source_context": "Requested cleaner code in multiple interactions"
+          "source_context": "Requested cleaner code in multiple interactions",
+          "topics": ["preferences", "tools"]
         },
         {
           "content": "User is preparing for a machine learning conference presentation in March",
-          "source_context": "Discussed timeline and content for upcoming talk"
+          "source_context": "Discussed timeline and content for upcoming talk",
+          "topics": ["work", "projects"]
         }
       ]
     }
@@ -198,4 +824,515 @@ mod rig {
     }
     ```
     "###;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::{MemoryGeneration, MemoryGenerator, TOOL_TRACE_INSTRUCTIONS, parse_memory_drafts};
+    use crate::{
+        memory::{
+            Confidence, MemoryDraft, MemoryEntryFields, MemoryKind, MetadataEntry, Visibility,
+            conversation::{ConversationTurn, ToolCallRecord},
+            importance::{ImportancePolicy, ImportanceRating, RateImportance},
+        },
+        storage::Storage,
+        test_util::{LetterEmbedder, entry},
+        vector_store::InMemoryDB,
+    };
+
+    struct FixedDrafts(Vec<MemoryDraft>);
+
+    impl MemoryGeneration for FixedDrafts {
+        async fn generate(&self, _: &str) -> Result<Vec<MemoryDraft>, crate::Error> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn draft(content: &str, confidence: Confidence, importance: f32) -> MemoryDraft {
+        MemoryDraft {
+            content: content.to_string(),
+            kind: MemoryKind::Semantic,
+            source_context: "test".to_string(),
+            importance: Some(importance),
+            confidence,
+            metadata: Vec::new(),
+            topics: Vec::new(),
+            entities: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn drafts_below_thresholds_are_dropped() {
+        let mut generator = MemoryGenerator::new(FixedDrafts(vec![
+            draft("kept", Confidence::High, 0.8),
+            draft("hallucinated", Confidence::Low, 0.9),
+            draft("trivial", Confidence::Medium, 0.1),
+        ]))
+        .min_confidence(Confidence::Medium)
+        .min_importance(0.5);
+
+        let entries = generator
+            .generate_memory([ConversationTurn::user("conversation")])
+            .await
+            .unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].content, "kept");
+        assert_eq!(generator.stats().dropped_low_confidence(), 1);
+        assert_eq!(generator.stats().dropped_low_importance(), 1);
+    }
+
+    #[tokio::test]
+    async fn topics_are_stored_as_metadata() {
+        let mut tagged = draft("User works at Acme", Confidence::High, 0.8);
+        tagged.topics = vec![
+            " Work".to_string(),
+            "work".to_string(),
+            "career".to_string(),
+        ];
+        let mut generator = MemoryGenerator::new(FixedDrafts(vec![tagged]));
+
+        let entries = generator
+            .generate_memory([ConversationTurn::user("conversation")])
+            .await
+            .unwrap();
+
+        assert_eq!(entries[0].topics().collect::<Vec<_>>(), ["career", "work"]);
+        assert!(entries[0].has_topic("WORK"));
+        assert!(!entries[0].has_topic("family"));
+    }
+
+    /// Turns the input into a single draft, failing if the input mentions a failure.
+    struct EchoInput;
+
+    impl MemoryGeneration for EchoInput {
+        async fn generate(&self, input: &str) -> Result<Vec<MemoryDraft>, crate::Error> {
+            if input.contains("fail") {
+                return Err(crate::Error::custom("generation failed"));
+            }
+
+            Ok(vec![draft(input, Confidence::High, 0.8)])
+        }
+    }
+
+    #[tokio::test]
+    async fn batches_are_generated_in_order() {
+        let mut generator = MemoryGenerator::new(EchoInput);
+
+        let results = generator
+            .generate_many(
+                [
+                    vec![ConversationTurn::user("first")],
+                    vec![ConversationTurn::user("fail")],
+                    vec![ConversationTurn::user("third")],
+                ],
+                2,
+            )
+            .await;
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap()[0].content, "User: first");
+        assert!(results[1].is_err());
+        assert_eq!(results[2].as_ref().unwrap()[0].content, "User: third");
+        assert_eq!(generator.stats().generated(), 2);
+    }
+
+    /// Returns a draft with empty content until it's given feedback.
+    struct NeedsFeedback;
+
+    impl MemoryGeneration for NeedsFeedback {
+        async fn generate(&self, input: &str) -> Result<Vec<MemoryDraft>, crate::Error> {
+            if input.contains("memory 0 has empty content") {
+                Ok(vec![draft("fixed", Confidence::High, 0.8)])
+            } else {
+                Ok(vec![draft(" ", Confidence::High, 0.8)])
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_drafts_are_retried_with_feedback() {
+        let mut generator = MemoryGenerator::new(NeedsFeedback);
+
+        let entries = generator
+            .generate_memory([ConversationTurn::user("conversation")])
+            .await
+            .unwrap();
+
+        assert_eq!(entries[0].content, "fixed");
+        assert_eq!(generator.stats().retries(), 1);
+
+        let mut generator =
+            MemoryGenerator::new(FixedDrafts(vec![draft("overrated", Confidence::High, 1.5)]))
+                .max_attempts(2);
+
+        let result = generator
+            .generate_memory([ConversationTurn::user("conversation")])
+            .await;
+
+        assert!(matches!(result, Err(crate::Error::InvalidOutput(_))));
+
+        let mut generator = MemoryGenerator::new(FixedDrafts(vec![
+            draft("overrated", Confidence::High, 8.0),
+            draft("underrated", Confidence::High, -0.2),
+        ]))
+        .importance_policy(ImportancePolicy::Clamp);
+
+        let entries = generator
+            .generate_memory([ConversationTurn::user("conversation")])
+            .await
+            .unwrap();
+
+        assert_eq!(entries[0].importance, 1.0);
+        assert_eq!(entries[1].importance, 0.0);
+        assert_eq!(generator.stats().retries(), 0);
+    }
+
+    /// Rates every draft as important.
+    struct RateAllHigh;
+
+    impl RateImportance for RateAllHigh {
+        async fn rate(
+            &self,
+            drafts: &[MemoryEntryFields],
+        ) -> Result<Vec<ImportanceRating>, crate::Error> {
+            Ok(drafts
+                .iter()
+                .map(|_| ImportanceRating {
+                    importance: 0.9,
+                    rationale: "important".to_string(),
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn importance_rater_overrides_generated_importance() {
+        let mut generator = MemoryGenerator::new(FixedDrafts(vec![draft(
+            "underrated",
+            Confidence::High,
+            0.1,
+        )]))
+        .importance_rater(RateAllHigh)
+        .min_importance(0.5);
+
+        let entries = generator
+            .generate_memory([ConversationTurn::user("conversation")])
+            .await
+            .unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].importance, 0.9);
+        assert_eq!(entries[0].metadata.len(), 1);
+    }
+
+    /// A domain-specific draft type.
+    struct ContactNote {
+        contact: String,
+        note: String,
+    }
+
+    impl From<ContactNote> for MemoryEntryFields {
+        fn from(draft: ContactNote) -> Self {
+            MemoryEntryFields {
+                content: format!("{}: {}", draft.contact, draft.note),
+                kind: MemoryKind::Semantic,
+                source_context: "crm".to_string(),
+                importance: Some(0.5),
+                confidence: Confidence::High,
+                metadata: vec![MetadataEntry::new("contact", draft.contact)],
+                agent_id: None,
+                visibility: Visibility::Shared,
+                tags: vec!["crm".to_string()],
+            }
+        }
+    }
+
+    struct ContactNotes;
+
+    impl MemoryGeneration<ContactNote> for ContactNotes {
+        async fn generate(&self, _: &str) -> Result<Vec<ContactNote>, crate::Error> {
+            Ok(vec![ContactNote {
+                contact: "Jane".to_string(),
+                note: "Renewal due in March".to_string(),
+            }])
+        }
+    }
+
+    #[tokio::test]
+    async fn custom_draft_types_become_entries() {
+        let mut generator = MemoryGenerator::new(ContactNotes).tags(["imported", "crm"]);
+
+        let entries = generator
+            .generate_memory([ConversationTurn::user("conversation")])
+            .await
+            .unwrap();
+
+        assert_eq!(entries[0].content, "Jane: Renewal due in March");
+        assert_eq!(entries[0].visibility, Visibility::Shared);
+        assert_eq!(entries[0].tags, ["crm", "imported"]);
+    }
+
+    #[tokio::test]
+    async fn draft_fields_are_carried_into_entries() {
+        let draft = MemoryDraft {
+            content: "User runs backups every Friday".to_string(),
+            kind: MemoryKind::Procedural,
+            source_context: "Discussed their backup routine".to_string(),
+            importance: Some(0.7),
+            confidence: Confidence::Medium,
+            metadata: crate::metadata! { "source" => "chat" },
+            topics: vec!["home".to_string()],
+            entities: Vec::new(),
+        };
+        let mut generator = MemoryGenerator::new(FixedDrafts(vec![draft.clone()]));
+
+        let entries = generator
+            .generate_memory([ConversationTurn::user("conversation")])
+            .await
+            .unwrap();
+
+        let entry = &entries[0];
+        assert_eq!(entry.content, draft.content);
+        assert_eq!(entry.kind, draft.kind);
+        assert_eq!(entry.source_context, draft.source_context);
+        assert_eq!(Some(entry.importance), draft.importance);
+        assert_eq!(entry.confidence, draft.confidence);
+        assert_eq!(
+            entry.metadata,
+            [
+                MetadataEntry::new("source", "chat"),
+                MetadataEntry::new("topic", "home")
+            ]
+        );
+        assert_eq!(entry.metadata_get("source"), Some("chat"));
+        assert_eq!(entry.metadata_get("missing"), None);
+    }
+
+    #[test]
+    fn malformed_drafts_are_repaired() {
+        let response = r#"Here are the memories:
+```json
+{
+  "memories": [
+    {
+      "content": "User likes commas, brackets]",
+      "kind": "Semantic",
+      "source_context": "chat",
+      "confidence": "High",
+      "metadata": [],
+    },
+  ]
+}
+```"#;
+
+        let drafts = parse_memory_drafts(response).unwrap();
+
+        assert_eq!(drafts.len(), 1);
+        assert_eq!(drafts[0].content, "User likes commas, brackets]");
+    }
+
+    /// Records the inputs it's given, returning the same drafts every time.
+    struct RecordInputs {
+        inputs: Mutex<Vec<String>>,
+        drafts: Vec<MemoryDraft>,
+    }
+
+    impl RecordInputs {
+        fn new(drafts: Vec<MemoryDraft>) -> Self {
+            Self {
+                inputs: Mutex::new(Vec::new()),
+                drafts,
+            }
+        }
+
+        fn last_input(&self) -> String {
+            self.inputs.lock().unwrap().last().cloned().unwrap()
+        }
+    }
+
+    impl MemoryGeneration for RecordInputs {
+        async fn generate(&self, input: &str) -> Result<Vec<MemoryDraft>, crate::Error> {
+            self.inputs.lock().unwrap().push(input.to_string());
+
+            Ok(self.drafts.clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn conversation_turns_are_formatted_by_role() {
+        let mut generator = MemoryGenerator::new(RecordInputs::new(Vec::new()));
+
+        generator
+            .generate_memory([
+                ConversationTurn::system("Be helpful"),
+                ConversationTurn::user("I live in Berlin").with_timestamp(0),
+                ConversationTurn::assistant("Noted!"),
+            ])
+            .await
+            .unwrap();
+
+        let (_, recorder) = generator.into_split();
+        assert_eq!(
+            recorder.last_input(),
+            "System: Be helpful\n[1970-01-01 00:00:00 UTC] User: I live in Berlin\nAssistant: Noted!"
+        );
+    }
+
+    #[tokio::test]
+    async fn every_draft_in_a_conversation_becomes_an_entry() {
+        let mut generator = MemoryGenerator::new(FixedDrafts(vec![
+            draft("User lives in Berlin", Confidence::High, 0.8),
+            draft("User has a cat", Confidence::High, 0.6),
+            draft("User works remotely", Confidence::Medium, 0.7),
+        ]));
+
+        let entries = generator
+            .generate_memory([ConversationTurn::user("conversation")])
+            .await
+            .unwrap();
+
+        let contents: Vec<_> = entries.iter().map(|entry| entry.content.as_str()).collect();
+        assert_eq!(
+            contents,
+            [
+                "User lives in Berlin",
+                "User has a cat",
+                "User works remotely"
+            ]
+        );
+        assert_ne!(entries[0].id, entries[1].id);
+        assert_ne!(entries[1].id, entries[2].id);
+        assert_eq!(generator.stats().generated(), 3);
+
+        // Bare arrays of drafts are accepted too
+        let drafts = parse_memory_drafts(
+            r#"[{"content": "a", "kind": "Semantic", "source_context": "chat", "confidence": "High", "metadata": []},
+                {"content": "b", "kind": "Semantic", "source_context": "chat", "confidence": "Low", "metadata": []}]"#,
+        )
+        .unwrap();
+        assert_eq!(drafts.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn existing_memories_are_included_in_the_input() {
+        let mut generator = MemoryGenerator::new(RecordInputs::new(Vec::new()));
+
+        generator
+            .generate_memory_with_existing(
+                [ConversationTurn::user("I still live in Berlin")],
+                &[entry("1", "User lives in Berlin")],
+            )
+            .await
+            .unwrap();
+        let input = generator.into_split().1.last_input();
+        assert!(input.starts_with("User: I still live in Berlin\n\n## Existing memories\n"));
+        assert!(input.ends_with("- User lives in Berlin\n"));
+
+        // Without existing memories, the input is only the conversation
+        let mut generator = MemoryGenerator::new(RecordInputs::new(Vec::new()));
+        generator
+            .generate_memory_with_existing([ConversationTurn::user("Hi")], &[])
+            .await
+            .unwrap();
+        assert_eq!(generator.into_split().1.last_input(), "User: Hi");
+    }
+
+    #[tokio::test]
+    async fn the_most_recent_stored_memories_are_included_in_the_input() {
+        let mut storage = InMemoryDB::new(4);
+        for (id, content, created_at) in [
+            ("1", "User has a dog", 1),
+            ("2", "User lives in Berlin", 3),
+            ("3", "User likes tea", 2),
+        ] {
+            let entry = crate::memory::MemoryEntry {
+                created_at,
+                ..entry(id, content)
+            };
+            let embedding = crate::embed::Embedder::embed_text(&LetterEmbedder, content)
+                .await
+                .unwrap();
+            storage.insert(embedding, entry).await.unwrap();
+        }
+
+        let mut generator = MemoryGenerator::new(RecordInputs::new(Vec::new()));
+        generator
+            .generate_memory_with_store([ConversationTurn::user("Hi")], &storage, 2)
+            .await
+            .unwrap();
+
+        let input = generator.into_split().1.last_input();
+        assert!(input.contains("- User lives in Berlin\n- User likes tea\n"));
+        assert!(!input.contains("User has a dog"));
+    }
+
+    /// Fails with a given error every time.
+    struct AlwaysFails(fn() -> crate::Error);
+
+    impl MemoryGeneration for AlwaysFails {
+        async fn generate(&self, _: &str) -> Result<Vec<MemoryDraft>, crate::Error> {
+            Err((self.0)())
+        }
+    }
+
+    #[tokio::test]
+    async fn generation_errors_are_returned_instead_of_panicking() {
+        // Errors other than invalid output aren't retried
+        let mut generator =
+            MemoryGenerator::new(AlwaysFails(|| crate::Error::custom("rate limited")));
+        let result = generator
+            .generate_memory([ConversationTurn::user("conversation")])
+            .await;
+        assert!(matches!(result, Err(crate::Error::Custom(_))));
+        assert_eq!(generator.stats().retries(), 0);
+
+        // Invalid output is retried until the attempts run out
+        let mut generator =
+            MemoryGenerator::new(AlwaysFails(|| parse_memory_drafts("not json").unwrap_err()))
+                .max_attempts(2);
+        let result = generator
+            .generate_memory([ConversationTurn::user("conversation")])
+            .await;
+        assert!(matches!(result, Err(crate::Error::InvalidOutput(_))));
+    }
+
+    #[tokio::test]
+    async fn tool_traces_are_generated_with_tool_instructions() {
+        let mut generator = MemoryGenerator::new(RecordInputs::new(vec![MemoryDraft {
+            kind: MemoryKind::Procedural,
+            ..draft(
+                "Use `search` with a `site:` filter for docs",
+                Confidence::High,
+                0.7,
+            )
+        }]));
+
+        let entries = generator
+            .generate_memory_from_tool_trace([
+                ConversationTurn::user("Find the docs"),
+                ToolCallRecord::failed("fetch", serde_json::json!({"url": "docs"}), "invalid URL")
+                    .into(),
+                ToolCallRecord::succeeded(
+                    "search",
+                    serde_json::json!({"query": "site:docs.rs"}),
+                    "found",
+                )
+                .approved(true)
+                .into(),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(entries[0].kind, MemoryKind::Procedural);
+
+        let input = generator.into_split().1.last_input();
+        assert!(input.starts_with(TOOL_TRACE_INSTRUCTIONS));
+        assert!(
+            input.contains("Tool: `fetch` called with {\"url\":\"docs\"} failed: invalid URL\n")
+        );
+        assert!(input.ends_with(
+            "Tool: `search` called with {\"query\":\"site:docs.rs\"} succeeded (approved by the user): found"
+        ));
+    }
 }
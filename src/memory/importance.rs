@@ -0,0 +1,293 @@
+//! Importance scoring for memory drafts.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::StorageError,
+    memory::{Confidence, MemoryEntryFields, MemoryKind},
+    wasm::{WasmCompatSend, WasmCompatSync},
+};
+
+#[cfg(feature = "rig")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rig")))]
+pub use rig::create_rig_importance_rater;
+
+/// What to do with importance values outside of 0.0 to 1.0 (eg, an LLM extractor rating a memory as 8 out of 10).
+/// Non-finite importance values (ie, NaN or infinity) are always rejected.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ImportancePolicy {
+    /// Clamp the importance into 0.0 to 1.0.
+    #[default]
+    Clamp,
+    /// Reject the memory with an error.
+    Reject,
+}
+
+impl ImportancePolicy {
+    /// Checks an importance value, returning the value to use (ie, the importance itself if it's already between 0.0 and 1.0).
+    pub fn apply(&self, importance: f32) -> Result<f32, crate::Error> {
+        if !importance.is_finite() {
+            Err(StorageError::invalid_importance(importance))?
+        }
+
+        match self {
+            Self::Clamp => Ok(importance.clamp(0.0, 1.0)),
+            Self::Reject if (0.0..=1.0).contains(&importance) => Ok(importance),
+            Self::Reject => Err(StorageError::invalid_importance(importance))?,
+        }
+    }
+}
+
+/// A trait for scoring how important a memory draft is.
+/// Used by [`crate::memory::generation::MemoryGenerator`] when a draft arrives without an importance value.
+pub trait ImportanceScorer: WasmCompatSend + WasmCompatSync {
+    /// Returns an importance score between 0.0 and 1.0.
+    fn score(&self, draft: &MemoryEntryFields) -> f32;
+}
+
+/// A heuristic importance scorer that doesn't require an LLM.
+/// Scores are based on the kind of memory, the confidence, the content length and how many named entities (capitalised words or numbers) the content mentions.
+#[derive(Clone, Debug)]
+pub struct HeuristicImportanceScorer {
+    /// The maximum bonus given for content length.
+    pub length_weight: f32,
+    /// The content length (in characters) at which the full length bonus is given.
+    pub length_saturation: usize,
+    /// The bonus given per named entity.
+    pub entity_weight: f32,
+    /// The maximum number of named entities that count towards the score.
+    pub max_entities: usize,
+}
+
+impl Default for HeuristicImportanceScorer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HeuristicImportanceScorer {
+    pub fn new() -> Self {
+        Self {
+            length_weight: 0.15,
+            length_saturation: 200,
+            entity_weight: 0.05,
+            max_entities: 3,
+        }
+    }
+
+    fn kind_score(kind: &MemoryKind) -> f32 {
+        match kind {
+            MemoryKind::Semantic => 0.5,
+            MemoryKind::Procedural => 0.45,
+            MemoryKind::Episodic => 0.35,
+            MemoryKind::Working => 0.15,
+        }
+    }
+
+    fn confidence_score(confidence: &Confidence) -> f32 {
+        match confidence {
+            Confidence::High => 0.15,
+            Confidence::Medium => 0.05,
+            Confidence::Low => -0.1,
+        }
+    }
+}
+
+impl ImportanceScorer for HeuristicImportanceScorer {
+    fn score(&self, draft: &MemoryEntryFields) -> f32 {
+        let length = draft.content.chars().count().min(self.length_saturation) as f32
+            / self.length_saturation.max(1) as f32;
+        let entities = count_named_entities(&draft.content).min(self.max_entities) as f32;
+
+        let score = Self::kind_score(&draft.kind)
+            + Self::confidence_score(&draft.confidence)
+            + length * self.length_weight
+            + entities * self.entity_weight;
+
+        score.clamp(0.0, 1.0)
+    }
+}
+
+/// An importance rating for a memory draft, along with the reasoning behind it.
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ImportanceRating {
+    /// How important the memory is (using a decimal number between 0.0 and 1.0).
+    pub importance: f32,
+    /// Why the memory was given this rating.
+    pub rationale: String,
+}
+
+/// A list of importance ratings, one per draft (in the same order as the drafts).
+/// This is the structured output schema used by LLM-backed importance raters.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
+pub struct ImportanceRatings {
+    pub ratings: Vec<ImportanceRating>,
+}
+
+/// A trait for rating the importance of memory drafts in a dedicated pass (eg, a second LLM call), rather than trusting the importance given by the memory generator.
+/// Used by [`crate::memory::generation::MemoryGenerator::importance_rater`].
+pub trait RateImportance: WasmCompatSend + WasmCompatSync {
+    /// Rates every draft, returning exactly one rating per draft in the same order.
+    fn rate(
+        &self,
+        drafts: &[MemoryEntryFields],
+    ) -> impl Future<Output = Result<Vec<ImportanceRating>, crate::Error>> + WasmCompatSend;
+}
+
+/// The default type for when an importance rater isn't set on a [`crate::memory::generation::MemoryGenerator`].
+pub struct ImportanceRaterNotSet;
+
+impl RateImportance for ImportanceRaterNotSet {
+    async fn rate(&self, _: &[MemoryEntryFields]) -> Result<Vec<ImportanceRating>, crate::Error> {
+        Err(crate::Error::NoOp)
+    }
+}
+
+/// Formats memory drafts as a numbered list. Used as the input to LLM-backed importance raters.
+pub fn format_drafts(drafts: &[MemoryEntryFields]) -> String {
+    drafts
+        .iter()
+        .enumerate()
+        .map(|(idx, draft)| {
+            format!(
+                "{}. [{:?}] {} (context: {})",
+                idx + 1,
+                draft.kind,
+                draft.content,
+                draft.source_context
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Counts words that look like named entities: capitalised words that don't start a sentence, as well as numbers.
+fn count_named_entities(content: &str) -> usize {
+    let mut sentence_start = true;
+    let mut count = 0;
+
+    for word in content.split_whitespace() {
+        let trimmed = word.trim_matches(|c: char| !c.is_alphanumeric());
+        let is_entity = trimmed.chars().next().is_some_and(|c| {
+            (c.is_uppercase() && !sentence_start && trimmed != "I") || c.is_ascii_digit()
+        });
+
+        if is_entity {
+            count += 1;
+        }
+
+        sentence_start = word.ends_with(['.', '!', '?']);
+    }
+
+    count
+}
+
+#[cfg(feature = "rig")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rig")))]
+mod rig {
+    use super::{ImportanceRating, ImportanceRatings, RateImportance, format_drafts};
+    use crate::memory::{MemoryEntryFields, generation::parse_json_response};
+    use rig::agent::Agent;
+    use rig::client::{Capabilities, Client, CompletionClient, Provider};
+    use rig::completion::{CompletionModel, Prompt};
+
+    impl<M> RateImportance for Agent<M>
+    where
+        M: CompletionModel,
+    {
+        async fn rate(
+            &self,
+            drafts: &[MemoryEntryFields],
+        ) -> Result<Vec<ImportanceRating>, crate::Error> {
+            let schema = serde_json::to_string(&schemars::schema_for!(ImportanceRatings))
+                .unwrap_or_default();
+            let prompt = format!(
+                "{}\n\nRespond with only a JSON object matching this JSON schema, with exactly one rating per memory in the same order:\n{schema}",
+                format_drafts(drafts)
+            );
+
+            let response = self
+                .prompt(prompt)
+                .await
+                .map_err(|err| crate::Error::wrap("Completion request failed", err))?;
+
+            parse_json_response::<ImportanceRatings>(&response)
+                .map(|ratings| ratings.ratings)
+                .map_err(|err| crate::Error::wrap("Failed to parse importance ratings", err))
+        }
+    }
+
+    /// Creates a [`rig::agent::Agent`] tailored to rating the importance of memories on a calibrated scale.
+    pub fn create_rig_importance_rater<Ext, HttpClient, Model>(
+        client: &Client<Ext, HttpClient>,
+        model_name: &str,
+    ) -> Agent<
+        <rig::client::Client<Ext, HttpClient> as rig::client::CompletionClient>::CompletionModel,
+    >
+    where
+        Ext:
+            Provider + Capabilities<HttpClient, Completion = rig::client::Capable<Model>> + 'static,
+        HttpClient: rig::http_client::HttpClientExt + 'static,
+        Model: rig::completion::CompletionModel,
+        Client<Ext, HttpClient>: CompletionClient,
+    {
+        client.agent(model_name).preamble(PREAMBLE).build()
+    }
+
+    const PREAMBLE: &str = r###"You are part of a memory system for an AI assistant. You will be given a numbered list of memories that were extracted from a conversation with a user.
+
+Rate how important each memory is for personalising future conversations, using a decimal number between 0.0 and 1.0, along with a one-sentence rationale.
+
+Use the full scale, calibrated as follows:
+- 0.9-1.0: Core identity, health, safety or long-term goals (eg, allergies, profession, major life events)
+- 0.7-0.8: Stable preferences and ongoing projects that affect many future conversations
+- 0.4-0.6: Useful context that is likely to come up again
+- 0.2-0.3: Minor details that are unlikely to matter later
+- 0.0-0.1: Trivial or ephemeral information
+
+Rate each memory independently, and don't inflate ratings: most memories should fall in the middle of the scale."###;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HeuristicImportanceScorer, ImportanceScorer, count_named_entities};
+    use crate::memory::{Confidence, MemoryEntryFields, MemoryKind, Visibility};
+
+    fn draft(content: &str, kind: MemoryKind, confidence: Confidence) -> MemoryEntryFields {
+        MemoryEntryFields {
+            content: content.into(),
+            kind,
+            source_context: "test".into(),
+            importance: None,
+            confidence,
+            metadata: Vec::new(),
+            agent_id: None,
+            visibility: Visibility::Private,
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn detailed_confident_facts_outrank_vague_notes() {
+        let scorer = HeuristicImportanceScorer::new();
+        let fact = draft(
+            "User moved to Berlin in 2021 and works at Acme as a data engineer.",
+            MemoryKind::Semantic,
+            Confidence::High,
+        );
+        let note = draft("maybe later", MemoryKind::Working, Confidence::Low);
+
+        let fact_score = scorer.score(&fact);
+        let note_score = scorer.score(&note);
+        assert!(fact_score > note_score);
+        assert!((0.0..=1.0).contains(&fact_score));
+        assert!((0.0..=1.0).contains(&note_score));
+    }
+
+    #[test]
+    fn sentence_starts_are_not_named_entities() {
+        assert_eq!(count_named_entities("The user likes tea. I agree."), 0);
+        assert_eq!(count_named_entities("User met Alice in Paris on 3 May."), 4);
+    }
+}
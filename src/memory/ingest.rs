@@ -0,0 +1,272 @@
+//! Background ingestion of memories, so that storing memories doesn't block the conversation loop.
+//!
+//! An [`IngestQueue`] is a bounded queue in front of a [`MemoryManager`]. Submitting a memory returns an [`IngestHandle`] straight away,
+//! while an [`IngestWorker`] embeds and stores queued memories in batches in the background. The queue is runtime-agnostic, so the worker needs to be spawned onto an executor:
+//!
+//! ```rust,ignore
+//! let (mut queue, worker) = IngestQueue::new(manager, 256);
+//! tokio::spawn(worker.batch_size(32).parallelism(4).run());
+//!
+//! let handle = queue.submit("User likes rabbits", entry).await?;
+//! // ...carry on with the conversation, then check the memory was stored
+//! handle.await?;
+//! ```
+
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::{
+    SinkExt, StreamExt,
+    channel::{mpsc, oneshot},
+    lock::Mutex,
+};
+
+use crate::{
+    embed::Embedder,
+    memory::{MemoryEntry, manager::MemoryManager},
+    storage::Storage,
+};
+
+pub use crate::memory::manager::SharedMemoryManager;
+
+/// A memory waiting to be stored, along with where to send the result.
+struct Submission {
+    content: String,
+    entry: MemoryEntry,
+    result: oneshot::Sender<Result<(), crate::Error>>,
+}
+
+/// A bounded queue of memories waiting to be stored by an [`IngestWorker`].
+///
+/// Once the queue is full, [`IngestQueue::submit`] waits for space (and [`IngestQueue::try_submit`] returns an error), so producers can't outrun the worker indefinitely.
+/// Clones of the queue submit to the same worker. The worker stops once every clone has been dropped.
+#[derive(Clone)]
+pub struct IngestQueue<E, S>
+where
+    E: Embedder,
+    S: Storage,
+{
+    sender: mpsc::Sender<Submission>,
+    manager: SharedMemoryManager<E, S>,
+}
+
+impl<E, S> IngestQueue<E, S>
+where
+    E: Embedder,
+    S: Storage,
+{
+    /// Creates a queue holding up to `capacity` memories (plus one per clone of the queue), along with the worker that stores them.
+    pub fn new(manager: MemoryManager<E, S>, capacity: usize) -> (Self, IngestWorker<E, S>) {
+        Self::with_shared(Arc::new(Mutex::new(manager)), capacity)
+    }
+
+    /// Creates a queue in front of a memory manager that's already shared (eg, with [`crate::server::router`] or the memory tools).
+    pub fn with_shared(
+        manager: SharedMemoryManager<E, S>,
+        capacity: usize,
+    ) -> (Self, IngestWorker<E, S>) {
+        let (sender, receiver) = mpsc::channel(capacity);
+
+        let queue = Self {
+            sender,
+            manager: manager.clone(),
+        };
+        let worker = IngestWorker {
+            receiver,
+            manager,
+            batch_size: 16,
+            parallelism: 4,
+        };
+
+        (queue, worker)
+    }
+
+    /// Get the memory manager that memories are stored through (eg, to retrieve memories while ingesting).
+    /// Locking it pauses the worker, so avoid holding the lock for longer than needed.
+    pub fn manager(&self) -> &SharedMemoryManager<E, S> {
+        &self.manager
+    }
+
+    /// Queues a memory to be stored, waiting for space if the queue is full.
+    /// The returned handle resolves once the memory has been stored (or has failed to be stored).
+    pub async fn submit<Content>(
+        &mut self,
+        content: Content,
+        entry: MemoryEntry,
+    ) -> Result<IngestHandle, crate::Error>
+    where
+        Content: Into<String>,
+    {
+        let (submission, handle) = submission(content.into(), entry);
+        self.sender
+            .send(submission)
+            .await
+            .map_err(|_| worker_stopped())?;
+
+        Ok(handle)
+    }
+
+    /// Queues a memory to be stored without waiting, returning an error if the queue is full.
+    pub fn try_submit<Content>(
+        &mut self,
+        content: Content,
+        entry: MemoryEntry,
+    ) -> Result<IngestHandle, crate::Error>
+    where
+        Content: Into<String>,
+    {
+        let (submission, handle) = submission(content.into(), entry);
+        self.sender.try_send(submission).map_err(|err| {
+            if err.is_full() {
+                crate::Error::custom("Ingest queue is full")
+            } else {
+                worker_stopped()
+            }
+        })?;
+
+        Ok(handle)
+    }
+}
+
+fn submission(content: String, entry: MemoryEntry) -> (Submission, IngestHandle) {
+    let (sender, receiver) = oneshot::channel();
+    let submission = Submission {
+        content,
+        entry,
+        result: sender,
+    };
+
+    (submission, IngestHandle { receiver })
+}
+
+fn worker_stopped() -> crate::Error {
+    crate::Error::custom("Ingest worker has stopped")
+}
+
+/// Resolves once a submitted memory has been stored (or has failed to be stored).
+/// Dropping the handle doesn't cancel the submission.
+pub struct IngestHandle {
+    receiver: oneshot::Receiver<Result<(), crate::Error>>,
+}
+
+impl Future for IngestHandle {
+    type Output = Result<(), crate::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.receiver)
+            .poll(cx)
+            .map(|result| result.unwrap_or_else(|_| Err(worker_stopped())))
+    }
+}
+
+/// Stores memories submitted to an [`IngestQueue`] in the background.
+pub struct IngestWorker<E, S>
+where
+    E: Embedder,
+    S: Storage,
+{
+    receiver: mpsc::Receiver<Submission>,
+    manager: SharedMemoryManager<E, S>,
+    batch_size: usize,
+    parallelism: usize,
+}
+
+impl<E, S> IngestWorker<E, S>
+where
+    E: Embedder,
+    S: Storage,
+{
+    /// Sets the maximum number of queued memories stored at once. Defaults to 16. Values below 1 are treated as 1.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Sets how many memories in a batch are embedded concurrently. Defaults to 4.
+    pub fn parallelism(mut self, parallelism: usize) -> Self {
+        self.parallelism = parallelism.max(1);
+        self
+    }
+
+    /// Stores queued memories until every [`IngestQueue`] has been dropped, then flushes any writes buffered by the hot cache.
+    /// Each batch holds whatever has been queued (up to the batch size) by the time the previous batch has been stored (see [`MemoryManager::store_batch`]).
+    pub async fn run(mut self) {
+        while let Some(first) = self.receiver.next().await {
+            let mut batch = vec![first];
+            while batch.len() < self.batch_size
+                && let Ok(Some(submission)) = self.receiver.try_next()
+            {
+                batch.push(submission);
+            }
+
+            let (memories, senders): (Vec<_>, Vec<_>) = batch
+                .into_iter()
+                .map(|submission| ((submission.content, submission.entry), submission.result))
+                .unzip();
+
+            let results = {
+                let mut manager = self.manager.lock().await;
+                manager.store_batch(memories, self.parallelism).await
+            };
+
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                stored = results.iter().filter(|result| result.is_ok()).count(),
+                failed = results.iter().filter(|result| result.is_err()).count(),
+                "ingested a batch of memories"
+            );
+
+            for (sender, result) in senders.into_iter().zip(results) {
+                // The submitter may have dropped the handle, which is fine
+                let _ = sender.send(result);
+            }
+        }
+
+        if let Err(_err) = self.manager.lock().await.flush().await {
+            #[cfg(feature = "tracing")]
+            tracing::error!(error = %_err, "failed to flush buffered writes after ingesting");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IngestQueue;
+    use crate::{
+        memory::manager::MemoryManager,
+        test_util::{LetterEmbedder, entry},
+        vector_store::InMemoryDB,
+    };
+
+    #[tokio::test]
+    async fn queued_memories_are_stored_in_the_background() {
+        let manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+        let (mut queue, worker) = IngestQueue::new(manager, 2);
+        let manager = queue.manager().clone();
+        let worker = tokio::spawn(worker.batch_size(4).run());
+
+        let mut handles = Vec::new();
+        for id in ["a", "bb", "", "ccc"] {
+            handles.push(queue.submit(id, entry(id, id)).await.unwrap());
+        }
+        drop(queue);
+
+        let results: Vec<bool> = futures::future::join_all(handles)
+            .await
+            .iter()
+            .map(Result::is_ok)
+            .collect();
+        // The memory with an empty ID fails on its own
+        assert_eq!(results, [true, true, false, true]);
+
+        worker.await.unwrap();
+        assert_eq!(manager.lock().await.count().await.unwrap(), 3);
+    }
+}
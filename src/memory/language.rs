@@ -0,0 +1,100 @@
+//! Language detection for conversations, so that memories can be extracted in the language they were discussed in.
+
+use crate::wasm::{WasmCompatSend, WasmCompatSync};
+
+/// A detected language.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Language {
+    /// The language code (eg, ISO 639-3 codes like `deu` for [`WhatlangDetector`]). Stored in the `language` metadata entry of generated memories.
+    pub code: String,
+    /// The English name of the language (eg, `German`). Used in the instructions given to memory generators.
+    pub name: String,
+}
+
+impl Language {
+    pub fn new<S1, S2>(code: S1, name: S2) -> Self
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        Self {
+            code: code.into(),
+            name: name.into(),
+        }
+    }
+}
+
+/// A trait for detecting the language of a conversation.
+/// Used by [`crate::memory::generation::MemoryGenerator::language_detector`].
+pub trait LanguageDetector: WasmCompatSend + WasmCompatSync {
+    /// Returns the language of some text, or `None` if it can't be reliably detected.
+    fn detect(&self, text: &str) -> Option<Language>;
+}
+
+/// Formats instructions telling a memory generator to keep memories in the language of the conversation.
+pub(crate) fn language_instructions(language: &Language) -> String {
+    format!(
+        "## Language\nThe conversation is in {name}. Write the content and source context of every memory in {name}, keeping names and quoted terms as they were written. Field names and enum values in the output must stay exactly as specified.\n\n",
+        name = language.name
+    )
+}
+
+/// A language detector using [`whatlang`](https://docs.rs/whatlang), which supports 69 languages without any models or network access.
+/// Languages are returned as ISO 639-3 codes (eg, `eng`, `deu`, `jpn`).
+#[cfg(feature = "multilingual")]
+#[cfg_attr(docsrs, doc(cfg(feature = "multilingual")))]
+#[derive(Clone, Debug, Default)]
+pub struct WhatlangDetector {
+    min_confidence: Option<f64>,
+}
+
+#[cfg(feature = "multilingual")]
+impl WhatlangDetector {
+    /// Creates a detector that only returns languages that `whatlang` considers reliably detected.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only returns languages detected with at least the given confidence (between 0.0 and 1.0), instead of relying on `whatlang`'s reliability check.
+    pub fn min_confidence(mut self, confidence: f64) -> Self {
+        self.min_confidence = Some(confidence);
+        self
+    }
+}
+
+#[cfg(feature = "multilingual")]
+impl LanguageDetector for WhatlangDetector {
+    fn detect(&self, text: &str) -> Option<Language> {
+        let info = whatlang::detect(text)?;
+
+        let reliable = match self.min_confidence {
+            Some(min) => info.confidence() >= min,
+            None => info.is_reliable(),
+        };
+        if !reliable {
+            return None;
+        }
+
+        let lang = info.lang();
+        Some(Language::new(lang.code(), lang.eng_name()))
+    }
+}
+
+#[cfg(all(test, feature = "multilingual"))]
+mod tests {
+    use super::{LanguageDetector, WhatlangDetector};
+
+    #[test]
+    fn conversation_language_is_detected() {
+        let detector = WhatlangDetector::new();
+
+        let language = detector
+            .detect(
+                "Ich wohne seit drei Jahren in Berlin und arbeite als Ärztin in einem Krankenhaus.",
+            )
+            .unwrap();
+
+        assert_eq!(language.code, "deu");
+        assert_eq!(language.name, "German");
+    }
+}
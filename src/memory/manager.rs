@@ -1,24 +1,58 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+use std::time::Duration;
+
 use chrono::Utc;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    embed::{Embedder, EmbedderNotSet},
-    error::BuildError,
-    memory::{MemoryEntry, cache::MemoryCache},
-    storage::{SearchResult, Storage, StorageNotSet},
-    vector_store::InMemoryDB,
+    embed::{
+        Embedder, EmbedderNotSet, ImageSource, MultimodalEmbedder, QuantizedEmbedder,
+        SparseEmbedder, SparseVector, normalized::is_normalized,
+    },
+    error::{BuildError, StorageError},
+    memory::{
+        EmbeddingModelTag, MemoryEntry, MemoryId, MemoryKind, Visibility,
+        cache::{CachePartition, CacheStats, MemoryCache, Stopwatch, WritePolicy},
+        digest::{DIGEST_TAG, DigestPolicy, DigestReport, digest_id},
+        entity::{EntityIndex, EntityRecord},
+        importance::ImportancePolicy,
+        query::QueryTransformer,
+        quota::{Quota, QuotaPolicy, QuotaTracker, QuotaUsage, namespace_of},
+        reconcile::{MemoryOperation, MemoryReconciler},
+        stats::{MemoryStats, MemoryStatsCollector},
+        strength::{self, INITIAL_STRENGTH},
+        summarize::{MemorySummarizer, SummarizedRetrieval},
+    },
+    metrics,
+    storage::{HealthStatus, HybridFusion, MetadataFilter, SearchResult, Storage, StorageNotSet},
+    vector_store::{InMemoryDB, cosine_similarity},
 };
 
+/// A memory manager that can be shared (eg, between memory tools, an [`crate::memory::ingest::IngestQueue`] and the HTTP or gRPC services).
+/// This uses an async mutex from `futures` rather than `tokio`, so it stays runtime-agnostic (and WASM compatible).
+pub type SharedMemoryManager<E, S> = std::sync::Arc<futures::lock::Mutex<MemoryManager<E, S>>>;
+
 /// An agentic memory management frontend.
 /// Handles storing and retrieving memories.
+///
+/// # Cancellation
+/// Dropping a future returned by the manager (eg, when a request times out) never leaves the hot cache holding memories that aren't in storage,
+/// and never drops writes buffered by a [`WritePolicy::WriteBack`] hot cache. A cancelled write may or may not have been applied to storage.
 pub struct MemoryManager<E, S>
 where
     E: Embedder,
     S: Storage,
 {
-    storage: S,
+    storage: BufferedStorage<S>,
     embedder: E,
     cfg: MemoryConfig,
     hot_cache: Option<MemoryCache>,
+    entities: EntityIndex,
+    /// Quota usage per namespace, loaded from storage the first time it's needed.
+    quota_tracker: Option<QuotaTracker>,
 }
 
 impl MemoryManager<EmbedderNotSet, StorageNotSet> {
@@ -34,28 +68,178 @@ where
     S: Storage,
 {
     /// Store a single memory.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(memory_id = %entry.id))
+    )]
     pub async fn store<AsRefStr>(
         &mut self,
         memory: AsRefStr,
-        entry: MemoryEntry,
+        mut entry: MemoryEntry,
     ) -> Result<(), crate::Error>
     where
         AsRefStr: AsRef<str>,
     {
-        let embedding = self.embedder.embed_text(memory.as_ref()).await?;
-        self.storage
-            .insert(embedding.clone(), entry.clone())
+        let timer = metrics::Timer::start();
+        self.validate_entry(&mut entry)?;
+
+        let embedding = self.embed(memory.as_ref(), EmbedInput::Document).await?;
+        entry.embedding_model = embedding_model_tag(&self.embedder, &embedding);
+        self.insert(embedding, entry).await?;
+
+        metrics::record_store(timer, 1);
+
+        Ok(())
+    }
+
+    /// Store several memories, embedding up to `parallelism` of them concurrently (eg, to ingest a backlog of memories quickly).
+    /// Unlike [`MemoryManager::store_all_or_nothing`], each memory is stored independently: results are returned in the same order as the memories, so a failed memory doesn't affect the others.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(count = memories.len()))
+    )]
+    pub async fn store_batch<AsRefStr>(
+        &mut self,
+        memories: Vec<(AsRefStr, MemoryEntry)>,
+        parallelism: usize,
+    ) -> Vec<Result<(), crate::Error>>
+    where
+        AsRefStr: AsRef<str>,
+    {
+        let timer = metrics::Timer::start();
+        let this = &*self;
+        let embedded: Vec<Result<(Vec<f32>, MemoryEntry), crate::Error>> =
+            futures::stream::iter(memories)
+                .map(|(memory, mut entry)| async move {
+                    this.validate_entry(&mut entry)?;
+                    let embedding = this.embed(memory.as_ref(), EmbedInput::Document).await?;
+                    entry.embedding_model = embedding_model_tag(&this.embedder, &embedding);
+
+                    Ok((embedding, entry))
+                })
+                .buffered(parallelism.max(1))
+                .collect()
+                .await;
+
+        let mut results = Vec::with_capacity(embedded.len());
+        for result in embedded {
+            results.push(match result {
+                Ok((embedding, entry)) => self.insert(embedding, entry).await,
+                Err(err) => Err(err),
+            });
+        }
+
+        metrics::record_store(
+            timer,
+            results.iter().filter(|result| result.is_ok()).count(),
+        );
+
+        results
+    }
+
+    /// Store several memories atomically, embedding the content of each one: either every memory is stored (and hot cached, where it should be), or none of them are.
+    /// Memories are written using [`Storage::insert_all`], which uses native transactions where the storage supports them and compensating deletes otherwise.
+    ///
+    /// The memories are written straight to storage, even with a [`WritePolicy::WriteBack`] hot cache (which gets flushed first).
+    /// If caching the memories fails after they've been stored, none of them are left in the hot cache. A cancelled transaction may leave some of the memories in storages without native transactions.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(count = entries.len()))
+    )]
+    pub async fn store_all_or_nothing(
+        &mut self,
+        mut entries: Vec<MemoryEntry>,
+    ) -> Result<(), crate::Error> {
+        let timer = metrics::Timer::start();
+        let mut ids = HashSet::with_capacity(entries.len());
+        for entry in &mut entries {
+            self.validate_entry(entry)?;
+            if !ids.insert(entry.id.clone()) {
+                return Err(crate::Error::Custom(format!(
+                    "Memory with ID {} appears more than once in the transaction",
+                    entry.id
+                )));
+            }
+        }
+
+        let mut batch = Vec::with_capacity(entries.len());
+        for mut entry in entries {
+            let embedding = self.embed(&entry.content, EmbedInput::Document).await?;
+            entry.embedding_model = embedding_model_tag(&self.embedder, &embedding);
+            batch.push((embedding, entry));
+        }
+
+        self.check_quotas(batch.iter().map(|(_, entry)| entry))
             .await?;
 
-        if let Some(cache) = &mut self.hot_cache
-            && self.cfg.should_cache(&entry)
-        {
-            if cache.store.count().await.unwrap() > cache.memory_limit() as usize {
-                cache.evict_from_cache(1).await?;
+        self.flush().await?;
+        // Cached query results are invalidated up front, so a cancelled transaction can't leave them stale
+        if let Some(cache) = &mut self.hot_cache {
+            cache.invalidate_query_cache();
+        }
+
+        with_deadline(
+            self.cfg.storage_deadline(),
+            self.storage.insert_all(batch.clone()),
+        )
+        .await?;
+        for (_, entry) in &batch {
+            self.entities.link(entry);
+            if let Some(tracker) = &mut self.quota_tracker {
+                tracker.track(entry);
+            }
+        }
+
+        if let Some(cache) = &mut self.hot_cache {
+            for (embedding, entry) in batch {
+                if !should_cache(&self.cfg, cache, &entry) {
+                    continue;
+                }
+
+                if let Err(err) = cache.insert(embedding, entry).await {
+                    for id in &ids {
+                        cache.remove(id.as_str()).await?;
+                    }
+
+                    return Err(err);
+                }
             }
-            cache.store.insert(embedding, entry).await?;
         }
 
+        self.report_store_size().await;
+        metrics::record_store(timer, ids.len());
+
+        Ok(())
+    }
+
+    /// Store a single memory about an image (eg, a screenshot or photo), embedding the image itself rather than the memory contents.
+    /// The image is attached to the stored memory (see [`MemoryEntry::image`]).
+    ///
+    /// As multimodal embedders embed text and images into the same space, the memory can be recalled with text queries using [`MemoryManager::retrieve`] as well as with [`MemoryManager::retrieve_by_image`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(memory_id = %entry.id))
+    )]
+    pub async fn store_image(
+        &mut self,
+        image: ImageSource,
+        mut entry: MemoryEntry,
+    ) -> Result<(), crate::Error>
+    where
+        E: MultimodalEmbedder,
+    {
+        let timer = metrics::Timer::start();
+        self.validate_entry(&mut entry)?;
+
+        let embedding =
+            with_deadline(self.cfg.embed_deadline(), self.embedder.embed_image(&image)).await?;
+        self.ensure_normalized(&embedding)?;
+        entry.embedding_model = embedding_model_tag(&self.embedder, &embedding);
+        entry.image = Some(image);
+        self.insert(embedding, entry).await?;
+
+        metrics::record_store(timer, 1);
+
         Ok(())
     }
 
@@ -68,192 +252,4117 @@ where
     where
         AsRefStr: AsRef<str>,
     {
-        let embedding = self.embedder.embed_text(query.as_ref()).await?;
-
-        let mut results = if let Some(cache) = &mut self.hot_cache {
-            let results = cache.store.search(embedding.clone(), limit).await?;
-            if !results.is_empty() {
-                cache.stats_mut().add_hit();
-            } else {
-                cache.stats_mut().add_miss();
-            };
+        self.retrieve_with_options(query, limit, RetrieveOptions::default())
+            .await
+    }
 
-            results
-        } else {
-            Vec::new()
-        };
+    /// Retrieve memories with per-call options, eg to skip the hot cache for consistency-sensitive code paths or to only search the hot cache for latency-sensitive ones (see [`CacheMode`]).
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(limit = limit, cache_mode = ?options.cache_mode))
+    )]
+    pub async fn retrieve_with_options<AsRefStr>(
+        &mut self,
+        query: AsRefStr,
+        limit: usize,
+        options: RetrieveOptions,
+    ) -> Result<Vec<SearchResult>, crate::Error>
+    where
+        AsRefStr: AsRef<str>,
+    {
+        let timer = metrics::Timer::start();
 
-        if results.len() < limit {
-            // TODO: We should probably add caching here
-            let deep_results = self
-                .storage
-                .search(embedding, limit - results.len())
-                .await?;
+        let embedding = self.embed(query.as_ref(), EmbedInput::Query).await?;
+        let results = self.search(embedding, limit, options.cache_mode).await?;
 
-            results.extend(deep_results);
-        }
+        metrics::record_retrieve(timer);
 
         Ok(results)
     }
 
-    /// Updates a memory and checks if it needs to be hot cached.
-    pub async fn update_memory_access(
+    /// Retrieve memories along with their scores, eg for relevance thresholds, displaying scores or reranking.
+    /// Results without a score reported by the storage (or hot cache) are scored by their cosine similarity to the query.
+    pub async fn retrieve_scored<AsRefStr>(
         &mut self,
-        mut memory: MemoryEntry,
-    ) -> Result<(), crate::Error> {
-        memory.last_accessed = Utc::now().timestamp();
-        memory.access_count += 1;
+        query: AsRefStr,
+        limit: usize,
+        options: RetrieveOptions,
+    ) -> Result<Vec<ScoredMemory>, crate::Error>
+    where
+        AsRefStr: AsRef<str>,
+    {
+        let timer = metrics::Timer::start();
 
-        if self.cfg.should_cache(&memory)
-            && let Some(cache) = &mut self.hot_cache
-        {
-            cache
-                .store
-                .update_payload_by_id(memory.id.clone(), memory)
-                .await?;
-        }
+        let embedding = self.embed(query.as_ref(), EmbedInput::Query).await?;
+        let results = self
+            .search(embedding.clone(), limit, options.cache_mode)
+            .await?
+            .into_iter()
+            .map(|result| ScoredMemory {
+                score: result
+                    .score()
+                    .unwrap_or_else(|| cosine_similarity(&embedding, result.embedding())),
+                memory: result.data_owned(),
+            })
+            .collect();
 
-        Ok(())
+        metrics::record_retrieve(timer);
+
+        Ok(results)
     }
-}
 
-/// A builder for `MemoryManager`.
-#[derive(Default)]
-pub struct MemoryManagerBuilder<E, S> {
-    storage: Option<S>,
-    embedder: Option<E>,
-    cfg: Option<MemoryConfig>,
-    hot_cache: Option<MemoryCache>,
-}
+    /// Retrieve memories similar to a given image, eg to recall memories about the same screen or object.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(limit = limit)))]
+    pub async fn retrieve_by_image(
+        &mut self,
+        image: &ImageSource,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, crate::Error>
+    where
+        E: MultimodalEmbedder,
+    {
+        let timer = metrics::Timer::start();
 
-impl MemoryManagerBuilder<EmbedderNotSet, StorageNotSet> {
-    pub fn new() -> Self {
-        MemoryManagerBuilder {
-            storage: None,
-            embedder: None,
-            cfg: None,
-            hot_cache: None,
-        }
+        let embedding =
+            with_deadline(self.cfg.embed_deadline(), self.embedder.embed_image(image)).await?;
+        self.ensure_normalized(&embedding)?;
+        let results = self.search(embedding, limit, CacheMode::Default).await?;
+
+        metrics::record_retrieve(timer);
+
+        Ok(results)
     }
-}
 
-impl<E, S> MemoryManagerBuilder<E, S>
-where
-    E: Embedder,
-    S: Storage,
-{
-    pub fn storage<S2>(self, storage: S2) -> MemoryManagerBuilder<E, S2>
+    /// Retrieve memories, transforming the query with a [`QueryTransformer`] first (eg, HyDE or query expansion).
+    /// The transformed query is embedded instead of the raw query.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(limit = limit)))]
+    pub async fn retrieve_with_transformer<AsRefStr, T>(
+        &mut self,
+        query: AsRefStr,
+        limit: usize,
+        transformer: &T,
+    ) -> Result<Vec<SearchResult>, crate::Error>
     where
-        S2: Storage,
+        AsRefStr: AsRef<str>,
+        T: QueryTransformer,
     {
-        MemoryManagerBuilder {
-            storage: Some(storage),
-            embedder: self.embedder,
-            cfg: self.cfg,
-            hot_cache: self.hot_cache,
-        }
+        let timer = metrics::Timer::start();
+
+        let query = transformer.transform(query.as_ref()).await?;
+        #[cfg(feature = "tracing")]
+        tracing::debug!(transformed_len = query.len(), "transformed query");
+
+        let embedding = self.embed(&query, EmbedInput::Query).await?;
+        let results = self.search(embedding, limit, CacheMode::Default).await?;
+
+        metrics::record_retrieve(timer);
+
+        Ok(results)
     }
 
-    pub fn embedder<E2>(self, embedder: E2) -> MemoryManagerBuilder<E2, S>
+    /// Retrieve memories that match a given predicate.
+    /// Candidates are fetched by similarity in progressively larger batches until either enough matching memories are found or the storage has been exhausted.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(limit = limit)))]
+    pub async fn retrieve_filtered<AsRefStr, F>(
+        &mut self,
+        query: AsRefStr,
+        limit: usize,
+        predicate: F,
+    ) -> Result<Vec<SearchResult>, crate::Error>
     where
-        E2: Embedder,
+        AsRefStr: AsRef<str>,
+        F: Fn(&MemoryEntry) -> bool,
     {
-        MemoryManagerBuilder {
-            storage: self.storage,
-            embedder: Some(embedder),
-            cfg: self.cfg,
-            hot_cache: self.hot_cache,
-        }
+        let timer = metrics::Timer::start();
+
+        let embedding = self.embed(query.as_ref(), EmbedInput::Query).await?;
+        let total = with_deadline(self.cfg.storage_deadline(), self.storage.count()).await?;
+        let mut candidates = limit.max(1);
+
+        let results = loop {
+            let mut seen = HashSet::new();
+            let mut results: Vec<SearchResult> = self
+                .search_candidates(embedding.clone(), candidates, CacheMode::Default)
+                .await?
+                .into_iter()
+                .filter(|result| predicate(result.data()) && seen.insert(result.data().id.clone()))
+                .collect();
+
+            if results.len() >= limit || candidates >= total {
+                results.truncate(limit);
+                break results;
+            }
+
+            candidates = (candidates * 2).min(total);
+        };
+
+        metrics::record_retrieve(timer);
+
+        Ok(results)
     }
 
-    pub fn config(mut self, cfg: MemoryConfig) -> Self {
-        self.cfg = Some(cfg);
+    /// Retrieve memories whose metadata matches a filter (eg, `MetadataFilter::eq("source", "chat")`), combining predicates using [`MetadataFilter::and`] and [`MetadataFilter::or`].
+    /// The filter is passed down to the storage (see [`Storage::search_filtered`]), so backends with native metadata filtering don't need to over-fetch candidates.
+    ///
+    /// NOTE: Filtered retrieval always searches the backing storage (flushing any buffered writes first), as the hot cache doesn't support metadata filters.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(limit = limit, filter = ?filter)))]
+    pub async fn retrieve_where<AsRefStr>(
+        &mut self,
+        query: AsRefStr,
+        limit: usize,
+        filter: MetadataFilter,
+    ) -> Result<Vec<SearchResult>, crate::Error>
+    where
+        AsRefStr: AsRef<str>,
+    {
+        let timer = metrics::Timer::start();
 
-        self
+        let embedding = self.embed(query.as_ref(), EmbedInput::Query).await?;
+        self.flush().await?;
+        let results = with_deadline(
+            self.cfg.storage_deadline(),
+            self.storage.search_filtered(embedding, limit, filter),
+        )
+        .await?;
+
+        metrics::record_retrieve(timer);
+
+        Ok(results)
     }
 
-    pub fn hot_cache(mut self, cache: InMemoryDB) -> Self {
-        self.hot_cache = Some(MemoryCache::new(cache));
-        self
+    /// Retrieve memories tagged with any of the given topics (eg, `work` or `family`), see [`MemoryEntry::topics`].
+    pub async fn retrieve_by_topic<AsRefStr>(
+        &mut self,
+        query: AsRefStr,
+        limit: usize,
+        topics: &[&str],
+    ) -> Result<Vec<SearchResult>, crate::Error>
+    where
+        AsRefStr: AsRef<str>,
+    {
+        self.retrieve_filtered(query, limit, |entry| {
+            topics.iter().any(|topic| entry.has_topic(topic))
+        })
+        .await
     }
 
-    pub fn build(self) -> Result<MemoryManager<E, S>, crate::Error> {
-        let Some(storage) = self.storage else {
-            return Err(BuildError::StorageNotFound)?;
-        };
+    /// Retrieve memories with any of the given tags (see [`MemoryEntry::tags`]).
+    pub async fn retrieve_by_tag<AsRefStr>(
+        &mut self,
+        query: AsRefStr,
+        limit: usize,
+        tags: &[&str],
+    ) -> Result<Vec<SearchResult>, crate::Error>
+    where
+        AsRefStr: AsRef<str>,
+    {
+        let filter = MetadataFilter::Or(tags.iter().map(|tag| MetadataFilter::tag(*tag)).collect());
 
-        let Some(embedder) = self.embedder else {
-            return Err(BuildError::EmbedderNotFound)?;
+        self.retrieve_where(query, limit, filter).await
+    }
+
+    /// Retrieve every memory that mentions an entity (eg, `Acme Corp`), looked up by name or alias in the entity index (see [`MemoryManager::entities`]).
+    /// Unlike similarity search, this isn't limited to the closest matches. Memories are returned most recent first.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn retrieve_about(
+        &mut self,
+        entity: &str,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        self.flush().await?;
+        let Some(record) = self.entities.get(entity) else {
+            return Ok(Vec::new());
         };
 
-        let cfg = self.cfg.unwrap_or_default();
+        let mut results = Vec::with_capacity(record.memory_ids.len());
+        for id in &record.memory_ids {
+            results.push(
+                with_deadline(
+                    self.cfg.storage_deadline(),
+                    self.storage.search_by_id(id.clone()),
+                )
+                .await?,
+            );
+        }
+        results.sort_by_key(|result| std::cmp::Reverse(result.data().created_at));
 
-        let mgr = MemoryManager {
-            storage,
-            embedder,
-            cfg,
-            hot_cache: self.hot_cache,
-        };
+        Ok(results)
+    }
 
-        Ok(mgr)
+    /// Gets the most recently created memories, most recent first. Every returned memory is marked as accessed.
+    pub async fn recent(&mut self, limit: usize) -> Result<Vec<SearchResult>, crate::Error> {
+        self.flush().await?;
+        let results =
+            with_deadline(self.cfg.storage_deadline(), self.storage.get_recent(limit)).await?;
+
+        self.touch_all(results).await
     }
-}
 
-/// Memory manager configuration
-pub struct MemoryConfig {
-    /// The maximum number of total memories (don't store any more after max has been reached)
-    pub max_total_memories: Option<usize>,
-    /// Delete memories after N days
-    pub max_age_days: Option<i64>,
-    /// The minimum score required to keep a given memory
-    pub min_retention_score: Option<f32>,
-    /// How many to evict during eviction
-    pub eviction_batch_size: usize,
-    pub custom_caching_strategy: Option<Box<CachingStrategyFn>>,
-}
+    /// Gets the oldest memories, oldest first. Every returned memory is marked as accessed.
+    pub async fn oldest(&mut self, limit: usize) -> Result<Vec<SearchResult>, crate::Error> {
+        self.flush().await?;
+        let results =
+            with_deadline(self.cfg.storage_deadline(), self.storage.get_oldest(limit)).await?;
 
-pub type CachingStrategyFn = dyn Fn(&MemoryConfig, &MemoryEntry) -> bool;
+        self.touch_all(results).await
+    }
 
-impl Default for MemoryConfig {
-    fn default() -> Self {
-        Self::new()
+    /// Gets every memory created within a time range (as Unix timestamps, including `start` but excluding `end`), oldest first.
+    /// Every returned memory is marked as accessed.
+    pub async fn between(
+        &mut self,
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        self.flush().await?;
+        let results = with_deadline(
+            self.cfg.storage_deadline(),
+            self.storage.get_between(start, end),
+        )
+        .await?;
+
+        self.touch_all(results).await
     }
-}
 
-impl MemoryConfig {
-    pub fn new() -> Self {
-        Self {
-            max_total_memories: None,
-            max_age_days: None,
-            min_retention_score: None,
-            eviction_batch_size: 1,
-            custom_caching_strategy: None,
+    /// Gets the record of an entity (including the IDs of every memory mentioning it) by name or alias.
+    pub fn entity(&self, name: &str) -> Option<&EntityRecord> {
+        self.entities.get(name)
+    }
+
+    /// The index linking entities to the memories that mention them. Memories are linked as they get stored by this manager.
+    pub fn entities(&self) -> &EntityIndex {
+        &self.entities
+    }
+
+    /// The entity index, eg, for adding aliases with [`EntityIndex::add_alias`].
+    pub fn entities_mut(&mut self) -> &mut EntityIndex {
+        &mut self.entities
+    }
+
+    /// Rebuilds the entity index from every memory in storage (eg, after restarting with a persistent storage), fetching memories in batches of the given size.
+    /// Aliases are kept.
+    pub async fn rebuild_entity_index(&mut self, batch_size: usize) -> Result<(), crate::Error> {
+        self.flush().await?;
+        let batch_size = batch_size.max(1);
+        let mut index = self.entities.clone();
+        index.clear_records();
+        let mut offset = 0;
+
+        loop {
+            let batch = self.storage.get_oldest_page(offset, batch_size).await?;
+            if batch.is_empty() {
+                break;
+            }
+
+            offset += batch.len();
+            for result in &batch {
+                index.link(result.data());
+            }
         }
+
+        self.entities = index;
+
+        Ok(())
     }
 
-    pub fn should_cache(&self, entry: &MemoryEntry) -> bool {
-        if let Some(strategy) = self.custom_caching_strategy.as_ref() {
-            return strategy(self, entry);
-        };
+    /// Writes any memories buffered by a [`WritePolicy::WriteBack`] hot cache to storage, returning how many memories were written.
+    /// If a write fails, the memories that haven't been written yet stay buffered.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn flush(&mut self) -> Result<usize, crate::Error> {
+        let written = self.storage.flush(self.cfg.storage_deadline()).await?;
 
-        entry.importance > 0.5 || entry.access_count > 0
+        if written > 0 {
+            self.report_store_size().await;
+        }
 
-        // // awaiting new Rig release
-        // match entry.kind {
-        //     MemoryKind::Working => false,
-        //     MemoryKind::Semantic => true,
-        //     MemoryKind::Episodic => entry.importance > 0.5 || entry.access_count > 0,
-        // }
+        Ok(written)
     }
 
-    pub fn should_retain_in_cache(&self, entry: &MemoryEntry) -> bool {
-        entry.importance > 0.6 && entry.access_count >= 2
-
-        // // awaiting new Rig release
-        // match entry.kind {
-        //     MemoryKind::Semantic => true,
-        //     MemoryKind::Episodic => entry.importance > 0.6 && entry.access_count >= 2,
-        //     MemoryKind::Working => false,
-        // }
+    /// Flushes any writes buffered by a [`WritePolicy::WriteBack`] hot cache, then drops the manager.
+    /// Dropping the manager also flushes, but has to block the current thread to do so (and can't report errors), so prefer calling this when shutting down.
+    pub async fn close(mut self) -> Result<(), crate::Error> {
+        self.flush().await?;
+
+        Ok(())
+    }
+
+    /// The number of stored memories, including writes buffered by a [`WritePolicy::WriteBack`] hot cache.
+    pub async fn count(&self) -> Result<usize, crate::Error> {
+        let count = with_deadline(self.cfg.storage_deadline(), self.storage.count()).await?;
+
+        Ok(count + self.storage.pending.len())
+    }
+
+    /// Gets statistics about every memory (eg, counts per kind, the importance distribution and age percentiles), for understanding what the agent knows.
+    /// Memories are read from storage in batches, and writes buffered by a write-back hot cache are included.
+    pub async fn stats(&self) -> Result<MemoryStats, crate::Error> {
+        const BATCH_SIZE: usize = 100;
+
+        let mut collector = MemoryStatsCollector::new(Utc::now().timestamp());
+        let mut storage_size = 0;
+        loop {
+            let batch = with_deadline(
+                self.cfg.storage_deadline(),
+                self.storage.get_oldest_page(storage_size, BATCH_SIZE),
+            )
+            .await?;
+            if batch.is_empty() {
+                break;
+            }
+
+            storage_size += batch.len();
+            for result in &batch {
+                collector.add(result.data());
+            }
+        }
+
+        for (_, entry) in &self.storage.pending {
+            collector.add(entry);
+        }
+
+        Ok(collector.finish(storage_size, self.cache_stats().map(CacheStats::hit_ratio)))
+    }
+
+    /// Checks whether the storage is reachable (see [`Storage::health_check`]), eg, for readiness probes in service deployments.
+    pub async fn health(&self) -> HealthStatus {
+        self.storage.health_check().await
+    }
+
+    /// The number of memories buffered by a [`WritePolicy::WriteBack`] hot cache that haven't been written to storage yet.
+    pub fn pending_writes(&self) -> usize {
+        self.storage.pending.len()
+    }
+
+    /// The underlying storage. Writes buffered by a [`WritePolicy::WriteBack`] hot cache only show up here once they've been flushed (see [`MemoryManager::flush`]).
+    pub fn storage(&self) -> &S {
+        &self.storage.inner
+    }
+
+    /// Takes a consistent, read-only copy of the storage (including any writes buffered by the hot cache), so long scans and searches can run against it without holding onto the manager.
+    /// For an [`InMemoryDB`], this is cheap (see [`InMemoryDB::read_snapshot`]). Reading from the copy doesn't update how recently memories were accessed.
+    pub async fn storage_snapshot(&mut self) -> Result<S, crate::Error>
+    where
+        S: Clone,
+    {
+        self.flush().await?;
+
+        Ok(self.storage.inner.clone())
+    }
+
+    /// Prepares a retrieval that runs against a read snapshot of the storage (see [`MemoryManager::storage_snapshot`]), embedding the query up front.
+    /// Running the retrieval doesn't need the manager, so with a [`SharedMemoryManager`] the lock only needs to be held while preparing it: long searches then don't block writers.
+    ///
+    /// NOTE: Snapshot retrievals always search the storage rather than the hot cache, and don't update how recently memories were accessed.
+    pub async fn snapshot_retrieval<AsRefStr>(
+        &mut self,
+        query: AsRefStr,
+        limit: usize,
+    ) -> Result<SnapshotRetrieval<S>, crate::Error>
+    where
+        AsRefStr: AsRef<str>,
+        S: Clone,
+    {
+        let embedding = self.embed(query.as_ref(), EmbedInput::Query).await?;
+        let snapshot = self.storage_snapshot().await?;
+
+        Ok(SnapshotRetrieval {
+            snapshot,
+            embedding,
+            limit,
+            deadline: self.cfg.storage_deadline(),
+        })
+    }
+
+    /// How much of its quota a namespace (ie, an agent ID, see [`crate::memory::quota`]) is using.
+    /// The first call loads usage from storage, after which usage is kept up to date as memories are stored and removed.
+    pub async fn quota_usage(&mut self, namespace: &str) -> Result<QuotaUsage, crate::Error> {
+        self.load_quota_usage().await?;
+
+        // SAFETY: Quota usage was loaded above
+        Ok(self.quota_tracker.as_ref().unwrap().usage(namespace))
+    }
+
+    /// How much of its quota every namespace with memories is using (see [`MemoryManager::quota_usage`]).
+    pub async fn all_quota_usage(&mut self) -> Result<HashMap<String, QuotaUsage>, crate::Error> {
+        self.load_quota_usage().await?;
+
+        // SAFETY: Quota usage was loaded above
+        Ok(self.quota_tracker.as_ref().unwrap().all_usage().clone())
+    }
+
+    /// Statistics about the hot cache (hits, misses, evictions, size and latencies), or `None` if there's no hot cache.
+    pub fn cache_stats(&self) -> Option<&CacheStats> {
+        self.hot_cache.as_ref().map(MemoryCache::stats)
+    }
+
+    /// Saves the hot cache to a file (see [`MemoryCache::save_snapshot`]), flushing any pending writes first so that every saved memory is also in storage.
+    /// Does nothing if there's no hot cache.
+    pub async fn save_cache_snapshot<P>(&mut self, path: P) -> Result<(), crate::Error>
+    where
+        P: AsRef<Path>,
+    {
+        self.flush().await?;
+
+        match &self.hot_cache {
+            Some(cache) => cache.save_snapshot(path),
+            None => Ok(()),
+        }
+    }
+
+    /// Restores the hot cache from a file saved by [`MemoryManager::save_cache_snapshot`], returning how many memories were restored (0 if there's no hot cache).
+    pub async fn restore_cache_snapshot<P>(&mut self, path: P) -> Result<usize, crate::Error>
+    where
+        P: AsRef<Path>,
+    {
+        match &mut self.hot_cache {
+            Some(cache) => cache.restore_snapshot(path).await,
+            None => Ok(0),
+        }
+    }
+
+    /// Preloads up to `n` memories from storage into the hot cache (eg, at startup), so that the first retrievals don't all miss the cache.
+    /// Memories are picked by importance plus a recency bonus that halves every week since they were last accessed. Use [`MemoryManager::warm_cache_by`] for a custom score.
+    /// Returns how many memories were loaded into the hot cache.
+    pub async fn warm_cache(&mut self, n: usize) -> Result<usize, crate::Error> {
+        let now = Utc::now().timestamp();
+
+        self.warm_cache_by(n, |entry| warm_score(entry, now)).await
+    }
+
+    /// Preloads up to `n` memories from storage into the hot cache, picking the memories with the highest score.
+    /// At most as many memories as the hot cache can hold are loaded. Memories already in the hot cache, as well as memories that shouldn't be cached (eg, working memories), are skipped.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(n = n)))]
+    pub async fn warm_cache_by<F>(&mut self, n: usize, score: F) -> Result<usize, crate::Error>
+    where
+        F: Fn(&MemoryEntry) -> f64,
+    {
+        const BATCH_SIZE: usize = 100;
+
+        let Some(cache) = &self.hot_cache else {
+            return Ok(0);
+        };
+        let n = n.min(cache.memory_limit() as usize);
+        if n == 0 {
+            return Ok(0);
+        }
+
+        self.flush().await?;
+        let mut best: Vec<(f64, SearchResult)> = Vec::with_capacity(n + BATCH_SIZE);
+        let mut offset = 0;
+        loop {
+            let batch = self.storage.get_oldest_page(offset, BATCH_SIZE).await?;
+            if batch.is_empty() {
+                break;
+            }
+
+            offset += batch.len();
+            // SAFETY: We checked that there's a hot cache above
+            let cache = self.hot_cache.as_ref().unwrap();
+            best.extend(
+                batch
+                    .into_iter()
+                    .filter(|result| should_cache(&self.cfg, cache, result.data()))
+                    .map(|result| (score(result.data()), result)),
+            );
+            best.sort_by(|a, b| b.0.total_cmp(&a.0));
+            best.truncate(n);
+        }
+
+        // SAFETY: We checked that there's a hot cache above
+        let cache = self.hot_cache.as_mut().unwrap();
+        let mut loaded = 0;
+        for (_, result) in best {
+            if cache.store.contains(&result.data().id) {
+                continue;
+            }
+
+            let entry = result.data_owned();
+            cache.insert(result.embedding_owned(), entry).await?;
+            loaded += 1;
+        }
+        cache.invalidate_query_cache();
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(loaded, "warmed hot cache");
+
+        Ok(loaded)
+    }
+
+    /// Evicts expired memories from the hot cache (see [`crate::memory::cache::MemoryCacheBuilder::ttl`]), returning how many memories were evicted.
+    /// Returns 0 if there's no hot cache.
+    pub async fn sweep_cache(&mut self) -> Result<usize, crate::Error> {
+        match &mut self.hot_cache {
+            Some(cache) => cache.sweep_expired().await,
+            None => Ok(0),
+        }
+    }
+
+    /// Runs a maintenance pass, deleting memories that break the retention policy for their kind (see [`MemoryConfig::retention`]) and evicting expired memories from the hot cache.
+    /// This is worth running on a schedule (eg, hourly). Memories are read from storage in batches, and deleted once every batch has been read.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn maintain(&mut self) -> Result<MaintenanceReport, crate::Error> {
+        const BATCH_SIZE: usize = 100;
+
+        let mut report = MaintenanceReport::default();
+        if !self.cfg.retention.is_empty() {
+            self.flush().await?;
+            let now = Utc::now().timestamp();
+            let mut expired: Vec<(MemoryKind, MemoryId)> = Vec::new();
+            // The remaining memories of each kind with a max count, oldest first
+            let mut remaining: HashMap<MemoryKind, Vec<MemoryId>> = HashMap::new();
+            let mut offset = 0;
+            loop {
+                let batch = with_deadline(
+                    self.cfg.storage_deadline(),
+                    self.storage.get_oldest_page(offset, BATCH_SIZE),
+                )
+                .await?;
+                if batch.is_empty() {
+                    break;
+                }
+
+                offset += batch.len();
+                for result in batch {
+                    let entry = result.data();
+                    let Some(policy) = self.cfg.retention.get(&entry.kind) else {
+                        continue;
+                    };
+
+                    if policy.is_expired(entry, now) {
+                        expired.push((entry.kind, entry.id.clone()));
+                    } else if policy.max_count.is_some() {
+                        remaining
+                            .entry(entry.kind)
+                            .or_default()
+                            .push(entry.id.clone());
+                    }
+                }
+            }
+
+            for (kind, ids) in remaining {
+                // SAFETY: Only kinds with a max count have remaining memories
+                let max_count = self.cfg.retention[&kind].max_count.unwrap();
+                let excess = ids.len().saturating_sub(max_count);
+                expired.extend(ids.into_iter().take(excess).map(|id| (kind, id)));
+            }
+
+            for (kind, id) in expired {
+                self.remove(&id).await?;
+                metrics::record_deletes("retention", 1);
+                *report.deleted.entry(kind).or_default() += 1;
+            }
+        }
+
+        report.cache_evicted = self.sweep_cache().await?;
+
+        #[cfg(feature = "tracing")]
+        tracing::info!(deleted = ?report.deleted, cache_evicted = report.cache_evicted, "ran memory maintenance");
+
+        Ok(report)
+    }
+
+    /// Retrieve memories and condense them into a single context string using a [`MemorySummarizer`].
+    /// The raw retrieved memories are returned alongside the summary. If no memories are found, the summarizer is not called.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(limit = limit)))]
+    pub async fn retrieve_summarized<AsRefStr, T>(
+        &mut self,
+        query: AsRefStr,
+        limit: usize,
+        summarizer: &T,
+    ) -> Result<SummarizedRetrieval, crate::Error>
+    where
+        AsRefStr: AsRef<str>,
+        T: MemorySummarizer,
+    {
+        let results = self.retrieve(query, limit).await?;
+
+        if results.is_empty() {
+            return Ok(SummarizedRetrieval {
+                summary: String::new(),
+                results,
+            });
+        }
+
+        let memories: Vec<MemoryEntry> = results.iter().map(|x| x.data_owned()).collect();
+        let summary = summarizer.summarize(&memories).await?;
+
+        Ok(SummarizedRetrieval { summary, results })
+    }
+
+    /// Rolls up old, low-importance memories (episodic memories by default) into one digest memory per week or month using a [`MemorySummarizer`], deleting the originals.
+    /// This keeps the number of stored memories bounded without losing the gist of history, so it's worth running on a schedule (eg, nightly).
+    ///
+    /// Digest memories are episodic, tagged with [`DIGEST_TAG`] and never digested again. If a period already has a digest (eg, from a previous pass), it's folded into the new digest.
+    /// Each digest is stored before its originals get deleted, so an interrupted pass never loses memories.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn digest<T>(
+        &mut self,
+        summarizer: &T,
+        policy: &DigestPolicy,
+    ) -> Result<DigestReport, crate::Error>
+    where
+        T: MemorySummarizer,
+    {
+        const BATCH_SIZE: usize = 100;
+
+        self.flush().await?;
+        let now = Utc::now().timestamp();
+        let mut periods: BTreeMap<(i64, String), Vec<MemoryEntry>> = BTreeMap::new();
+        let mut offset = 0;
+        loop {
+            let batch = with_deadline(
+                self.cfg.storage_deadline(),
+                self.storage.get_oldest_page(offset, BATCH_SIZE),
+            )
+            .await?;
+            if batch.is_empty() {
+                break;
+            }
+
+            offset += batch.len();
+            for result in batch {
+                if policy.should_digest(result.data(), now) {
+                    let period = policy.period.period_of(result.data().created_at);
+                    periods.entry(period).or_default().push(result.data_owned());
+                }
+            }
+        }
+
+        let mut report = DigestReport::default();
+        for ((start, label), mut memories) in periods {
+            if memories.len() < policy.min_memories.max(1) {
+                continue;
+            }
+
+            let id = digest_id(&label);
+            let existing = match self.storage.search_by_id(id.clone()).await {
+                Ok(existing) => Some(existing.data_owned()),
+                Err(crate::Error::Storage(StorageError::EmbeddingNotExists(_))) => None,
+                Err(err) => return Err(err),
+            };
+            let originals = memories.len();
+            if let Some(existing) = existing {
+                memories.insert(0, existing);
+            }
+
+            let content = summarizer.summarize(&memories).await?;
+            let digest = MemoryEntry {
+                id: id.clone(),
+                content: content.clone(),
+                kind: MemoryKind::Episodic,
+                importance: memories
+                    .iter()
+                    .map(|memory| memory.importance)
+                    .fold(0.0, f32::max),
+                created_at: start,
+                last_accessed: now,
+                access_count: memories.iter().map(|memory| memory.access_count).sum(),
+                source_context: format!("Digest of {originals} memories ({label})"),
+                // SAFETY: Periods are never empty
+                confidence: memories
+                    .iter()
+                    .map(|memory| memory.confidence.clone())
+                    .min()
+                    .unwrap(),
+                metadata: Vec::new(),
+                agent_id: None,
+                visibility: Visibility::default(),
+                embedding_model: None,
+                image: None,
+                tags: vec![DIGEST_TAG.to_string()],
+                strength: INITIAL_STRENGTH,
+            };
+
+            // Replace any previous digest first, as it's been folded into the new one
+            if memories[0].id == id {
+                self.remove(&id).await?;
+            }
+            self.store(content, digest).await?;
+            for memory in memories.iter().filter(|memory| memory.id != id) {
+                self.remove(&memory.id).await?;
+            }
+
+            report.digests.push(id);
+            report.digested += originals;
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            digests = report.digests.len(),
+            digested = report.digested,
+            "digested memories"
+        );
+
+        Ok(report)
+    }
+
+    /// Store a newly extracted memory, first letting a [`MemoryReconciler`] compare it against up to `candidates` similar existing memories.
+    /// The reconciler decides whether the memory gets added, updates or merges existing memories, deletes an existing memory, or is discarded. The executed operation is returned.
+    ///
+    /// Returns an error if the reconciler refers to a memory that wasn't one of the similar memories it was given.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(memory_id = %entry.id))
+    )]
+    pub async fn store_reconciled<R>(
+        &mut self,
+        mut entry: MemoryEntry,
+        reconciler: &R,
+        candidates: usize,
+    ) -> Result<MemoryOperation, crate::Error>
+    where
+        R: MemoryReconciler,
+    {
+        let timer = metrics::Timer::start();
+        self.validate_entry(&mut entry)?;
+
+        let embedding = self.embed(&entry.content, EmbedInput::Document).await?;
+        let existing: Vec<MemoryEntry> = self
+            .search_candidates(embedding.clone(), candidates, CacheMode::Default)
+            .await?
+            .into_iter()
+            .map(|result| result.data_owned())
+            .collect();
+
+        let operation = if existing.is_empty() {
+            MemoryOperation::Add
+        } else {
+            reconciler.reconcile(&entry, &existing).await?
+        };
+
+        if let Some(id) = operation
+            .target_ids()
+            .into_iter()
+            .find(|id| !existing.iter().any(|memory| memory.id == *id))
+        {
+            return Err(crate::Error::Custom(format!(
+                "Memory reconciler referred to unknown memory {id}"
+            )));
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(?operation, "reconciled memory");
+
+        match &operation {
+            MemoryOperation::Add => {
+                entry.embedding_model = embedding_model_tag(&self.embedder, &embedding);
+                self.insert(embedding, entry).await?;
+            }
+            MemoryOperation::Update { id, content } => {
+                self.replace_content(id, content).await?;
+            }
+            MemoryOperation::Merge { ids, content } => {
+                if let Some((id, rest)) = ids.split_first() {
+                    self.replace_content(id, content).await?;
+                    for id in rest {
+                        self.remove(id).await?;
+                    }
+                    metrics::record_deletes("delete", rest.len());
+                }
+            }
+            MemoryOperation::Delete { id } => {
+                self.remove(id).await?;
+                metrics::record_deletes("delete", 1);
+            }
+            MemoryOperation::None => {}
+        }
+
+        metrics::record_store(
+            timer,
+            usize::from(matches!(operation, MemoryOperation::Add)),
+        );
+
+        Ok(operation)
+    }
+
+    /// Store a single memory along with a sparse embedding (eg, SPLADE), so that it can be found with [`MemoryManager::retrieve_hybrid`].
+    /// The storage must support sparse vectors (see [`Storage::insert_sparse`]).
+    ///
+    /// As the sparse embedding is attached to the stored memory, a write buffered by a [`WritePolicy::WriteBack`] hot cache gets flushed first.
+    /// If the sparse embedding can't be attached, the memory is removed again so that it's never stored without one.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(memory_id = %entry.id))
+    )]
+    pub async fn store_hybrid<AsRefStr, T>(
+        &mut self,
+        memory: AsRefStr,
+        entry: MemoryEntry,
+        sparse_embedder: &T,
+    ) -> Result<(), crate::Error>
+    where
+        AsRefStr: AsRef<str>,
+        T: SparseEmbedder,
+    {
+        let sparse = sparse_embedder.embed_sparse(memory.as_ref()).await?;
+        let id = entry.id.clone();
+
+        self.store(memory, entry).await?;
+        if let Err(err) = self.attach_sparse(id.clone(), sparse).await {
+            if let Err(_err) = self.remove(&id).await {
+                #[cfg(feature = "tracing")]
+                tracing::error!(error = %_err, memory_id = %id, "failed to remove memory without a sparse embedding");
+            }
+
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// Attaches a sparse embedding to a stored memory, flushing buffered writes first if the memory hasn't been written to storage yet.
+    async fn attach_sparse(
+        &mut self,
+        id: MemoryId,
+        sparse: SparseVector,
+    ) -> Result<(), crate::Error> {
+        if self
+            .storage
+            .pending
+            .iter()
+            .any(|(_, pending)| pending.id == id)
+        {
+            self.flush().await?;
+        }
+
+        with_deadline(
+            self.cfg.storage_deadline(),
+            self.storage.insert_sparse(id, sparse),
+        )
+        .await
+    }
+
+    /// Retrieve memories using both dense and sparse embeddings, fusing the scores together.
+    /// Sparse embeddings help with recalling rare tokens (eg, names, IDs or error codes) that dense embeddings tend to miss.
+    ///
+    /// NOTE: Hybrid retrieval always searches the backing storage, as the hot cache only holds dense embeddings.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(limit = limit)))]
+    pub async fn retrieve_hybrid<AsRefStr, T>(
+        &mut self,
+        query: AsRefStr,
+        limit: usize,
+        sparse_embedder: &T,
+        fusion: HybridFusion,
+    ) -> Result<Vec<SearchResult>, crate::Error>
+    where
+        AsRefStr: AsRef<str>,
+        T: SparseEmbedder,
+    {
+        let timer = metrics::Timer::start();
+
+        let embedding = self.embed(query.as_ref(), EmbedInput::Query).await?;
+        let sparse = sparse_embedder.embed_sparse(query.as_ref()).await?;
+        self.flush().await?;
+        let results = with_deadline(
+            self.cfg.storage_deadline(),
+            self.storage.search_hybrid(embedding, sparse, limit, fusion),
+        )
+        .await?;
+
+        metrics::record_retrieve(timer);
+
+        Ok(results)
+    }
+
+    /// Store a single memory using an int8-quantized embedding, cutting transfer and memory costs when paired with a quantized storage (eg, [`crate::vector_store::QuantizedInMemoryDB`]).
+    ///
+    /// NOTE: Quantized memories aren't added to the hot cache, and embeddings aren't checked for normalization.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(memory_id = %entry.id))
+    )]
+    pub async fn store_quantized<AsRefStr>(
+        &mut self,
+        memory: AsRefStr,
+        mut entry: MemoryEntry,
+    ) -> Result<(), crate::Error>
+    where
+        AsRefStr: AsRef<str>,
+        E: QuantizedEmbedder,
+    {
+        let timer = metrics::Timer::start();
+        self.validate_entry(&mut entry)?;
+
+        let embedding = self
+            .embedder
+            .embed_document_quantized(memory.as_ref())
+            .await?;
+        entry.embedding_model = self.embedder.model_id().map(|model| EmbeddingModelTag {
+            model,
+            dims: embedding.len(),
+        });
+        self.enforce_quota(&entry).await?;
+
+        // Cached query results are invalidated up front, so a cancelled insert can't leave them stale
+        if let Some(cache) = &mut self.hot_cache {
+            cache.invalidate_query_cache();
+        }
+
+        with_deadline(
+            self.cfg.storage_deadline(),
+            self.storage.insert_quantized(embedding, entry.clone()),
+        )
+        .await?;
+        self.entities.link(&entry);
+        if let Some(tracker) = &mut self.quota_tracker {
+            tracker.track(&entry);
+        }
+
+        self.report_store_size().await;
+        metrics::record_store(timer, 1);
+
+        Ok(())
+    }
+
+    /// Retrieve memories using an int8-quantized query embedding.
+    ///
+    /// NOTE: Quantized retrieval always searches the backing storage, as the hot cache only holds float embeddings.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(limit = limit)))]
+    pub async fn retrieve_quantized<AsRefStr>(
+        &mut self,
+        query: AsRefStr,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, crate::Error>
+    where
+        AsRefStr: AsRef<str>,
+        E: QuantizedEmbedder,
+    {
+        let timer = metrics::Timer::start();
+
+        let embedding = self.embedder.embed_query_quantized(query.as_ref()).await?;
+        self.flush().await?;
+        let results = with_deadline(
+            self.cfg.storage_deadline(),
+            self.storage.search_quantized(embedding, limit),
+        )
+        .await?;
+
+        metrics::record_retrieve(timer);
+
+        Ok(results)
+    }
+
+    /// Marks a memory as accessed (in storage as well as the hot cache), and caches it if it now needs to be hot cached.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(memory_id = %memory.id))
+    )]
+    pub async fn update_memory_access(&mut self, memory: MemoryEntry) -> Result<(), crate::Error> {
+        let memory = self.touch(memory).await?;
+
+        if let Some(cache) = &mut self.hot_cache
+            && !cache.store.contains(&memory.id)
+            && should_cache(&self.cfg, cache, &memory)
+        {
+            let pending = self
+                .storage
+                .pending
+                .iter()
+                .find(|(_, pending)| pending.id == memory.id)
+                .map(|(embedding, _)| embedding.clone());
+            let embedding = match pending {
+                Some(embedding) => embedding,
+                None => self
+                    .storage
+                    .search_by_id(memory.id.clone())
+                    .await?
+                    .embedding_owned(),
+            };
+
+            cache.insert(embedding, memory).await?;
+            cache.invalidate_query_cache();
+        }
+
+        Ok(())
+    }
+
+    /// Updates a stored memory (matched by ID), keeping the hot cache and entity index in sync.
+    /// If the content of the memory has changed, it gets re-embedded. Otherwise, only the payload is updated.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(memory_id = %memory.id))
+    )]
+    pub async fn update(&mut self, mut memory: MemoryEntry) -> Result<(), crate::Error> {
+        memory.importance = self.cfg.importance_policy.apply(memory.importance)?;
+        self.enforce_quota(&memory).await?;
+        self.flush().await?;
+        let existing = with_deadline(
+            self.cfg.storage_deadline(),
+            self.storage.search_by_id(memory.id.clone()),
+        )
+        .await?;
+
+        if existing.data().content != memory.content {
+            let embedding = self.embed(&memory.content, EmbedInput::Document).await?;
+            memory.embedding_model = embedding_model_tag(&self.embedder, &embedding);
+
+            self.remove(&memory.id).await?;
+            return self.insert(embedding, memory).await;
+        }
+
+        with_deadline(
+            self.cfg.storage_deadline(),
+            self.storage
+                .update_payload_by_id(memory.id.clone(), memory.clone()),
+        )
+        .await?;
+        self.entities.unlink(&memory.id);
+        self.entities.link(&memory);
+        if let Some(tracker) = &mut self.quota_tracker {
+            tracker.track(&memory);
+        }
+
+        if let Some(cache) = &mut self.hot_cache {
+            if cache.store.contains(&memory.id) {
+                cache
+                    .store
+                    .update_payload_by_id(memory.id.clone(), memory)
+                    .await?;
+            }
+            cache.invalidate_query_cache();
+        }
+
+        Ok(())
+    }
+
+    /// Deletes a stored memory, removing it from the hot cache and entity index as well.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(memory_id = %id))
+    )]
+    pub async fn delete(&mut self, id: &str) -> Result<(), crate::Error> {
+        self.remove(id).await?;
+        metrics::record_deletes("delete", 1);
+
+        Ok(())
+    }
+
+    /// Re-embeds every stored memory using a new embedder, replacing the existing embeddings in place.
+    ///
+    /// Memories are processed oldest first in batches, and `progress` is updated after every memory. If an error occurs, calling this again with the same `progress` resumes where it left off.
+    /// Once complete, the hot cache (if any) is cleared and you should swap over to the new embedder using [`MemoryManager::with_embedder`].
+    ///
+    /// NOTE: Memories are re-embedded using their `content`. The storage must accept the new embedding dimensions - if it doesn't, use [`MemoryManager::reembed_into`] instead.
+    pub async fn reembed_in_place<E2>(
+        &mut self,
+        embedder: &E2,
+        progress: &mut ReembedProgress,
+    ) -> Result<(), crate::Error>
+    where
+        E2: Embedder,
+    {
+        self.flush().await?;
+        progress.start(&*self.storage).await?;
+        let mut dims = None;
+
+        loop {
+            let batch = progress.next_batch(&*self.storage).await?;
+            if batch.is_empty() {
+                break;
+            }
+
+            for result in batch {
+                let mut entry = result.data_owned();
+                let embedding = embedder.embed_document(&entry.content).await?;
+                dims = Some(embedding.len());
+                entry.embedding_model = embedding_model_tag(embedder, &embedding);
+
+                self.storage.delete(entry.id.clone()).await?;
+                if let Err(err) = self.storage.insert(embedding, entry.clone()).await {
+                    // Put the original embedding back so a failed insert doesn't lose the memory
+                    if let Err(restore_err) = self
+                        .storage
+                        .insert(result.embedding_owned(), result.data_owned())
+                        .await
+                    {
+                        return Err(crate::Error::Custom(format!(
+                            "Failed to re-embed memory {} ({err}), and restoring its original embedding failed ({restore_err}): the memory has been lost",
+                            entry.id
+                        )));
+                    }
+                    return Err(err);
+                }
+                progress.advance(&entry);
+            }
+        }
+
+        if let Some(cache) = &mut self.hot_cache {
+            let dims = dims.unwrap_or(cache.store.dims());
+            cache.replace_store(InMemoryDB::new(dims));
+        }
+
+        Ok(())
+    }
+
+    /// Re-embeds every stored memory using a new embedder, writing the results into a new storage.
+    /// The existing storage is left untouched.
+    ///
+    /// Memories are processed oldest first in batches, and `progress` is updated after every memory. If an error occurs, calling this again with the same `progress` resumes where it left off.
+    /// Once complete, build a new [`MemoryManager`] using the new embedder and storage.
+    ///
+    /// NOTE: Writes buffered by a [`WritePolicy::WriteBack`] hot cache aren't included, so call [`MemoryManager::flush`] first.
+    pub async fn reembed_into<E2, S2>(
+        &self,
+        embedder: &E2,
+        target: &mut S2,
+        progress: &mut ReembedProgress,
+    ) -> Result<(), crate::Error>
+    where
+        E2: Embedder,
+        S2: Storage,
+    {
+        progress.start(&*self.storage).await?;
+
+        loop {
+            let batch = progress.next_batch(&*self.storage).await?;
+            if batch.is_empty() {
+                break;
+            }
+
+            for result in batch {
+                let mut entry = result.data_owned();
+                let embedding = embedder.embed_document(&entry.content).await?;
+                entry.embedding_model = embedding_model_tag(embedder, &embedding);
+
+                target.insert(embedding, entry.clone()).await?;
+                progress.advance(&entry);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Re-embeds every stored memory in place using a new embedder (see [`MemoryManager::reembed_in_place`]), then swaps over to it.
+    /// To write the re-embedded memories into a new storage instead, use [`MemoryManager::reembed_into`].
+    ///
+    /// If re-embedding fails, the returned [`ReembedFailure`] hands back the manager (still using the old embedder) along with the new embedder.
+    /// Calling this again with the same `progress` resumes where it left off.
+    pub async fn reembed_all<E2>(
+        mut self,
+        embedder: E2,
+        progress: &mut ReembedProgress,
+    ) -> Result<MemoryManager<E2, S>, Box<ReembedFailure<E, E2, S>>>
+    where
+        E2: Embedder,
+    {
+        match self.reembed_in_place(&embedder, progress).await {
+            Ok(()) => Ok(self.with_embedder(embedder)),
+            Err(error) => Err(Box::new(ReembedFailure {
+                manager: self,
+                embedder,
+                error,
+            })),
+        }
+    }
+
+    /// Swaps out the embedder, returning a new memory manager.
+    /// Make sure stored memories have been re-embedded (see [`MemoryManager::reembed_in_place`]) before doing this, otherwise similarity search will return garbage results.
+    pub fn with_embedder<E2>(self, embedder: E2) -> MemoryManager<E2, S>
+    where
+        E2: Embedder,
+    {
+        MemoryManager {
+            storage: self.storage,
+            embedder,
+            cfg: self.cfg,
+            hot_cache: self.hot_cache,
+            entities: self.entities,
+            quota_tracker: self.quota_tracker,
+        }
+    }
+
+    /// Searches for memories using the configured [`RetrievalMode`].
+    async fn search(
+        &mut self,
+        embedding: Vec<f32>,
+        limit: usize,
+        cache_mode: CacheMode,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        let RetrievalMode::TimeWeighted {
+            decay_rate,
+            candidates,
+        } = self.cfg.retrieval_mode
+        else {
+            return self.search_candidates(embedding, limit, cache_mode).await;
+        };
+
+        let candidates = self
+            .search_candidates(embedding.clone(), candidates.max(limit), cache_mode)
+            .await?;
+
+        let now = Utc::now().timestamp();
+        let mut seen = HashSet::new();
+        let mut scored: Vec<(f32, SearchResult)> = candidates
+            .into_iter()
+            .filter(|result| seen.insert(result.data().id.clone()))
+            .map(|result| {
+                let score = time_weighted_score(&embedding, &result, decay_rate, now);
+                (score, result)
+            })
+            .collect();
+
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored.truncate(limit);
+
+        let mut results = Vec::with_capacity(scored.len());
+        for (score, result) in scored {
+            let entry = self.touch(result.data_owned()).await?;
+            results.push(SearchResult::new(result.embedding_owned(), entry).with_score(score));
+        }
+
+        Ok(results)
+    }
+
+    /// Marks a memory as accessed, persisting the new access metadata to storage (as well as the hot cache if it holds the memory).
+    async fn touch(&mut self, mut memory: MemoryEntry) -> Result<MemoryEntry, crate::Error> {
+        let now = Utc::now().timestamp();
+        memory.strength = strength::reinforce(memory.strength, now - memory.last_accessed);
+        memory.last_accessed = now;
+        memory.access_count += 1;
+
+        if let Some((_, pending)) = self
+            .storage
+            .pending
+            .iter_mut()
+            .find(|(_, pending)| pending.id == memory.id)
+        {
+            *pending = memory.clone();
+        } else {
+            with_deadline(
+                self.cfg.storage_deadline(),
+                self.storage
+                    .update_payload_by_id(memory.id.clone(), memory.clone()),
+            )
+            .await?;
+        }
+
+        if let Some(cache) = &mut self.hot_cache
+            && cache.store.contains(&memory.id)
+        {
+            cache
+                .store
+                .update_payload_by_id(memory.id.clone(), memory.clone())
+                .await?;
+        }
+
+        Ok(memory)
+    }
+
+    /// Marks every result as accessed, keeping their embeddings and scores.
+    async fn touch_all(
+        &mut self,
+        results: Vec<SearchResult>,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        let mut touched = Vec::with_capacity(results.len());
+        for result in results {
+            let entry = self.touch(result.data_owned()).await?;
+            let mut updated = SearchResult::new(result.embedding_owned(), entry);
+            if let Some(score) = result.score() {
+                updated = updated.with_score(score);
+            }
+            touched.push(updated);
+        }
+
+        Ok(touched)
+    }
+
+    /// Searches for candidate memories, checking that they were embedded using the same embedding model as the current embedder.
+    /// What happens with mismatched memories depends on the configured [`EmbeddingMismatchPolicy`].
+    async fn search_candidates(
+        &mut self,
+        embedding: Vec<f32>,
+        limit: usize,
+        cache_mode: CacheMode,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        let results = self
+            .search_candidates_once(embedding.clone(), limit, cache_mode)
+            .await?;
+
+        // Without a model identifier, there's nothing to compare stored memories against
+        let Some(current) = embedding_model_tag(&self.embedder, &embedding) else {
+            return Ok(results);
+        };
+        let mismatched: Vec<&SearchResult> = results
+            .iter()
+            .filter(|result| {
+                result
+                    .data()
+                    .embedding_model
+                    .as_ref()
+                    .is_some_and(|tag| tag != &current)
+            })
+            .collect();
+
+        let Some(first) = mismatched.first() else {
+            return Ok(results);
+        };
+
+        match self.cfg.embedding_mismatch_policy {
+            EmbeddingMismatchPolicy::Ignore => Ok(results),
+            EmbeddingMismatchPolicy::Refuse => {
+                let data = first.data();
+                // SAFETY: Mismatched results always have an embedding model tag
+                let stored = data.embedding_model.as_ref().unwrap();
+                Err(StorageError::mismatched_embedding_model(
+                    &data.id, stored, &current,
+                ))?
+            }
+            EmbeddingMismatchPolicy::Reembed => {
+                let mismatched: Vec<SearchResult> = mismatched.into_iter().cloned().collect();
+                for result in mismatched {
+                    let mut entry = result.data_owned();
+                    let new_embedding = self.embed(&entry.content, EmbedInput::Document).await?;
+                    entry.embedding_model = Some(current.clone());
+
+                    // Drop the cached copy first, so the cache never holds a memory that's missing from storage
+                    if let Some(cache) = &mut self.hot_cache {
+                        cache.remove(&entry.id).await?;
+                    }
+
+                    self.storage.delete(entry.id.clone()).await?;
+                    if let Err(err) = self.storage.insert(new_embedding, entry.clone()).await {
+                        // Put the original embedding back so a failed insert doesn't lose the memory
+                        self.storage
+                            .insert(result.embedding_owned(), result.data_owned())
+                            .await?;
+                        return Err(err);
+                    }
+                }
+
+                if let Some(cache) = &mut self.hot_cache {
+                    cache.invalidate_query_cache();
+                }
+
+                self.search_candidates_once(embedding, limit, cache_mode)
+                    .await
+            }
+        }
+    }
+
+    /// Searches the hot cache (if there is one) and then the backing storage for a given embedding, depending on the cache mode.
+    async fn search_candidates_once(
+        &mut self,
+        embedding: Vec<f32>,
+        limit: usize,
+        cache_mode: CacheMode,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        let stopwatch = Stopwatch::start();
+        let mut cache = match cache_mode {
+            CacheMode::Bypass => None,
+            CacheMode::Default | CacheMode::CacheOnly => self.hot_cache.as_mut(),
+        };
+
+        if let Some(cache) = &mut cache
+            && let Some(results) = cache.cached_query_results(&embedding, limit)
+        {
+            cache.stats_mut().add_hit();
+            stopwatch.record(cache.stats_mut().lookup_latency_mut(), "lookup");
+            metrics::record_cache_lookup(true, cache.stats().hit_ratio());
+            #[cfg(feature = "tracing")]
+            tracing::debug!(candidates = results.len(), "served query from query cache");
+
+            return Ok(results);
+        }
+
+        if let Some(cache) = &cache
+            && cache.is_known_miss(&embedding)
+        {
+            #[cfg(feature = "tracing")]
+            tracing::debug!("served query from negative cache");
+
+            return Ok(Vec::new());
+        }
+
+        let mut results = if let Some(cache) = &mut cache {
+            let results = cache.search(embedding.clone(), limit).await?;
+            if !results.is_empty() {
+                cache.stats_mut().add_hit();
+            } else {
+                cache.stats_mut().add_miss();
+            };
+            stopwatch.record(cache.stats_mut().lookup_latency_mut(), "lookup");
+            metrics::record_cache_lookup(!results.is_empty(), cache.stats().hit_ratio());
+            #[cfg(feature = "tracing")]
+            tracing::debug!(candidates = results.len(), "searched hot cache");
+
+            results
+        } else {
+            Vec::new()
+        };
+
+        // Cache-only results may be incomplete, so they aren't cached as query results
+        if cache_mode == CacheMode::CacheOnly {
+            return Ok(results);
+        }
+
+        let mut deep_results = Vec::new();
+        if results.len() < limit {
+            // Storage is searched for the full limit, as it also holds the memories found in the hot cache
+            let found = with_deadline(
+                self.cfg.storage_deadline(),
+                self.storage.search(embedding.clone(), limit),
+            )
+            .await?;
+            #[cfg(feature = "tracing")]
+            tracing::debug!(candidates = found.len(), "searched storage");
+
+            let seen: HashSet<MemoryId> = results.iter().map(|x| x.data().id.clone()).collect();
+            deep_results = found
+                .into_iter()
+                .filter(|result| !seen.contains(&result.data().id))
+                .take(limit - results.len())
+                .collect();
+        }
+
+        if let Some(cache) = cache {
+            // Populate the hot cache with memories that had to be fetched from storage, so that repeated queries hit the cache
+            for result in &deep_results {
+                if !cache.store.contains(&result.data().id)
+                    && should_cache(&self.cfg, cache, result.data())
+                {
+                    cache
+                        .insert(result.embedding_owned(), result.data_owned())
+                        .await?;
+                }
+            }
+
+            results.extend(deep_results);
+            cache.cache_miss(embedding.clone(), &results);
+            cache.cache_query_results(embedding, limit, results.clone());
+        } else {
+            results.extend(deep_results);
+        }
+
+        Ok(results)
+    }
+
+    /// Embeds text using the configured embedder, emitting the embedding duration as a tracing event.
+    async fn embed(&self, text: &str, input: EmbedInput) -> Result<Vec<f32>, crate::Error> {
+        #[cfg(feature = "tracing")]
+        let start = std::time::Instant::now();
+
+        let deadline = self.cfg.embed_deadline();
+        let embedding = match input {
+            EmbedInput::Query => with_deadline(deadline, self.embedder.embed_query(text)).await?,
+            EmbedInput::Document => {
+                with_deadline(deadline, self.embedder.embed_document(text)).await?
+            }
+        };
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            duration_ms = start.elapsed().as_secs_f64() * 1000.0,
+            dims = embedding.len(),
+            "embedded text"
+        );
+
+        self.ensure_normalized(&embedding)?;
+
+        Ok(embedding)
+    }
+
+    /// Loads quota usage from storage (including any buffered writes), if it hasn't been loaded yet. From then on, usage is kept up to date as memories are stored and removed.
+    async fn load_quota_usage(&mut self) -> Result<(), crate::Error> {
+        const BATCH_SIZE: usize = 100;
+
+        if self.quota_tracker.is_some() {
+            return Ok(());
+        }
+
+        let mut tracker = QuotaTracker::default();
+        for (_, entry) in &self.storage.pending {
+            tracker.track(entry);
+        }
+
+        let mut offset = 0;
+        loop {
+            let batch = with_deadline(
+                self.cfg.storage_deadline(),
+                self.storage.get_oldest_page(offset, BATCH_SIZE),
+            )
+            .await?;
+            if batch.is_empty() {
+                break;
+            }
+
+            offset += batch.len();
+            for result in batch {
+                tracker.track(result.data());
+            }
+        }
+
+        self.quota_tracker = Some(tracker);
+
+        Ok(())
+    }
+
+    /// Makes room for a memory within the quota of its namespace (if it has one), evicting the oldest memories in the namespace or returning an error depending on the quota.
+    async fn enforce_quota(&mut self, entry: &MemoryEntry) -> Result<(), crate::Error> {
+        let namespace = namespace_of(entry);
+        let Some(quota) = self.cfg.quota_for(namespace).cloned() else {
+            return Ok(());
+        };
+        self.load_quota_usage().await?;
+
+        loop {
+            // SAFETY: Quota usage was loaded above
+            let tracker = self.quota_tracker.as_ref().unwrap();
+            let Some(violation) = quota.violation(&tracker.usage_with(entry)) else {
+                return Ok(());
+            };
+
+            let oldest = match quota.on_exceeded {
+                QuotaPolicy::EvictOldest => tracker.oldest_in(namespace, &entry.id),
+                QuotaPolicy::Reject => None,
+            };
+            let Some(oldest) = oldest else {
+                return Err(StorageError::quota_exceeded(namespace, &violation))?;
+            };
+
+            #[cfg(feature = "tracing")]
+            tracing::debug!(namespace, memory_id = %oldest, "evicted memory to stay within quota");
+
+            self.remove(&oldest).await?;
+        }
+    }
+
+    /// Returns an error if storing every given memory would exceed the quota of any namespace. Memories are never evicted to make room.
+    async fn check_quotas<'a, I>(&mut self, entries: I) -> Result<(), crate::Error>
+    where
+        I: IntoIterator<Item = &'a MemoryEntry>,
+    {
+        let entries: Vec<&MemoryEntry> = entries.into_iter().collect();
+        if !entries
+            .iter()
+            .any(|entry| self.cfg.quota_for(namespace_of(entry)).is_some())
+        {
+            return Ok(());
+        }
+        self.load_quota_usage().await?;
+
+        // SAFETY: Quota usage was loaded above
+        let mut tracker = self.quota_tracker.clone().unwrap();
+        for entry in &entries {
+            tracker.track(entry);
+        }
+
+        for entry in entries {
+            let namespace = namespace_of(entry);
+            if let Some(quota) = self.cfg.quota_for(namespace)
+                && let Some(violation) = quota.violation(&tracker.usage(namespace))
+            {
+                return Err(StorageError::quota_exceeded(namespace, &violation))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validates a memory before it gets stored, applying the configured [`ImportancePolicy`] to its importance.
+    fn validate_entry(&self, entry: &mut MemoryEntry) -> Result<(), crate::Error> {
+        entry.id.validate()?;
+        entry.importance = self.cfg.importance_policy.apply(entry.importance)?;
+
+        Ok(())
+    }
+
+    /// Returns an error if embeddings are required to be normalized and the given embedding isn't.
+    fn ensure_normalized(&self, embedding: &[f32]) -> Result<(), crate::Error> {
+        if (self.cfg.require_normalized_embeddings || self.storage.requires_normalized_embeddings())
+            && !is_normalized(embedding)
+        {
+            return Err(crate::Error::custom(
+                "Embedding is not normalized. Wrap the embedder in a `NormalizedEmbedder` to normalize embeddings",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the content of a stored memory, re-embedding it.
+    async fn replace_content(&mut self, id: &str, content: &str) -> Result<(), crate::Error> {
+        self.flush().await?;
+        let mut entry = with_deadline(
+            self.cfg.storage_deadline(),
+            self.storage.search_by_id(id.into()),
+        )
+        .await?
+        .data_owned();
+        entry.content = content.to_string();
+
+        let embedding = self.embed(content, EmbedInput::Document).await?;
+        entry.embedding_model = embedding_model_tag(&self.embedder, &embedding);
+
+        self.remove(id).await?;
+        self.insert(embedding, entry).await
+    }
+
+    /// Deletes a memory from storage, as well as the hot cache if it holds the memory.
+    async fn remove(&mut self, id: &str) -> Result<(), crate::Error> {
+        // The memory is removed from the hot cache before storage, so a cancelled delete can't leave it cached
+        if let Some(cache) = &mut self.hot_cache {
+            cache.remove(id).await?;
+            cache.invalidate_query_cache();
+        }
+        self.entities.unlink(id);
+        if let Some(tracker) = &mut self.quota_tracker {
+            tracker.untrack(id);
+        }
+
+        if !self.storage.remove_pending(id) {
+            with_deadline(self.cfg.storage_deadline(), self.storage.delete(id.into())).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Reports the number of stored memories to the telemetry sink.
+    /// Failing to count them is only logged, as the write that's being reported has already been made.
+    async fn report_store_size(&self) {
+        match with_deadline(self.cfg.storage_deadline(), self.storage.count()).await {
+            Ok(size) => metrics::record_store_size(size),
+            Err(_err) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(error = %_err, "failed to count stored memories for telemetry");
+            }
+        }
+    }
+
+    /// Inserts an embedded memory into storage, as well as the hot cache if the memory should be cached.
+    /// With a [`WritePolicy::WriteBack`] hot cache, the memory is always inserted into the hot cache while the write to storage is buffered.
+    async fn insert(
+        &mut self,
+        embedding: Vec<f32>,
+        entry: MemoryEntry,
+    ) -> Result<(), crate::Error> {
+        self.enforce_quota(&entry).await?;
+
+        if let Some(cache) = &mut self.hot_cache
+            && let WritePolicy::WriteBack { batch_size } = cache.write_policy()
+        {
+            cache.invalidate_query_cache();
+            cache.insert(embedding.clone(), entry.clone()).await?;
+            self.entities.link(&entry);
+            if let Some(tracker) = &mut self.quota_tracker {
+                tracker.track(&entry);
+            }
+            self.storage.pending.push((embedding, entry));
+
+            if self.storage.pending.len() >= batch_size {
+                self.flush().await?;
+            }
+
+            return Ok(());
+        }
+
+        // Cached query results are invalidated up front, so a cancelled insert can't leave them stale
+        if let Some(cache) = &mut self.hot_cache {
+            cache.invalidate_query_cache();
+        }
+
+        with_deadline(
+            self.cfg.storage_deadline(),
+            self.storage.insert(embedding.clone(), entry.clone()),
+        )
+        .await?;
+        self.entities.link(&entry);
+        if let Some(tracker) = &mut self.quota_tracker {
+            tracker.track(&entry);
+        }
+
+        if let Some(cache) = &mut self.hot_cache
+            && should_cache(&self.cfg, cache, &entry)
+        {
+            cache.insert(embedding, entry).await?;
+        }
+
+        self.report_store_size().await;
+
+        Ok(())
+    }
+}
+
+/// Storage along with any writes buffered by a [`WritePolicy::WriteBack`] hot cache.
+/// Buffered writes are flushed (on a best-effort basis) when dropped.
+struct BufferedStorage<S>
+where
+    S: Storage,
+{
+    inner: S,
+    pending: Vec<(Vec<f32>, MemoryEntry)>,
+    /// The deadline used when flushing on drop.
+    #[cfg_attr(target_arch = "wasm32", expect(dead_code))]
+    deadline: Option<Duration>,
+}
+
+impl<S> BufferedStorage<S>
+where
+    S: Storage,
+{
+    fn new(inner: S, deadline: Option<Duration>) -> Self {
+        Self {
+            inner,
+            pending: Vec::new(),
+            deadline,
+        }
+    }
+
+    /// Writes buffered memories to storage, bounding each write with a deadline (if any).
+    /// Each memory stays buffered until its write succeeds, so a failed or cancelled flush never loses writes.
+    async fn flush(&mut self, deadline: Option<Duration>) -> Result<usize, crate::Error> {
+        let mut written = 0;
+        while let Some((embedding, entry)) = self.pending.first().cloned() {
+            with_deadline(deadline, self.inner.insert(embedding, entry)).await?;
+            self.pending.remove(0);
+            written += 1;
+        }
+
+        Ok(written)
+    }
+
+    /// Drops a buffered write, returning whether or not there was one for the given ID.
+    fn remove_pending(&mut self, id: &str) -> bool {
+        let len = self.pending.len();
+        self.pending.retain(|(_, entry)| entry.id != id);
+
+        self.pending.len() != len
+    }
+}
+
+impl<S> Deref for BufferedStorage<S>
+where
+    S: Storage,
+{
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S> DerefMut for BufferedStorage<S>
+where
+    S: Storage,
+{
+    fn deref_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+}
+
+impl<S> Drop for BufferedStorage<S>
+where
+    S: Storage,
+{
+    /// Pending writes are flushed by blocking the current thread until the storage has written them, logging any that fail.
+    /// Storages that need an async runtime to make progress (eg, network-backed storages on a single-threaded runtime) can deadlock here,
+    /// so prefer flushing explicitly with [`MemoryManager::close`] (or [`MemoryManager::flush`]) before dropping the manager.
+    fn drop(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if !self.pending.is_empty() {
+            let deadline = self.deadline;
+            let res = futures::executor::block_on(self.flush(deadline));
+            #[cfg(feature = "tracing")]
+            if let Err(err) = &res {
+                tracing::error!(
+                    error = %err,
+                    pending = self.pending.len(),
+                    "failed to flush buffered writes when dropping the manager"
+                );
+            }
+            #[cfg(not(feature = "tracing"))]
+            let _ = res;
+        }
+
+        // WASM can't block, so pending writes are lost there
+        #[cfg(all(target_arch = "wasm32", feature = "tracing"))]
+        if !self.pending.is_empty() {
+            tracing::error!(
+                pending = self.pending.len(),
+                "dropped buffered writes, call `MemoryManager::close` before dropping the manager"
+            );
+        }
+    }
+}
+
+/// A retrieval prepared against a consistent, read-only snapshot of the storage, created with [`MemoryManager::snapshot_retrieval`].
+pub struct SnapshotRetrieval<S> {
+    snapshot: S,
+    embedding: Vec<f32>,
+    limit: usize,
+    deadline: Option<Duration>,
+}
+
+impl<S> SnapshotRetrieval<S>
+where
+    S: Storage,
+{
+    /// Searches the snapshot. Writes made after the retrieval was prepared aren't visible, however long the search takes.
+    pub async fn run(&self) -> Result<Vec<SearchResult>, crate::Error> {
+        let timer = metrics::Timer::start();
+
+        let results = with_deadline(
+            self.deadline,
+            self.snapshot.search(self.embedding.clone(), self.limit),
+        )
+        .await?;
+
+        metrics::record_retrieve(timer);
+
+        Ok(results)
+    }
+
+    /// The snapshot being searched.
+    pub fn snapshot(&self) -> &S {
+        &self.snapshot
+    }
+}
+
+/// Whether text is being embedded as a search query or as a document to be stored.
+enum EmbedInput {
+    Query,
+    Document,
+}
+
+/// Tracks the progress of re-embedding memories, allowing an interrupted migration to be resumed.
+#[derive(Clone, Debug)]
+pub struct ReembedProgress {
+    batch_size: usize,
+    processed: usize,
+    total: usize,
+    /// The creation time and ID of the last re-embedded memory, which the next batch starts after.
+    cursor: Option<(i64, MemoryId)>,
+}
+
+impl Default for ReembedProgress {
+    fn default() -> Self {
+        Self::new(100)
+    }
+}
+
+impl ReembedProgress {
+    /// Creates a new progress tracker that fetches memories in batches of a given size.
+    pub fn new(batch_size: usize) -> Self {
+        Self {
+            batch_size: batch_size.max(1),
+            processed: 0,
+            total: 0,
+            cursor: None,
+        }
+    }
+
+    /// The number of memories that have been re-embedded so far.
+    pub fn processed(&self) -> usize {
+        self.processed
+    }
+
+    /// The total number of memories to re-embed (as of when the migration started).
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Whether or not every memory has been re-embedded.
+    pub fn is_complete(&self) -> bool {
+        self.processed >= self.total
+    }
+
+    /// Counts the memories to re-embed, unless resuming a migration that's already been counted.
+    async fn start<S>(&mut self, storage: &S) -> Result<(), crate::Error>
+    where
+        S: Storage,
+    {
+        if self.cursor.is_none() {
+            self.total = storage.count().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Fetches the next batch of memories to re-embed, oldest first.
+    async fn next_batch<S>(&self, storage: &S) -> Result<Vec<SearchResult>, crate::Error>
+    where
+        S: Storage,
+    {
+        match &self.cursor {
+            Some((created_at, id)) => {
+                storage
+                    .get_oldest_after(*created_at, id.clone(), self.batch_size)
+                    .await
+            }
+            None => storage.get_oldest_page(0, self.batch_size).await,
+        }
+    }
+
+    /// Records a memory as re-embedded.
+    fn advance(&mut self, entry: &MemoryEntry) {
+        self.processed += 1;
+        self.cursor = Some((entry.created_at, entry.id.clone()));
+    }
+}
+
+/// A failed [`MemoryManager::reembed_all`] migration, which can be resumed using the returned manager and embedder.
+pub struct ReembedFailure<E, E2, S>
+where
+    E: Embedder,
+    S: Storage,
+{
+    /// The memory manager, still using the old embedder.
+    pub manager: MemoryManager<E, S>,
+    /// The new embedder.
+    pub embedder: E2,
+    /// The error that interrupted the migration.
+    pub error: crate::Error,
+}
+
+impl<E, E2, S> std::fmt::Debug for ReembedFailure<E, E2, S>
+where
+    E: Embedder,
+    S: Storage,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReembedFailure")
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A builder for `MemoryManager`.
+#[derive(Default)]
+pub struct MemoryManagerBuilder<E, S> {
+    storage: Option<S>,
+    embedder: Option<E>,
+    cfg: Option<MemoryConfig>,
+    hot_cache: Option<MemoryCache>,
+}
+
+impl MemoryManagerBuilder<EmbedderNotSet, StorageNotSet> {
+    pub fn new() -> Self {
+        MemoryManagerBuilder {
+            storage: None,
+            embedder: None,
+            cfg: None,
+            hot_cache: None,
+        }
+    }
+}
+
+impl<E, S> MemoryManagerBuilder<E, S>
+where
+    E: Embedder,
+    S: Storage,
+{
+    pub fn storage<S2>(self, storage: S2) -> MemoryManagerBuilder<E, S2>
+    where
+        S2: Storage,
+    {
+        MemoryManagerBuilder {
+            storage: Some(storage),
+            embedder: self.embedder,
+            cfg: self.cfg,
+            hot_cache: self.hot_cache,
+        }
+    }
+
+    pub fn embedder<E2>(self, embedder: E2) -> MemoryManagerBuilder<E2, S>
+    where
+        E2: Embedder,
+    {
+        MemoryManagerBuilder {
+            storage: self.storage,
+            embedder: Some(embedder),
+            cfg: self.cfg,
+            hot_cache: self.hot_cache,
+        }
+    }
+
+    pub fn config(mut self, cfg: MemoryConfig) -> Self {
+        self.cfg = Some(cfg);
+
+        self
+    }
+
+    pub fn hot_cache(mut self, cache: InMemoryDB) -> Self {
+        self.hot_cache = Some(MemoryCache::new(cache));
+        self
+    }
+
+    /// Use a pre-configured [`MemoryCache`] (eg, one created using [`MemoryCache::builder`]) as the hot cache.
+    pub fn memory_cache(mut self, cache: MemoryCache) -> Self {
+        self.hot_cache = Some(cache);
+        self
+    }
+
+    pub fn build(self) -> Result<MemoryManager<E, S>, crate::Error> {
+        let Some(storage) = self.storage else {
+            return Err(BuildError::StorageNotFound)?;
+        };
+
+        let Some(embedder) = self.embedder else {
+            return Err(BuildError::EmbedderNotFound)?;
+        };
+
+        if let Some(embed_dims) = embedder.dims() {
+            if let Some(storage_dims) = storage.dims()
+                && storage_dims != embed_dims
+            {
+                return Err(BuildError::mismatched_dimensions(
+                    "storage",
+                    storage_dims,
+                    embed_dims,
+                ))?;
+            }
+
+            if let Some(cache) = &self.hot_cache
+                && cache.store.dims() != embed_dims
+            {
+                return Err(BuildError::mismatched_dimensions(
+                    "hot cache",
+                    cache.store.dims(),
+                    embed_dims,
+                ))?;
+            }
+        }
+
+        if let Some(cache) = &self.hot_cache
+            && let Some(storage_dims) = storage.dims()
+            && cache.store.dims() != storage_dims
+        {
+            return Err(BuildError::mismatched_cache_dimensions(
+                cache.store.dims(),
+                storage_dims,
+            ))?;
+        }
+
+        let cfg = self.cfg.unwrap_or_default();
+        cfg.validate()?;
+
+        let mgr = MemoryManager {
+            storage: BufferedStorage::new(storage, cfg.storage_deadline()),
+            embedder,
+            cfg,
+            hot_cache: self.hot_cache,
+            entities: EntityIndex::new(),
+            quota_tracker: None,
+        };
+
+        Ok(mgr)
+    }
+
+    /// Builds the memory manager like [`MemoryManagerBuilder::build`], then checks that the whole stack works before returning it, so misconfigurations surface at startup rather than on the first request.
+    /// This checks that the storage passes a health check (see [`Storage::health_check`]), and that the embedder can embed a probe input with the dimensions expected by the storage and the hot cache
+    /// (which catches mismatches for embedders that don't know their dimensions ahead of time).
+    pub async fn build_validated(self) -> Result<MemoryManager<E, S>, crate::Error> {
+        let mgr = self.build()?;
+
+        let health = with_deadline(mgr.cfg.storage_deadline(), async {
+            Ok(mgr.storage.health_check().await)
+        })
+        .await?;
+        if !health.reachable {
+            return Err(BuildError::storage_unreachable(
+                health.error.as_deref().unwrap_or("health check failed"),
+            ))?;
+        }
+
+        let probe = with_deadline(
+            mgr.cfg.embed_deadline(),
+            mgr.embedder.embed_text("braindump"),
+        )
+        .await
+        .map_err(|err| BuildError::embedder_unreachable(&err.to_string()))?;
+        let embed_dims = probe.len();
+
+        if let Some(storage_dims) = mgr.storage.dims()
+            && storage_dims != embed_dims
+        {
+            return Err(BuildError::mismatched_dimensions(
+                "storage",
+                storage_dims,
+                embed_dims,
+            ))?;
+        }
+
+        if let Some(cache) = &mgr.hot_cache
+            && cache.store.dims() != embed_dims
+        {
+            return Err(BuildError::mismatched_dimensions(
+                "hot cache",
+                cache.store.dims(),
+                embed_dims,
+            ))?;
+        }
+
+        Ok(mgr)
+    }
+}
+
+/// Memory manager configuration
+pub struct MemoryConfig {
+    /// The maximum number of total memories (don't store any more after max has been reached)
+    pub max_total_memories: Option<usize>,
+    /// Delete memories after N days
+    pub max_age_days: Option<i64>,
+    /// The minimum score required to keep a given memory
+    pub min_retention_score: Option<f32>,
+    /// How many to evict during eviction
+    pub eviction_batch_size: usize,
+    pub custom_caching_strategy: Option<Box<CachingStrategyFn>>,
+    /// How retrieved memories should be ranked.
+    pub retrieval_mode: RetrievalMode,
+    /// What to do when retrieved memories were embedded using a different embedding model to the current embedder.
+    pub embedding_mismatch_policy: EmbeddingMismatchPolicy,
+    /// Reject embeddings that aren't L2-normalized (see [`crate::embed::NormalizedEmbedder`]).
+    /// This is always enforced if the storage requires normalized embeddings.
+    pub require_normalized_embeddings: bool,
+    /// A deadline for each embedding call made while storing or retrieving memories, failing the operation with [`crate::Error::Timeout`] if it's exceeded.
+    #[cfg(feature = "timeout")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "timeout")))]
+    pub embed_timeout: Option<Duration>,
+    /// A deadline for each storage call made while storing, retrieving, updating or deleting memories, failing the operation with [`crate::Error::Timeout`] if it's exceeded.
+    /// Long-running migrations (eg, [`MemoryManager::reembed_in_place`]) aren't bounded.
+    #[cfg(feature = "timeout")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "timeout")))]
+    pub storage_timeout: Option<Duration>,
+    /// What to do with memories that have an importance outside of 0.0 to 1.0 when they're stored or updated. Defaults to clamping the importance.
+    pub importance_policy: ImportancePolicy,
+    /// Quotas for each namespace (ie, agent ID, see [`crate::memory::quota`]), enforced whenever a memory is stored or updated.
+    pub quotas: HashMap<String, Quota>,
+    /// The quota for namespaces without a quota of their own in [`MemoryConfig::quotas`]. Defaults to `None` (ie, no quota).
+    pub default_quota: Option<Quota>,
+    /// Retention settings for each kind of memory, enforced by [`MemoryManager::maintain`] (eg, working memories expire after a few hours, while semantic memories are kept indefinitely).
+    /// Kinds without a retention policy are kept indefinitely.
+    pub retention: HashMap<MemoryKind, RetentionPolicy>,
+}
+
+/// How long memories of a given kind are kept (see [`MemoryConfig::retention`]).
+/// Memories that have become permanent through repeated use (see [`strength`]) are exempt from the age and importance limits.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RetentionPolicy {
+    /// Memories created longer ago than this get deleted.
+    pub max_age: Option<Duration>,
+    /// Only the most recently created memories up to this count are kept.
+    pub max_count: Option<usize>,
+    /// Memories with an importance below this get deleted.
+    pub min_importance: Option<f32>,
+}
+
+impl RetentionPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether or not a memory should be deleted because of its age or importance, given the current time (as a Unix timestamp).
+    pub fn is_expired(&self, entry: &MemoryEntry, now: i64) -> bool {
+        if strength::is_permanent(entry.strength) {
+            return false;
+        }
+
+        self.max_age
+            .is_some_and(|max_age| now - entry.created_at > max_age.as_secs() as i64)
+            || self
+                .min_importance
+                .is_some_and(|min_importance| entry.importance < min_importance)
+    }
+}
+
+/// The outcome of a maintenance pass (see [`MemoryManager::maintain`]).
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct MaintenanceReport {
+    /// The number of memories of each kind deleted by retention policies.
+    pub deleted: HashMap<MemoryKind, usize>,
+    /// The number of expired memories evicted from the hot cache.
+    pub cache_evicted: usize,
+}
+
+/// What to do when retrieved memories were embedded using a different embedding model (or dimensions) to the current embedder.
+/// Memories without an embedding model tag are assumed to be compatible, and nothing is checked if the current embedder doesn't identify its model (see [`Embedder::model_id`]).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EmbeddingMismatchPolicy {
+    /// Return a [`StorageError::MismatchedEmbeddingModel`] error.
+    #[default]
+    Refuse,
+    /// Transparently re-embed mismatched memories using the current embedder, then search again.
+    Reembed,
+    /// Return the results as-is.
+    Ignore,
+}
+
+/// Runs a future, bounding it with a deadline if there is one.
+async fn with_deadline<F, T>(deadline: Option<Duration>, fut: F) -> Result<T, crate::Error>
+where
+    F: Future<Output = Result<T, crate::Error>>,
+{
+    #[cfg(feature = "timeout")]
+    if let Some(deadline) = deadline {
+        return crate::timeout::with_timeout(deadline, fut).await;
+    }
+    #[cfg(not(feature = "timeout"))]
+    let _ = deadline;
+
+    fut.await
+}
+
+/// The tag recorded on memories embedded by an embedder, or `None` if the embedder doesn't identify its model.
+fn embedding_model_tag<E>(embedder: &E, embedding: &[f32]) -> Option<EmbeddingModelTag>
+where
+    E: Embedder,
+{
+    embedder.model_id().map(|model| EmbeddingModelTag {
+        model,
+        dims: embedding.len(),
+    })
+}
+
+/// How retrieved memories should be ranked.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum RetrievalMode {
+    /// Rank purely by semantic similarity.
+    #[default]
+    Semantic,
+    /// Rank by semantic similarity plus a recency bonus (similar to LangChain's time-weighted retriever).
+    ///
+    /// The score of each memory is `similarity + (1 - decay_rate) ^ hours_since_last_access`.
+    /// Retrieved memories automatically have their `last_accessed` timestamp and access count updated.
+    TimeWeighted {
+        /// The rate at which the recency bonus decays per hour (between 0.0 and 1.0).
+        decay_rate: f32,
+        /// How many candidates to fetch by similarity before re-ranking.
+        candidates: usize,
+    },
+}
+
+/// Whether a retrieval uses the hot cache.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheMode {
+    /// Search the hot cache first, falling back to storage for the remaining results.
+    #[default]
+    Default,
+    /// Skip the hot cache (including cached query results) and always search storage, eg for consistency-sensitive code paths.
+    Bypass,
+    /// Only search the hot cache, never storage. Results are best-effort and may be incomplete, but are returned with very low latency.
+    /// Returns no results if there's no hot cache.
+    CacheOnly,
+}
+
+/// Per-call retrieval options (see [`MemoryManager::retrieve_with_options`]).
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct RetrieveOptions {
+    #[serde(default)]
+    pub cache_mode: CacheMode,
+}
+
+impl RetrieveOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Skip the hot cache and always search storage.
+    pub fn bypass_cache(mut self) -> Self {
+        self.cache_mode = CacheMode::Bypass;
+        self
+    }
+
+    /// Only search the hot cache.
+    pub fn cache_only(mut self) -> Self {
+        self.cache_mode = CacheMode::CacheOnly;
+        self
+    }
+}
+
+/// A retrieved memory along with its score (see [`MemoryManager::retrieve_scored`]).
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ScoredMemory {
+    pub memory: MemoryEntry,
+    /// How relevant the memory is to the query. This is the cosine similarity to the query, or the time-weighted score when using [`RetrievalMode::TimeWeighted`].
+    pub score: f32,
+}
+
+impl RetrievalMode {
+    /// Creates a time-weighted retrieval mode with a given decay rate, fetching 100 candidates before re-ranking.
+    pub fn time_weighted(decay_rate: f32) -> Self {
+        Self::TimeWeighted {
+            decay_rate,
+            candidates: 100,
+        }
+    }
+}
+
+/// Whether a memory should be hot cached, using the custom caching strategy if there is one and otherwise the partition of the hot cache holding its kind.
+fn should_cache(cfg: &MemoryConfig, cache: &MemoryCache, entry: &MemoryEntry) -> bool {
+    match &cfg.custom_caching_strategy {
+        Some(strategy) => strategy(cfg, entry),
+        None => cache.admits(entry),
+    }
+}
+
+/// The score used to pick memories to warm the hot cache with: importance plus a recency bonus that halves every week since the memory was last accessed.
+fn warm_score(entry: &MemoryEntry, now: i64) -> f64 {
+    let weeks_passed = (now - entry.last_accessed).max(0) as f64 / 604_800.0;
+
+    entry.importance as f64 + 0.5f64.powf(weeks_passed)
+}
+
+/// Scores a search result by its semantic similarity plus an exponentially decaying recency bonus.
+fn time_weighted_score(query: &[f32], result: &SearchResult, decay_rate: f32, now: i64) -> f32 {
+    let similarity = cosine_similarity(query, result.embedding());
+    let hours_passed = (now - result.data().last_accessed).max(0) as f32 / 3600.0;
+
+    similarity + (1.0 - decay_rate).powf(hours_passed)
+}
+
+pub type CachingStrategyFn = dyn Fn(&MemoryConfig, &MemoryEntry) -> bool + Send + Sync;
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryConfig {
+    pub fn new() -> Self {
+        Self {
+            max_total_memories: None,
+            max_age_days: None,
+            min_retention_score: None,
+            eviction_batch_size: 1,
+            custom_caching_strategy: None,
+            retrieval_mode: RetrievalMode::default(),
+            embedding_mismatch_policy: EmbeddingMismatchPolicy::default(),
+            require_normalized_embeddings: false,
+            #[cfg(feature = "timeout")]
+            embed_timeout: None,
+            #[cfg(feature = "timeout")]
+            storage_timeout: None,
+            importance_policy: ImportancePolicy::default(),
+            quotas: HashMap::new(),
+            default_quota: None,
+            retention: HashMap::new(),
+        }
+    }
+
+    /// Checks that the configuration makes sense (eg, a non-zero eviction batch size and importance thresholds between 0.0 and 1.0).
+    /// This is called when building a [`MemoryManager`].
+    pub fn validate(&self) -> Result<(), BuildError> {
+        if self.eviction_batch_size == 0 {
+            return Err(BuildError::invalid_config(
+                "the eviction batch size must be at least 1",
+            ));
+        }
+
+        if self.max_total_memories == Some(0) {
+            return Err(BuildError::invalid_config(
+                "the maximum number of memories must be at least 1",
+            ));
+        }
+
+        #[cfg(feature = "timeout")]
+        if self.embed_timeout.is_some_and(|timeout| timeout.is_zero())
+            || self
+                .storage_timeout
+                .is_some_and(|timeout| timeout.is_zero())
+        {
+            return Err(BuildError::invalid_config("timeouts must be non-zero"));
+        }
+
+        if self
+            .quotas
+            .values()
+            .chain(&self.default_quota)
+            .any(|quota| quota.max_memories == Some(0))
+        {
+            return Err(BuildError::invalid_config(
+                "quotas must allow at least 1 memory",
+            ));
+        }
+
+        let thresholds = self.min_retention_score.into_iter().chain(
+            self.retention
+                .values()
+                .filter_map(|policy| policy.min_importance),
+        );
+        for threshold in thresholds {
+            if !(0.0..=1.0).contains(&threshold) {
+                return Err(BuildError::invalid_config(&format!(
+                    "minimum scores must be between 0.0 and 1.0, got {threshold}"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sets the quota for a namespace (ie, an agent ID).
+    pub fn quota<Namespace>(mut self, namespace: Namespace, quota: Quota) -> Self
+    where
+        Namespace: Into<String>,
+    {
+        self.quotas.insert(namespace.into(), quota);
+        self
+    }
+
+    /// The quota for a namespace, if it has one.
+    pub fn quota_for(&self, namespace: &str) -> Option<&Quota> {
+        self.quotas.get(namespace).or(self.default_quota.as_ref())
+    }
+
+    /// Sets the retention policy for a kind of memory.
+    pub fn retention(mut self, kind: MemoryKind, policy: RetentionPolicy) -> Self {
+        self.retention.insert(kind, policy);
+        self
+    }
+
+    /// The deadline for embedding calls, which is always `None` without the `timeout` feature.
+    fn embed_deadline(&self) -> Option<Duration> {
+        #[cfg(feature = "timeout")]
+        return self.embed_timeout;
+        #[cfg(not(feature = "timeout"))]
+        None
+    }
+
+    /// The deadline for storage calls, which is always `None` without the `timeout` feature.
+    fn storage_deadline(&self) -> Option<Duration> {
+        #[cfg(feature = "timeout")]
+        return self.storage_timeout;
+        #[cfg(not(feature = "timeout"))]
+        None
+    }
+
+    /// Whether a memory should be hot cached, using the custom caching strategy if there is one and otherwise the default partition for its kind (see [`CachePartition::default_for`]).
+    /// [`MemoryManager`] uses the partitions configured on its hot cache in place of the defaults.
+    pub fn should_cache(&self, entry: &MemoryEntry) -> bool {
+        if let Some(strategy) = self.custom_caching_strategy.as_ref() {
+            return strategy(self, entry);
+        };
+
+        CachePartition::default_for(entry.kind)
+            .admission
+            .admits(entry)
+    }
+
+    /// Whether a memory should be kept in the hot cache. Memories that have become permanent through repeated use (see [`strength`]) are always kept.
+    pub fn should_retain_in_cache(&self, entry: &MemoryEntry) -> bool {
+        match entry.kind {
+            MemoryKind::Semantic | MemoryKind::Procedural => true,
+            MemoryKind::Episodic => {
+                (entry.importance > 0.6 && entry.access_count >= 2)
+                    || strength::is_permanent(entry.strength)
+            }
+            MemoryKind::Working => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{
+        EmbeddingMismatchPolicy, MemoryConfig, MemoryManager, ReembedProgress, RetentionPolicy,
+        RetrievalMode, RetrieveOptions,
+    };
+    use crate::{
+        embed::{Embedder, ImageSource, SparseEmbedder, SparseVector},
+        error::{BuildError, StorageError},
+        memory::{
+            MemoryEntry, MemoryId, MemoryKind,
+            cache::{MemoryCache, WritePolicy},
+            digest::{DIGEST_TAG, DigestPolicy},
+            entity::{Entity, EntityKind},
+            importance::ImportancePolicy,
+            query::QueryTransformer,
+            quota::{Quota, QuotaPolicy},
+            reconcile::{MemoryOperation, MemoryReconciler},
+            strength::INITIAL_STRENGTH,
+            summarize::MemorySummarizer,
+        },
+        storage::{HybridFusion, MetadataFilter, SearchResult, Storage},
+        test_util::{self, LetterEmbedder},
+        vector_store::{InMemoryDB, QuantizedInMemoryDB},
+    };
+
+    fn entry(id: &str, content: &str, last_accessed: i64) -> MemoryEntry {
+        MemoryEntry {
+            created_at: last_accessed,
+            last_accessed,
+            ..test_util::entry(id, content)
+        }
+    }
+
+    #[tokio::test]
+    async fn hot_cache_follows_updates_and_deletes() {
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .hot_cache(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+
+        let now = chrono::Utc::now().timestamp();
+        manager
+            .store("aaaa", entry("a", "aaaa", now))
+            .await
+            .unwrap();
+        assert!(manager.hot_cache.as_ref().unwrap().store.contains("a"));
+
+        let mut updated = entry("a", "aaaa", now);
+        updated.importance = 0.9;
+        manager.update(updated).await.unwrap();
+        let cached = manager
+            .hot_cache
+            .as_ref()
+            .unwrap()
+            .store
+            .search_by_id("a".into())
+            .await
+            .unwrap();
+        assert_eq!(cached.data().importance, 0.9);
+        assert_eq!(
+            manager.retrieve("aaaa", 1).await.unwrap()[0]
+                .data()
+                .importance,
+            0.9
+        );
+
+        manager.delete("a").await.unwrap();
+        assert!(manager.retrieve("aaaa", 1).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn cache_mode_can_be_chosen_per_call() {
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .hot_cache(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+
+        let now = chrono::Utc::now().timestamp();
+        manager
+            .store("aaaa", entry("a", "aaaa", now))
+            .await
+            .unwrap();
+        let mut episode = entry("e", "eeee", now);
+        episode.kind = MemoryKind::Episodic;
+        manager.store("eeee", episode).await.unwrap();
+
+        let cached = manager
+            .retrieve_with_options("aaaa", 2, RetrieveOptions::new().cache_only())
+            .await
+            .unwrap();
+        assert_eq!(cached.len(), 1);
+        assert_eq!(cached[0].data().id, "a");
+
+        let fresh = manager
+            .retrieve_with_options("aaaa", 2, RetrieveOptions::new().bypass_cache())
+            .await
+            .unwrap();
+        assert_eq!(fresh.len(), 2);
+        assert_eq!(manager.cache_stats().unwrap().hits(), 1);
+    }
+
+    #[tokio::test]
+    async fn memories_in_the_hot_cache_and_storage_are_retrieved_once() {
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .hot_cache(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+
+        let now = chrono::Utc::now().timestamp();
+        manager
+            .store("aaaa", entry("a", "aaaa", now))
+            .await
+            .unwrap();
+        manager
+            .store("oooo", entry("o", "oooo", now))
+            .await
+            .unwrap();
+        let cache = manager.hot_cache.as_mut().unwrap();
+        cache.remove("o").await.unwrap();
+        cache.invalidate_query_cache();
+
+        let results = manager.retrieve("aaaa", 3).await.unwrap();
+        let ids: Vec<&str> = results.iter().map(|x| x.data().id.as_str()).collect();
+        assert_eq!(ids, ["a", "o"]);
+    }
+
+    #[tokio::test]
+    async fn storage_results_populate_the_hot_cache() {
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .hot_cache(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+
+        let now = chrono::Utc::now().timestamp();
+        manager
+            .store("aaaa", entry("a", "aaaa", now))
+            .await
+            .unwrap();
+        let cache = manager.hot_cache.as_mut().unwrap();
+        cache.remove("a").await.unwrap();
+        cache.invalidate_query_cache();
+
+        let results = manager.retrieve("aaaa", 1).await.unwrap();
+        assert_eq!(results[0].data().id, "a");
+        assert!(manager.hot_cache.as_ref().unwrap().store.contains("a"));
+    }
+
+    /// Records the names of spans and the messages of events.
+    #[cfg(feature = "tracing")]
+    #[derive(Clone, Default)]
+    struct RecordingSubscriber(std::sync::Arc<std::sync::Mutex<Vec<String>>>);
+
+    #[cfg(feature = "tracing")]
+    impl tracing::Subscriber for RecordingSubscriber {
+        fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            let mut recorded = self.0.lock().unwrap();
+            recorded.push(span.metadata().name().to_string());
+            tracing::span::Id::from_u64(recorded.len() as u64)
+        }
+
+        fn record(&self, _span: &tracing::span::Id, _values: &tracing::span::Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &tracing::span::Id, _follows: &tracing::span::Id) {}
+
+        fn event(&self, event: &tracing::Event<'_>) {
+            struct Message(String);
+            impl tracing::field::Visit for Message {
+                fn record_debug(
+                    &mut self,
+                    field: &tracing::field::Field,
+                    value: &dyn std::fmt::Debug,
+                ) {
+                    if field.name() == "message" {
+                        self.0 = format!("{value:?}");
+                    }
+                }
+            }
+
+            let mut message = Message(String::new());
+            event.record(&mut message);
+            self.0.lock().unwrap().push(message.0);
+        }
+
+        fn enter(&self, _span: &tracing::span::Id) {}
+
+        fn exit(&self, _span: &tracing::span::Id) {}
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn retrieval_pipeline_is_traced() {
+        let subscriber = RecordingSubscriber::default();
+        let _guard = tracing::subscriber::set_default(subscriber.clone());
+
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .hot_cache(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+        manager.store("aaaa", entry("a", "aaaa", 0)).await.unwrap();
+        manager
+            .retrieve_with_options("aaaa", 1, RetrieveOptions::new().bypass_cache())
+            .await
+            .unwrap();
+
+        let recorded = subscriber.0.lock().unwrap();
+        for expected in [
+            "store",
+            "in_memory_db.insert",
+            "retrieve_with_options",
+            "embedded text",
+            "in_memory_db.search",
+            "searched storage",
+        ] {
+            assert!(
+                recorded.iter().any(|recorded| recorded == expected),
+                "{expected} wasn't traced: {recorded:?}"
+            );
+        }
+    }
+
+    /// Expands every query with a hypothetical memory made of the letter A.
+    struct HydeTransformer;
+
+    impl QueryTransformer for HydeTransformer {
+        async fn transform(&self, query: &str) -> Result<String, crate::Error> {
+            Ok(format!("{query} aaaa"))
+        }
+    }
+
+    #[tokio::test]
+    async fn transformed_queries_are_embedded_instead_of_the_raw_query() {
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+        manager.store("aaaa", entry("a", "aaaa", 0)).await.unwrap();
+        manager.store("eeee", entry("e", "eeee", 0)).await.unwrap();
+
+        let results = manager.retrieve("e", 1).await.unwrap();
+        assert_eq!(results[0].data().id, "e");
+
+        let results = manager
+            .retrieve_with_transformer("e", 1, &HydeTransformer)
+            .await
+            .unwrap();
+        assert_eq!(results[0].data().id, "a");
+    }
+
+    #[tokio::test]
+    async fn time_weighted_retrieval_prefers_recent_memories() {
+        let mut cfg = MemoryConfig::new();
+        cfg.retrieval_mode = RetrievalMode::time_weighted(0.5);
+
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .config(cfg)
+            .build()
+            .unwrap();
+
+        let now = chrono::Utc::now().timestamp();
+        let content = "User likes rabbits";
+        manager
+            .store(content, entry("old", content, now - 86_400))
+            .await
+            .unwrap();
+        manager
+            .store(content, entry("new", content, now))
+            .await
+            .unwrap();
+
+        let results = manager.retrieve(content, 1).await.unwrap();
+        assert_eq!(results[0].data().id, "new");
+        assert_eq!(results[0].data().access_count, 1);
+
+        let stored = manager.storage.search_by_id("new".into()).await.unwrap();
+        assert_eq!(stored.data().access_count, 1);
+    }
+
+    /// Joins the contents of memories together.
+    struct JoiningSummarizer;
+
+    impl MemorySummarizer for JoiningSummarizer {
+        async fn summarize(&self, memories: &[MemoryEntry]) -> Result<String, crate::Error> {
+            Ok(memories
+                .iter()
+                .map(|memory| memory.content.as_str())
+                .collect::<Vec<_>>()
+                .join("; "))
+        }
+    }
+
+    #[tokio::test]
+    async fn retrieved_memories_can_be_summarized() {
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+
+        let empty = manager
+            .retrieve_summarized("aaaa", 2, &JoiningSummarizer)
+            .await
+            .unwrap();
+        assert!(empty.summary.is_empty());
+        assert!(empty.results.is_empty());
+
+        manager.store("aaaa", entry("a", "aaaa", 0)).await.unwrap();
+        manager.store("eeee", entry("e", "eeee", 0)).await.unwrap();
+        manager.store("iiii", entry("i", "iiii", 0)).await.unwrap();
+
+        let summarized = manager
+            .retrieve_summarized("aaaa", 2, &JoiningSummarizer)
+            .await
+            .unwrap();
+        assert_eq!(summarized.results.len(), 2);
+        assert!(summarized.summary.starts_with("aaaa; "));
+    }
+
+    /// A [`LetterEmbedder`] with its dimensions in reverse order, which fails the first time it embeds a given input.
+    struct ReversedLetterEmbedder {
+        fail_on: std::sync::Mutex<Option<&'static str>>,
+    }
+
+    impl Embedder for ReversedLetterEmbedder {
+        async fn embed_text(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+            if self
+                .fail_on
+                .lock()
+                .unwrap()
+                .take_if(|fail_on| *fail_on == input)
+                .is_some()
+            {
+                return Err(crate::Error::custom("embedding failed"));
+            }
+
+            let mut embedding = LetterEmbedder.embed_text(input).await?;
+            embedding.reverse();
+
+            Ok(embedding)
+        }
+
+        fn dims(&self) -> Option<usize> {
+            Some(4)
+        }
+    }
+
+    #[tokio::test]
+    async fn interrupted_migrations_can_be_resumed() {
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+        manager.store("aaaa", entry("a", "aaaa", 1)).await.unwrap();
+        manager.store("eeee", entry("e", "eeee", 2)).await.unwrap();
+        manager.store("iiii", entry("i", "iiii", 3)).await.unwrap();
+
+        let embedder = ReversedLetterEmbedder {
+            fail_on: std::sync::Mutex::new(Some("eeee")),
+        };
+        let mut target = InMemoryDB::new(4);
+        let mut progress = ReembedProgress::new(1);
+        assert!(
+            manager
+                .reembed_into(&embedder, &mut target, &mut progress)
+                .await
+                .is_err()
+        );
+        assert_eq!(progress.processed(), 1);
+        assert!(!progress.is_complete());
+
+        manager
+            .reembed_into(&embedder, &mut target, &mut progress)
+            .await
+            .unwrap();
+        assert!(progress.is_complete());
+        assert_eq!((progress.processed(), progress.total()), (3, 3));
+        assert_eq!(target.count().await.unwrap(), 3);
+        let migrated = target.search_by_id("a".into()).await.unwrap();
+        assert_eq!(migrated.embedding(), [1.0, 1.0, 1.0, 5.0]);
+
+        // Failures in place leave the memory where it was
+        let embedder = ReversedLetterEmbedder {
+            fail_on: std::sync::Mutex::new(Some("iiii")),
+        };
+        let mut progress = ReembedProgress::new(2);
+        assert!(
+            manager
+                .reembed_in_place(&embedder, &mut progress)
+                .await
+                .is_err()
+        );
+        let unmigrated = manager.storage.search_by_id("i".into()).await.unwrap();
+        assert_eq!(unmigrated.embedding(), [1.0, 1.0, 5.0, 1.0]);
+
+        manager
+            .reembed_in_place(&embedder, &mut progress)
+            .await
+            .unwrap();
+        let results = manager.with_embedder(embedder).retrieve("iiii", 1).await;
+        assert_eq!(results.unwrap()[0].data().id, "i");
+    }
+
+    #[tokio::test]
+    async fn resumed_migrations_page_by_creation_time_and_id() {
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+        for (id, content) in [("c", "iiii"), ("a", "aaaa"), ("b", "eeee")] {
+            manager.store(content, entry(id, content, 5)).await.unwrap();
+        }
+
+        let embedder = ReversedLetterEmbedder {
+            fail_on: std::sync::Mutex::new(Some("eeee")),
+        };
+        let mut progress = ReembedProgress::new(1);
+        assert!(
+            manager
+                .reembed_in_place(&embedder, &mut progress)
+                .await
+                .is_err()
+        );
+        assert_eq!(progress.processed(), 1);
+
+        // Memories stored while the migration is paused don't change its total
+        manager.store("oooo", entry("d", "oooo", 6)).await.unwrap();
+        manager
+            .reembed_in_place(&embedder, &mut progress)
+            .await
+            .unwrap();
+        assert_eq!(progress.total(), 3);
+
+        for (id, expected) in [
+            ("a", [1.0, 1.0, 1.0, 5.0]),
+            ("b", [1.0, 1.0, 5.0, 1.0]),
+            ("c", [1.0, 5.0, 1.0, 1.0]),
+            ("d", [5.0, 1.0, 1.0, 1.0]),
+        ] {
+            let migrated = manager.storage.search_by_id(id.into()).await.unwrap();
+            assert_eq!(migrated.embedding(), expected, "{id}");
+        }
+    }
+
+    #[tokio::test]
+    async fn reembed_all_swaps_embedders_and_can_be_resumed() {
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+        manager.store("aaaa", entry("a", "aaaa", 1)).await.unwrap();
+        manager.store("eeee", entry("e", "eeee", 2)).await.unwrap();
+
+        let embedder = ReversedLetterEmbedder {
+            fail_on: std::sync::Mutex::new(Some("eeee")),
+        };
+        let mut progress = ReembedProgress::new(1);
+        let Err(failure) = manager.reembed_all(embedder, &mut progress).await else {
+            panic!("the migration should have been interrupted");
+        };
+        assert_eq!(progress.processed(), 1);
+
+        let mut manager = failure
+            .manager
+            .reembed_all(failure.embedder, &mut progress)
+            .await
+            .unwrap();
+        assert!(progress.is_complete());
+
+        let migrated = manager.storage.search_by_id("a".into()).await.unwrap();
+        assert_eq!(migrated.embedding(), [1.0, 1.0, 1.0, 5.0]);
+        let results = manager.retrieve("eeee", 1).await.unwrap();
+        assert_eq!(results[0].data().id, "e");
+    }
+
+    /// A [`LetterEmbedder`] that identifies itself as a given model.
+    struct NamedLetterEmbedder(&'static str);
+
+    impl Embedder for NamedLetterEmbedder {
+        async fn embed_text(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+            LetterEmbedder.embed_text(input).await
+        }
+
+        fn dims(&self) -> Option<usize> {
+            Some(4)
+        }
+
+        fn model_id(&self) -> Option<String> {
+            Some(self.0.to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn mismatched_embedding_models_are_refused_or_reembedded() {
+        // Embedders without a model identifier leave memories untagged
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+        manager
+            .store("eeee", entry("untagged", "eeee", 0))
+            .await
+            .unwrap();
+
+        let mut manager = manager.with_embedder(NamedLetterEmbedder("letters/v1"));
+        manager.store("aaaa", entry("a", "aaaa", 0)).await.unwrap();
+        assert_eq!(manager.retrieve("aaaa", 2).await.unwrap().len(), 2);
+
+        // Memories from another model are refused by default, but untagged memories never are
+        let mut manager = manager.with_embedder(NamedLetterEmbedder("letters/v2"));
+        let err = manager.retrieve("aaaa", 2).await.unwrap_err();
+        assert!(matches!(
+            err,
+            crate::Error::Storage(StorageError::MismatchedEmbeddingModel(..))
+        ));
+
+        manager.cfg.embedding_mismatch_policy = EmbeddingMismatchPolicy::Reembed;
+        let results = manager.retrieve("aaaa", 2).await.unwrap();
+        assert_eq!(results[0].data().id, "a");
+
+        let stored = manager.storage.search_by_id("a".into()).await.unwrap();
+        let tag = stored.data().embedding_model.as_ref().unwrap();
+        assert_eq!(tag.model, "letters/v2");
+        let untagged = manager
+            .storage
+            .search_by_id("untagged".into())
+            .await
+            .unwrap();
+        assert!(untagged.data().embedding_model.is_none());
+    }
+
+    #[tokio::test]
+    async fn memories_can_be_retrieved_by_entity() {
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+
+        let now = chrono::Utc::now().timestamp();
+        let mentions = [
+            ("contract", "Acme Corp renewed their contract", "Acme Corp"),
+            ("office", "Acme's office is in Leeds", "Acme"),
+            ("ceo", "Jane is the CEO of acme corp.", "acme corp."),
+        ];
+        for (id, content, name) in mentions {
+            let mut memory = entry(id, content, now);
+            memory
+                .metadata
+                .push(Entity::new(name, EntityKind::Organization).to_metadata());
+            manager.store(content, memory).await.unwrap();
+        }
+
+        assert_eq!(manager.retrieve_about("ACME CORP").await.unwrap().len(), 2);
+
+        manager.entities_mut().add_alias("Acme Corp", "Acme");
+        let results = manager.retrieve_about("Acme").await.unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(manager.entity("acme").unwrap().name, "Acme Corp");
+
+        manager.rebuild_entity_index(2).await.unwrap();
+        assert_eq!(manager.retrieve_about("Acme Corp").await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn health_reports_the_storage_status() {
+        let manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+
+        let health = manager.health().await;
+        assert!(health.reachable);
+        assert_eq!(health.error, None);
+    }
+
+    #[tokio::test]
+    async fn cache_stats_count_hits_misses_and_evictions() {
+        let cache = MemoryCache::builder()
+            .store(InMemoryDB::new(4))
+            .max_memory_limit(2)
+            .build()
+            .unwrap();
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .memory_cache(cache)
+            .build()
+            .unwrap();
+
+        assert!(manager.retrieve("aaaa", 1).await.unwrap().is_empty());
+        let stats = manager.cache_stats().unwrap();
+        assert_eq!((stats.hits(), stats.misses()), (0, 1));
+        assert_eq!(stats.hit_ratio(), 0.0);
+
+        let now = chrono::Utc::now().timestamp();
+        for (id, content) in [("a", "aaaa"), ("e", "eeee"), ("i", "iiii")] {
+            manager
+                .store(content, entry(id, content, now))
+                .await
+                .unwrap();
+        }
+        let stats = manager.cache_stats().unwrap();
+        assert_eq!(stats.inserts(), 3);
+        assert_eq!(stats.evictions(), 1);
+        assert_eq!(stats.size(), 2);
+        assert_eq!(stats.eviction_latency().count(), 1);
+
+        manager.retrieve("iiii", 1).await.unwrap();
+        let stats = manager.cache_stats().unwrap();
+        assert_eq!((stats.hits(), stats.misses()), (1, 1));
+        assert_eq!(stats.hit_ratio(), 0.5);
+        assert_eq!(stats.lookup_latency().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn write_back_buffers_storage_writes() {
+        let cache = MemoryCache::builder()
+            .store(InMemoryDB::new(4))
+            .max_memory_limit(10)
+            .write_policy(WritePolicy::WriteBack { batch_size: 3 })
+            .build()
+            .unwrap();
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .memory_cache(cache)
+            .build()
+            .unwrap();
+
+        let now = chrono::Utc::now().timestamp();
+        for id in ["first", "second"] {
+            manager.store(id, entry(id, id, now)).await.unwrap();
+        }
+
+        assert_eq!(manager.pending_writes(), 2);
+        assert_eq!(manager.storage.count().await.unwrap(), 0);
+        assert_eq!(
+            manager.retrieve("first", 1).await.unwrap()[0].data().id,
+            "first"
+        );
+
+        manager
+            .store("third", entry("third", "third", now))
+            .await
+            .unwrap();
+        assert_eq!(manager.pending_writes(), 0);
+        assert_eq!(manager.storage.count().await.unwrap(), 3);
+
+        manager
+            .store("fourth", entry("fourth", "fourth", now))
+            .await
+            .unwrap();
+        assert_eq!(manager.flush().await.unwrap(), 1);
+        assert_eq!(manager.storage.count().await.unwrap(), 4);
+    }
+
+    /// A storage that only records the IDs written to it, shared between clones.
+    #[derive(Clone, Default)]
+    struct InsertLog(std::sync::Arc<std::sync::Mutex<Vec<MemoryId>>>);
+
+    impl Storage for InsertLog {
+        async fn count(&self) -> Result<usize, crate::Error> {
+            Ok(self.0.lock().unwrap().len())
+        }
+
+        async fn delete(&mut self, _: MemoryId) -> Result<(), crate::Error> {
+            Ok(())
+        }
+
+        async fn delete_batch(&mut self, _: Vec<MemoryId>) -> Result<(), crate::Error> {
+            Ok(())
+        }
+
+        async fn get_oldest(&self, _: usize) -> Result<Vec<SearchResult>, crate::Error> {
+            Ok(Vec::new())
+        }
+
+        async fn get_recent(&self, _: usize) -> Result<Vec<SearchResult>, crate::Error> {
+            Ok(Vec::new())
+        }
+
+        async fn insert(&mut self, _: Vec<f32>, entry: MemoryEntry) -> Result<(), crate::Error> {
+            self.0.lock().unwrap().push(entry.id);
+            Ok(())
+        }
+
+        async fn search(&self, _: Vec<f32>, _: usize) -> Result<Vec<SearchResult>, crate::Error> {
+            Ok(Vec::new())
+        }
+
+        async fn search_by_id(&self, id: MemoryId) -> Result<SearchResult, crate::Error> {
+            Err(StorageError::EmbeddingNotExists(id.to_string()).into())
+        }
+
+        async fn update_payload_by_id(
+            &mut self,
+            _: MemoryId,
+            _: MemoryEntry,
+        ) -> Result<(), crate::Error> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn buffered_writes_are_flushed_when_closed_or_dropped() {
+        let build = |storage: InsertLog| {
+            let cache = MemoryCache::builder()
+                .store(InMemoryDB::new(4))
+                .max_memory_limit(10)
+                .write_policy(WritePolicy::WriteBack { batch_size: 3 })
+                .build()
+                .unwrap();
+            MemoryManager::builder()
+                .embedder(LetterEmbedder)
+                .storage(storage)
+                .memory_cache(cache)
+                .build()
+                .unwrap()
+        };
+        let log = InsertLog::default();
+
+        let mut manager = build(log.clone());
+        manager.store("aaa", entry("a", "aaa", 0)).await.unwrap();
+        assert_eq!(manager.pending_writes(), 1);
+        manager.close().await.unwrap();
+        assert_eq!(*log.0.lock().unwrap(), vec![MemoryId::from("a")]);
+
+        let mut manager = build(log.clone());
+        manager.store("eee", entry("e", "eee", 0)).await.unwrap();
+        assert_eq!(manager.pending_writes(), 1);
+        drop(manager);
+        assert_eq!(
+            *log.0.lock().unwrap(),
+            vec![MemoryId::from("a"), MemoryId::from("e")]
+        );
+    }
+
+    #[tokio::test]
+    async fn cache_is_warmed_with_the_best_memories() {
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .hot_cache(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+
+        let now = chrono::Utc::now().timestamp();
+        for (id, importance) in [("trivial", 0.1), ("vital", 0.9), ("useful", 0.6)] {
+            let mut memory = entry(id, id, now);
+            memory.importance = importance;
+            manager.storage.insert(vec![1.0; 4], memory).await.unwrap();
+        }
+
+        assert_eq!(manager.warm_cache(2).await.unwrap(), 2);
+
+        let cache = &manager.hot_cache.as_ref().unwrap().store;
+        assert!(cache.contains("vital"));
+        assert!(cache.contains("useful"));
+        assert!(!cache.contains("trivial"));
+        assert_eq!(manager.warm_cache(2).await.unwrap(), 0);
+    }
+
+    #[test]
+    fn build_rejects_mismatched_dimensions() {
+        let res = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(3))
+            .build();
+        assert!(res.is_err());
+
+        let res = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .hot_cache(InMemoryDB::new(8))
+            .build();
+        assert!(res.is_err());
+    }
+
+    #[tokio::test]
+    async fn images_can_be_recalled_with_text_queries() {
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+
+        let now = chrono::Utc::now().timestamp();
+        let image = ImageSource::Bytes(b"aaaa".to_vec());
+        manager
+            .store_image(image.clone(), entry("image", "A screenshot", now))
+            .await
+            .unwrap();
+        manager
+            .store("oooo", entry("text", "oooo", now))
+            .await
+            .unwrap();
+
+        let results = manager.retrieve("aaa", 1).await.unwrap();
+        assert_eq!(results[0].data().id, "image");
+        assert_eq!(results[0].data().image, Some(image));
+    }
+
+    /// Updates the most similar memory with the new memory's content.
+    struct UpdateFirst;
+
+    impl MemoryReconciler for UpdateFirst {
+        async fn reconcile(
+            &self,
+            memory: &MemoryEntry,
+            existing: &[MemoryEntry],
+        ) -> Result<MemoryOperation, crate::Error> {
+            Ok(MemoryOperation::Update {
+                id: existing[0].id.to_string(),
+                content: memory.content.clone(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn reconciled_memories_update_existing_memories() {
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+
+        let op = manager
+            .store_reconciled(entry("1", "User drinks tea", 0), &UpdateFirst, 3)
+            .await
+            .unwrap();
+        assert_eq!(op, MemoryOperation::Add);
+
+        manager
+            .store_reconciled(entry("2", "User drinks coffee", 0), &UpdateFirst, 3)
+            .await
+            .unwrap();
+
+        let results = manager.retrieve("User drinks coffee", 5).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].data().id, "1");
+        assert_eq!(results[0].data().content, "User drinks coffee");
+    }
+
+    /// An embedder that never responds.
+    #[cfg(feature = "timeout")]
+    struct StalledEmbedder;
+
+    #[cfg(feature = "timeout")]
+    impl Embedder for StalledEmbedder {
+        async fn embed_text(&self, _input: &str) -> Result<Vec<f32>, crate::Error> {
+            std::future::pending().await
+        }
+
+        fn dims(&self) -> Option<usize> {
+            Some(4)
+        }
+    }
+
+    #[cfg(feature = "timeout")]
+    #[tokio::test]
+    async fn embedding_calls_are_bounded_by_the_configured_deadline() {
+        let mut cfg = MemoryConfig::new();
+        cfg.embed_timeout = Some(std::time::Duration::from_millis(10));
+        let mut manager = MemoryManager::builder()
+            .embedder(StalledEmbedder)
+            .storage(InMemoryDB::new(4))
+            .config(cfg)
+            .build()
+            .unwrap();
+
+        let err = manager
+            .store("aaaa", entry("a", "aaaa", 0))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, crate::Error::Timeout(_)));
+        assert_eq!(manager.count().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn invalid_memory_ids_are_rejected() {
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+
+        assert!(MemoryId::new("").is_err());
+        assert!(MemoryId::new("a\nb").is_err());
+        assert!(MemoryId::new("a".repeat(MemoryId::MAX_LEN + 1)).is_err());
+        assert_eq!(MemoryId::new("a").unwrap(), "a");
+
+        let err = manager
+            .store("aaaa", entry("", "aaaa", 0))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            crate::Error::Storage(StorageError::InvalidMemoryId(_))
+        ));
+        assert_eq!(manager.count().await.unwrap(), 0);
+    }
+
+    /// An embedder that doesn't know its dimensions ahead of time.
+    struct UnsizedEmbedder;
+
+    impl Embedder for UnsizedEmbedder {
+        async fn embed_text(&self, _: &str) -> Result<Vec<f32>, crate::Error> {
+            Ok(vec![1.0; 3])
+        }
+    }
+
+    #[tokio::test]
+    async fn misconfigured_stacks_fail_to_build() {
+        let mut cfg = MemoryConfig::new();
+        cfg.eviction_batch_size = 0;
+        let result = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .config(cfg)
+            .build();
+        assert!(matches!(
+            result,
+            Err(crate::Error::Build(BuildError::InvalidConfig(_)))
+        ));
+
+        // The dimensions of an unsized embedder can only be checked by embedding something
+        assert!(
+            MemoryManager::builder()
+                .embedder(UnsizedEmbedder)
+                .storage(InMemoryDB::new(4))
+                .build()
+                .is_ok()
+        );
+        let result = MemoryManager::builder()
+            .embedder(UnsizedEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build_validated()
+            .await;
+        assert!(matches!(
+            result,
+            Err(crate::Error::Build(BuildError::MismatchedDimensions(
+                "storage", 4, 3
+            )))
+        ));
+
+        assert!(
+            MemoryManager::builder()
+                .embedder(LetterEmbedder)
+                .storage(InMemoryDB::new(4))
+                .hot_cache(InMemoryDB::new(4))
+                .build_validated()
+                .await
+                .is_ok()
+        );
+    }
+
+    /// Embeds like [`LetterEmbedder`], except for inputs mentioning a glitch, which get embeddings with the wrong dimensions.
+    struct GlitchyEmbedder;
+
+    impl Embedder for GlitchyEmbedder {
+        async fn embed_text(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+            if input.contains("glitch") {
+                return Ok(vec![1.0; 5]);
+            }
+
+            LetterEmbedder.embed_text(input).await
+        }
+    }
+
+    #[tokio::test]
+    async fn transactions_store_every_memory_or_none() {
+        let mut manager = MemoryManager::builder()
+            .embedder(GlitchyEmbedder)
+            .storage(InMemoryDB::new(4))
+            .hot_cache(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+        manager
+            .store("original", entry("existing", "original", 0))
+            .await
+            .unwrap();
+
+        let result = manager
+            .store_all_or_nothing(vec![
+                entry("new", "new", 0),
+                entry("existing", "replaced", 0),
+                entry("broken", "glitch", 0),
+            ])
+            .await;
+        assert!(result.is_err());
+        assert_eq!(manager.count().await.unwrap(), 1);
+        let existing = manager
+            .storage
+            .search_by_id("existing".into())
+            .await
+            .unwrap();
+        assert_eq!(existing.data().content, "original");
+        assert!(!manager.hot_cache.as_ref().unwrap().store.contains("new"));
+
+        manager
+            .store_all_or_nothing(vec![
+                entry("new", "new", 0),
+                entry("existing", "replaced", 0),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(manager.count().await.unwrap(), 2);
+        let existing = manager
+            .storage
+            .search_by_id("existing".into())
+            .await
+            .unwrap();
+        assert_eq!(existing.data().content, "replaced");
+        assert!(manager.hot_cache.as_ref().unwrap().store.contains("new"));
+    }
+
+    #[tokio::test]
+    async fn storage_snapshots_are_isolated_from_later_writes() {
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+        manager.store("aaa", entry("a", "aaa", 0)).await.unwrap();
+
+        let snapshot = manager.storage_snapshot().await.unwrap();
+        manager.store("eee", entry("e", "eee", 0)).await.unwrap();
+        manager.delete("a").await.unwrap();
+
+        assert_eq!(snapshot.count().await.unwrap(), 1);
+        let results = snapshot.search(vec![4.0, 1.0, 1.0, 1.0], 5).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].data().id, "a");
+        assert_eq!(manager.count().await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn snapshot_retrievals_ignore_writes_made_while_they_are_held() {
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+        manager.store("aaa", entry("a", "aaa", 0)).await.unwrap();
+
+        let retrieval = manager.snapshot_retrieval("aaa", 5).await.unwrap();
+        manager.store("aaaa", entry("aa", "aaaa", 0)).await.unwrap();
+        manager.delete("a").await.unwrap();
+
+        let results = retrieval.run().await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].data().id, "a");
+
+        let results = manager.retrieve("aaa", 5).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].data().id, "aa");
+
+        // The snapshot stays readable (and unchanged) for as long as it's held
+        drop(manager);
+        assert_eq!(retrieval.snapshot().count().await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn namespace_quotas_reject_or_evict() {
+        let cfg = MemoryConfig::new()
+            .quota("alice", Quota::new().max_memories(2))
+            .quota(
+                "bob",
+                Quota::new()
+                    .max_memories(2)
+                    .on_exceeded(QuotaPolicy::EvictOldest),
+            );
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .config(cfg)
+            .build()
+            .unwrap();
+
+        for (agent, id, created_at) in [
+            ("alice", "a1", 1),
+            ("alice", "a2", 2),
+            ("bob", "b1", 1),
+            ("bob", "b2", 2),
+            ("bob", "b3", 3),
+        ] {
+            let mut memory = entry(id, id, created_at);
+            memory.agent_id = Some(agent.to_string());
+            manager.store(id, memory).await.unwrap();
+        }
+
+        let mut memory = entry("a3", "a3", 3);
+        memory.agent_id = Some("alice".to_string());
+        let err = manager.store("a3", memory).await.unwrap_err();
+        assert!(matches!(
+            err,
+            crate::Error::Storage(StorageError::QuotaExceeded(..))
+        ));
+
+        // Unattributed memories have no quota
+        manager
+            .store("free", entry("free", "free", 0))
+            .await
+            .unwrap();
+
+        assert_eq!(manager.count().await.unwrap(), 5);
+        assert!(manager.storage.search_by_id("b1".into()).await.is_err());
+        let usage = manager.quota_usage("bob").await.unwrap();
+        assert_eq!(usage.memories, 2);
+        assert_eq!(usage.bytes, 2 * "b2test".len());
+
+        manager.delete("a1").await.unwrap();
+        assert_eq!(manager.quota_usage("alice").await.unwrap().memories, 1);
+    }
+
+    #[tokio::test]
+    async fn quantized_stores_count_towards_quotas() {
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(QuantizedInMemoryDB::new(4))
+            .config(MemoryConfig::new().quota("alice", Quota::new().max_memories(1)))
+            .build()
+            .unwrap();
+
+        let mut memory = entry("a1", "aaa", 1);
+        memory.agent_id = Some("alice".to_string());
+        manager.store_quantized("aaa", memory).await.unwrap();
+        assert_eq!(manager.quota_usage("alice").await.unwrap().memories, 1);
+
+        let mut memory = entry("a2", "eee", 2);
+        memory.agent_id = Some("alice".to_string());
+        let err = manager.store_quantized("eee", memory).await.unwrap_err();
+        assert!(matches!(
+            err,
+            crate::Error::Storage(StorageError::QuotaExceeded(..))
+        ));
+        assert_eq!(manager.count().await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn out_of_range_importance_is_clamped_or_rejected() {
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+
+        let mut overrated = entry("overrated", "overrated", 0);
+        overrated.importance = 8.0;
+        manager.store("overrated", overrated).await.unwrap();
+        let stored = manager
+            .storage
+            .search_by_id("overrated".into())
+            .await
+            .unwrap();
+        assert_eq!(stored.data().importance, 1.0);
+
+        let mut broken = entry("broken", "broken", 0);
+        broken.importance = f32::NAN;
+        assert!(manager.store("broken", broken).await.is_err());
+
+        let mut cfg = MemoryConfig::new();
+        cfg.importance_policy = ImportancePolicy::Reject;
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .config(cfg)
+            .build()
+            .unwrap();
+
+        let mut underrated = entry("underrated", "underrated", 0);
+        underrated.importance = -0.2;
+        let err = manager.store("underrated", underrated).await.unwrap_err();
+        assert!(matches!(
+            err,
+            crate::Error::Storage(StorageError::InvalidImportance(_))
+        ));
+        assert_eq!(manager.count().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn memories_can_be_retrieved_by_metadata() {
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+
+        for (id, source, topic) in [
+            ("a", "chat", "work"),
+            ("b", "chat", "home"),
+            ("c", "email", "work"),
+        ] {
+            let mut memory = entry(id, "aaaa", 0);
+            memory.metadata = crate::metadata! { "source" => source, "topic" => topic };
+            manager.store("aaaa", memory).await.unwrap();
+        }
+
+        let ids = |results: Vec<crate::storage::SearchResult>| {
+            let mut ids: Vec<String> = results
+                .into_iter()
+                .map(|result| result.data().id.to_string())
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        let chat = MetadataFilter::eq("source", "chat");
+        let results = manager
+            .retrieve_where("aaaa", 5, chat.clone())
+            .await
+            .unwrap();
+        assert_eq!(ids(results), ["a", "b"]);
+
+        let filter = chat.clone().and(MetadataFilter::eq("topic", "work"));
+        let results = manager.retrieve_where("aaaa", 5, filter).await.unwrap();
+        assert_eq!(ids(results), ["a"]);
+
+        let filter = chat.or(MetadataFilter::eq("topic", "work"));
+        let results = manager.retrieve_where("aaaa", 5, filter).await.unwrap();
+        assert_eq!(ids(results), ["a", "b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn memories_can_be_looked_up_by_tag() {
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+
+        for (id, tags) in [
+            ("a", vec!["pinned"]),
+            ("b", vec!["pinned", "draft"]),
+            ("c", vec![]),
+        ] {
+            let mut memory = entry(id, "aaaa", 0);
+            memory.tags = tags.into_iter().map(String::from).collect();
+            manager.store("aaaa", memory).await.unwrap();
+        }
+
+        let results = manager
+            .retrieve_by_tag("aaaa", 5, &["draft"])
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].data().id, "b");
+        assert_eq!(manager.storage.get_by_tag("pinned").len(), 2);
+
+        let mut untagged = entry("b", "aaaa", 0);
+        untagged.tags = vec!["draft".to_string()];
+        manager.update(untagged).await.unwrap();
+        manager.delete("a").await.unwrap();
+        assert!(manager.storage.get_by_tag("pinned").is_empty());
+        assert_eq!(manager.storage.get_by_tag("draft").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn retrieved_memories_carry_their_similarity() {
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+
+        manager.store("aaaa", entry("a", "aaaa", 0)).await.unwrap();
+        manager.store("eeee", entry("e", "eeee", 0)).await.unwrap();
+
+        let results = manager
+            .retrieve_scored("aaaa", 2, RetrieveOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(results[0].memory.id, "a");
+        assert!((results[0].score - 1.0).abs() < 1e-5);
+        assert!(results[1].score < results[0].score);
+    }
+
+    #[tokio::test]
+    async fn memories_can_be_listed_by_creation_time() {
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+
+        for (id, created_at) in [("a", 10), ("b", 20), ("c", 30)] {
+            manager
+                .store("aaaa", entry(id, "aaaa", created_at))
+                .await
+                .unwrap();
+        }
+
+        let ids = |results: &[crate::storage::SearchResult]| {
+            results
+                .iter()
+                .map(|result| result.data().id.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let recent = manager.recent(2).await.unwrap();
+        assert_eq!(ids(&recent), ["c", "b"]);
+        assert_eq!(recent[0].data().access_count, 1);
+        // The memory hadn't been accessed in a long time, so it gets fully reinforced
+        assert_eq!(recent[0].data().strength, INITIAL_STRENGTH * 2.5);
+
+        let oldest = manager.oldest(1).await.unwrap();
+        assert_eq!(ids(&oldest), ["a"]);
+
+        let between = manager.between(15, 30).await.unwrap();
+        assert_eq!(ids(&between), ["b"]);
+        assert_eq!(between[0].data().access_count, 2);
+    }
+
+    #[tokio::test]
+    async fn stats_summarize_every_memory() {
+        let cache = MemoryCache::builder()
+            .store(InMemoryDB::new(4))
+            .max_memory_limit(10)
+            .write_policy(WritePolicy::WriteBack { batch_size: 10 })
+            .build()
+            .unwrap();
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .memory_cache(cache)
+            .build()
+            .unwrap();
+
+        let now = chrono::Utc::now().timestamp();
+        let mut stored = entry("a", "aaaa", now - 100);
+        stored.importance = 1.0;
+        manager.store("aaaa", stored).await.unwrap();
+        manager.flush().await.unwrap();
+
+        let mut buffered = entry("b", "eeee", now - 300);
+        buffered.kind = MemoryKind::Episodic;
+        buffered.importance = 0.25;
+        manager.store("eeee", buffered).await.unwrap();
+
+        let stats = manager.stats().await.unwrap();
+        assert_eq!(stats.total, 2);
+        assert_eq!(stats.storage_size, 1);
+        assert_eq!(stats.by_kind[&MemoryKind::Semantic], 1);
+        assert_eq!(stats.by_kind[&MemoryKind::Episodic], 1);
+        assert_eq!(stats.importance.buckets[2], 1);
+        assert_eq!(stats.importance.buckets[9], 1);
+        assert!(stats.age.max >= 300);
+        assert!(stats.age.p50 >= 100 && stats.age.p50 < 300);
+        assert!(stats.cache_hit_ratio.is_some());
+        manager.flush().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn old_episodic_memories_are_digested_by_week() {
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+
+        // 2024-01-01 was a Monday
+        let monday = 1_704_067_200;
+        let day = 24 * 60 * 60;
+        for (id, created_at) in [
+            ("a", monday),
+            ("b", monday + 2 * day),
+            ("c", monday + 9 * day),
+        ] {
+            let mut memory = entry(id, id, created_at);
+            memory.kind = MemoryKind::Episodic;
+            memory.importance = 0.1;
+            manager.store(id, memory).await.unwrap();
+        }
+        let mut recent = entry("recent", "recent", chrono::Utc::now().timestamp());
+        recent.kind = MemoryKind::Episodic;
+        manager.store("recent", recent).await.unwrap();
+
+        let mut policy = DigestPolicy::new();
+        policy.min_memories = 1;
+        let report = manager.digest(&JoiningSummarizer, &policy).await.unwrap();
+        assert_eq!(report.digested, 3);
+        assert_eq!(
+            report.digests,
+            ["digest-week-2024-W01", "digest-week-2024-W02"]
+        );
+        assert_eq!(manager.count().await.unwrap(), 3);
+
+        let digest = manager
+            .storage
+            .search_by_id("digest-week-2024-W01".into())
+            .await
+            .unwrap();
+        assert_eq!(digest.data().content, "a; b");
+        assert_eq!(digest.data().created_at, monday);
+        assert!(digest.data().has_tag(DIGEST_TAG));
+
+        // Digests aren't digested again
+        let report = manager.digest(&JoiningSummarizer, &policy).await.unwrap();
+        assert_eq!(report.digested, 0);
+    }
+
+    #[tokio::test]
+    async fn maintenance_enforces_retention_per_kind() {
+        let cfg = MemoryConfig::new()
+            .retention(
+                MemoryKind::Working,
+                RetentionPolicy {
+                    max_age: Some(Duration::from_secs(60 * 60)),
+                    ..Default::default()
+                },
+            )
+            .retention(
+                MemoryKind::Episodic,
+                RetentionPolicy {
+                    max_count: Some(2),
+                    min_importance: Some(0.2),
+                    ..Default::default()
+                },
+            );
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .config(cfg)
+            .build()
+            .unwrap();
+
+        let now = chrono::Utc::now().timestamp();
+        for (id, kind, created_at, importance) in [
+            ("stale", MemoryKind::Working, now - 2 * 60 * 60, 0.5),
+            ("fresh", MemoryKind::Working, now - 60, 0.5),
+            ("first", MemoryKind::Episodic, now - 30, 0.5),
+            ("second", MemoryKind::Episodic, now - 20, 0.5),
+            ("third", MemoryKind::Episodic, now - 10, 0.5),
+            ("trivial", MemoryKind::Episodic, now, 0.1),
+            ("fact", MemoryKind::Semantic, 0, 0.0),
+        ] {
+            let mut memory = entry(id, id, created_at);
+            memory.kind = kind;
+            memory.importance = importance;
+            manager.store(id, memory).await.unwrap();
+        }
+
+        let report = manager.maintain().await.unwrap();
+        assert_eq!(report.deleted[&MemoryKind::Working], 1);
+        assert_eq!(report.deleted[&MemoryKind::Episodic], 2);
+
+        let mut remaining: Vec<_> = manager
+            .oldest(10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|memory| memory.data().id.to_string())
+            .collect();
+        remaining.sort();
+        assert_eq!(remaining, ["fact", "fresh", "second", "third"]);
+    }
+
+    /// Hashes each whitespace-separated word into an index, so that memories sharing exact words overlap.
+    struct WordSparseEmbedder;
+
+    impl SparseEmbedder for WordSparseEmbedder {
+        async fn embed_sparse(&self, input: &str) -> Result<SparseVector, crate::Error> {
+            use std::hash::{DefaultHasher, Hash, Hasher};
+
+            let indices = input
+                .split_whitespace()
+                .map(|word| {
+                    let mut hasher = DefaultHasher::new();
+                    word.hash(&mut hasher);
+                    hasher.finish() as u32
+                })
+                .collect::<Vec<_>>();
+            let values = vec![1.0; indices.len()];
+
+            Ok(SparseVector::new(indices, values))
+        }
+    }
+
+    async fn hybrid_manager() -> MemoryManager<LetterEmbedder, InMemoryDB> {
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+
+        let now = chrono::Utc::now().timestamp();
+        // "b" and "c" are equally similar to the query, but only "b" shares a rare token with it
+        for (id, content) in [("a", "aaaa"), ("b", "E42 oooo"), ("c", "oooo")] {
+            manager
+                .store_hybrid(content, entry(id, content, now), &WordSparseEmbedder)
+                .await
+                .unwrap();
+        }
+
+        manager
+    }
+
+    #[tokio::test]
+    async fn hybrid_retrieval_fuses_reciprocal_ranks() {
+        let mut manager = hybrid_manager().await;
+
+        let results = manager
+            .retrieve_hybrid(
+                "aaaa E42",
+                2,
+                &WordSparseEmbedder,
+                HybridFusion::ReciprocalRank { k: 60.0 },
+            )
+            .await
+            .unwrap();
+        let mut ids: Vec<&str> = results.iter().map(|x| x.data().id.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, ["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn hybrid_retrieval_fuses_weighted_scores() {
+        let mut manager = hybrid_manager().await;
+
+        let results = manager
+            .retrieve_hybrid(
+                "aaaa E42",
+                3,
+                &WordSparseEmbedder,
+                HybridFusion::Weighted { dense_weight: 0.5 },
+            )
+            .await
+            .unwrap();
+        let ids: Vec<&str> = results.iter().map(|x| x.data().id.as_str()).collect();
+        assert_eq!(ids, ["a", "b", "c"]);
+
+        // "b" and "c" have a cosine similarity of 12/28 (scaled to 20/28), and only "b" gets the normalized sparse score
+        let dense = 20.0 / 28.0;
+        let scores: Vec<f32> = results.iter().map(|x| x.score().unwrap()).collect();
+        for (score, expected) in scores.iter().zip([1.0, 0.5 * dense + 0.5, 0.5 * dense]) {
+            assert!((score - expected).abs() < 1e-5, "{score} != {expected}");
+        }
+    }
+
+    #[tokio::test]
+    async fn hybrid_stores_flush_buffered_writes_and_roll_back_on_failure() {
+        let cache = MemoryCache::builder()
+            .store(InMemoryDB::new(4))
+            .max_memory_limit(10)
+            .write_policy(WritePolicy::WriteBack { batch_size: 10 })
+            .build()
+            .unwrap();
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .memory_cache(cache)
+            .build()
+            .unwrap();
+
+        let now = chrono::Utc::now().timestamp();
+        manager
+            .store_hybrid("E42", entry("a", "E42", now), &WordSparseEmbedder)
+            .await
+            .unwrap();
+        assert_eq!(manager.pending_writes(), 0);
+        let results = manager
+            .retrieve_hybrid("E42", 1, &WordSparseEmbedder, HybridFusion::default())
+            .await
+            .unwrap();
+        assert_eq!(results[0].data().id, "a");
+
+        // Hybrid retrieval searches the backing storage, so buffered writes are flushed first
+        manager.store("ooo", entry("b", "ooo", now)).await.unwrap();
+        assert_eq!(manager.pending_writes(), 1);
+        let results = manager
+            .retrieve_hybrid("ooo", 2, &WordSparseEmbedder, HybridFusion::default())
+            .await
+            .unwrap();
+        assert_eq!(manager.pending_writes(), 0);
+        assert!(results.iter().any(|result| result.data().id == "b"));
+
+        // Quantized storage doesn't support sparse vectors
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(QuantizedInMemoryDB::new(4))
+            .build()
+            .unwrap();
+        assert!(
+            manager
+                .store_hybrid("E42", entry("a", "E42", now), &WordSparseEmbedder)
+                .await
+                .is_err()
+        );
+        assert_eq!(manager.count().await.unwrap(), 0);
     }
 }
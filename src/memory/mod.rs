@@ -1,16 +1,35 @@
 use serde::{Deserialize, Serialize};
+use std::{borrow::Borrow, fmt, ops::Deref};
+
+use crate::{embed::ImageSource, error::StorageError};
 
 pub mod cache;
+pub mod conversation;
+pub mod digest;
+pub mod entity;
 pub mod generation;
+pub mod importance;
+pub mod ingest;
+pub mod language;
 pub mod manager;
+pub mod pool;
+pub mod query;
+pub mod quota;
+pub mod reconcile;
+pub mod redact;
+pub mod stats;
+pub mod stream;
+pub mod strength;
+pub mod summarize;
+pub mod tools;
 
 /// A memory entry (ie, a summarized version of a conversation).
 ///
 /// It is generally advised that the contents of an agent memory be generated from an LLM as the contents are often very non-deterministic.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct MemoryEntry {
     /// Memory ID
-    pub id: String,
+    pub id: MemoryId,
     /// The content of the memory (eg, a fact or a summarization of a previous conversation).
     pub content: String,
     pub kind: MemoryKind,
@@ -27,10 +46,91 @@ pub struct MemoryEntry {
     pub confidence: Confidence,
     /// Any additional metadata
     pub metadata: Vec<MetadataEntry>,
+    /// The ID of the agent that created this memory (if any).
+    pub agent_id: Option<String>,
+    /// Who is able to see this memory when using a [`pool::SharedMemoryPool`].
+    pub visibility: Visibility,
+    /// The embedding model used to embed this memory. This is set automatically when storing a memory.
+    pub embedding_model: Option<EmbeddingModelTag>,
+    /// The image this memory refers to (if any). This is set automatically when storing a memory with [`manager::MemoryManager::store_image`].
+    pub image: Option<ImageSource>,
+    /// Simple labels for the memory (eg, `pinned` or `onboarding`), for when a metadata key-value pair would be overkill.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// How strongly the memory is retained (roughly, how many days it takes to fade since it was last accessed), reinforced every time it's accessed.
+    /// See [`strength`] for the spaced-repetition model. New memories start at [`strength::INITIAL_STRENGTH`].
+    #[serde(default = "strength::initial_strength")]
+    pub strength: f32,
+}
+
+impl MemoryEntry {
+    /// The topics this memory was tagged with at generation time (stored as `topic` metadata entries).
+    pub fn topics(&self) -> impl Iterator<Item = &str> {
+        self.metadata
+            .iter()
+            .filter(|entry| entry.key == TOPIC_METADATA_KEY)
+            .map(|entry| entry.value.as_str())
+    }
+
+    /// The entities (eg, people, places and projects) mentioned in this memory (stored as `entity` metadata entries).
+    pub fn entities(&self) -> impl Iterator<Item = entity::Entity> {
+        self.metadata
+            .iter()
+            .filter(|entry| entry.key == entity::ENTITY_METADATA_KEY)
+            .filter_map(|entry| entity::Entity::from_metadata(&entry.value))
+    }
+
+    /// Whether or not this memory was tagged with a given topic (case-insensitive).
+    pub fn has_topic(&self, topic: &str) -> bool {
+        self.topics()
+            .any(|existing| existing.eq_ignore_ascii_case(topic.trim()))
+    }
+
+    /// Whether or not this memory has a given tag.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|existing| existing == tag)
+    }
+
+    /// Gets the value of the first metadata entry with a given key (if there is one).
+    /// Keys can repeat (eg, a memory can have several `topic` entries), so use [`MemoryEntry::metadata`] directly to get every value.
+    pub fn metadata_get(&self, key: &str) -> Option<&str> {
+        self.metadata
+            .iter()
+            .find(|entry| entry.key == key)
+            .map(|entry| entry.value.as_str())
+    }
+}
+
+/// The metadata key that memory topics are stored under.
+pub const TOPIC_METADATA_KEY: &str = "topic";
+
+/// The embedding model (and the dimensions of the embedding) used to embed a memory.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct EmbeddingModelTag {
+    pub model: String,
+    pub dims: usize,
+}
+
+impl fmt::Display for EmbeddingModelTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({} dims)", self.model, self.dims)
+    }
+}
+
+/// The visibility of a memory when multiple agents share a single store.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, schemars::JsonSchema,
+)]
+pub enum Visibility {
+    /// Only visible to the agent that created it.
+    #[default]
+    Private,
+    /// Visible to every agent using the store.
+    Shared,
 }
 
 /// The type of memory.
-#[derive(Clone, Debug, Deserialize, Serialize, schemars::JsonSchema)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize, schemars::JsonSchema)]
 pub enum MemoryKind {
     /// Working memory (ie, stuff that's in the current context window)
     Working,
@@ -38,6 +138,117 @@ pub enum MemoryKind {
     Episodic,
     /// Facts and ground truths
     Semantic,
+    /// Learned procedures (eg, how to use a tool, or which steps work in a given environment)
+    Procedural,
+}
+
+/// The ID of a memory.
+///
+/// Converting from a `String` (or `&str`) is cheap and unchecked, but IDs are validated (see [`MemoryId::new`]) when they're stored by a [`manager::MemoryManager`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct MemoryId(String);
+
+impl MemoryId {
+    /// The maximum length of a memory ID, in bytes.
+    pub const MAX_LEN: usize = 256;
+
+    /// Creates a memory ID, returning an error if it isn't valid (ie, it's empty, longer than [`MemoryId::MAX_LEN`] bytes or contains control characters).
+    pub fn new<S>(id: S) -> Result<Self, crate::Error>
+    where
+        S: Into<String>,
+    {
+        let id = Self(id.into());
+        id.validate()?;
+
+        Ok(id)
+    }
+
+    /// Returns an error if the ID isn't valid (see [`MemoryId::new`]).
+    pub fn validate(&self) -> Result<(), crate::Error> {
+        if self.0.is_empty() || self.0.len() > Self::MAX_LEN || self.0.chars().any(char::is_control)
+        {
+            Err(StorageError::invalid_memory_id(&self.0))?
+        }
+
+        Ok(())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl From<String> for MemoryId {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for MemoryId {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl From<&String> for MemoryId {
+    fn from(value: &String) -> Self {
+        Self(value.clone())
+    }
+}
+
+impl From<MemoryId> for String {
+    fn from(value: MemoryId) -> Self {
+        value.0
+    }
+}
+
+impl Deref for MemoryId {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for MemoryId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for MemoryId {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for MemoryId {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for MemoryId {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<String> for MemoryId {
+    fn eq(&self, other: &String) -> bool {
+        &self.0 == other
+    }
+}
+
+impl fmt::Display for MemoryId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
 }
 
 /// A memory entry draft.
@@ -49,21 +260,161 @@ pub struct MemoryDraft {
     /// The context in which this memory has been created
     pub source_context: String,
     /// How important the memory is (using a decimal number between 0.0 and 1.0).
-    pub importance: f32,
+    /// If not provided, an importance score will be assigned by an [`importance::ImportanceScorer`] at generation time.
+    #[serde(default)]
+    pub importance: Option<f32>,
     pub confidence: Confidence,
     /// Any additional metadata
     pub metadata: Vec<MetadataEntry>,
+    /// Short, lowercase topic tags for the memory (eg, `work`, `family`, `health`, `preferences`, `projects`), stored as `topic` metadata entries.
+    #[serde(default)]
+    pub topics: Vec<String>,
+    /// The people, places, organizations and projects the memory is about, stored as `entity` metadata entries.
+    #[serde(default)]
+    pub entities: Vec<entity::Entity>,
 }
 
+/// A list of memory drafts extracted from a single input (eg, a conversation).
+/// This is the structured output schema used by LLM-backed memory generators. Custom draft types (see [`MemoryEntryFields`]) can be used in place of [`MemoryDraft`].
 #[derive(Clone, Debug, Deserialize, Serialize, schemars::JsonSchema)]
+pub struct MemoryDrafts<D = MemoryDraft> {
+    /// Every memory extracted from the input. May be empty if there was nothing worth remembering.
+    pub memories: Vec<D>,
+}
+
+impl<D> Default for MemoryDrafts<D> {
+    fn default() -> Self {
+        Self {
+            memories: Vec::new(),
+        }
+    }
+}
+
+/// The fields of a memory entry that come from a memory generator (ie, everything except the ID, timestamps and access statistics, which are filled in by [`generation::MemoryGenerator`]).
+///
+/// Memory generators can extract custom draft types (eg, with CRM-specific fields) as long as they convert into this type.
+/// Domain-specific data that doesn't fit the memory content is best kept in the metadata.
+#[derive(Clone, Debug)]
+pub struct MemoryEntryFields {
+    /// The content of the memory (eg, a fact or a summarization of a previous conversation).
+    pub content: String,
+    pub kind: MemoryKind,
+    /// The context in which this memory has been created
+    pub source_context: String,
+    /// How important the memory is (using a decimal number between 0.0 and 1.0).
+    /// If not provided, an importance score will be assigned by an [`importance::ImportanceScorer`] at generation time.
+    pub importance: Option<f32>,
+    pub confidence: Confidence,
+    /// Any additional metadata
+    pub metadata: Vec<MetadataEntry>,
+    /// The ID of the agent that created this memory (if any).
+    pub agent_id: Option<String>,
+    /// Who is able to see this memory when using a [`pool::SharedMemoryPool`].
+    pub visibility: Visibility,
+    /// Simple labels for the memory (see [`MemoryEntry::tags`]).
+    pub tags: Vec<String>,
+}
+
+impl From<MemoryDraft> for MemoryEntryFields {
+    fn from(draft: MemoryDraft) -> Self {
+        let mut metadata = draft.metadata;
+        let mut topics: Vec<String> = draft
+            .topics
+            .into_iter()
+            .map(|topic| topic.trim().to_lowercase())
+            .filter(|topic| !topic.is_empty())
+            .collect();
+        topics.sort_unstable();
+        topics.dedup();
+        metadata.extend(
+            topics
+                .into_iter()
+                .map(|topic| MetadataEntry::new(TOPIC_METADATA_KEY, topic)),
+        );
+        metadata.extend(
+            draft
+                .entities
+                .iter()
+                .filter(|entity| !entity.key().is_empty())
+                .map(entity::Entity::to_metadata),
+        );
+
+        Self {
+            content: draft.content,
+            kind: draft.kind,
+            source_context: draft.source_context,
+            importance: draft.importance,
+            confidence: draft.confidence,
+            metadata,
+            agent_id: None,
+            visibility: Visibility::default(),
+            tags: Vec::new(),
+        }
+    }
+}
+
+/// A key-value metadata entry attached to a memory.
+/// A list of entries can be created using the [`metadata!`](crate::metadata) macro.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, schemars::JsonSchema)]
 pub struct MetadataEntry {
     key: String,
     value: String,
 }
 
+impl MetadataEntry {
+    /// Creates a metadata entry.
+    pub fn new<S1, S2>(key: S1, value: S2) -> Self
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        Self {
+            key: key.into(),
+            value: value.into(),
+        }
+    }
+
+    /// The key of this entry.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// The value of this entry.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+/// Creates a `Vec<MetadataEntry>` from key-value pairs, for use as [`MemoryEntry::metadata`].
+///
+/// ```rust
+/// use braindump::{memory::MetadataEntry, metadata};
+///
+/// let metadata = metadata! {
+///     "source" => "chat",
+///     "topic" => "home",
+/// };
+/// assert_eq!(metadata[0], MetadataEntry::new("source", "chat"));
+/// assert_eq!(metadata[1], MetadataEntry::new("topic", "home"));
+///
+/// // Trailing commas are optional, and the macro can be empty
+/// assert_eq!(metadata! { "source" => "chat" }, [MetadataEntry::new("source", "chat")]);
+/// let empty: Vec<MetadataEntry> = metadata! {};
+/// assert!(empty.is_empty());
+/// ```
+#[macro_export]
+macro_rules! metadata {
+    ($($key:expr => $value:expr),* $(,)?) => {
+        ::std::vec![$($crate::memory::MetadataEntry::new($key, $value)),*]
+    };
+}
+
 /// A confidence score (provided by an LLM). Can either be low, medium or high.
 /// Represents the LLM's confidence about a fact or conversation history observation.
-#[derive(Clone, Debug, Deserialize, Serialize, schemars::JsonSchema)]
+/// Confidence levels are ordered from low to high.
+#[derive(
+    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, schemars::JsonSchema,
+)]
 pub enum Confidence {
     Low,
     Medium,
@@ -0,0 +1,172 @@
+//! Shared memory for multi-agent systems.
+
+use crate::{
+    embed::Embedder,
+    memory::{MemoryEntry, Visibility, manager::MemoryManager},
+    storage::{SearchResult, Storage},
+};
+
+/// A memory pool shared between multiple agents.
+///
+/// Every memory stored through the pool is attributed to an agent and given a [`Visibility`].
+/// Agents can see their own memories, any memories marked as [`Visibility::Shared`], as well as any unattributed memories (ie, memories stored directly through the [`MemoryManager`]).
+pub struct SharedMemoryPool<E, S>
+where
+    E: Embedder,
+    S: Storage,
+{
+    manager: MemoryManager<E, S>,
+}
+
+impl<E, S> SharedMemoryPool<E, S>
+where
+    E: Embedder,
+    S: Storage,
+{
+    /// Creates a new shared memory pool from a memory manager.
+    pub fn new(manager: MemoryManager<E, S>) -> Self {
+        Self { manager }
+    }
+
+    /// Get a reference to the underlying memory manager.
+    pub fn manager(&self) -> &MemoryManager<E, S> {
+        &self.manager
+    }
+
+    /// Get a mutable reference to the underlying memory manager.
+    pub fn manager_mut(&mut self) -> &mut MemoryManager<E, S> {
+        &mut self.manager
+    }
+
+    /// Consumes the pool, returning the underlying memory manager.
+    pub fn into_inner(self) -> MemoryManager<E, S> {
+        self.manager
+    }
+
+    /// Store a memory on behalf of an agent with a given visibility.
+    pub async fn store<AsRefStr>(
+        &mut self,
+        agent_id: &str,
+        memory: AsRefStr,
+        mut entry: MemoryEntry,
+        visibility: Visibility,
+    ) -> Result<(), crate::Error>
+    where
+        AsRefStr: AsRef<str>,
+    {
+        entry.agent_id = Some(agent_id.to_string());
+        entry.visibility = visibility;
+
+        self.manager.store(memory, entry).await
+    }
+
+    /// Retrieve every memory visible to a given agent: its own memories, shared memories and unattributed memories.
+    pub async fn retrieve<AsRefStr>(
+        &mut self,
+        agent_id: &str,
+        query: AsRefStr,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, crate::Error>
+    where
+        AsRefStr: AsRef<str>,
+    {
+        self.manager
+            .retrieve_filtered(query, limit, |entry| is_visible_to(entry, agent_id))
+            .await
+    }
+
+    /// Retrieve only the memories created by a given agent.
+    pub async fn retrieve_own<AsRefStr>(
+        &mut self,
+        agent_id: &str,
+        query: AsRefStr,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, crate::Error>
+    where
+        AsRefStr: AsRef<str>,
+    {
+        self.manager
+            .retrieve_filtered(query, limit, |entry| {
+                entry.agent_id.as_deref() == Some(agent_id)
+            })
+            .await
+    }
+
+    /// Retrieve only shared memories.
+    pub async fn retrieve_shared<AsRefStr>(
+        &mut self,
+        query: AsRefStr,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, crate::Error>
+    where
+        AsRefStr: AsRef<str>,
+    {
+        self.manager
+            .retrieve_filtered(query, limit, |entry| entry.visibility == Visibility::Shared)
+            .await
+    }
+}
+
+/// Whether or not a memory is visible to a given agent.
+pub fn is_visible_to(entry: &MemoryEntry, agent_id: &str) -> bool {
+    match entry.agent_id.as_deref() {
+        None => true,
+        Some(owner) => owner == agent_id || entry.visibility == Visibility::Shared,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SharedMemoryPool;
+    use crate::{
+        memory::{Visibility, manager::MemoryManager},
+        test_util::{LetterEmbedder, entry},
+        vector_store::InMemoryDB,
+    };
+
+    fn ids(results: &[crate::storage::SearchResult]) -> Vec<&str> {
+        let mut ids: Vec<&str> = results
+            .iter()
+            .map(|result| result.data().id.as_str())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[tokio::test]
+    async fn agents_only_see_their_own_and_shared_memories() {
+        let manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+        let mut pool = SharedMemoryPool::new(manager);
+
+        pool.store(
+            "alice",
+            "aaaa",
+            entry("private", "aaaa"),
+            Visibility::Private,
+        )
+        .await
+        .unwrap();
+        pool.store("alice", "aaee", entry("team", "aaee"), Visibility::Shared)
+            .await
+            .unwrap();
+        pool.manager_mut()
+            .store("aaii", entry("unattributed", "aaii"))
+            .await
+            .unwrap();
+
+        let alice = pool.retrieve("alice", "aaaa", 10).await.unwrap();
+        assert_eq!(ids(&alice), ["private", "team", "unattributed"]);
+        let bob = pool.retrieve("bob", "aaaa", 10).await.unwrap();
+        assert_eq!(ids(&bob), ["team", "unattributed"]);
+
+        let own = pool.retrieve_own("alice", "aaaa", 10).await.unwrap();
+        assert_eq!(ids(&own), ["private", "team"]);
+        let shared = pool.retrieve_shared("aaaa", 10).await.unwrap();
+        assert_eq!(ids(&shared), ["team"]);
+        assert_eq!(shared[0].data().agent_id.as_deref(), Some("alice"));
+    }
+}
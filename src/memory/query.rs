@@ -0,0 +1,91 @@
+//! Query transformation (ie, query expansion and HyDE).
+//!
+//! Short or vague queries like "that thing from last week" tend to embed poorly.
+//! A [`QueryTransformer`] rewrites the query before it gets embedded, which can then be used with [`crate::memory::manager::MemoryManager::retrieve_with_transformer`].
+
+use crate::wasm::{WasmCompatSend, WasmCompatSync};
+
+#[cfg(feature = "rig")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rig")))]
+pub use rig::create_rig_query_transformer;
+
+/// A trait for transforming a retrieval query before it is embedded.
+/// The output of [`QueryTransformer::transform`] is embedded in place of the raw query.
+pub trait QueryTransformer: WasmCompatSend + WasmCompatSync {
+    fn transform(
+        &self,
+        query: &str,
+    ) -> impl Future<Output = Result<String, crate::Error>> + WasmCompatSend;
+}
+
+/// The strategy to use when transforming a query using an LLM.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum QueryTransformStrategy {
+    /// Hypothetical Document Embeddings - the LLM writes a hypothetical memory that would answer the query.
+    #[default]
+    Hyde,
+    /// The LLM rewrites the query into a more descriptive, self-contained search query.
+    Expansion,
+}
+
+impl QueryTransformStrategy {
+    /// The system prompt used for a given strategy.
+    pub fn preamble(&self) -> &'static str {
+        match self {
+            Self::Hyde => HYDE_PREAMBLE,
+            Self::Expansion => EXPANSION_PREAMBLE,
+        }
+    }
+}
+
+const HYDE_PREAMBLE: &str = r###"You are part of a memory retrieval system for an AI assistant. Given a question or request from a user, write a short hypothetical memory (one to three sentences) that would perfectly answer it.
+
+Write the memory as a clear, standalone statement in the present tense (e.g., "User is a software engineer"). Do not hedge, do not explain yourself and do not mention that the memory is hypothetical. Respond with the memory only."###;
+
+const EXPANSION_PREAMBLE: &str = r###"You are part of a memory retrieval system for an AI assistant. Given a short or vague query from a user, rewrite it into a single descriptive search query that captures what they are most likely looking for, including any useful synonyms or related terms.
+
+Respond with the rewritten query only."###;
+
+#[cfg(feature = "rig")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rig")))]
+mod rig {
+    use super::{QueryTransformStrategy, QueryTransformer};
+    use rig::agent::Agent;
+    use rig::client::{Capabilities, Client, CompletionClient, Provider};
+    use rig::completion::{CompletionModel, Prompt};
+
+    impl<M> QueryTransformer for Agent<M>
+    where
+        M: CompletionModel,
+    {
+        async fn transform(&self, query: &str) -> Result<String, crate::Error> {
+            let res = self
+                .prompt(query)
+                .await
+                .map_err(|err| crate::Error::wrap("Completion request failed", err))?;
+
+            Ok(res)
+        }
+    }
+
+    /// Creates a [`rig::agent::Agent`] tailored to transforming retrieval queries using a given [`QueryTransformStrategy`].
+    pub fn create_rig_query_transformer<Ext, HttpClient, Model>(
+        client: &Client<Ext, HttpClient>,
+        model_name: &str,
+        strategy: QueryTransformStrategy,
+    ) -> Agent<
+        <rig::client::Client<Ext, HttpClient> as rig::client::CompletionClient>::CompletionModel,
+    >
+    where
+        Ext:
+            Provider + Capabilities<HttpClient, Completion = rig::client::Capable<Model>> + 'static,
+        HttpClient: rig::http_client::HttpClientExt + 'static,
+        Model: rig::completion::CompletionModel,
+        Client<Ext, HttpClient>: CompletionClient,
+    {
+        client
+            .agent(model_name)
+            .preamble(strategy.preamble())
+            .build()
+    }
+}
@@ -0,0 +1,185 @@
+//! Per-namespace quotas on the number (and size) of stored memories, for fairness in multi-tenant deployments.
+//!
+//! A memory's namespace is the agent (or user) it's attributed to (see [`MemoryEntry::agent_id`]). Memories without an agent ID belong to the default namespace (`""`).
+//! Quotas are configured through [`crate::memory::manager::MemoryConfig::quotas`] and enforced by [`crate::memory::manager::MemoryManager`] whenever a memory is stored or updated.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::memory::{MemoryEntry, MemoryId};
+
+/// The namespace of memories without an agent ID.
+pub const DEFAULT_NAMESPACE: &str = "";
+
+/// The namespace a memory belongs to (ie, its agent ID, or [`DEFAULT_NAMESPACE`] if it doesn't have one).
+pub fn namespace_of(entry: &MemoryEntry) -> &str {
+    entry.agent_id.as_deref().unwrap_or(DEFAULT_NAMESPACE)
+}
+
+/// The approximate size of a memory in bytes, as counted towards [`Quota::max_bytes`].
+/// This counts the text of the memory (ie, its content, source context, metadata and tags), but not its embedding.
+pub fn memory_size(entry: &MemoryEntry) -> usize {
+    entry.content.len()
+        + entry.source_context.len()
+        + entry
+            .metadata
+            .iter()
+            .map(|metadata| metadata.key().len() + metadata.value().len())
+            .sum::<usize>()
+        + entry.tags.iter().map(String::len).sum::<usize>()
+}
+
+/// Limits on the memories held by a namespace.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct Quota {
+    /// The maximum number of memories.
+    pub max_memories: Option<usize>,
+    /// The maximum total size of the memories (see [`memory_size`]).
+    pub max_bytes: Option<usize>,
+    /// What happens when storing a memory would exceed the quota.
+    pub on_exceeded: QuotaPolicy,
+}
+
+impl Quota {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_memories(mut self, max_memories: usize) -> Self {
+        self.max_memories = Some(max_memories);
+        self
+    }
+
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    pub fn on_exceeded(mut self, policy: QuotaPolicy) -> Self {
+        self.on_exceeded = policy;
+        self
+    }
+
+    /// Describes how the given usage exceeds the quota, if it does.
+    pub(crate) fn violation(&self, usage: &QuotaUsage) -> Option<String> {
+        if let Some(max) = self.max_memories
+            && usage.memories > max
+        {
+            return Some(format!("{} memories (max {max})", usage.memories));
+        }
+
+        if let Some(max) = self.max_bytes
+            && usage.bytes > max
+        {
+            return Some(format!("{} bytes (max {max})", usage.bytes));
+        }
+
+        None
+    }
+}
+
+/// What happens when storing a memory would exceed a [`Quota`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum QuotaPolicy {
+    /// Reject the memory with a [`crate::error::StorageError::QuotaExceeded`] error.
+    #[default]
+    Reject,
+    /// Delete the oldest memories in the namespace until the new memory fits.
+    /// Memories that are too big for the quota on their own are still rejected.
+    EvictOldest,
+}
+
+/// How much of its quota a namespace is using.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct QuotaUsage {
+    /// The number of memories in the namespace.
+    pub memories: usize,
+    /// The total size of the memories in the namespace (see [`memory_size`]).
+    pub bytes: usize,
+}
+
+/// What's tracked for each memory, so that usage can be updated when it's removed.
+#[derive(Clone)]
+struct TrackedMemory {
+    namespace: String,
+    bytes: usize,
+    created_at: i64,
+}
+
+/// Tracks quota usage per namespace as memories are stored and removed.
+#[derive(Clone, Default)]
+pub(crate) struct QuotaTracker {
+    usage: HashMap<String, QuotaUsage>,
+    memories: HashMap<MemoryId, TrackedMemory>,
+}
+
+impl QuotaTracker {
+    /// Starts tracking a memory, replacing any memory with the same ID.
+    pub(crate) fn track(&mut self, entry: &MemoryEntry) {
+        self.untrack(&entry.id);
+
+        let namespace = namespace_of(entry);
+        let bytes = memory_size(entry);
+        let usage = self.usage.entry(namespace.to_string()).or_default();
+        usage.memories += 1;
+        usage.bytes += bytes;
+
+        self.memories.insert(
+            entry.id.clone(),
+            TrackedMemory {
+                namespace: namespace.to_string(),
+                bytes,
+                created_at: entry.created_at,
+            },
+        );
+    }
+
+    /// Stops tracking a memory, if it's being tracked.
+    pub(crate) fn untrack(&mut self, id: &str) {
+        let Some(tracked) = self.memories.remove(id) else {
+            return;
+        };
+
+        if let Some(usage) = self.usage.get_mut(&tracked.namespace) {
+            usage.memories -= 1;
+            usage.bytes -= tracked.bytes;
+            if usage.memories == 0 {
+                self.usage.remove(&tracked.namespace);
+            }
+        }
+    }
+
+    pub(crate) fn usage(&self, namespace: &str) -> QuotaUsage {
+        self.usage.get(namespace).copied().unwrap_or_default()
+    }
+
+    pub(crate) fn all_usage(&self) -> &HashMap<String, QuotaUsage> {
+        &self.usage
+    }
+
+    /// The usage of a memory's namespace if the memory were stored (replacing any memory with the same ID).
+    pub(crate) fn usage_with(&self, entry: &MemoryEntry) -> QuotaUsage {
+        let namespace = namespace_of(entry);
+        let mut usage = self.usage(namespace);
+        if let Some(existing) = self.memories.get(&entry.id)
+            && existing.namespace == namespace
+        {
+            usage.memories -= 1;
+            usage.bytes -= existing.bytes;
+        }
+
+        usage.memories += 1;
+        usage.bytes += memory_size(entry);
+        usage
+    }
+
+    /// The oldest memory in a namespace, other than the given memory.
+    pub(crate) fn oldest_in(&self, namespace: &str, except: &MemoryId) -> Option<MemoryId> {
+        self.memories
+            .iter()
+            .filter(|(id, tracked)| tracked.namespace == namespace && *id != except)
+            .min_by_key(|(id, tracked)| (tracked.created_at, *id))
+            .map(|(id, _)| id.clone())
+    }
+}
@@ -0,0 +1,156 @@
+//! Reconciliation of newly extracted memories against existing memories (ie, deciding whether to add, update, merge or delete memories).
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    memory::MemoryEntry,
+    wasm::{WasmCompatSend, WasmCompatSync},
+};
+
+#[cfg(feature = "rig")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rig")))]
+pub use rig::create_rig_memory_reconciler;
+
+/// What to do with a newly extracted memory, given the existing memories that are similar to it.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(tag = "operation", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MemoryOperation {
+    /// The memory is new information, so it should be stored as-is.
+    Add,
+    /// The memory refines or corrects an existing memory, whose content should be replaced.
+    Update {
+        /// The ID of the existing memory.
+        id: String,
+        /// The updated content of the existing memory.
+        content: String,
+    },
+    /// The memory overlaps with several existing memories, which should be combined into one.
+    /// The first memory is updated with the merged content and the rest are deleted.
+    Merge {
+        /// The IDs of the existing memories.
+        ids: Vec<String>,
+        /// The content of the merged memory.
+        content: String,
+    },
+    /// The memory invalidates an existing memory (eg, the user no longer likes something), which should be deleted.
+    /// The new memory isn't stored.
+    Delete {
+        /// The ID of the existing memory.
+        id: String,
+    },
+    /// The memory is already known, so nothing should change.
+    None,
+}
+
+impl MemoryOperation {
+    /// The IDs of existing memories that this operation touches.
+    pub fn target_ids(&self) -> Vec<&str> {
+        match self {
+            Self::Update { id, .. } | Self::Delete { id } => vec![id],
+            Self::Merge { ids, .. } => ids.iter().map(String::as_str).collect(),
+            Self::Add | Self::None => Vec::new(),
+        }
+    }
+}
+
+/// A trait for deciding what should happen to a newly extracted memory, given similar existing memories.
+/// Used by [`crate::memory::manager::MemoryManager::store_reconciled`].
+pub trait MemoryReconciler: WasmCompatSend + WasmCompatSync {
+    fn reconcile(
+        &self,
+        memory: &MemoryEntry,
+        existing: &[MemoryEntry],
+    ) -> impl Future<Output = Result<MemoryOperation, crate::Error>> + WasmCompatSend;
+}
+
+/// Formats a new memory and its similar existing memories as the input to LLM-backed reconcilers.
+pub fn format_reconciliation(memory: &MemoryEntry, existing: &[MemoryEntry]) -> String {
+    let existing = existing
+        .iter()
+        .map(|memory| format!("- [{}] {}", memory.id, memory.content))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        "## Existing memories\n{existing}\n\n## New memory\n{}",
+        memory.content
+    )
+}
+
+#[cfg(feature = "rig")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rig")))]
+mod rig {
+    use super::{MemoryOperation, MemoryReconciler, format_reconciliation};
+    use crate::memory::{MemoryEntry, generation::parse_json_response};
+    use rig::agent::Agent;
+    use rig::client::{Capabilities, Client, CompletionClient, Provider};
+    use rig::completion::{CompletionModel, Prompt};
+
+    impl<M> MemoryReconciler for Agent<M>
+    where
+        M: CompletionModel,
+    {
+        async fn reconcile(
+            &self,
+            memory: &MemoryEntry,
+            existing: &[MemoryEntry],
+        ) -> Result<MemoryOperation, crate::Error> {
+            let schema =
+                serde_json::to_string(&schemars::schema_for!(MemoryOperation)).unwrap_or_default();
+            let prompt = format!(
+                "{}\n\nRespond with only a JSON object matching this JSON schema:\n{schema}",
+                format_reconciliation(memory, existing)
+            );
+
+            let response = self
+                .prompt(prompt)
+                .await
+                .map_err(|err| crate::Error::wrap("Completion request failed", err))?;
+
+            parse_json_response(&response)
+                .map_err(|err| crate::Error::wrap("Failed to parse memory operation", err))
+        }
+    }
+
+    /// Creates a [`rig::agent::Agent`] tailored to reconciling new memories against existing memories.
+    pub fn create_rig_memory_reconciler<Ext, HttpClient, Model>(
+        client: &Client<Ext, HttpClient>,
+        model_name: &str,
+    ) -> Agent<
+        <rig::client::Client<Ext, HttpClient> as rig::client::CompletionClient>::CompletionModel,
+    >
+    where
+        Ext:
+            Provider + Capabilities<HttpClient, Completion = rig::client::Capable<Model>> + 'static,
+        HttpClient: rig::http_client::HttpClientExt + 'static,
+        Model: rig::completion::CompletionModel,
+        Client<Ext, HttpClient>: CompletionClient,
+    {
+        client.agent(model_name).preamble(PREAMBLE).build()
+    }
+
+    const PREAMBLE: &str = r###"
+    You are a memory manager for an AI agent. You will be given a list of existing memories (each with an ID) and a newly extracted memory.
+    Decide what should happen to the new memory:
+
+    - ADD: The new memory contains information that isn't in any existing memory.
+    - UPDATE: The new memory refines, extends or corrects a single existing memory. Provide the ID of the existing memory and its full updated content.
+    - MERGE: The new memory overlaps with several existing memories that describe the same thing. Provide the IDs of those memories and the content of a single combined memory.
+    - DELETE: The new memory invalidates an existing memory (eg, "User no longer drinks coffee" invalidates "User drinks coffee every morning"). Provide the ID of the existing memory.
+    - NONE: The new memory is already fully covered by the existing memories.
+
+    Guidelines:
+    - Only use IDs from the list of existing memories.
+    - Prefer UPDATE over ADD when the new memory is about the same subject as an existing memory.
+    - Updated and merged content should be a single, self-contained statement in the same style as the existing memories.
+    - When the new memory contradicts an existing memory and contains information worth keeping, use UPDATE rather than DELETE.
+
+    Examples:
+    {"operation": "ADD"}
+    {"operation": "UPDATE", "id": "mem_123", "content": "User is a senior data scientist at a healthcare startup"}
+    {"operation": "MERGE", "ids": ["mem_123", "mem_456"], "content": "User prefers concise code examples without excessive comments"}
+    {"operation": "DELETE", "id": "mem_789"}
+    {"operation": "NONE"}
+    "###;
+}
@@ -0,0 +1,224 @@
+//! Redaction of sensitive data (eg, PII) from memories before they get embedded and stored.
+
+use crate::wasm::{WasmCompatSend, WasmCompatSync};
+
+/// A trait for scrubbing or masking sensitive data from text.
+/// Used by [`crate::memory::generation::MemoryGenerator::redactor`].
+///
+/// Closures taking a `&str` and returning a `String` can be used as redactors.
+pub trait Redactor: WasmCompatSend + WasmCompatSync {
+    fn redact(&self, input: &str) -> String;
+}
+
+impl<F> Redactor for F
+where
+    F: Fn(&str) -> String + WasmCompatSend + WasmCompatSync,
+{
+    fn redact(&self, input: &str) -> String {
+        self(input)
+    }
+}
+
+/// How redacted data gets replaced.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RedactionStyle {
+    /// Replaces data with a placeholder naming what was removed (eg, `[EMAIL]`).
+    #[default]
+    Scrub,
+    /// Replaces every alphanumeric character with `*` except the last 4 (eg, `************1111`), keeping some context about the data.
+    Mask,
+}
+
+/// A regex-based redactor for email addresses, phone numbers and credit card numbers.
+/// Credit card numbers are only redacted if they pass the Luhn checksum, and phone numbers need at least 7 digits split by separators, so other numbers (eg, order IDs and dates) are left alone.
+#[cfg(feature = "redact")]
+#[cfg_attr(docsrs, doc(cfg(feature = "redact")))]
+#[derive(Clone, Debug)]
+pub struct PiiRedactor {
+    style: RedactionStyle,
+    patterns: Vec<(PiiKind, regex::Regex)>,
+}
+
+/// The kinds of data redacted by [`PiiRedactor`].
+#[cfg(feature = "redact")]
+#[cfg_attr(docsrs, doc(cfg(feature = "redact")))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PiiKind {
+    Email,
+    Phone,
+    CreditCard,
+}
+
+#[cfg(feature = "redact")]
+impl PiiKind {
+    fn placeholder(&self) -> &'static str {
+        match self {
+            Self::Email => "[EMAIL]",
+            Self::Phone => "[PHONE]",
+            Self::CreditCard => "[CREDIT_CARD]",
+        }
+    }
+
+    fn pattern(&self) -> &'static str {
+        match self {
+            Self::Email => r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}",
+            // Checked before phone numbers, as card numbers would otherwise match as phone numbers
+            Self::CreditCard => r"\b\d(?:[ -]?\d){12,18}\b",
+            Self::Phone => {
+                r"(?:\+\d{1,3}[ .-]?)?(?:\(\d{1,4}\)[ .-]?)?\d{2,4}(?:[ .-]\d{3,4}){1,3}\b"
+            }
+        }
+    }
+}
+
+#[cfg(feature = "redact")]
+impl Default for PiiRedactor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "redact")]
+impl PiiRedactor {
+    /// Creates a redactor for every kind of PII, scrubbing it with placeholders.
+    pub fn new() -> Self {
+        Self::with_kinds([PiiKind::Email, PiiKind::CreditCard, PiiKind::Phone])
+    }
+
+    /// Creates a redactor for the given kinds of PII.
+    pub fn with_kinds<I>(kinds: I) -> Self
+    where
+        I: IntoIterator<Item = PiiKind>,
+    {
+        let mut kinds: Vec<PiiKind> = kinds.into_iter().collect();
+        kinds.sort_by_key(|kind| match kind {
+            PiiKind::Email => 0,
+            PiiKind::CreditCard => 1,
+            PiiKind::Phone => 2,
+        });
+        kinds.dedup();
+
+        let patterns = kinds
+            .into_iter()
+            .map(|kind| {
+                // SAFETY: The patterns are constant and known to be valid
+                let regex = regex::Regex::new(kind.pattern()).unwrap();
+                (kind, regex)
+            })
+            .collect();
+
+        Self {
+            style: RedactionStyle::default(),
+            patterns,
+        }
+    }
+
+    /// Sets how redacted data gets replaced. Defaults to [`RedactionStyle::Scrub`].
+    pub fn style(mut self, style: RedactionStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    fn replacement(&self, kind: PiiKind, matched: &str) -> String {
+        match self.style {
+            RedactionStyle::Scrub => kind.placeholder().to_string(),
+            RedactionStyle::Mask => mask(matched),
+        }
+    }
+}
+
+#[cfg(feature = "redact")]
+impl Redactor for PiiRedactor {
+    fn redact(&self, input: &str) -> String {
+        let mut output = input.to_string();
+
+        for (kind, regex) in &self.patterns {
+            output = regex
+                .replace_all(&output, |captures: &regex::Captures| {
+                    let matched = &captures[0];
+                    let is_false_positive = match kind {
+                        PiiKind::CreditCard => !passes_luhn(matched),
+                        PiiKind::Phone => matched.chars().filter(char::is_ascii_digit).count() < 7,
+                        PiiKind::Email => false,
+                    };
+                    if is_false_positive {
+                        return matched.to_string();
+                    }
+
+                    self.replacement(*kind, matched)
+                })
+                .into_owned();
+        }
+
+        output
+    }
+}
+
+/// Replaces every alphanumeric character except the last 4 with `*`, keeping separators.
+#[cfg(feature = "redact")]
+fn mask(input: &str) -> String {
+    let total = input.chars().filter(|c| c.is_alphanumeric()).count();
+    let mut seen = 0;
+
+    input
+        .chars()
+        .map(|c| {
+            if !c.is_alphanumeric() {
+                return c;
+            }
+
+            seen += 1;
+            if seen + 4 > total { c } else { '*' }
+        })
+        .collect()
+}
+
+/// Whether the digits in a string pass the Luhn checksum used by credit card numbers.
+#[cfg(feature = "redact")]
+fn passes_luhn(input: &str) -> bool {
+    let digits: Vec<u32> = input.chars().filter_map(|c| c.to_digit(10)).collect();
+
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(idx, &digit)| {
+            if idx % 2 == 1 {
+                let doubled = digit * 2;
+                if doubled > 9 { doubled - 9 } else { doubled }
+            } else {
+                digit
+            }
+        })
+        .sum();
+
+    sum.is_multiple_of(10)
+}
+
+#[cfg(all(test, feature = "redact"))]
+mod tests {
+    use super::{PiiRedactor, RedactionStyle, Redactor};
+
+    #[test]
+    fn pii_is_redacted() {
+        let redactor = PiiRedactor::new();
+
+        assert_eq!(
+            redactor.redact(
+                "Email jane.doe@example.com or call +1 555-123-4567, card 4111 1111 1111 1111"
+            ),
+            "Email [EMAIL] or call [PHONE], card [CREDIT_CARD]"
+        );
+        // Fails the Luhn checksum, so it's not a card number
+        assert_eq!(
+            redactor.redact("Order 1234567890123 on 2024-10-16"),
+            "Order 1234567890123 on 2024-10-16"
+        );
+
+        let redactor = PiiRedactor::new().style(RedactionStyle::Mask);
+        assert_eq!(
+            redactor.redact("card 4111-1111-1111-1111"),
+            "card ****-****-****-1111"
+        );
+    }
+}
@@ -0,0 +1,114 @@
+//! Statistics about the memories held by a [`super::manager::MemoryManager`], for understanding what an agent knows (eg, on a dashboard).
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::memory::{MemoryEntry, MemoryKind};
+
+/// The number of buckets in an [`ImportanceDistribution`].
+const IMPORTANCE_BUCKETS: usize = 10;
+
+/// Statistics about stored memories, returned by [`super::manager::MemoryManager::stats`].
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct MemoryStats {
+    /// The number of memories (including writes buffered by a write-back hot cache).
+    pub total: usize,
+    /// The number of memories of each kind.
+    pub by_kind: HashMap<MemoryKind, usize>,
+    pub importance: ImportanceDistribution,
+    /// How long ago memories were created.
+    pub age: AgePercentiles,
+    /// The average number of times a memory has been accessed.
+    pub average_access_count: f64,
+    /// The hit ratio of the hot cache (between 0.0 and 1.0), if there is one.
+    pub cache_hit_ratio: Option<f64>,
+    /// The number of memories held in the backing storage (ie, excluding buffered writes).
+    pub storage_size: usize,
+}
+
+/// The distribution of memory importance.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ImportanceDistribution {
+    /// The number of memories in each tenth of the importance range (ie, the first bucket counts memories with an importance between 0.0 and 0.1).
+    /// An importance of exactly 1.0 falls into the last bucket.
+    pub buckets: [usize; IMPORTANCE_BUCKETS],
+    /// The average importance.
+    pub mean: f32,
+}
+
+/// Percentiles of memory ages (in seconds).
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct AgePercentiles {
+    pub p50: i64,
+    pub p90: i64,
+    pub p99: i64,
+    /// The age of the oldest memory.
+    pub max: i64,
+}
+
+/// Accumulates [`MemoryStats`] over memories, one batch at a time.
+pub(crate) struct MemoryStatsCollector {
+    now: i64,
+    stats: MemoryStats,
+    importance_sum: f64,
+    access_count_sum: u64,
+    ages: Vec<i64>,
+}
+
+impl MemoryStatsCollector {
+    pub(crate) fn new(now: i64) -> Self {
+        Self {
+            now,
+            stats: MemoryStats::default(),
+            importance_sum: 0.0,
+            access_count_sum: 0,
+            ages: Vec::new(),
+        }
+    }
+
+    pub(crate) fn add(&mut self, entry: &MemoryEntry) {
+        self.stats.total += 1;
+        *self.stats.by_kind.entry(entry.kind).or_default() += 1;
+
+        let importance = entry.importance.clamp(0.0, 1.0);
+        let bucket =
+            ((importance * IMPORTANCE_BUCKETS as f32) as usize).min(IMPORTANCE_BUCKETS - 1);
+        self.stats.importance.buckets[bucket] += 1;
+        self.importance_sum += importance as f64;
+
+        self.access_count_sum += entry.access_count as u64;
+        self.ages.push((self.now - entry.created_at).max(0));
+    }
+
+    pub(crate) fn finish(
+        mut self,
+        storage_size: usize,
+        cache_hit_ratio: Option<f64>,
+    ) -> MemoryStats {
+        let total = self.stats.total;
+        if total > 0 {
+            self.stats.importance.mean = (self.importance_sum / total as f64) as f32;
+            self.stats.average_access_count = self.access_count_sum as f64 / total as f64;
+
+            self.ages.sort_unstable();
+            self.stats.age = AgePercentiles {
+                p50: percentile(&self.ages, 0.5),
+                p90: percentile(&self.ages, 0.9),
+                p99: percentile(&self.ages, 0.99),
+                max: self.ages[total - 1],
+            };
+        }
+
+        self.stats.storage_size = storage_size;
+        self.stats.cache_hit_ratio = cache_hit_ratio;
+        self.stats
+    }
+}
+
+/// The nearest-rank percentile of a sorted, non-empty list.
+fn percentile(sorted: &[i64], p: f64) -> i64 {
+    let rank = (p * sorted.len() as f64).ceil() as usize;
+
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
@@ -0,0 +1,233 @@
+//! Incremental memory generation from a live conversation.
+
+use std::collections::VecDeque;
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::{
+    id_gen::IdGenerationStrategy,
+    memory::{
+        MemoryDraft, MemoryEntry, MemoryEntryFields,
+        conversation::ConversationTurn,
+        generation::{MemoryGeneration, MemoryGenerator},
+        importance::{ImportanceRaterNotSet, RateImportance},
+    },
+};
+
+/// Generates memories from a conversation as it happens, rather than from the whole history at the end of a session.
+///
+/// Turns are fed in with [`MemoryStream::push`] and buffered until the debounce is reached (a number of turns and/or an amount of time since the first buffered turn),
+/// at which point the buffered turns are sent to the memory generator. The turns before them are included as context so that references to earlier turns can still be resolved,
+/// but the generator is told to only extract memories from the new turns.
+pub struct MemoryStream<IdGen, T, R = ImportanceRaterNotSet, D = MemoryDraft>
+where
+    T: MemoryGeneration<D>,
+{
+    generator: MemoryGenerator<IdGen, T, R, D>,
+    pending: Vec<ConversationTurn>,
+    pending_since: Option<DateTime<Utc>>,
+    context: VecDeque<ConversationTurn>,
+    context_turns: usize,
+    debounce_turns: usize,
+    debounce_interval: Option<TimeDelta>,
+}
+
+impl<IdGen, T, R, D> MemoryStream<IdGen, T, R, D>
+where
+    IdGen: IdGenerationStrategy,
+    T: MemoryGeneration<D>,
+    R: RateImportance,
+    D: Into<MemoryEntryFields>,
+{
+    /// Creates a stream that generates memories after every 4 turns, with the 4 previous turns as context.
+    pub fn new(generator: MemoryGenerator<IdGen, T, R, D>) -> Self {
+        Self {
+            generator,
+            pending: Vec::new(),
+            pending_since: None,
+            context: VecDeque::new(),
+            context_turns: 4,
+            debounce_turns: 4,
+            debounce_interval: None,
+        }
+    }
+
+    /// Sets how many turns are buffered before memories are generated. Values below 1 are treated as 1.
+    pub fn debounce_turns(mut self, turns: usize) -> Self {
+        self.debounce_turns = turns.max(1);
+        self
+    }
+
+    /// Generates memories once the oldest buffered turn is older than the given interval, even if fewer turns than the debounce have been buffered.
+    /// The interval is only checked when a turn is pushed.
+    pub fn debounce_interval(mut self, interval: std::time::Duration) -> Self {
+        self.debounce_interval = TimeDelta::from_std(interval).ok();
+        self
+    }
+
+    /// Sets how many already-processed turns are included as context when generating memories.
+    pub fn context_turns(mut self, turns: usize) -> Self {
+        self.context_turns = turns;
+        self
+    }
+
+    /// Get a reference to the underlying memory generator.
+    pub fn generator(&self) -> &MemoryGenerator<IdGen, T, R, D> {
+        &self.generator
+    }
+
+    /// Consumes the stream, returning the underlying memory generator. Buffered turns are discarded.
+    pub fn into_generator(self) -> MemoryGenerator<IdGen, T, R, D> {
+        self.generator
+    }
+
+    /// The number of turns waiting to be processed.
+    pub fn pending_turns(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Adds a conversation turn to the stream. Returns any memories generated as a result (ie, when the debounce has been reached).
+    pub async fn push<Turn>(&mut self, turn: Turn) -> Result<Vec<MemoryEntry>, crate::Error>
+    where
+        Turn: Into<ConversationTurn>,
+    {
+        let turn = turn.into();
+        let now = Utc::now();
+
+        self.pending.push(turn);
+        let pending_since = *self.pending_since.get_or_insert(now);
+
+        let interval_elapsed = self
+            .debounce_interval
+            .is_some_and(|interval| now - pending_since >= interval);
+
+        if self.pending.len() >= self.debounce_turns || interval_elapsed {
+            self.flush().await
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    /// Generates memories from any buffered turns, regardless of the debounce (eg, at the end of a session).
+    /// If generation fails, the turns are kept buffered so that they can be retried by the next flush.
+    pub async fn flush(&mut self) -> Result<Vec<MemoryEntry>, crate::Error> {
+        if self.pending.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let input = self.format_input(&self.pending);
+        let entries = self
+            .generator
+            .generate_from_input(input, &self.pending)
+            .await?;
+
+        let turns = std::mem::take(&mut self.pending);
+        self.pending_since = None;
+
+        self.context.extend(turns);
+        while self.context.len() > self.context_turns {
+            self.context.pop_front();
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            context_turns = self.context.len(),
+            "generated memories from buffered turns"
+        );
+
+        Ok(entries)
+    }
+
+    fn format_input(&self, turns: &[ConversationTurn]) -> String {
+        let mut input = String::new();
+
+        if !self.context.is_empty() {
+            input.push_str("## Earlier turns (context only, memories have already been extracted from these)\n");
+            for turn in &self.context {
+                input.push_str(&turn.to_string());
+                input.push('\n');
+            }
+            input.push('\n');
+        }
+
+        input.push_str("## New turns (extract memories from these)\n");
+        for turn in turns {
+            input.push_str(&turn.to_string());
+            input.push('\n');
+        }
+
+        input
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::MemoryStream;
+    use crate::memory::{
+        Confidence, MemoryDraft, MemoryKind,
+        conversation::ConversationTurn,
+        generation::{MemoryGeneration, MemoryGenerator},
+    };
+
+    #[derive(Clone, Default)]
+    struct RecordingGenerator(Arc<Mutex<Vec<String>>>);
+
+    impl MemoryGeneration for RecordingGenerator {
+        async fn generate(&self, input: &str) -> Result<Vec<MemoryDraft>, crate::Error> {
+            self.0.lock().unwrap().push(input.to_string());
+
+            Ok(vec![MemoryDraft {
+                content: "generated".to_string(),
+                kind: MemoryKind::Semantic,
+                source_context: "test".to_string(),
+                importance: Some(0.5),
+                confidence: Confidence::High,
+                metadata: Vec::new(),
+                topics: Vec::new(),
+                entities: Vec::new(),
+            }])
+        }
+    }
+
+    #[tokio::test]
+    async fn turns_are_debounced_with_earlier_context() {
+        let recorder = RecordingGenerator::default();
+        let mut stream = MemoryStream::new(MemoryGenerator::new(recorder.clone()))
+            .debounce_turns(2)
+            .context_turns(1);
+
+        assert!(
+            stream
+                .push(ConversationTurn::user("first"))
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            stream
+                .push(ConversationTurn::user("second"))
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(
+            stream
+                .push(ConversationTurn::user("third"))
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(stream.flush().await.unwrap().len(), 1);
+        assert!(stream.flush().await.unwrap().is_empty());
+
+        let inputs = recorder.0.lock().unwrap();
+        assert_eq!(inputs.len(), 2);
+        assert!(!inputs[0].contains("Earlier turns"));
+        assert!(inputs[1].contains("User: second\n\n## New turns"));
+        assert!(!inputs[1].contains("first"));
+        assert!(inputs[1].ends_with("User: third\n"));
+    }
+}
@@ -0,0 +1,73 @@
+//! A spaced-repetition model of memory strength, loosely based on SM-2.
+//!
+//! Every memory has a strength (see [`super::MemoryEntry::strength`]): roughly, how many days it takes for the memory to fade since it was last accessed.
+//! Each access reinforces the memory, and accesses that are spaced out reinforce it more than accesses in quick succession.
+//! Memories that keep being useful grow strong enough to become effectively permanent, while memories that are only used once fade away.
+
+/// The strength of a newly stored memory (in days).
+pub const INITIAL_STRENGTH: f32 = 1.0;
+
+/// Memories at least this strong (in days) are considered permanent, and are never evicted or digested in favor of weaker memories.
+pub const PERMANENT_STRENGTH: f32 = 365.0;
+
+/// How much a memory's strength is multiplied by when it's accessed once it has (almost) faded, like the ease factor in SM-2.
+const EASE: f32 = 2.5;
+
+/// The maximum strength of a memory (in days), to keep strengths finite.
+const MAX_STRENGTH: f32 = 36_500.0;
+
+const SECONDS_PER_DAY: f32 = 24.0 * 60.0 * 60.0;
+
+/// Used as the serde default for [`super::MemoryEntry::strength`].
+pub(crate) fn initial_strength() -> f32 {
+    INITIAL_STRENGTH
+}
+
+/// The new strength of a memory after it gets accessed, given how many seconds it's been since it was last accessed.
+/// Accesses right after the previous one barely reinforce the memory, while accesses a full strength interval (or more) later multiply its strength by the ease factor.
+pub fn reinforce(strength: f32, elapsed_secs: i64) -> f32 {
+    let strength = strength.max(INITIAL_STRENGTH);
+    let elapsed_days = elapsed_secs.max(0) as f32 / SECONDS_PER_DAY;
+    let spacing = (elapsed_days / strength).min(1.0);
+
+    (strength * (1.0 + (EASE - 1.0) * spacing)).min(MAX_STRENGTH)
+}
+
+/// How likely a memory is to still be retained (between 0.0 and 1.0), given its strength and how many seconds it's been since it was last accessed.
+/// This follows an exponential forgetting curve, and is always 1.0 for permanent memories.
+pub fn retention(strength: f32, elapsed_secs: i64) -> f32 {
+    if strength >= PERMANENT_STRENGTH {
+        return 1.0;
+    }
+
+    let elapsed_days = elapsed_secs.max(0) as f32 / SECONDS_PER_DAY;
+
+    (-elapsed_days / strength.max(f32::EPSILON)).exp()
+}
+
+/// Whether or not a memory is strong enough to be considered permanent.
+pub fn is_permanent(strength: f32) -> bool {
+    strength >= PERMANENT_STRENGTH
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{INITIAL_STRENGTH, PERMANENT_STRENGTH, reinforce, retention};
+
+    const DAY: i64 = 24 * 60 * 60;
+
+    #[test]
+    fn spaced_accesses_reinforce_more_than_crammed_ones() {
+        let crammed = (0..10).fold(INITIAL_STRENGTH, |strength, _| reinforce(strength, 60));
+        assert!(crammed < 1.1);
+
+        let mut strength = INITIAL_STRENGTH;
+        for _ in 0..8 {
+            strength = reinforce(strength, (strength * DAY as f32) as i64);
+        }
+        assert!(strength >= PERMANENT_STRENGTH);
+        assert_eq!(retention(strength, 1000 * DAY), 1.0);
+
+        assert!(retention(INITIAL_STRENGTH, 7 * DAY) < 0.01);
+    }
+}
@@ -0,0 +1,92 @@
+//! Memory summarization (ie, condensing multiple memories into a single piece of context).
+
+use crate::{
+    memory::MemoryEntry,
+    storage::SearchResult,
+    wasm::{WasmCompatSend, WasmCompatSync},
+};
+
+#[cfg(feature = "rig")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rig")))]
+pub use rig::create_rig_memory_summarizer;
+
+/// A trait for condensing a list of memories into a single context string.
+/// This is useful for when the downstream prompt budget is too small to include every retrieved memory verbatim.
+pub trait MemorySummarizer: WasmCompatSend + WasmCompatSync {
+    fn summarize(
+        &self,
+        memories: &[MemoryEntry],
+    ) -> impl Future<Output = Result<String, crate::Error>> + WasmCompatSend;
+}
+
+/// The result of a summarized retrieval: a condensed context string along with the raw retrieved memories.
+#[derive(Clone, Debug)]
+pub struct SummarizedRetrieval {
+    /// The condensed context. Empty if no memories were retrieved.
+    pub summary: String,
+    /// The raw retrieved memories.
+    pub results: Vec<SearchResult>,
+}
+
+/// Formats memories as a bulleted list, one memory per line. Used as the input to LLM-backed summarizers.
+pub fn format_memories(memories: &[MemoryEntry]) -> String {
+    memories
+        .iter()
+        .map(|memory| {
+            format!(
+                "- [{kind:?}] {content} (context: {context})",
+                kind = memory.kind,
+                content = memory.content,
+                context = memory.source_context
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(feature = "rig")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rig")))]
+mod rig {
+    use super::{MemorySummarizer, format_memories};
+    use crate::memory::MemoryEntry;
+    use rig::agent::Agent;
+    use rig::client::{Capabilities, Client, CompletionClient, Provider};
+    use rig::completion::{CompletionModel, Prompt};
+
+    impl<M> MemorySummarizer for Agent<M>
+    where
+        M: CompletionModel,
+    {
+        async fn summarize(&self, memories: &[MemoryEntry]) -> Result<String, crate::Error> {
+            let res = self
+                .prompt(format_memories(memories))
+                .await
+                .map_err(|err| crate::Error::wrap("Completion request failed", err))?;
+
+            Ok(res)
+        }
+    }
+
+    /// Creates a [`rig::agent::Agent`] tailored to condensing memories into a short piece of context.
+    pub fn create_rig_memory_summarizer<Ext, HttpClient, Model>(
+        client: &Client<Ext, HttpClient>,
+        model_name: &str,
+    ) -> Agent<
+        <rig::client::Client<Ext, HttpClient> as rig::client::CompletionClient>::CompletionModel,
+    >
+    where
+        Ext:
+            Provider + Capabilities<HttpClient, Completion = rig::client::Capable<Model>> + 'static,
+        HttpClient: rig::http_client::HttpClientExt + 'static,
+        Model: rig::completion::CompletionModel,
+        Client<Ext, HttpClient>: CompletionClient,
+    {
+        client.agent(model_name).preamble(PREAMBLE).build()
+    }
+
+    const PREAMBLE: &str = r###"You are part of a memory system for an AI assistant. You will be given a list of memories about a user, one per line.
+
+Condense them into a single short paragraph of context that another AI assistant can use to personalise its responses. Keep every fact that matters, merge duplicates, prefer more specific and more recent information when memories conflict, and leave out anything trivial.
+
+Respond with the condensed context only."###;
+}
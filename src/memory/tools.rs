@@ -0,0 +1,554 @@
+//! Memory tools (remember, recall and forget), so that an agent can decide for itself when to store or retrieve memories during a conversation
+//! instead of memories only being extracted out-of-band.
+//!
+//! Each tool wraps a [`SharedMemoryManager`], so the same memory manager can be used by several tools (and outside of them) at once.
+//! With the `rig` feature, the tools implement `rig::tool::Tool` and can be added straight to a `rig` agent:
+//!
+//! ```rust,ignore
+//! let manager = Arc::new(futures::lock::Mutex::new(manager));
+//! let agent = client
+//!     .agent("gpt-5")
+//!     .tool(RememberTool::new(manager.clone(), UuidV4Generator))
+//!     .tool(RecallTool::new(manager.clone()))
+//!     .tool(ForgetTool::new(manager))
+//!     .build();
+//! ```
+//!
+//! Without `rig`, [`openai_tool_definitions`] exports the tools in the OpenAI function-calling format (which most LLM APIs accept),
+//! and [`MemoryToolDispatcher`] executes the tool calls the model makes.
+
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    embed::Embedder,
+    id_gen::IdGenerationStrategy,
+    memory::{
+        Confidence, MemoryEntry, MemoryId, MemoryKind, MetadataEntry, TOPIC_METADATA_KEY,
+        Visibility,
+    },
+    storage::Storage,
+    wasm::WasmCompatSend,
+};
+
+pub use crate::memory::manager::SharedMemoryManager;
+
+/// The name of [`RememberTool`].
+pub const REMEMBER_TOOL_NAME: &str = "remember";
+/// The name of [`RecallTool`].
+pub const RECALL_TOOL_NAME: &str = "recall";
+/// The name of [`ForgetTool`].
+pub const FORGET_TOOL_NAME: &str = "forget";
+
+const REMEMBER_TOOL_DESCRIPTION: &str = "Stores a memory for later conversations. Use this for facts, preferences and events worth remembering.";
+const RECALL_TOOL_DESCRIPTION: &str =
+    "Searches stored memories, returning the memories most relevant to a query.";
+const FORGET_TOOL_DESCRIPTION: &str = "Deletes a stored memory by ID. Use this when a memory is wrong or the user asks for it to be forgotten.";
+
+/// The default number of memories returned by [`RecallTool`], if the agent doesn't ask for a specific number.
+const DEFAULT_RECALL_LIMIT: usize = 5;
+
+/// The source context given to memories stored using [`RememberTool`].
+const REMEMBER_SOURCE_CONTEXT: &str = "Stored by the agent using the remember tool";
+
+/// The arguments for [`RememberTool`].
+#[derive(Clone, Debug, Deserialize, Serialize, schemars::JsonSchema)]
+pub struct RememberArgs {
+    /// The memory to store, written as a short, self-contained statement (eg, "The user's favourite language is Rust").
+    pub content: String,
+    /// The type of memory. Defaults to `Semantic` (a fact).
+    #[serde(default = "default_kind")]
+    pub kind: MemoryKind,
+    /// How important the memory is, between 0.0 and 1.0. Defaults to 0.5.
+    #[serde(default)]
+    pub importance: Option<f32>,
+    /// Short, lowercase topics the memory is about (eg, "programming").
+    #[serde(default)]
+    pub topics: Vec<String>,
+}
+
+fn default_kind() -> MemoryKind {
+    MemoryKind::Semantic
+}
+
+/// The output of [`RememberTool`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RememberOutput {
+    /// The ID of the stored memory.
+    pub id: String,
+}
+
+/// The arguments for [`RecallTool`].
+#[derive(Clone, Debug, Deserialize, Serialize, schemars::JsonSchema)]
+pub struct RecallArgs {
+    /// What to search memories for.
+    pub query: String,
+    /// The maximum number of memories to return. Defaults to 5.
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// A memory returned by [`RecallTool`]. This only contains the parts of a memory that are useful to an agent.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RecalledMemory {
+    pub id: String,
+    pub content: String,
+    pub kind: MemoryKind,
+    /// Whenever the memory was created (as a Unix timestamp).
+    pub created_at: i64,
+}
+
+impl From<MemoryEntry> for RecalledMemory {
+    fn from(entry: MemoryEntry) -> Self {
+        Self {
+            id: entry.id.into(),
+            content: entry.content,
+            kind: entry.kind,
+            created_at: entry.created_at,
+        }
+    }
+}
+
+/// The arguments for [`ForgetTool`].
+#[derive(Clone, Debug, Deserialize, Serialize, schemars::JsonSchema)]
+pub struct ForgetArgs {
+    /// The ID of the memory to forget (as returned by the recall tool).
+    pub id: String,
+}
+
+/// The output of [`ForgetTool`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ForgetOutput {
+    /// The ID of the forgotten memory.
+    pub id: String,
+}
+
+/// A tool for storing a memory. New memories are given an ID using an ID generation strategy.
+pub struct RememberTool<E, S, IdGen>
+where
+    E: Embedder,
+    S: Storage,
+{
+    manager: SharedMemoryManager<E, S>,
+    id_generator: Mutex<IdGen>,
+}
+
+impl<E, S, IdGen> RememberTool<E, S, IdGen>
+where
+    E: Embedder,
+    S: Storage,
+    IdGen: IdGenerationStrategy + WasmCompatSend,
+{
+    pub fn new(manager: SharedMemoryManager<E, S>, id_generator: IdGen) -> Self {
+        Self {
+            manager,
+            id_generator: Mutex::new(id_generator),
+        }
+    }
+
+    /// Stores a memory, returning its ID.
+    pub async fn remember(&self, args: RememberArgs) -> Result<RememberOutput, crate::Error> {
+        let id = self
+            .id_generator
+            .lock()
+            .map_err(|_| crate::Error::custom("ID generator mutex was poisoned"))?
+            .generate_id();
+        let now = chrono::Utc::now().timestamp();
+        let metadata = args
+            .topics
+            .iter()
+            .map(|topic| topic.trim().to_lowercase())
+            .filter(|topic| !topic.is_empty())
+            .map(|topic| MetadataEntry::new(TOPIC_METADATA_KEY, topic))
+            .collect();
+
+        let entry = MemoryEntry {
+            id: MemoryId::from(&id),
+            content: args.content.clone(),
+            kind: args.kind,
+            importance: args.importance.unwrap_or(0.5).clamp(0.0, 1.0),
+            created_at: now,
+            last_accessed: now,
+            access_count: 0,
+            source_context: REMEMBER_SOURCE_CONTEXT.to_string(),
+            confidence: Confidence::High,
+            metadata,
+            agent_id: None,
+            visibility: Visibility::default(),
+            embedding_model: None,
+            image: None,
+            tags: Vec::new(),
+            strength: crate::memory::strength::INITIAL_STRENGTH,
+        };
+        self.manager.lock().await.store(args.content, entry).await?;
+
+        Ok(RememberOutput { id })
+    }
+}
+
+/// A tool for retrieving the memories most relevant to a query.
+pub struct RecallTool<E, S>
+where
+    E: Embedder,
+    S: Storage,
+{
+    manager: SharedMemoryManager<E, S>,
+}
+
+impl<E, S> RecallTool<E, S>
+where
+    E: Embedder,
+    S: Storage,
+{
+    pub fn new(manager: SharedMemoryManager<E, S>) -> Self {
+        Self { manager }
+    }
+
+    /// Retrieves the memories most relevant to a query.
+    pub async fn recall(&self, args: RecallArgs) -> Result<Vec<RecalledMemory>, crate::Error> {
+        let limit = args.limit.unwrap_or(DEFAULT_RECALL_LIMIT);
+        let results = self
+            .manager
+            .lock()
+            .await
+            .retrieve(args.query, limit)
+            .await?;
+
+        Ok(results
+            .iter()
+            .map(|result| result.data_owned().into())
+            .collect())
+    }
+}
+
+/// A tool for deleting a memory (eg, when the user asks for something to be forgotten, or a memory turns out to be wrong).
+pub struct ForgetTool<E, S>
+where
+    E: Embedder,
+    S: Storage,
+{
+    manager: SharedMemoryManager<E, S>,
+}
+
+impl<E, S> ForgetTool<E, S>
+where
+    E: Embedder,
+    S: Storage,
+{
+    pub fn new(manager: SharedMemoryManager<E, S>) -> Self {
+        Self { manager }
+    }
+
+    /// Deletes a memory.
+    pub async fn forget(&self, args: ForgetArgs) -> Result<ForgetOutput, crate::Error> {
+        self.manager.lock().await.delete(&args.id).await?;
+
+        Ok(ForgetOutput { id: args.id })
+    }
+}
+
+/// The name, description and JSON schema (generated from the argument types) of a memory tool.
+#[derive(Clone, Debug, Serialize)]
+pub struct MemoryToolSchema {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// A JSON schema for the tool's arguments.
+    pub parameters: serde_json::Value,
+}
+
+impl MemoryToolSchema {
+    pub fn remember() -> Self {
+        Self::new::<RememberArgs>(REMEMBER_TOOL_NAME, REMEMBER_TOOL_DESCRIPTION)
+    }
+
+    pub fn recall() -> Self {
+        Self::new::<RecallArgs>(RECALL_TOOL_NAME, RECALL_TOOL_DESCRIPTION)
+    }
+
+    pub fn forget() -> Self {
+        Self::new::<ForgetArgs>(FORGET_TOOL_NAME, FORGET_TOOL_DESCRIPTION)
+    }
+
+    /// The schemas of every memory tool.
+    pub fn all() -> Vec<Self> {
+        vec![Self::remember(), Self::recall(), Self::forget()]
+    }
+
+    fn new<T: schemars::JsonSchema>(name: &'static str, description: &'static str) -> Self {
+        let mut parameters = schemars::schema_for!(T).to_value();
+        // Function-calling APIs expect a bare schema object
+        if let Some(object) = parameters.as_object_mut() {
+            object.remove("$schema");
+            object.remove("title");
+        }
+
+        Self {
+            name,
+            description,
+            parameters,
+        }
+    }
+
+    /// The tool definition in the OpenAI function-calling format (`{"type": "function", "function": {...}}`).
+    pub fn to_openai_tool(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "function",
+            "function": {
+                "name": self.name,
+                "description": self.description,
+                "parameters": self.parameters,
+            }
+        })
+    }
+}
+
+/// The definitions of every memory tool in the OpenAI function-calling format, ready to be sent as the `tools` of a chat completion request.
+pub fn openai_tool_definitions() -> Vec<serde_json::Value> {
+    MemoryToolSchema::all()
+        .iter()
+        .map(MemoryToolSchema::to_openai_tool)
+        .collect()
+}
+
+/// Executes memory tool calls made by an LLM (eg, from the `tool_calls` of an OpenAI chat completion) against a memory manager.
+pub struct MemoryToolDispatcher<E, S, IdGen>
+where
+    E: Embedder,
+    S: Storage,
+{
+    remember: RememberTool<E, S, IdGen>,
+    recall: RecallTool<E, S>,
+    forget: ForgetTool<E, S>,
+}
+
+impl<E, S, IdGen> MemoryToolDispatcher<E, S, IdGen>
+where
+    E: Embedder,
+    S: Storage,
+    IdGen: IdGenerationStrategy + WasmCompatSend,
+{
+    pub fn new(manager: SharedMemoryManager<E, S>, id_generator: IdGen) -> Self {
+        Self {
+            remember: RememberTool::new(manager.clone(), id_generator),
+            recall: RecallTool::new(manager.clone()),
+            forget: ForgetTool::new(manager),
+        }
+    }
+
+    /// Whether or not a tool is one of the memory tools, so that other tool calls can be routed elsewhere.
+    pub fn handles(&self, name: &str) -> bool {
+        [REMEMBER_TOOL_NAME, RECALL_TOOL_NAME, FORGET_TOOL_NAME].contains(&name)
+    }
+
+    /// Executes a tool call, given the name of the tool and its arguments (as a JSON string), returning the output of the tool as JSON.
+    /// Unknown tools and invalid arguments return [`crate::Error::InvalidOutput`], as they're a mistake made by the model.
+    pub async fn dispatch(
+        &self,
+        name: &str,
+        arguments: &str,
+    ) -> Result<serde_json::Value, crate::Error> {
+        match name {
+            REMEMBER_TOOL_NAME => to_json(self.remember.remember(parse_args(arguments)?).await?),
+            RECALL_TOOL_NAME => to_json(self.recall.recall(parse_args(arguments)?).await?),
+            FORGET_TOOL_NAME => to_json(self.forget.forget(parse_args(arguments)?).await?),
+            _ => Err(crate::Error::InvalidOutput(format!(
+                "Unknown memory tool `{name}`"
+            ))),
+        }
+    }
+}
+
+fn parse_args<T>(arguments: &str) -> Result<T, crate::Error>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    serde_json::from_str(arguments)
+        .map_err(|err| crate::Error::InvalidOutput(format!("Invalid tool arguments: {err}")))
+}
+
+fn to_json<T: Serialize>(output: T) -> Result<serde_json::Value, crate::Error> {
+    serde_json::to_value(output)
+        .map_err(|err| crate::Error::wrap("Failed to serialize tool output", err))
+}
+
+#[cfg(feature = "rig")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rig")))]
+mod rig {
+    use super::{
+        FORGET_TOOL_NAME, ForgetArgs, ForgetOutput, ForgetTool, MemoryToolSchema, RECALL_TOOL_NAME,
+        REMEMBER_TOOL_NAME, RecallArgs, RecallTool, RecalledMemory, RememberArgs, RememberOutput,
+        RememberTool,
+    };
+    use crate::{
+        embed::Embedder, id_gen::IdGenerationStrategy, storage::Storage, wasm::WasmCompatSend,
+    };
+    use rig::{completion::ToolDefinition, tool::Tool};
+
+    impl From<MemoryToolSchema> for ToolDefinition {
+        fn from(schema: MemoryToolSchema) -> Self {
+            Self {
+                name: schema.name.to_string(),
+                description: schema.description.to_string(),
+                parameters: schema.parameters,
+            }
+        }
+    }
+
+    impl<E, S, IdGen> Tool for RememberTool<E, S, IdGen>
+    where
+        E: Embedder + 'static,
+        S: Storage + 'static,
+        IdGen: IdGenerationStrategy + WasmCompatSend,
+    {
+        const NAME: &'static str = REMEMBER_TOOL_NAME;
+
+        type Error = crate::Error;
+        type Args = RememberArgs;
+        type Output = RememberOutput;
+
+        async fn definition(&self, _prompt: String) -> ToolDefinition {
+            MemoryToolSchema::remember().into()
+        }
+
+        async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+            self.remember(args).await
+        }
+    }
+
+    impl<E, S> Tool for RecallTool<E, S>
+    where
+        E: Embedder + 'static,
+        S: Storage + 'static,
+    {
+        const NAME: &'static str = RECALL_TOOL_NAME;
+
+        type Error = crate::Error;
+        type Args = RecallArgs;
+        type Output = Vec<RecalledMemory>;
+
+        async fn definition(&self, _prompt: String) -> ToolDefinition {
+            MemoryToolSchema::recall().into()
+        }
+
+        async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+            self.recall(args).await
+        }
+    }
+
+    impl<E, S> Tool for ForgetTool<E, S>
+    where
+        E: Embedder + 'static,
+        S: Storage + 'static,
+    {
+        const NAME: &'static str = FORGET_TOOL_NAME;
+
+        type Error = crate::Error;
+        type Args = ForgetArgs;
+        type Output = ForgetOutput;
+
+        async fn definition(&self, _prompt: String) -> ToolDefinition {
+            MemoryToolSchema::forget().into()
+        }
+
+        async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+            self.forget(args).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{
+        ForgetArgs, ForgetTool, MemoryToolDispatcher, RecallArgs, RecallTool, RememberArgs,
+        RememberTool, openai_tool_definitions,
+    };
+    use crate::{
+        id_gen::MemoryIdGenerator,
+        memory::{MemoryKind, manager::MemoryManager},
+        test_util::LetterEmbedder,
+        vector_store::InMemoryDB,
+    };
+
+    #[tokio::test]
+    async fn tools_remember_recall_and_forget() {
+        let manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+        let manager = Arc::new(futures::lock::Mutex::new(manager));
+        let remember = RememberTool::new(manager.clone(), MemoryIdGenerator::default());
+        let recall = RecallTool::new(manager.clone());
+        let forget = ForgetTool::new(manager.clone());
+
+        let args: RememberArgs = serde_json::from_value(serde_json::json!({
+            "content": "aaaa",
+            "topics": [" Letters "],
+        }))
+        .unwrap();
+        let stored = remember.remember(args).await.unwrap();
+
+        let recalled = recall
+            .recall(RecallArgs {
+                query: "aaa".to_string(),
+                limit: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(recalled.len(), 1);
+        assert_eq!(recalled[0].id, stored.id);
+        assert_eq!(recalled[0].kind, MemoryKind::Semantic);
+
+        forget
+            .forget(ForgetArgs {
+                id: stored.id.clone(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(manager.lock().await.count().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn tool_calls_are_dispatched() {
+        let definitions = openai_tool_definitions();
+        assert_eq!(definitions.len(), 3);
+        assert_eq!(definitions[0]["function"]["name"], "remember");
+        assert_eq!(
+            definitions[1]["function"]["parameters"]["required"],
+            serde_json::json!(["query"])
+        );
+
+        let manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+        let dispatcher = MemoryToolDispatcher::new(
+            Arc::new(futures::lock::Mutex::new(manager)),
+            MemoryIdGenerator::default(),
+        );
+
+        let stored = dispatcher
+            .dispatch("remember", r#"{"content": "aaaa"}"#)
+            .await
+            .unwrap();
+        let recalled = dispatcher
+            .dispatch("recall", r#"{"query": "aaa", "limit": 1}"#)
+            .await
+            .unwrap();
+        assert_eq!(recalled[0]["id"], stored["id"]);
+
+        assert!(!dispatcher.handles("search_web"));
+        assert!(matches!(
+            dispatcher.dispatch("search_web", "{}").await,
+            Err(crate::Error::InvalidOutput(_))
+        ));
+        assert!(matches!(
+            dispatcher.dispatch("forget", "{}").await,
+            Err(crate::Error::InvalidOutput(_))
+        ));
+    }
+}
@@ -0,0 +1,296 @@
+//! Metrics instrumentation.
+//! The memory manager and caches report counters, gauges and timings into a single [`TelemetrySink`], which can be set once per process using [`set_sink`].
+//!
+//! If no sink has been set, [`MetricsSink`] is used when the `metrics` feature is enabled, emitting counters, gauges and histograms through the [`metrics`](https://docs.rs/metrics) crate facade,
+//! so any installed recorder (Prometheus, OpenTelemetry, etc) will pick them up automatically. Otherwise, everything is discarded by [`NoopSink`].
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Counter of memories stored by the memory manager.
+pub const STORES: &str = "braindump_stores_total";
+/// Counter of retrievals made through the memory manager.
+pub const RETRIEVALS: &str = "braindump_retrievals_total";
+/// Counter of memories deleted from storage by the memory manager, labelled by `reason` (`delete` or `retention`).
+pub const DELETES: &str = "braindump_deletes_total";
+/// Histogram of `MemoryManager::store` latency (in seconds).
+pub const STORE_LATENCY: &str = "braindump_store_latency_seconds";
+/// Histogram of `MemoryManager::retrieve` latency (in seconds).
+pub const RETRIEVE_LATENCY: &str = "braindump_retrieve_latency_seconds";
+/// Counter of hot cache hits.
+pub const CACHE_HITS: &str = "braindump_cache_hits_total";
+/// Counter of hot cache misses.
+pub const CACHE_MISSES: &str = "braindump_cache_misses_total";
+/// Gauge of the hot cache hit ratio (between 0.0 and 1.0).
+pub const CACHE_HIT_RATIO: &str = "braindump_cache_hit_ratio";
+/// Counter of memories evicted from the hot cache.
+pub const CACHE_EVICTIONS: &str = "braindump_cache_evictions_total";
+/// Counter of memories inserted into the hot cache.
+pub const CACHE_INSERTS: &str = "braindump_cache_inserts_total";
+/// Gauge of the number of memories held in the hot cache.
+pub const CACHE_SIZE: &str = "braindump_cache_size";
+/// Histogram of hot cache operation latency (in seconds), labelled by `operation` (`lookup`, `insert` or `evict`).
+pub const CACHE_LATENCY: &str = "braindump_cache_latency_seconds";
+/// Gauge of the number of memories held in the backing storage.
+pub const STORE_SIZE: &str = "braindump_store_size";
+
+/// Labels attached to a metric, as key-value pairs.
+pub type Labels<'a> = &'a [(&'static str, &'static str)];
+
+/// A trait for receiving telemetry (counters, gauges and timings), giving a single integration point for exporting it.
+/// Metric names are the constants in this module.
+pub trait TelemetrySink: Send + Sync {
+    /// Increments a counter.
+    fn counter(&self, name: &'static str, value: u64, labels: Labels);
+
+    /// Sets a gauge to a value.
+    fn gauge(&self, name: &'static str, value: f64, labels: Labels);
+
+    /// Records how long an operation took.
+    fn timing(&self, name: &'static str, duration: Duration, labels: Labels);
+}
+
+/// A sink that discards everything.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopSink;
+
+impl TelemetrySink for NoopSink {
+    fn counter(&self, _name: &'static str, _value: u64, _labels: Labels) {}
+
+    fn gauge(&self, _name: &'static str, _value: f64, _labels: Labels) {}
+
+    fn timing(&self, _name: &'static str, _duration: Duration, _labels: Labels) {}
+}
+
+/// A sink that emits everything as `tracing` events at the `TRACE` level (or as `log` records, with the `log` feature).
+#[cfg(feature = "tracing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tracing")))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LogSink;
+
+#[cfg(feature = "tracing")]
+impl TelemetrySink for LogSink {
+    fn counter(&self, name: &'static str, value: u64, labels: Labels) {
+        tracing::trace!(metric = name, value, ?labels, "counter");
+    }
+
+    fn gauge(&self, name: &'static str, value: f64, labels: Labels) {
+        tracing::trace!(metric = name, value, ?labels, "gauge");
+    }
+
+    fn timing(&self, name: &'static str, duration: Duration, labels: Labels) {
+        tracing::trace!(metric = name, ?duration, ?labels, "timing");
+    }
+}
+
+/// A sink that emits everything through the `metrics` crate facade, recording timings as histograms (in seconds).
+#[cfg(feature = "metrics")]
+#[cfg_attr(docsrs, doc(cfg(feature = "metrics")))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MetricsSink;
+
+#[cfg(feature = "metrics")]
+impl TelemetrySink for MetricsSink {
+    fn counter(&self, name: &'static str, value: u64, labels: Labels) {
+        ::metrics::counter!(name, to_labels(labels)).increment(value);
+    }
+
+    fn gauge(&self, name: &'static str, value: f64, labels: Labels) {
+        ::metrics::gauge!(name, to_labels(labels)).set(value);
+    }
+
+    fn timing(&self, name: &'static str, duration: Duration, labels: Labels) {
+        ::metrics::histogram!(name, to_labels(labels)).record(duration.as_secs_f64());
+    }
+}
+
+#[cfg(feature = "metrics")]
+fn to_labels(labels: Labels) -> Vec<::metrics::Label> {
+    labels
+        .iter()
+        .map(|(key, value)| ::metrics::Label::new(*key, *value))
+        .collect()
+}
+
+static SINK: OnceLock<Box<dyn TelemetrySink>> = OnceLock::new();
+
+/// Sets the sink that all telemetry is reported into. This can only be done once, so it should be done at startup, before any telemetry is reported.
+/// Returns an error if a sink has already been set.
+pub fn set_sink<T>(sink: T) -> Result<(), crate::Error>
+where
+    T: TelemetrySink + 'static,
+{
+    SINK.set(Box::new(sink))
+        .map_err(|_| crate::Error::custom("A telemetry sink has already been set"))
+}
+
+/// The sink that telemetry is currently reported into.
+pub fn sink() -> &'static dyn TelemetrySink {
+    match SINK.get() {
+        Some(sink) => sink.as_ref(),
+        #[cfg(feature = "metrics")]
+        None => &MetricsSink,
+        #[cfg(not(feature = "metrics"))]
+        None => &NoopSink,
+    }
+}
+
+/// A simple timer used to measure operation latency.
+/// On `wasm32` (where there's no monotonic clock) this is zero-sized and does nothing.
+pub(crate) struct Timer {
+    #[cfg(not(target_arch = "wasm32"))]
+    start: std::time::Instant,
+}
+
+impl Timer {
+    pub(crate) fn start() -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+fn record_latency(name: &'static str, timer: Timer) {
+    #[cfg(not(target_arch = "wasm32"))]
+    sink().timing(name, timer.start.elapsed(), &[]);
+    #[cfg(target_arch = "wasm32")]
+    let _ = (name, timer);
+}
+
+/// Records memories being stored, as well as how long storing them took.
+pub(crate) fn record_store(timer: Timer, count: usize) {
+    sink().counter(STORES, count as u64, &[]);
+    record_latency(STORE_LATENCY, timer);
+}
+
+/// Records a retrieval, as well as how long it took.
+pub(crate) fn record_retrieve(timer: Timer) {
+    sink().counter(RETRIEVALS, 1, &[]);
+    record_latency(RETRIEVE_LATENCY, timer);
+}
+
+/// Records memories being deleted from storage, either explicitly (`delete`) or by a retention policy (`retention`).
+pub(crate) fn record_deletes(reason: &'static str, count: usize) {
+    if count > 0 {
+        sink().counter(DELETES, count as u64, &[("reason", reason)]);
+    }
+}
+
+/// Records a cache lookup, as well as the current hit ratio.
+pub(crate) fn record_cache_lookup(hit: bool, hit_ratio: f64) {
+    let name = if hit { CACHE_HITS } else { CACHE_MISSES };
+    sink().counter(name, 1, &[]);
+    sink().gauge(CACHE_HIT_RATIO, hit_ratio, &[]);
+}
+
+pub(crate) fn record_evictions(count: usize) {
+    sink().counter(CACHE_EVICTIONS, count as u64, &[]);
+}
+
+/// Records a memory being inserted into the hot cache, as well as the current size of the cache.
+pub(crate) fn record_cache_insert(size: usize) {
+    sink().counter(CACHE_INSERTS, 1, &[]);
+    record_cache_size(size);
+}
+
+pub(crate) fn record_cache_size(size: usize) {
+    sink().gauge(CACHE_SIZE, size as f64, &[]);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn record_cache_latency(operation: &'static str, latency: Duration) {
+    sink().timing(CACHE_LATENCY, latency, &[("operation", operation)]);
+}
+
+pub(crate) fn record_store_size(size: usize) {
+    sink().gauge(STORE_SIZE, size as f64, &[]);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, time::Duration};
+
+    use super::{
+        DELETES, Labels, RETRIEVALS, RETRIEVE_LATENCY, STORE_LATENCY, STORE_SIZE, STORES,
+        TelemetrySink, set_sink,
+    };
+    use crate::{
+        memory::manager::MemoryManager,
+        test_util::{LetterEmbedder, entry},
+        vector_store::InMemoryDB,
+    };
+
+    thread_local! {
+        static RECORDED: RefCell<Vec<(&'static str, u64)>> = const { RefCell::new(Vec::new()) };
+        static GAUGES: RefCell<Vec<(&'static str, f64)>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// Records telemetry reported on the current thread, so tests running in parallel don't see each other's telemetry.
+    struct RecordingSink;
+
+    impl TelemetrySink for RecordingSink {
+        fn counter(&self, name: &'static str, value: u64, _labels: Labels) {
+            RECORDED.with(|recorded| recorded.borrow_mut().push((name, value)));
+        }
+
+        fn gauge(&self, name: &'static str, value: f64, _labels: Labels) {
+            GAUGES.with(|gauges| gauges.borrow_mut().push((name, value)));
+        }
+
+        fn timing(&self, name: &'static str, _duration: Duration, _labels: Labels) {
+            RECORDED.with(|recorded| recorded.borrow_mut().push((name, 1)));
+        }
+    }
+
+    fn recorded(name: &str) -> u64 {
+        RECORDED.with(|recorded| {
+            recorded
+                .borrow()
+                .iter()
+                .filter(|(recorded, _)| *recorded == name)
+                .map(|(_, value)| value)
+                .sum()
+        })
+    }
+
+    fn last_gauge(name: &str) -> Option<f64> {
+        GAUGES.with(|gauges| {
+            gauges
+                .borrow()
+                .iter()
+                .rev()
+                .find(|(recorded, _)| *recorded == name)
+                .map(|(_, value)| *value)
+        })
+    }
+
+    #[tokio::test]
+    async fn manager_reports_into_the_sink() {
+        // Telemetry is recorded per thread, so the sink doesn't affect other tests
+        let _ = set_sink(RecordingSink);
+
+        let mut manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+        manager.store("aaaa", entry("a", "aaaa")).await.unwrap();
+        let results = manager
+            .store_batch(
+                vec![("eeee", entry("e", "eeee")), ("iiii", entry("i", "iiii"))],
+                2,
+            )
+            .await;
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(last_gauge(STORE_SIZE), Some(3.0));
+        manager.retrieve("aaaa", 1).await.unwrap();
+        manager.delete("a").await.unwrap();
+
+        assert_eq!(recorded(STORES), 3);
+        assert_eq!(recorded(STORE_LATENCY), 2);
+        assert_eq!(recorded(RETRIEVALS), 1);
+        assert_eq!(recorded(RETRIEVE_LATENCY), 1);
+        assert_eq!(recorded(DELETES), 1);
+    }
+}
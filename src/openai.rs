@@ -0,0 +1,330 @@
+//! A module for handling integrations with [`async-openai`](https://docs.rs/async-openai), for use with OpenAI and OpenAI-compatible APIs.
+//! Ensure that you have the `openai` feature enabled.
+
+use crate::memory::{MemoryDraft, MemoryDrafts, generation::MemoryGeneration};
+use async_openai::{
+    Client,
+    config::{Config, OpenAIConfig},
+    types::{
+        chat::{
+            ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage,
+            CreateChatCompletionRequest, ResponseFormat, ResponseFormatJsonSchema,
+        },
+        embeddings::{CreateEmbeddingRequest, EmbeddingInput},
+    },
+};
+
+/// `text-embedding-3-small`
+pub const TEXT_EMBEDDING_3_SMALL: &str = "text-embedding-3-small";
+/// `text-embedding-3-large`
+pub const TEXT_EMBEDDING_3_LARGE: &str = "text-embedding-3-large";
+/// `text-embedding-ada-002`
+pub const TEXT_EMBEDDING_ADA_002: &str = "text-embedding-ada-002";
+
+/// A text embedder using the OpenAI embeddings API, made compliant to work with the `Embedder` trait.
+/// Any `async-openai` config can be used, so this also works with Azure OpenAI and OpenAI-compatible servers.
+pub struct OpenAIEmbedder<C = OpenAIConfig>
+where
+    C: Config,
+{
+    client: Client<C>,
+    model: String,
+    dimensions: Option<u32>,
+}
+
+impl OpenAIEmbedder<OpenAIConfig> {
+    /// Creates a new instance of `OpenAIEmbedder`, using the `OPENAI_API_KEY` environment variable as the API key.
+    pub fn from_env<S>(model: S) -> Result<Self, crate::Error>
+    where
+        S: Into<String>,
+    {
+        Ok(Self::new(Client::with_config(config_from_env()?), model))
+    }
+}
+
+impl<C> OpenAIEmbedder<C>
+where
+    C: Config,
+{
+    /// Creates a new instance of `OpenAIEmbedder` using a given client and model (see the model constants in this module).
+    pub fn new<S>(client: Client<C>, model: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            client,
+            model: model.into(),
+            dimensions: None,
+        }
+    }
+
+    /// Truncates the output embeddings to a given number of dimensions. Only supported by `text-embedding-3` and later models.
+    pub fn dimensions(mut self, dims: u32) -> Self {
+        self.dimensions = Some(dims);
+        self
+    }
+
+    fn embedding_request(&self, text: &str) -> CreateEmbeddingRequest {
+        CreateEmbeddingRequest {
+            model: self.model.clone(),
+            input: EmbeddingInput::String(text.to_string()),
+            dimensions: self.dimensions,
+            ..Default::default()
+        }
+    }
+}
+
+impl<C> crate::embed::Embedder for OpenAIEmbedder<C>
+where
+    C: Config,
+{
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "openai_embed_text", skip_all, fields(input_len = text.len()))
+    )]
+    async fn embed_text(&self, text: &str) -> Result<Vec<f32>, crate::Error> {
+        let request = self.embedding_request(text);
+
+        let response = self
+            .client
+            .embeddings()
+            .create(request)
+            .await
+            .map_err(openai_error)?;
+
+        response
+            .data
+            .into_iter()
+            .next()
+            .map(|embedding| embedding.embedding)
+            .ok_or_else(|| crate::Error::custom("OpenAI returned no embeddings"))
+    }
+
+    fn dims(&self) -> Option<usize> {
+        self.dimensions
+            .map(|dims| dims as usize)
+            .or(match self.model.as_str() {
+                TEXT_EMBEDDING_3_SMALL | TEXT_EMBEDDING_ADA_002 => Some(1536),
+                TEXT_EMBEDDING_3_LARGE => Some(3072),
+                _ => None,
+            })
+    }
+
+    fn max_input_tokens(&self) -> Option<usize> {
+        match self.model.as_str() {
+            TEXT_EMBEDDING_3_SMALL | TEXT_EMBEDDING_3_LARGE | TEXT_EMBEDDING_ADA_002 => Some(8191),
+            _ => None,
+        }
+    }
+
+    fn model_id(&self) -> Option<String> {
+        Some(match self.dimensions {
+            Some(dims) => format!("openai/{}/{dims}", self.model),
+            None => format!("openai/{}", self.model),
+        })
+    }
+}
+
+/// A memory generator using OpenAI chat completions with structured outputs.
+/// The model is asked to return a JSON object matching the schema of a list of [`MemoryDraft`]s.
+pub struct OpenAIMemoryGenerator<C = OpenAIConfig>
+where
+    C: Config,
+{
+    client: Client<C>,
+    model: String,
+    preamble: String,
+}
+
+impl OpenAIMemoryGenerator<OpenAIConfig> {
+    /// Creates a new instance of `OpenAIMemoryGenerator`, using the `OPENAI_API_KEY` environment variable as the API key.
+    pub fn from_env<S>(model: S) -> Result<Self, crate::Error>
+    where
+        S: Into<String>,
+    {
+        Ok(Self::new(Client::with_config(config_from_env()?), model))
+    }
+}
+
+impl<C> OpenAIMemoryGenerator<C>
+where
+    C: Config,
+{
+    /// Creates a new instance of `OpenAIMemoryGenerator` using a given client and chat model (eg, `gpt-4o-mini`).
+    pub fn new<S>(client: Client<C>, model: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            client,
+            model: model.into(),
+            preamble: crate::memory::generation::PREAMBLE.to_string(),
+        }
+    }
+
+    /// Replaces the default memory extraction system prompt.
+    pub fn preamble<S>(mut self, preamble: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.preamble = preamble.into();
+        self
+    }
+
+    fn chat_request(&self, input: &str) -> Result<CreateChatCompletionRequest, crate::Error> {
+        let schema = serde_json::to_value(schemars::schema_for!(MemoryDrafts))
+            .map_err(|err| crate::Error::wrap("Failed to serialize JSON schema", err))?;
+
+        Ok(CreateChatCompletionRequest {
+            model: self.model.clone(),
+            messages: vec![
+                ChatCompletionRequestSystemMessage::from(self.preamble.as_str()).into(),
+                ChatCompletionRequestUserMessage::from(input).into(),
+            ],
+            response_format: Some(ResponseFormat::JsonSchema {
+                json_schema: ResponseFormatJsonSchema {
+                    description: None,
+                    name: "memories".to_string(),
+                    schema: Some(schema),
+                    strict: None,
+                },
+            }),
+            ..Default::default()
+        })
+    }
+}
+
+impl<C> MemoryGeneration for OpenAIMemoryGenerator<C>
+where
+    C: Config,
+{
+    async fn generate(&self, input: &str) -> Result<Vec<MemoryDraft>, crate::Error> {
+        let request = self.chat_request(input)?;
+
+        let response = self
+            .client
+            .chat()
+            .create(request)
+            .await
+            .map_err(openai_error)?;
+
+        let Some(content) = response
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+        else {
+            return Err(crate::Error::custom("OpenAI returned no message content"));
+        };
+
+        parse_drafts(&content)
+    }
+}
+
+/// Parses the drafts out of a structured output response.
+fn parse_drafts(content: &str) -> Result<Vec<MemoryDraft>, crate::Error> {
+    let drafts: MemoryDrafts =
+        serde_json::from_str(content).map_err(|e| crate::Error::InvalidOutput(e.to_string()))?;
+
+    Ok(drafts.memories)
+}
+
+fn config_from_env() -> Result<OpenAIConfig, crate::Error> {
+    let api_key = std::env::var("OPENAI_API_KEY")
+        .map_err(|_| crate::Error::custom("OPENAI_API_KEY environment variable not set"))?;
+
+    Ok(OpenAIConfig::new().with_api_key(api_key))
+}
+
+fn openai_error<E>(err: E) -> crate::Error
+where
+    E: Into<crate::error::BoxedError>,
+{
+    crate::Error::wrap("OpenAI request failed", err)
+}
+
+#[cfg(test)]
+mod tests {
+    use async_openai::{Client, types::chat::CreateChatCompletionResponse};
+
+    use super::{OpenAIEmbedder, OpenAIMemoryGenerator, parse_drafts};
+    use crate::memory::{Confidence, MemoryKind};
+
+    #[test]
+    fn embedding_requests_match_the_api() {
+        let embedder = OpenAIEmbedder::new(Client::new(), super::TEXT_EMBEDDING_3_SMALL);
+        assert_eq!(
+            serde_json::to_value(embedder.embedding_request("User lives in Berlin")).unwrap(),
+            serde_json::json!({
+                "model": "text-embedding-3-small",
+                "input": "User lives in Berlin"
+            })
+        );
+
+        let embedder = embedder.dimensions(256);
+        let json = serde_json::to_value(embedder.embedding_request("hi")).unwrap();
+        assert_eq!(json["dimensions"], 256);
+    }
+
+    #[test]
+    fn chat_requests_use_structured_outputs() {
+        let generator =
+            OpenAIMemoryGenerator::new(Client::new(), "gpt-4o-mini").preamble("Extract");
+        let json = serde_json::to_value(generator.chat_request("User: Hi").unwrap()).unwrap();
+
+        assert_eq!(json["model"], "gpt-4o-mini");
+        assert_eq!(
+            json["messages"],
+            serde_json::json!([
+                {"role": "system", "content": "Extract"},
+                {"role": "user", "content": "User: Hi"}
+            ])
+        );
+        assert_eq!(json["response_format"]["type"], "json_schema");
+        assert_eq!(json["response_format"]["json_schema"]["name"], "memories");
+        assert!(
+            json["response_format"]["json_schema"]["schema"]["properties"]
+                .get("memories")
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn structured_output_responses_are_parsed() {
+        let response: CreateChatCompletionResponse = serde_json::from_str(
+            r#"{
+                "id": "chatcmpl-B9MHDbslfkBeAs8l4bebGdFOJ6PeG",
+                "object": "chat.completion",
+                "created": 1741570283,
+                "model": "gpt-4o-mini-2024-07-18",
+                "choices": [{
+                    "index": 0,
+                    "message": {
+                        "role": "assistant",
+                        "content": "{\"memories\":[{\"content\":\"User lives in Berlin\",\"kind\":\"Semantic\",\"source_context\":\"Mentioned while introducing themselves\",\"confidence\":\"High\",\"metadata\":[]}]}",
+                        "refusal": null,
+                        "annotations": []
+                    },
+                    "logprobs": null,
+                    "finish_reason": "stop"
+                }],
+                "usage": {"prompt_tokens": 1117, "completion_tokens": 46, "total_tokens": 1163},
+                "service_tier": "default",
+                "system_fingerprint": "fp_06737a9306"
+            }"#,
+        )
+        .unwrap();
+        let content = response.choices[0].message.content.as_deref().unwrap();
+
+        let drafts = parse_drafts(content).unwrap();
+        assert_eq!(drafts.len(), 1);
+        assert_eq!(drafts[0].content, "User lives in Berlin");
+        assert_eq!(drafts[0].kind, MemoryKind::Semantic);
+        assert_eq!(drafts[0].confidence, Confidence::High);
+
+        assert!(matches!(
+            parse_drafts("I can't help with that"),
+            Err(crate::Error::InvalidOutput(_))
+        ));
+    }
+}
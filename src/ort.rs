@@ -0,0 +1,250 @@
+//! A module for handling embeddings using custom ONNX models directly through [`ort`](https://ort.pyke.io) (ONNX Runtime).
+//! This is useful for custom or fine-tuned embedding models that aren't covered by `fastembed`'s model registry.
+//! Ensure that you have the `ort` feature enabled. The ONNX Runtime shared library is loaded dynamically at runtime (see the `ORT_DYLIB_PATH` environment variable).
+//! NOTE: This module is not WASM-friendly. Attempting to compile this module to `wasm` architecture will return an error.
+
+use std::path::Path;
+use std::sync::Mutex;
+
+use ort::session::Session;
+use ort::value::Tensor;
+use tokenizers::{Tokenizer, TruncationParams};
+
+/// How token embeddings should be pooled into a single sentence embedding.
+/// Ignored if the model already outputs a sentence embedding (ie, a 2-dimensional output).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Pooling {
+    /// Average all (non-padding) token embeddings.
+    #[default]
+    Mean,
+    /// Use the embedding of the first (`[CLS]`) token.
+    Cls,
+}
+
+/// A text embedder that runs a user-supplied ONNX model and tokenizer, made compliant to work with the `Embedder` trait.
+/// Under the hood, `std::sync::Mutex` is used due to `ort::session::Session` requiring `&mut self` to run.
+pub struct OrtEmbedder {
+    session: Mutex<Session>,
+    tokenizer: Tokenizer,
+    needs_token_type_ids: bool,
+    dims: Option<usize>,
+    max_tokens: Option<usize>,
+    pooling: Pooling,
+    normalize: bool,
+    model_id: String,
+}
+
+impl OrtEmbedder {
+    /// Loads an ONNX model along with its tokenizer (`tokenizer.json`) from local files.
+    pub fn from_files<P1, P2>(model: P1, tokenizer: P2) -> Result<Self, crate::Error>
+    where
+        P1: AsRef<Path>,
+        P2: AsRef<Path>,
+    {
+        let session = Session::builder()
+            .and_then(|builder| builder.commit_from_file(model.as_ref()))
+            .map_err(ort_error)?;
+        let tokenizer = Tokenizer::from_file(tokenizer).map_err(ort_error)?;
+
+        Ok(Self::new(
+            session,
+            tokenizer,
+            model.as_ref().display().to_string(),
+        ))
+    }
+
+    /// Creates an embedder from a pre-configured ONNX Runtime session (eg, one with execution providers set up) and tokenizer.
+    pub fn new<S>(session: Session, tokenizer: Tokenizer, model_id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        let needs_token_type_ids = session
+            .inputs
+            .iter()
+            .any(|input| input.name == "token_type_ids");
+
+        let dims = session
+            .outputs
+            .first()
+            .and_then(|output| output.output_type.tensor_shape())
+            .and_then(|shape| shape.last().copied())
+            .and_then(|dims| usize::try_from(dims).ok());
+
+        let mut embedder = Self {
+            session: Mutex::new(session),
+            tokenizer,
+            needs_token_type_ids,
+            dims,
+            max_tokens: None,
+            pooling: Pooling::default(),
+            normalize: true,
+            model_id: format!("ort/{}", model_id.into()),
+        };
+        // Models with a dynamic output dimension are probed instead. If that fails, embedding will fail in the same way later on
+        if embedder.dims.is_none() {
+            embedder.dims = embedder.embed("dims").ok().map(|embedding| embedding.len());
+        }
+
+        embedder
+    }
+
+    /// Sets how token embeddings are pooled. Defaults to [`Pooling::Mean`].
+    pub fn pooling(mut self, pooling: Pooling) -> Self {
+        self.pooling = pooling;
+        self
+    }
+
+    /// Sets whether or not embeddings should be L2-normalized. Defaults to `true`.
+    pub fn normalize(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
+    /// Sets the maximum number of tokens to embed. Longer inputs are truncated.
+    pub fn max_length(mut self, max_length: usize) -> Result<Self, crate::Error> {
+        self.tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length,
+                ..Default::default()
+            }))
+            .map_err(ort_error)?;
+        self.max_tokens = Some(max_length);
+
+        Ok(self)
+    }
+
+    /// The dimensions of the embeddings created by this model, taken from its output shape (or by embedding a short probe input, if the output dimension is dynamic).
+    /// This is only `None` if the model failed to embed the probe input.
+    pub fn dims(&self) -> Option<usize> {
+        self.dims
+    }
+
+    fn embed(&self, text: &str) -> Result<Vec<f32>, crate::Error> {
+        let encoding = self.tokenizer.encode(text, true).map_err(ort_error)?;
+        let len = encoding.len();
+        let to_i64 = |values: &[u32]| values.iter().map(|x| *x as i64).collect::<Vec<_>>();
+
+        let mask = encoding.get_attention_mask().to_vec();
+        let mut inputs = ort::inputs![
+            "input_ids" => Tensor::from_array(([1, len], to_i64(encoding.get_ids()))).map_err(ort_error)?,
+            "attention_mask" => Tensor::from_array(([1, len], to_i64(&mask))).map_err(ort_error)?,
+        ];
+
+        if self.needs_token_type_ids {
+            let type_ids = Tensor::from_array(([1, len], to_i64(encoding.get_type_ids())))
+                .map_err(ort_error)?;
+            inputs.push(("token_type_ids".into(), type_ids.into()));
+        }
+
+        let mut session = self
+            .session
+            .lock()
+            .map_err(|_| crate::Error::custom("ONNX Runtime session mutex was poisoned"))?;
+        let outputs = session.run(inputs).map_err(ort_error)?;
+        let (shape, data) = outputs[0].try_extract_tensor::<f32>().map_err(ort_error)?;
+
+        pool(shape, data, &mask, self.pooling, self.normalize)
+    }
+}
+
+/// Turns the output of a model into a sentence embedding, pooling token embeddings (ie, an output of shape `(1, tokens, hidden)`) where needed.
+fn pool(
+    shape: &[i64],
+    data: &[f32],
+    mask: &[u32],
+    pooling: Pooling,
+    normalize: bool,
+) -> Result<Vec<f32>, crate::Error> {
+    let mut embedding = match shape.len() {
+        // The model already outputs a pooled sentence embedding
+        2 => data.to_vec(),
+        // Token embeddings of shape (1, tokens, hidden)
+        3 => {
+            let hidden = shape[2] as usize;
+            let tokens = data.chunks(hidden);
+
+            match pooling {
+                Pooling::Cls => data[..hidden].to_vec(),
+                Pooling::Mean => {
+                    let mut pooled = vec![0.0; hidden];
+                    let mut count = 0.0;
+                    for (token, mask) in tokens.zip(mask) {
+                        if *mask == 0 {
+                            continue;
+                        }
+                        count += 1.0;
+                        for (acc, value) in pooled.iter_mut().zip(token) {
+                            *acc += value;
+                        }
+                    }
+                    pooled.iter_mut().for_each(|x| *x /= f32::max(count, 1.0));
+                    pooled
+                }
+            }
+        }
+        dims => {
+            return Err(crate::Error::Custom(format!(
+                "Unexpected ONNX model output rank: {dims}"
+            )));
+        }
+    };
+
+    if normalize {
+        let norm = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            embedding.iter_mut().for_each(|x| *x /= norm);
+        }
+    }
+
+    Ok(embedding)
+}
+
+impl crate::embed::Embedder for OrtEmbedder {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "ort_embed_text", skip_all, fields(input_len = text.len()))
+    )]
+    async fn embed_text(&self, text: &str) -> Result<Vec<f32>, crate::Error> {
+        self.embed(text)
+    }
+
+    fn dims(&self) -> Option<usize> {
+        self.dims
+    }
+
+    fn max_input_tokens(&self) -> Option<usize> {
+        self.max_tokens
+    }
+
+    fn model_id(&self) -> Option<String> {
+        Some(self.model_id.clone())
+    }
+}
+
+fn ort_error<E>(err: E) -> crate::Error
+where
+    E: Into<crate::error::BoxedError>,
+{
+    crate::Error::wrap("ONNX Runtime error", err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Pooling, pool};
+
+    #[test]
+    fn token_embeddings_are_pooled() {
+        let tokens = [1.0, 2.0, 3.0, 6.0, 100.0, 100.0];
+        let mask = [1, 1, 0];
+
+        let mean = pool(&[1, 3, 2], &tokens, &mask, Pooling::Mean, false).unwrap();
+        assert_eq!(mean, [2.0, 4.0]);
+        let cls = pool(&[1, 3, 2], &tokens, &mask, Pooling::Cls, false).unwrap();
+        assert_eq!(cls, [1.0, 2.0]);
+
+        // Sentence embeddings are passed through, apart from normalization
+        let sentence = pool(&[1, 2], &[3.0, 4.0], &mask, Pooling::Mean, true).unwrap();
+        assert_eq!(sentence, [0.6, 0.8]);
+        assert!(pool(&[6], &tokens, &mask, Pooling::Mean, false).is_err());
+    }
+}
@@ -0,0 +1,301 @@
+//! An HTTP memory service using `axum`, for running braindump as a sidecar memory service for agents that aren't written in Rust.
+//!
+//! The service has the following routes:
+//! - `POST /memories` stores a memory, given a JSON [`MemoryEntry`]. The content of the memory is embedded before it gets stored.
+//! - `POST /retrieve` retrieves memories, given a JSON [`RetrieveRequest`]. Returns a JSON list of memory entries.
+//! - `DELETE /memories/{id}` deletes a memory.
+//! - `GET /stats` returns [`StatsResponse`] as JSON.
+//! - `GET /health` returns [`HealthResponse`] as JSON, with a `503 Service Unavailable` status if the storage is unreachable (for readiness probes).
+//!
+//! Errors are returned as a JSON object with an `error` field.
+//!
+//! ```rust,ignore
+//! use std::sync::Arc;
+//! use futures::lock::Mutex;
+//!
+//! let manager = Arc::new(Mutex::new(manager));
+//! let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await?;
+//! axum::serve(listener, braindump::server::router(manager)).await?;
+//! ```
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    embed::Embedder,
+    error::StorageError,
+    memory::{MemoryEntry, MemoryId, cache::CacheStats, manager::RetrieveOptions},
+    storage::Storage,
+};
+
+pub use crate::memory::manager::SharedMemoryManager;
+
+/// Creates a router exposing a memory manager over HTTP (see the [module docs](self) for the routes).
+/// The manager is shared, so it can still be used outside of the service.
+pub fn router<E, S>(manager: SharedMemoryManager<E, S>) -> Router
+where
+    E: Embedder + 'static,
+    S: Storage + 'static,
+{
+    Router::new()
+        .route("/memories", post(store_memory::<E, S>))
+        .route("/memories/{id}", delete(delete_memory::<E, S>))
+        .route("/retrieve", post(retrieve::<E, S>))
+        .route("/stats", get(stats::<E, S>))
+        .route("/health", get(health::<E, S>))
+        .with_state(manager)
+}
+
+/// The body of a `POST /retrieve` request.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RetrieveRequest {
+    pub query: String,
+    /// The maximum number of memories to return. Defaults to 5.
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+    #[serde(flatten)]
+    pub options: RetrieveOptions,
+}
+
+fn default_limit() -> usize {
+    5
+}
+
+/// The body of a `POST /memories` response.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StoreResponse {
+    pub id: MemoryId,
+}
+
+/// The body of a `GET /stats` response.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StatsResponse {
+    /// The number of stored memories.
+    pub memories: usize,
+    /// The number of writes buffered by a write-back hot cache.
+    pub pending_writes: usize,
+    /// Statistics about the hot cache, if there is one.
+    pub cache: Option<CacheStats>,
+}
+
+/// The body of a `GET /health` response.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HealthResponse {
+    /// Whether or not the storage is reachable.
+    pub healthy: bool,
+    /// How long the storage took to respond, in milliseconds.
+    pub latency_ms: Option<f64>,
+    /// Why the storage is unreachable.
+    pub error: Option<String>,
+}
+
+async fn store_memory<E, S>(
+    State(manager): State<SharedMemoryManager<E, S>>,
+    Json(entry): Json<MemoryEntry>,
+) -> Result<(StatusCode, Json<StoreResponse>), ApiError>
+where
+    E: Embedder,
+    S: Storage,
+{
+    let id = entry.id.clone();
+    let content = entry.content.clone();
+    manager.lock().await.store(content, entry).await?;
+
+    Ok((StatusCode::CREATED, Json(StoreResponse { id })))
+}
+
+async fn retrieve<E, S>(
+    State(manager): State<SharedMemoryManager<E, S>>,
+    Json(request): Json<RetrieveRequest>,
+) -> Result<Json<Vec<MemoryEntry>>, ApiError>
+where
+    E: Embedder,
+    S: Storage,
+{
+    let results = manager
+        .lock()
+        .await
+        .retrieve_with_options(request.query, request.limit, request.options)
+        .await?;
+
+    Ok(Json(
+        results.iter().map(|result| result.data_owned()).collect(),
+    ))
+}
+
+async fn delete_memory<E, S>(
+    State(manager): State<SharedMemoryManager<E, S>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError>
+where
+    E: Embedder,
+    S: Storage,
+{
+    manager.lock().await.delete(&id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn stats<E, S>(
+    State(manager): State<SharedMemoryManager<E, S>>,
+) -> Result<Json<StatsResponse>, ApiError>
+where
+    E: Embedder,
+    S: Storage,
+{
+    let manager = manager.lock().await;
+
+    Ok(Json(StatsResponse {
+        memories: manager.count().await?,
+        pending_writes: manager.pending_writes(),
+        cache: manager.cache_stats().cloned(),
+    }))
+}
+
+async fn health<E, S>(State(manager): State<SharedMemoryManager<E, S>>) -> impl IntoResponse
+where
+    E: Embedder,
+    S: Storage,
+{
+    let health = manager.lock().await.health().await;
+    let status = if health.reachable {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    let body = HealthResponse {
+        healthy: health.reachable,
+        latency_ms: health.latency.map(|latency| latency.as_secs_f64() * 1000.0),
+        error: health.error,
+    };
+    (status, Json(body))
+}
+
+/// A [`crate::Error`] converted into an HTTP response.
+struct ApiError(crate::Error);
+
+impl From<crate::Error> for ApiError {
+    fn from(value: crate::Error) -> Self {
+        Self(value)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match &self.0 {
+            crate::Error::Storage(StorageError::EmbeddingNotExists(_)) => StatusCode::NOT_FOUND,
+            crate::Error::Storage(StorageError::MismatchedDimensions(..)) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            crate::Error::Storage(StorageError::QuotaExceeded(..)) => StatusCode::TOO_MANY_REQUESTS,
+            err if err.is_retryable() => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        #[cfg(feature = "tracing")]
+        if status.is_server_error() {
+            tracing::error!(error = %self.0, "memory service request failed");
+        }
+
+        let body = serde_json::json!({ "error": self.0.to_string() });
+        (status, Json(body)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{
+        body::{Body, to_bytes},
+        http::{Request, StatusCode},
+    };
+    use futures::lock::Mutex;
+    use tower::ServiceExt;
+
+    use super::{HealthResponse, StatsResponse, router};
+    use crate::{
+        memory::{MemoryEntry, manager::MemoryManager},
+        test_util::{LetterEmbedder, entry},
+        vector_store::InMemoryDB,
+    };
+
+    fn request(method: &str, uri: &str, body: serde_json::Value) -> Request<Body> {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn memories_can_be_stored_retrieved_and_deleted() {
+        let manager = MemoryManager::builder()
+            .embedder(LetterEmbedder)
+            .storage(InMemoryDB::new(4))
+            .build()
+            .unwrap();
+        let app = router(Arc::new(Mutex::new(manager)));
+
+        let entry = entry("a", "aaaa");
+        let res = app
+            .clone()
+            .oneshot(request(
+                "POST",
+                "/memories",
+                serde_json::to_value(&entry).unwrap(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::CREATED);
+
+        let res = app
+            .clone()
+            .oneshot(request(
+                "POST",
+                "/retrieve",
+                serde_json::json!({ "query": "aaa", "cache_mode": "bypass" }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let results: Vec<MemoryEntry> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, "a");
+
+        let res = app
+            .clone()
+            .oneshot(Request::delete("/memories/a").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        let res = app
+            .clone()
+            .oneshot(Request::get("/health").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let health: HealthResponse = serde_json::from_slice(&body).unwrap();
+        assert!(health.healthy);
+
+        let res = app
+            .oneshot(Request::get("/stats").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let stats: StatsResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(stats.memories, 0);
+        assert!(stats.cache.is_none());
+    }
+}
@@ -1,8 +1,10 @@
 use crate::{
-    memory::MemoryEntry,
+    embed::{QuantizedVector, SparseVector},
+    error::StorageError,
+    memory::{MemoryEntry, MemoryId},
     wasm::{WasmCompatSend, WasmCompatSync},
 };
-use std::fmt;
+use std::{fmt, time::Duration};
 
 /// Handle storage.
 /// This should be implemented for vector stores as well as any databases that have vector database functionality.
@@ -14,6 +16,50 @@ pub trait Storage: WasmCompatSend + WasmCompatSync {
         entry: MemoryEntry,
     ) -> impl Future<Output = Result<(), crate::Error>> + WasmCompatSend;
 
+    /// Insert several documents atomically: either every document gets inserted, or none of them do.
+    /// The default implementation inserts documents one at a time. If an insert fails, it compensates by deleting the documents inserted so far and restoring any documents they replaced.
+    /// Backends with native transactions should override this.
+    fn insert_all(
+        &mut self,
+        entries: Vec<(Vec<f32>, MemoryEntry)>,
+    ) -> impl Future<Output = Result<(), crate::Error>> + WasmCompatSend {
+        async move {
+            let mut replaced = Vec::new();
+            for (_, entry) in &entries {
+                match self.search_by_id(entry.id.clone()).await {
+                    Ok(existing) => replaced.push(existing),
+                    Err(crate::Error::Storage(StorageError::EmbeddingNotExists(_))) => {}
+                    Err(err) => return Err(err),
+                }
+            }
+
+            let mut inserted = Vec::with_capacity(entries.len());
+            for (embedding, entry) in entries {
+                let id = entry.id.clone();
+                if let Err(err) = self.insert(embedding, entry).await {
+                    let rollback = async {
+                        self.delete_batch(inserted).await?;
+                        for existing in replaced {
+                            self.insert(existing.embedding_owned(), existing.data_owned())
+                                .await?;
+                        }
+
+                        Ok::<_, crate::Error>(())
+                    };
+                    if let Err(_rollback_err) = rollback.await {
+                        #[cfg(feature = "tracing")]
+                        tracing::error!(error = %_rollback_err, "failed to roll back a partially inserted batch");
+                    }
+
+                    return Err(err);
+                }
+                inserted.push(id);
+            }
+
+            Ok(())
+        }
+    }
+
     /// Search (typically, using semantic search)
     fn search(
         &self,
@@ -24,7 +70,7 @@ pub trait Storage: WasmCompatSend + WasmCompatSync {
     /// Search the storage for a single record by ID and get the embedding as well as the memory entry
     fn search_by_id(
         &self,
-        id: String,
+        id: MemoryId,
     ) -> impl Future<Output = Result<SearchResult, crate::Error>> + WasmCompatSend;
 
     /// Search for all recent inserts
@@ -36,13 +82,13 @@ pub trait Storage: WasmCompatSend + WasmCompatSync {
     /// Delete a document (by ID)
     fn delete(
         &mut self,
-        id: String,
+        id: MemoryId,
     ) -> impl Future<Output = Result<(), crate::Error>> + WasmCompatSend;
 
     /// Delete multiple documents (by ID)
     fn delete_batch(
         &mut self,
-        ids: Vec<String>,
+        ids: Vec<MemoryId>,
     ) -> impl Future<Output = Result<(), crate::Error>> + WasmCompatSend;
 
     /// Get documents with the oldest inserts
@@ -51,21 +97,345 @@ pub trait Storage: WasmCompatSend + WasmCompatSync {
         limit: usize,
     ) -> impl Future<Output = Result<Vec<SearchResult>, crate::Error>> + WasmCompatSend;
 
+    /// Get a page of documents ordered by insert time (oldest first), skipping the first `offset` documents.
+    /// The default implementation fetches `offset + limit` documents using [`Storage::get_oldest`] and skips the first `offset`. Backends with native pagination should override this.
+    fn get_oldest_page(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> impl Future<Output = Result<Vec<SearchResult>, crate::Error>> + WasmCompatSend {
+        async move {
+            let results = self.get_oldest(offset + limit).await?;
+
+            Ok(results.into_iter().skip(offset).collect())
+        }
+    }
+
+    /// Get a page of documents ordered by creation time (oldest first, with ties broken by ID), starting after the document created at `created_at` with the given ID.
+    /// Unlike [`Storage::get_oldest_page`], pages don't shift when documents are deleted or re-inserted, so this can be used to page through documents while modifying them.
+    /// The default implementation pages through documents using [`Storage::get_oldest_page`], skipping every document up to the cursor. Backends with native pagination should override this.
+    fn get_oldest_after(
+        &self,
+        created_at: i64,
+        id: MemoryId,
+        limit: usize,
+    ) -> impl Future<Output = Result<Vec<SearchResult>, crate::Error>> + WasmCompatSend {
+        const BATCH_SIZE: usize = 256;
+
+        async move {
+            let mut results = Vec::with_capacity(limit);
+            let mut offset = 0;
+
+            while results.len() < limit {
+                let batch = self.get_oldest_page(offset, BATCH_SIZE).await?;
+                let fetched = batch.len();
+                results.extend(batch.into_iter().filter(|result| {
+                    (result.data().created_at, &result.data().id) > (created_at, &id)
+                }));
+
+                if fetched < BATCH_SIZE {
+                    break;
+                }
+                offset += fetched;
+            }
+            results.truncate(limit);
+
+            Ok(results)
+        }
+    }
+
+    /// Get documents created within a time range (as Unix timestamps, including `start` but excluding `end`), oldest first.
+    /// The default implementation pages through documents using [`Storage::get_oldest_page`], stopping at the first document created after the range. Backends with native range queries should override this.
+    fn get_between(
+        &self,
+        start: i64,
+        end: i64,
+    ) -> impl Future<Output = Result<Vec<SearchResult>, crate::Error>> + WasmCompatSend {
+        const BATCH_SIZE: usize = 256;
+
+        async move {
+            let mut results = Vec::new();
+            let mut offset = 0;
+
+            loop {
+                let batch = self.get_oldest_page(offset, BATCH_SIZE).await?;
+                let fetched = batch.len();
+                for result in batch {
+                    let created_at = result.data().created_at;
+                    if created_at >= end {
+                        return Ok(results);
+                    }
+                    if created_at >= start {
+                        results.push(result);
+                    }
+                }
+
+                if fetched < BATCH_SIZE {
+                    return Ok(results);
+                }
+                offset += fetched;
+            }
+        }
+    }
+
     /// Update a payload by ID
     fn update_payload_by_id(
         &mut self,
-        id: String,
+        id: MemoryId,
         payload: MemoryEntry,
     ) -> impl Future<Output = Result<(), crate::Error>> + WasmCompatSend;
 
     /// Get the total count of storage
     fn count(&self) -> impl Future<Output = Result<usize, crate::Error>> + WasmCompatSend;
+
+    /// The dimensions of the embeddings held by this storage, if fixed. Defaults to `None`.
+    fn dims(&self) -> Option<usize> {
+        None
+    }
+
+    /// Whether or not the storage expects embeddings to be L2-normalized (eg, backends that score using the dot product).
+    /// When this returns `true`, [`crate::memory::manager::MemoryManager`] rejects unnormalized embeddings. Defaults to `false`.
+    fn requires_normalized_embeddings(&self) -> bool {
+        false
+    }
+
+    /// Insert a document using an int8-quantized embedding.
+    /// The default implementation dequantizes the embedding and calls [`Storage::insert`]. Backends with a quantized storage mode should override this to store the embedding as-is.
+    fn insert_quantized(
+        &mut self,
+        embedding: QuantizedVector,
+        entry: MemoryEntry,
+    ) -> impl Future<Output = Result<(), crate::Error>> + WasmCompatSend {
+        self.insert(embedding.dequantize(), entry)
+    }
+
+    /// Search using an int8-quantized embedding.
+    /// The default implementation dequantizes the embedding and calls [`Storage::search`].
+    fn search_quantized(
+        &self,
+        embedding: QuantizedVector,
+        limit: usize,
+    ) -> impl Future<Output = Result<Vec<SearchResult>, crate::Error>> + WasmCompatSend {
+        self.search(embedding.dequantize(), limit)
+    }
+
+    /// Attach a sparse embedding to an existing document (by ID), for use with [`Storage::search_hybrid`].
+    /// The default implementation returns an error, as not every backend supports sparse vectors.
+    fn insert_sparse(
+        &mut self,
+        id: MemoryId,
+        sparse: SparseVector,
+    ) -> impl Future<Output = Result<(), crate::Error>> + WasmCompatSend {
+        let _ = (id, sparse);
+        async {
+            Err(crate::Error::custom(
+                "This storage does not support sparse vectors",
+            ))
+        }
+    }
+
+    /// Search using both a dense and a sparse embedding, fusing the scores together.
+    /// The default implementation ignores the sparse embedding and falls back to [`Storage::search`].
+    fn search_hybrid(
+        &self,
+        embedding: Vec<f32>,
+        sparse: SparseVector,
+        limit: usize,
+        fusion: HybridFusion,
+    ) -> impl Future<Output = Result<Vec<SearchResult>, crate::Error>> + WasmCompatSend {
+        let _ = (sparse, fusion);
+        self.search(embedding, limit)
+    }
+
+    /// Search for the most similar documents whose metadata matches a filter.
+    /// The default implementation fetches candidates using [`Storage::search`] in progressively larger batches, filtering them until enough matching documents are found or the storage has been exhausted.
+    /// Backends with native metadata filtering should override this.
+    fn search_filtered(
+        &self,
+        embedding: Vec<f32>,
+        limit: usize,
+        filter: MetadataFilter,
+    ) -> impl Future<Output = Result<Vec<SearchResult>, crate::Error>> + WasmCompatSend {
+        async move {
+            let total = self.count().await?;
+            let mut candidates = limit.max(1);
+
+            loop {
+                let mut results: Vec<SearchResult> = self
+                    .search(embedding.clone(), candidates)
+                    .await?
+                    .into_iter()
+                    .filter(|result| filter.matches(result.data()))
+                    .collect();
+
+                if results.len() >= limit || candidates >= total {
+                    results.truncate(limit);
+                    return Ok(results);
+                }
+
+                candidates = (candidates * 2).min(total);
+            }
+        }
+    }
+
+    /// Checks whether the storage is reachable, and how long it took to respond (eg, for readiness probes).
+    /// The default implementation pings the storage using [`Storage::count`]. In-memory storages should override this to always be healthy.
+    fn health_check(&self) -> impl Future<Output = HealthStatus> + WasmCompatSend {
+        HealthStatus::ping(self.count())
+    }
+}
+
+/// The result of a storage health check (see [`Storage::health_check`]).
+#[derive(Clone, Debug, PartialEq)]
+pub struct HealthStatus {
+    /// Whether or not the storage responded successfully.
+    pub reachable: bool,
+    /// How long the storage took to respond. `None` if it couldn't be measured (eg, on `wasm32`).
+    pub latency: Option<Duration>,
+    /// Why the storage is unreachable.
+    pub error: Option<String>,
+}
+
+impl HealthStatus {
+    /// Create a status where the storage is reachable.
+    pub fn healthy(latency: Option<Duration>) -> Self {
+        Self {
+            reachable: true,
+            latency,
+            error: None,
+        }
+    }
+
+    /// Create a status where the storage is unreachable.
+    pub fn unhealthy(error: &str, latency: Option<Duration>) -> Self {
+        Self {
+            reachable: false,
+            latency,
+            error: Some(error.to_string()),
+        }
+    }
+
+    /// Runs a request against the storage (eg, a count or a ping), timing how long it takes to respond.
+    pub async fn ping<F, T>(request: F) -> Self
+    where
+        F: Future<Output = Result<T, crate::Error>>,
+    {
+        #[cfg(not(target_arch = "wasm32"))]
+        let start = std::time::Instant::now();
+        let res = request.await;
+        #[cfg(not(target_arch = "wasm32"))]
+        let latency = Some(start.elapsed());
+        #[cfg(target_arch = "wasm32")]
+        let latency = None;
+
+        match res {
+            Ok(_) => Self::healthy(latency),
+            Err(err) => Self::unhealthy(&err.to_string(), latency),
+        }
+    }
+}
+
+/// How dense and sparse search scores are fused together in a hybrid search.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HybridFusion {
+    /// Reciprocal rank fusion: each result is scored by the sum of `1 / (k + rank)` across the dense and sparse rankings.
+    /// Robust to the two scores being on completely different scales.
+    ReciprocalRank { k: f32 },
+    /// A weighted sum of the dense (cosine) score and the sparse score, normalized against the best sparse score.
+    /// `dense_weight` should be between 0.0 and 1.0; the sparse score is weighted by `1.0 - dense_weight`.
+    Weighted { dense_weight: f32 },
+}
+
+impl Default for HybridFusion {
+    fn default() -> Self {
+        Self::ReciprocalRank { k: 60.0 }
+    }
+}
+
+/// A filter on the metadata (see [`MemoryEntry::metadata`]) and tags of a memory, used by [`Storage::search_filtered`].
+/// Filters can be combined using [`MetadataFilter::and`] and [`MetadataFilter::or`].
+///
+/// ```rust
+/// use braindump::storage::MetadataFilter;
+///
+/// // source == "chat" AND (topic == "work" OR topic == "travel")
+/// let filter = MetadataFilter::eq("source", "chat")
+///     .and(MetadataFilter::eq("topic", "work").or(MetadataFilter::eq("topic", "travel")));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MetadataFilter {
+    /// Matches memories with a metadata entry with the given key and value.
+    Eq { key: String, value: String },
+    /// Matches memories that match every filter. An empty list matches every memory.
+    And(Vec<MetadataFilter>),
+    /// Matches memories that match any filter. An empty list matches no memories.
+    Or(Vec<MetadataFilter>),
+    /// Matches memories with the given tag (see [`MemoryEntry::tags`]).
+    Tag(String),
+}
+
+impl MetadataFilter {
+    /// Creates a filter matching memories with a metadata entry with the given key and value.
+    pub fn eq<S1, S2>(key: S1, value: S2) -> Self
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        Self::Eq {
+            key: key.into(),
+            value: value.into(),
+        }
+    }
+
+    /// Creates a filter matching memories with the given tag.
+    pub fn tag<S>(tag: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::Tag(tag.into())
+    }
+
+    /// Combines this filter with another, matching memories that match both.
+    pub fn and(self, other: Self) -> Self {
+        match self {
+            Self::And(mut filters) => {
+                filters.push(other);
+                Self::And(filters)
+            }
+            filter => Self::And(vec![filter, other]),
+        }
+    }
+
+    /// Combines this filter with another, matching memories that match either.
+    pub fn or(self, other: Self) -> Self {
+        match self {
+            Self::Or(mut filters) => {
+                filters.push(other);
+                Self::Or(filters)
+            }
+            filter => Self::Or(vec![filter, other]),
+        }
+    }
+
+    /// Whether or not a memory matches this filter.
+    pub fn matches(&self, entry: &MemoryEntry) -> bool {
+        match self {
+            Self::Eq { key, value } => entry
+                .metadata
+                .iter()
+                .any(|metadata| metadata.key() == key && metadata.value() == value),
+            Self::And(filters) => filters.iter().all(|filter| filter.matches(entry)),
+            Self::Or(filters) => filters.iter().any(|filter| filter.matches(entry)),
+            Self::Tag(tag) => entry.has_tag(tag),
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct SearchResult {
     vec: Vec<f32>,
     data: MemoryEntry,
+    score: Option<f32>,
 }
 
 impl fmt::Debug for SearchResult {
@@ -73,13 +443,30 @@ impl fmt::Debug for SearchResult {
         f.debug_struct("SearchResult")
             .field("vec", &"<truncated>")
             .field("data", &self.data)
+            .field("score", &self.score)
             .finish()
     }
 }
 
 impl SearchResult {
     pub fn new(vec: Vec<f32>, data: MemoryEntry) -> Self {
-        Self { vec, data }
+        Self {
+            vec,
+            data,
+            score: None,
+        }
+    }
+
+    /// Sets the score of the result (eg, the similarity to the query embedding).
+    pub fn with_score(mut self, score: f32) -> Self {
+        self.score = Some(score);
+        self
+    }
+
+    /// The score of the result, if the storage reported one.
+    /// For similarity searches this is typically the cosine similarity to the query embedding, while lookups (eg, by ID) don't have a score.
+    pub fn score(&self) -> Option<f32> {
+        self.score
     }
 
     pub fn embedding(&self) -> &[f32] {
@@ -108,11 +495,11 @@ impl Storage for StorageNotSet {
         Err(crate::Error::NoOp)
     }
 
-    async fn delete(&mut self, _: String) -> Result<(), crate::Error> {
+    async fn delete(&mut self, _: MemoryId) -> Result<(), crate::Error> {
         Err(crate::Error::NoOp)
     }
 
-    async fn delete_batch(&mut self, _: Vec<String>) -> Result<(), crate::Error> {
+    async fn delete_batch(&mut self, _: Vec<MemoryId>) -> Result<(), crate::Error> {
         Err(crate::Error::NoOp)
     }
 
@@ -132,15 +519,39 @@ impl Storage for StorageNotSet {
         Err(crate::Error::NoOp)
     }
 
-    async fn search_by_id(&self, _: String) -> Result<SearchResult, crate::Error> {
+    async fn search_by_id(&self, _: MemoryId) -> Result<SearchResult, crate::Error> {
         Err(crate::Error::NoOp)
     }
 
     async fn update_payload_by_id(
         &mut self,
-        _: String,
+        _: MemoryId,
         _: MemoryEntry,
     ) -> Result<(), crate::Error> {
         Err(crate::Error::NoOp)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{HealthStatus, Storage, StorageNotSet};
+    use crate::vector_store::InMemoryDB;
+
+    #[tokio::test]
+    async fn health_checks_report_reachability() {
+        let status = HealthStatus::ping(async { Ok(()) }).await;
+        assert!(status.reachable);
+        assert!(status.latency.is_some());
+        assert_eq!(status.error, None);
+
+        // The default health check pings the storage by counting memories
+        let status = StorageNotSet.health_check().await;
+        assert!(!status.reachable);
+        assert_eq!(status.error, Some(crate::Error::NoOp.to_string()));
+
+        let status = InMemoryDB::new(4).health_check().await;
+        assert_eq!(status, HealthStatus::healthy(Some(Duration::ZERO)));
+    }
+}
@@ -0,0 +1,56 @@
+//! Helpers shared between unit tests.
+
+use crate::{
+    embed::{Embedder, ImageSource, MultimodalEmbedder, QuantizedEmbedder},
+    memory::{Confidence, MemoryEntry, MemoryKind, Visibility, strength::INITIAL_STRENGTH},
+};
+
+/// A deterministic embedder that counts occurrences of a handful of letters.
+pub(crate) struct LetterEmbedder;
+
+impl Embedder for LetterEmbedder {
+    async fn embed_text(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        Ok(['a', 'e', 'i', 'o']
+            .iter()
+            .map(|c| input.matches(*c).count() as f32 + 1.0)
+            .collect())
+    }
+
+    fn dims(&self) -> Option<usize> {
+        Some(4)
+    }
+}
+
+/// Treats image bytes as text, so images land in the same embedding space as text.
+impl MultimodalEmbedder for LetterEmbedder {
+    async fn embed_image(&self, image: &ImageSource) -> Result<Vec<f32>, crate::Error> {
+        match image {
+            ImageSource::Bytes(bytes) => self.embed_text(&String::from_utf8_lossy(bytes)).await,
+            ImageSource::Uri(uri) => self.embed_text(uri).await,
+        }
+    }
+}
+
+impl QuantizedEmbedder for LetterEmbedder {}
+
+/// A semantic memory with the given ID and content, and defaults for everything else.
+pub(crate) fn entry(id: &str, content: &str) -> MemoryEntry {
+    MemoryEntry {
+        id: id.into(),
+        content: content.to_string(),
+        kind: MemoryKind::Semantic,
+        importance: 0.5,
+        created_at: 0,
+        last_accessed: 0,
+        access_count: 0,
+        source_context: "test".to_string(),
+        confidence: Confidence::High,
+        metadata: Vec::new(),
+        agent_id: None,
+        visibility: Visibility::Private,
+        embedding_model: None,
+        image: None,
+        tags: Vec::new(),
+        strength: INITIAL_STRENGTH,
+    }
+}
@@ -0,0 +1,344 @@
+//! Deadlines for embedders and storage.
+//! Ensure that you have the `timeout` feature enabled. Timers are runtime-agnostic (using `futures-timer`), so they work with any async runtime.
+
+use std::{pin::pin, task::Poll, time::Duration};
+
+use futures_timer::Delay;
+
+use crate::{
+    embed::{Embedder, QuantizedVector, SparseVector},
+    memory::{MemoryEntry, MemoryId},
+    storage::{HealthStatus, HybridFusion, MetadataFilter, SearchResult, Storage},
+};
+
+/// Runs a future to completion, returning [`crate::Error::Timeout`] if it doesn't complete within a given duration.
+pub async fn with_timeout<F, T>(duration: Duration, fut: F) -> Result<T, crate::Error>
+where
+    F: Future<Output = Result<T, crate::Error>>,
+{
+    let mut fut = pin!(fut);
+    let mut delay = pin!(Delay::new(duration));
+
+    std::future::poll_fn(|cx| {
+        if let Poll::Ready(res) = fut.as_mut().poll(cx) {
+            return Poll::Ready(res);
+        }
+
+        delay
+            .as_mut()
+            .poll(cx)
+            .map(|_| Err(crate::Error::Timeout(duration)))
+    })
+    .await
+}
+
+/// An embedder wrapper that bounds every embedding call with a deadline.
+pub struct TimeoutEmbedder<E> {
+    inner: E,
+    timeout: Duration,
+}
+
+impl<E> TimeoutEmbedder<E>
+where
+    E: Embedder,
+{
+    /// Wraps an embedder, failing any embedding call that takes longer than `timeout`.
+    pub fn new(inner: E, timeout: Duration) -> Self {
+        Self { inner, timeout }
+    }
+
+    /// Get a reference to the wrapped embedder.
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    /// Consumes the wrapper, returning the wrapped embedder.
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+}
+
+impl<E> Embedder for TimeoutEmbedder<E>
+where
+    E: Embedder,
+{
+    async fn embed_text(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        with_timeout(self.timeout, self.inner.embed_text(input)).await
+    }
+
+    async fn embed_query(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        with_timeout(self.timeout, self.inner.embed_query(input)).await
+    }
+
+    async fn embed_document(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        with_timeout(self.timeout, self.inner.embed_document(input)).await
+    }
+
+    fn dims(&self) -> Option<usize> {
+        self.inner.dims()
+    }
+
+    fn max_input_tokens(&self) -> Option<usize> {
+        self.inner.max_input_tokens()
+    }
+
+    fn model_id(&self) -> Option<String> {
+        self.inner.model_id()
+    }
+}
+
+/// A storage wrapper that bounds every storage call with a deadline.
+///
+/// NOTE: A timed out write may still have been applied by the underlying storage.
+pub struct TimeoutStorage<S> {
+    inner: S,
+    timeout: Duration,
+}
+
+impl<S> TimeoutStorage<S>
+where
+    S: Storage,
+{
+    /// Wraps a storage, failing any storage call that takes longer than `timeout`.
+    pub fn new(inner: S, timeout: Duration) -> Self {
+        Self { inner, timeout }
+    }
+
+    /// Get a reference to the wrapped storage.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the wrapped storage.
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Consumes the wrapper, returning the wrapped storage.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S> Storage for TimeoutStorage<S>
+where
+    S: Storage,
+{
+    async fn insert(
+        &mut self,
+        embedding: Vec<f32>,
+        entry: MemoryEntry,
+    ) -> Result<(), crate::Error> {
+        with_timeout(self.timeout, self.inner.insert(embedding, entry)).await
+    }
+
+    async fn search(
+        &self,
+        embedding: Vec<f32>,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        with_timeout(self.timeout, self.inner.search(embedding, limit)).await
+    }
+
+    async fn search_by_id(&self, id: MemoryId) -> Result<SearchResult, crate::Error> {
+        with_timeout(self.timeout, self.inner.search_by_id(id)).await
+    }
+
+    async fn get_recent(&self, limit: usize) -> Result<Vec<SearchResult>, crate::Error> {
+        with_timeout(self.timeout, self.inner.get_recent(limit)).await
+    }
+
+    async fn delete(&mut self, id: MemoryId) -> Result<(), crate::Error> {
+        with_timeout(self.timeout, self.inner.delete(id)).await
+    }
+
+    async fn delete_batch(&mut self, ids: Vec<MemoryId>) -> Result<(), crate::Error> {
+        with_timeout(self.timeout, self.inner.delete_batch(ids)).await
+    }
+
+    async fn get_oldest(&self, limit: usize) -> Result<Vec<SearchResult>, crate::Error> {
+        with_timeout(self.timeout, self.inner.get_oldest(limit)).await
+    }
+
+    async fn get_between(&self, start: i64, end: i64) -> Result<Vec<SearchResult>, crate::Error> {
+        with_timeout(self.timeout, self.inner.get_between(start, end)).await
+    }
+
+    async fn get_oldest_page(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        with_timeout(self.timeout, self.inner.get_oldest_page(offset, limit)).await
+    }
+
+    async fn get_oldest_after(
+        &self,
+        created_at: i64,
+        id: MemoryId,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        with_timeout(
+            self.timeout,
+            self.inner.get_oldest_after(created_at, id, limit),
+        )
+        .await
+    }
+
+    async fn update_payload_by_id(
+        &mut self,
+        id: MemoryId,
+        payload: MemoryEntry,
+    ) -> Result<(), crate::Error> {
+        with_timeout(self.timeout, self.inner.update_payload_by_id(id, payload)).await
+    }
+
+    async fn count(&self) -> Result<usize, crate::Error> {
+        with_timeout(self.timeout, self.inner.count()).await
+    }
+
+    fn dims(&self) -> Option<usize> {
+        self.inner.dims()
+    }
+
+    fn requires_normalized_embeddings(&self) -> bool {
+        self.inner.requires_normalized_embeddings()
+    }
+
+    async fn health_check(&self) -> HealthStatus {
+        with_timeout(self.timeout, async { Ok(self.inner.health_check().await) })
+            .await
+            .unwrap_or_else(|err| HealthStatus::unhealthy(&err.to_string(), Some(self.timeout)))
+    }
+
+    async fn insert_quantized(
+        &mut self,
+        embedding: QuantizedVector,
+        entry: MemoryEntry,
+    ) -> Result<(), crate::Error> {
+        with_timeout(self.timeout, self.inner.insert_quantized(embedding, entry)).await
+    }
+
+    async fn search_quantized(
+        &self,
+        embedding: QuantizedVector,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        with_timeout(self.timeout, self.inner.search_quantized(embedding, limit)).await
+    }
+
+    async fn insert_sparse(
+        &mut self,
+        id: MemoryId,
+        sparse: SparseVector,
+    ) -> Result<(), crate::Error> {
+        with_timeout(self.timeout, self.inner.insert_sparse(id, sparse)).await
+    }
+
+    async fn search_hybrid(
+        &self,
+        embedding: Vec<f32>,
+        sparse: SparseVector,
+        limit: usize,
+        fusion: HybridFusion,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        with_timeout(
+            self.timeout,
+            self.inner.search_hybrid(embedding, sparse, limit, fusion),
+        )
+        .await
+    }
+
+    async fn insert_all(
+        &mut self,
+        entries: Vec<(Vec<f32>, MemoryEntry)>,
+    ) -> Result<(), crate::Error> {
+        with_timeout(self.timeout, self.inner.insert_all(entries)).await
+    }
+
+    async fn search_filtered(
+        &self,
+        embedding: Vec<f32>,
+        limit: usize,
+        filter: MetadataFilter,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        with_timeout(
+            self.timeout,
+            self.inner.search_filtered(embedding, limit, filter),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn hung_calls_time_out() {
+        let res: Result<(), _> =
+            with_timeout(Duration::from_millis(10), std::future::pending()).await;
+
+        assert!(matches!(res, Err(crate::Error::Timeout(_))));
+    }
+
+    /// A storage whose every call hangs.
+    struct HungStorage;
+
+    impl Storage for HungStorage {
+        async fn count(&self) -> Result<usize, crate::Error> {
+            std::future::pending().await
+        }
+
+        async fn delete(&mut self, _: MemoryId) -> Result<(), crate::Error> {
+            std::future::pending().await
+        }
+
+        async fn delete_batch(&mut self, _: Vec<MemoryId>) -> Result<(), crate::Error> {
+            std::future::pending().await
+        }
+
+        async fn get_oldest(&self, _: usize) -> Result<Vec<SearchResult>, crate::Error> {
+            std::future::pending().await
+        }
+
+        async fn get_recent(&self, _: usize) -> Result<Vec<SearchResult>, crate::Error> {
+            std::future::pending().await
+        }
+
+        async fn insert(&mut self, _: Vec<f32>, _: MemoryEntry) -> Result<(), crate::Error> {
+            std::future::pending().await
+        }
+
+        async fn search(&self, _: Vec<f32>, _: usize) -> Result<Vec<SearchResult>, crate::Error> {
+            std::future::pending().await
+        }
+
+        async fn search_by_id(&self, _: MemoryId) -> Result<SearchResult, crate::Error> {
+            std::future::pending().await
+        }
+
+        async fn update_payload_by_id(
+            &mut self,
+            _: MemoryId,
+            _: MemoryEntry,
+        ) -> Result<(), crate::Error> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn hung_health_checks_are_unhealthy() {
+        let timeout = Duration::from_millis(10);
+        let status = TimeoutStorage::new(HungStorage, timeout)
+            .health_check()
+            .await;
+
+        assert!(!status.reachable);
+        assert_eq!(status.latency, Some(timeout));
+        assert_eq!(
+            status.error,
+            Some(crate::Error::Timeout(timeout).to_string())
+        );
+    }
+}
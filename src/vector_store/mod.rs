@@ -0,0 +1,714 @@
+//! A Rust implementation of an in-memory vector store.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+
+use rand::seq::IteratorRandom;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    embed::SparseVector,
+    error::StorageError,
+    memory::{MemoryEntry, MemoryId},
+    storage::{HealthStatus, HybridFusion, MetadataFilter, SearchResult, Storage},
+};
+
+pub mod quantized;
+
+pub use quantized::QuantizedInMemoryDB;
+
+/// An in-memory vector store database. Used to store embeddings.
+/// This data structure primarily stores vectors as one long piece of contiguous memory, using separate hashmaps for entries, indexes as well as a separate vector for getting positions of soft-deleted payloads.
+///
+/// Each of these is copy-on-write, so taking a read snapshot (see [`InMemoryDB::read_snapshot`]) is cheap: the snapshot shares memory with the store until the store is next written to.
+/// The first write to each part while a snapshot is alive copies that part in full (eg, every embedding), so snapshots should be dropped once they're no longer needed.
+#[derive(Clone)]
+pub struct InMemoryDB {
+    /// The dimensions of the contained embeddings.
+    dim: usize,
+    /// The embedding data. Length is calculated by the dimension number plus the number of keys in `id_to_idx` + `free_list`.
+    data: Arc<Vec<f32>>,
+    /// A hashmap of currently existing string keys that map to a payload.
+    payloads: Arc<HashMap<MemoryId, MemoryEntry>>,
+    /// A hashmap of currently existing string keys that map to a position in `data`. The value represents the starting position of the vec.
+    id_to_idx: Arc<HashMap<MemoryId, usize>>,
+    /// A list of "deleted" keys. We keep these in memory because deleting the vec data in question and shifting everything along may become an extremely computationally intensive process when dealing with even just tens of thousands or hundreds of thousands of embeddings.
+    free_list: Arc<Vec<usize>>,
+    /// A hashmap of string keys that map to optional sparse embeddings, used for hybrid search.
+    sparse: Arc<HashMap<MemoryId, SparseVector>>,
+    /// An index of tags to the IDs of every entry with that tag.
+    tags: Arc<HashMap<String, HashSet<MemoryId>>>,
+}
+
+impl InMemoryDB {
+    pub fn new(dim: usize) -> Self {
+        Self {
+            dim,
+            data: Arc::default(),
+            payloads: Arc::default(),
+            id_to_idx: Arc::default(),
+            free_list: Arc::default(),
+            sparse: Arc::default(),
+            tags: Arc::default(),
+        }
+    }
+
+    /// Takes a consistent, read-only view of the store as it is right now (eg, to run a long scan or search without blocking writers).
+    /// Taking the snapshot is cheap, as it shares memory with the store. Writes to the store afterwards aren't visible in the snapshot.
+    /// Instead, the first write to each part of the store (the embeddings, payloads, ID index, sparse embeddings or tag index) while the snapshot is alive copies that whole part,
+    /// so writing a single memory can cost a full copy of the store. Later writes don't copy again until another snapshot is taken.
+    pub fn read_snapshot(&self) -> InMemoryDB {
+        self.clone()
+    }
+
+    /// The dimensions of the contained embeddings.
+    pub fn dims(&self) -> usize {
+        self.dim
+    }
+
+    fn matches_dim_size<R>(&self, embedding: R) -> bool
+    where
+        R: AsRef<[f32]>,
+    {
+        let array = embedding.as_ref();
+
+        array.len() == self.dim
+    }
+
+    pub(crate) fn fetch_embedding<S>(&self, id: S) -> Result<Vec<f32>, crate::Error>
+    where
+        S: AsRef<str>,
+    {
+        let id = id.as_ref();
+
+        let Some(&pos_offset) = self.id_to_idx.get(id) else {
+            return Err(StorageError::embedding_not_exists(id))?;
+        };
+
+        let arr = self.data[pos_offset..pos_offset + self.dim].to_vec();
+
+        Ok(arr)
+    }
+
+    /// Every entry in the store, oldest first (with ties broken by ID, so the order is stable between calls).
+    fn oldest_first(&self) -> Vec<&MemoryEntry> {
+        let mut entries: Vec<&MemoryEntry> = self.payloads.values().collect();
+        entries.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+
+        entries
+    }
+
+    /// Pairs entries up with their embeddings.
+    fn with_embeddings<'a, I>(&self, entries: I) -> Result<Vec<SearchResult>, crate::Error>
+    where
+        I: IntoIterator<Item = &'a MemoryEntry>,
+    {
+        entries
+            .into_iter()
+            .map(|payload| {
+                let embedding = self.fetch_embedding(&payload.id)?;
+
+                Ok(SearchResult::new(embedding, payload.clone()))
+            })
+            .collect()
+    }
+
+    /// Whether or not an entry with the given ID exists in the store.
+    pub fn contains<S>(&self, id: S) -> bool
+    where
+        S: AsRef<str>,
+    {
+        self.id_to_idx.contains_key(id.as_ref())
+    }
+
+    /// The payload of the entry with the given ID, if any.
+    pub(crate) fn payload(&self, id: &str) -> Option<&MemoryEntry> {
+        self.payloads.get(id)
+    }
+
+    /// The number of entries in the store.
+    pub(crate) fn len(&self) -> usize {
+        self.payloads.len()
+    }
+
+    /// Every entry in the store, in no particular order.
+    pub(crate) fn entries(&self) -> impl Iterator<Item = &MemoryEntry> {
+        self.payloads.values()
+    }
+
+    /// Gets every entry with a given tag (see [`MemoryEntry::tags`]) using the tag index, most recent first.
+    pub fn get_by_tag(&self, tag: &str) -> Vec<SearchResult> {
+        let Some(ids) = self.tags.get(tag) else {
+            return Vec::new();
+        };
+
+        let mut entries: Vec<&MemoryEntry> =
+            ids.iter().filter_map(|id| self.payloads.get(id)).collect();
+        entries.sort_by(|a, b| (b.created_at, &b.id).cmp(&(a.created_at, &a.id)));
+
+        entries
+            .into_iter()
+            .filter_map(|entry| {
+                let offset = *self.id_to_idx.get(&entry.id)?;
+                let embedding = self.data[offset..offset + self.dim].to_vec();
+
+                Some(SearchResult::new(embedding, entry.clone()))
+            })
+            .collect()
+    }
+
+    fn index_tags(&mut self, entry: &MemoryEntry) {
+        for tag in &entry.tags {
+            Arc::make_mut(&mut self.tags)
+                .entry(tag.clone())
+                .or_default()
+                .insert(entry.id.clone());
+        }
+    }
+
+    fn unindex_tags(&mut self, entry: &MemoryEntry) {
+        for tag in &entry.tags {
+            if let Some(ids) = Arc::make_mut(&mut self.tags).get_mut(tag) {
+                ids.remove(&entry.id);
+                if ids.is_empty() {
+                    Arc::make_mut(&mut self.tags).remove(tag);
+                }
+            }
+        }
+    }
+
+    /// Inserts an entry without going through the (async) [`Storage`] trait.
+    pub(crate) fn insert_entry(
+        &mut self,
+        embedding: Vec<f32>,
+        entry: MemoryEntry,
+    ) -> Result<(), crate::Error> {
+        if !self.matches_dim_size(&embedding) {
+            Err(StorageError::mismatched_dimensions(
+                self.dim,
+                embedding.len(),
+            ))?
+        }
+
+        let mut embedding = embedding;
+
+        let idx = if let Some(offset) = Arc::make_mut(&mut self.free_list).pop() {
+            // SAFETY: We already checked the dimensions of the embedding and the size of already-existing embeddings
+            Arc::make_mut(&mut self.data)[offset..offset + self.dim].copy_from_slice(&embedding);
+            offset
+        } else {
+            let vec_len = self.data.len();
+            Arc::make_mut(&mut self.data).append(&mut embedding);
+            vec_len
+        };
+
+        Arc::make_mut(&mut self.id_to_idx).insert(entry.id.clone(), idx);
+        if let Some(existing) = Arc::make_mut(&mut self.payloads).remove(&entry.id) {
+            self.unindex_tags(&existing);
+        }
+        self.index_tags(&entry);
+        Arc::make_mut(&mut self.payloads).insert(entry.id.clone(), entry);
+
+        Ok(())
+    }
+
+    /// Searches the store without going through the (async) [`Storage`] trait, returning the most similar entries along with their similarity.
+    pub(crate) fn search_scored(
+        &self,
+        embedding: &[f32],
+        limit: usize,
+    ) -> Vec<(f32, SearchResult)> {
+        self.search_scored_where(embedding, limit, |_| true)
+    }
+
+    /// Like [`InMemoryDB::search_scored`], but only scores entries matching a predicate.
+    pub(crate) fn search_scored_where<F>(
+        &self,
+        embedding: &[f32],
+        limit: usize,
+        predicate: F,
+    ) -> Vec<(f32, SearchResult)>
+    where
+        F: Fn(&MemoryEntry) -> bool,
+    {
+        let mut out = Vec::new();
+        let idx_map = &self.id_to_idx;
+        for (id, &offset) in idx_map.iter() {
+            if !self.payloads.get(id).is_some_and(&predicate) {
+                continue;
+            }
+
+            let arr = &self.data[offset..offset + self.dim];
+
+            let score = cosine_similarity(embedding, arr);
+
+            out.push((id, arr, score));
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(candidates = out.len(), "scored candidates");
+
+        // SAFETY: This should never fail because there's no reason that there would *not* be an ordering (ie, -0 vs 0 or NaN vs NaN)
+        out.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap());
+        out.truncate(limit);
+
+        out.into_iter()
+            .map(|(id, embedding, score)| {
+                // SAFETY: It is pretty much guaranteed that the payload will exist since the only way to access the payload list is through internal methods
+                let payload = self.payloads.get(id).cloned().unwrap();
+
+                (
+                    score,
+                    SearchResult::new(embedding.to_vec(), payload).with_score(score),
+                )
+            })
+            .collect()
+    }
+
+    /// Deletes an entry without going through the (async) [`Storage`] trait.
+    pub(crate) fn delete_entry(&mut self, id: &str) -> Result<(), crate::Error> {
+        let Some(arr_pos) = Arc::make_mut(&mut self.id_to_idx).remove(id) else {
+            return Err(StorageError::embedding_not_exists(id))?;
+        };
+
+        if let Some(entry) = Arc::make_mut(&mut self.payloads).remove(id) {
+            self.unindex_tags(&entry);
+        }
+        Arc::make_mut(&mut self.sparse).remove(id);
+        Arc::make_mut(&mut self.free_list).push(arr_pos);
+
+        Ok(())
+    }
+
+    /// Copies every entry in the store (along with its embedding and sparse embedding, if any) into a serializable snapshot.
+    pub fn snapshot(&self) -> InMemoryDBSnapshot {
+        let entries = self
+            .id_to_idx
+            .keys()
+            .filter_map(|id| self.snapshot_entry(id))
+            .collect();
+
+        InMemoryDBSnapshot {
+            dims: self.dim,
+            entries,
+        }
+    }
+
+    /// Copies a single entry (along with its embedding and sparse embedding, if any) into a snapshot entry.
+    pub(crate) fn snapshot_entry(&self, id: &str) -> Option<SnapshotEntry> {
+        let offset = *self.id_to_idx.get(id)?;
+
+        Some(SnapshotEntry {
+            embedding: self.data[offset..offset + self.dim].to_vec(),
+            entry: self.payloads.get(id)?.clone(),
+            sparse: self.sparse.get(id).cloned(),
+        })
+    }
+
+    /// Creates a store from a snapshot. Returns an error if any embedding doesn't match the dimensions of the snapshot.
+    pub fn from_snapshot(snapshot: InMemoryDBSnapshot) -> Result<Self, crate::Error> {
+        let mut db = Self::new(snapshot.dims);
+        for SnapshotEntry {
+            embedding,
+            entry,
+            sparse,
+        } in snapshot.entries
+        {
+            if !db.matches_dim_size(&embedding) {
+                Err(StorageError::mismatched_dimensions(db.dim, embedding.len()))?
+            }
+
+            Arc::make_mut(&mut db.id_to_idx).insert(entry.id.clone(), db.data.len());
+            Arc::make_mut(&mut db.data).extend(embedding);
+            if let Some(sparse) = sparse {
+                Arc::make_mut(&mut db.sparse).insert(entry.id.clone(), sparse);
+            }
+            db.index_tags(&entry);
+            Arc::make_mut(&mut db.payloads).insert(entry.id.clone(), entry);
+        }
+
+        Ok(db)
+    }
+
+    /// Saves a snapshot of the store to a file (as JSON), overwriting the file if it exists.
+    pub fn save_snapshot<P>(&self, path: P) -> Result<(), crate::Error>
+    where
+        P: AsRef<Path>,
+    {
+        write_json(path.as_ref(), &self.snapshot())
+    }
+
+    /// Loads a store from a snapshot file created by [`InMemoryDB::save_snapshot`].
+    pub fn load_snapshot<P>(path: P) -> Result<Self, crate::Error>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_snapshot(read_json(path.as_ref())?)
+    }
+
+    /// Random sampling of entries matching a predicate, using the `rand` crate.
+    pub(crate) fn random_sample<F>(&self, count: usize, predicate: F) -> Vec<&MemoryEntry>
+    where
+        F: Fn(&MemoryEntry) -> bool,
+    {
+        let mut rng = rand::rng();
+        self.payloads
+            .values()
+            .filter(|entry| predicate(entry))
+            .choose_multiple(&mut rng, count)
+    }
+}
+
+/// A serializable copy of an [`InMemoryDB`], created using [`InMemoryDB::snapshot`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct InMemoryDBSnapshot {
+    /// The dimensions of the contained embeddings.
+    pub dims: usize,
+    pub entries: Vec<SnapshotEntry>,
+}
+
+/// A single entry of an [`InMemoryDBSnapshot`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SnapshotEntry {
+    pub embedding: Vec<f32>,
+    pub entry: MemoryEntry,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sparse: Option<SparseVector>,
+}
+
+/// Writes a value to a file as JSON, creating the parent directory if it doesn't exist.
+pub(crate) fn write_json<T>(path: &Path, value: &T) -> Result<(), crate::Error>
+where
+    T: Serialize,
+{
+    let bytes = serde_json::to_vec(value)
+        .map_err(|err| crate::Error::wrap("Failed to serialize snapshot", err))?;
+
+    path.parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(path, bytes))
+        .map_err(|err| {
+            crate::Error::wrap(
+                format!("Failed to write snapshot to {}", path.display()),
+                err,
+            )
+        })
+}
+
+/// Reads a JSON value from a file written by [`write_json`].
+pub(crate) fn read_json<T>(path: &Path) -> Result<T, crate::Error>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    let bytes = std::fs::read(path).map_err(|err| {
+        crate::Error::wrap(
+            format!("Failed to read snapshot from {}", path.display()),
+            err,
+        )
+    })?;
+
+    serde_json::from_slice(&bytes)
+        .map_err(|err| crate::Error::wrap("Failed to parse snapshot", err))
+}
+
+impl Storage for InMemoryDB {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "in_memory_db.insert", skip_all, fields(memory_id = %entry.id))
+    )]
+    async fn insert(
+        &mut self,
+        embedding: Vec<f32>,
+        entry: crate::memory::MemoryEntry,
+    ) -> Result<(), crate::Error> {
+        self.insert_entry(embedding, entry)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "in_memory_db.search", skip_all, fields(limit = limit))
+    )]
+    async fn search(
+        &self,
+        embedding: Vec<f32>,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        Ok(self
+            .search_scored(&embedding, limit)
+            .into_iter()
+            .map(|(_, result)| result)
+            .collect())
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "in_memory_db.search_by_id", skip_all, fields(memory_id = %id))
+    )]
+    async fn search_by_id(&self, id: MemoryId) -> Result<SearchResult, crate::Error> {
+        let arr = self.fetch_embedding(&id)?;
+
+        let Some(payload) = self.payloads.get(&id).cloned() else {
+            return Err(StorageError::embedding_not_exists(&id))?;
+        };
+
+        let result = SearchResult::new(arr, payload);
+
+        Ok(result)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "in_memory_db.get_oldest", skip_all, fields(limit = limit))
+    )]
+    async fn get_oldest(&self, limit: usize) -> Result<Vec<SearchResult>, crate::Error> {
+        self.with_embeddings(self.oldest_first().into_iter().take(limit))
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "in_memory_db.get_oldest_page", skip_all, fields(offset = offset, limit = limit))
+    )]
+    async fn get_oldest_page(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        self.with_embeddings(self.oldest_first().into_iter().skip(offset).take(limit))
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "in_memory_db.get_oldest_after", skip_all, fields(created_at = created_at, memory_id = %id, limit = limit))
+    )]
+    async fn get_oldest_after(
+        &self,
+        created_at: i64,
+        id: MemoryId,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        self.with_embeddings(
+            self.oldest_first()
+                .into_iter()
+                .filter(|entry| (entry.created_at, &entry.id) > (created_at, &id))
+                .take(limit),
+        )
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "in_memory_db.get_recent", skip_all, fields(limit = limit))
+    )]
+    async fn get_recent(&self, limit: usize) -> Result<Vec<SearchResult>, crate::Error> {
+        let mut entries: Vec<_> = self.payloads.iter().map(|x| x.1.to_owned()).collect();
+
+        entries.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+        entries.truncate(limit);
+
+        let entries = entries
+            .into_iter()
+            .map(|payload| {
+                let embedding = self.fetch_embedding(&payload.id)?;
+
+                Ok(SearchResult::new(embedding, payload))
+            })
+            .collect::<Result<Vec<SearchResult>, crate::Error>>()?;
+
+        Ok(entries)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "in_memory_db.get_between", skip_all, fields(start = start, end = end))
+    )]
+    async fn get_between(&self, start: i64, end: i64) -> Result<Vec<SearchResult>, crate::Error> {
+        let mut entries: Vec<_> = self
+            .payloads
+            .values()
+            .filter(|entry| (start..end).contains(&entry.created_at))
+            .cloned()
+            .collect();
+
+        entries.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+
+        entries
+            .into_iter()
+            .map(|payload| {
+                let embedding = self.fetch_embedding(&payload.id)?;
+
+                Ok(SearchResult::new(embedding, payload))
+            })
+            .collect()
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "in_memory_db.delete", skip_all, fields(memory_id = %id))
+    )]
+    async fn delete(&mut self, id: MemoryId) -> Result<(), crate::Error> {
+        self.delete_entry(&id)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "in_memory_db.delete_batch", skip_all, fields(count = ids.len()))
+    )]
+    async fn delete_batch(&mut self, ids: Vec<MemoryId>) -> Result<(), crate::Error> {
+        for id in ids {
+            self.delete(id).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn count(&self) -> Result<usize, crate::Error> {
+        Ok(self.id_to_idx.len())
+    }
+
+    fn dims(&self) -> Option<usize> {
+        Some(self.dim)
+    }
+
+    async fn health_check(&self) -> HealthStatus {
+        HealthStatus::healthy(Some(std::time::Duration::ZERO))
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "in_memory_db.update_payload_by_id", skip_all, fields(memory_id = %id))
+    )]
+    async fn update_payload_by_id(
+        &mut self,
+        id: MemoryId,
+        payload: MemoryEntry,
+    ) -> Result<(), crate::Error> {
+        if let Some(existing) = Arc::make_mut(&mut self.payloads).remove(&id) {
+            self.unindex_tags(&existing);
+        }
+        self.index_tags(&payload);
+        Arc::make_mut(&mut self.payloads).insert(id, payload);
+
+        Ok(())
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "in_memory_db.insert_sparse", skip_all, fields(memory_id = %id))
+    )]
+    async fn insert_sparse(
+        &mut self,
+        id: MemoryId,
+        sparse: SparseVector,
+    ) -> Result<(), crate::Error> {
+        if !self.contains(&id) {
+            return Err(StorageError::embedding_not_exists(&id))?;
+        }
+
+        Arc::make_mut(&mut self.sparse).insert(id, sparse);
+
+        Ok(())
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "in_memory_db.search_hybrid", skip_all, fields(limit = limit))
+    )]
+    async fn search_hybrid(
+        &self,
+        embedding: Vec<f32>,
+        sparse: SparseVector,
+        limit: usize,
+        fusion: HybridFusion,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        let mut scored: Vec<(&MemoryId, f32, f32)> = self
+            .id_to_idx
+            .iter()
+            .map(|(id, &offset)| {
+                let dense = cosine_similarity(&embedding, &self.data[offset..offset + self.dim]);
+                let sparse = self.sparse.get(id).map_or(0.0, |x| x.dot(&sparse));
+                (id, dense, sparse)
+            })
+            .collect();
+
+        let mut fused: HashMap<&MemoryId, f32> = HashMap::with_capacity(scored.len());
+        match fusion {
+            HybridFusion::ReciprocalRank { k } => {
+                scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+                for (rank, (id, _, _)) in scored.iter().enumerate() {
+                    *fused.entry(id).or_default() += 1.0 / (k + rank as f32 + 1.0);
+                }
+
+                scored.sort_by(|a, b| b.2.total_cmp(&a.2));
+                // Memories without any overlapping terms aren't ranked by the sparse search
+                for (rank, (id, _, _)) in scored.iter().filter(|x| x.2 > 0.0).enumerate() {
+                    *fused.entry(id).or_default() += 1.0 / (k + rank as f32 + 1.0);
+                }
+            }
+            HybridFusion::Weighted { dense_weight } => {
+                let max_sparse = scored.iter().map(|x| x.2).fold(0.0, f32::max);
+                for (id, dense, sparse) in &scored {
+                    let sparse = if max_sparse > 0.0 {
+                        sparse / max_sparse
+                    } else {
+                        0.0
+                    };
+                    fused.insert(id, dense_weight * dense + (1.0 - dense_weight) * sparse);
+                }
+            }
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(candidates = fused.len(), "scored hybrid candidates");
+
+        let mut fused: Vec<(&MemoryId, f32)> = fused.into_iter().collect();
+        fused.sort_by(|a, b| b.1.total_cmp(&a.1));
+        fused.truncate(limit);
+
+        let out = fused
+            .into_iter()
+            .map(|(id, score)| {
+                let offset = self.id_to_idx[id];
+                let embedding = self.data[offset..offset + self.dim].to_vec();
+                // SAFETY: Every ID in `id_to_idx` has a payload
+                let payload = self.payloads.get(id).cloned().unwrap();
+
+                SearchResult::new(embedding, payload).with_score(score)
+            })
+            .collect();
+
+        Ok(out)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "in_memory_db.search_filtered", skip_all, fields(limit = limit))
+    )]
+    async fn search_filtered(
+        &self,
+        embedding: Vec<f32>,
+        limit: usize,
+        filter: MetadataFilter,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        Ok(self
+            .search_scored_where(&embedding, limit, |entry| filter.matches(entry))
+            .into_iter()
+            .map(|(_, result)| result)
+            .collect())
+    }
+}
+
+/// Computes the cosine similarity between two embeddings and returns a result between 0.0 and 1.0.
+pub(crate) fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let mut dot = 0.0;
+    let mut norm_a = 0.0;
+    let mut norm_b = 0.0;
+
+    for i in 0..a.len() {
+        dot += a[i] * b[i];
+        norm_a += a[i] * a[i];
+        norm_b += b[i] * b[i];
+    }
+
+    let cos = dot / (norm_a.sqrt() * norm_b.sqrt());
+    (cos + 1.0) / 2.0
+}
@@ -0,0 +1,190 @@
+//! An in-memory vector store that keeps embeddings int8-quantized.
+
+use std::collections::HashMap;
+
+use crate::{
+    embed::QuantizedVector,
+    error::StorageError,
+    memory::{MemoryEntry, MemoryId},
+    storage::{HealthStatus, SearchResult, Storage},
+    vector_store::cosine_similarity,
+};
+
+/// An in-memory vector store that stores embeddings as int8-quantized vectors, using roughly a quarter of the memory of [`super::InMemoryDB`].
+///
+/// Float embeddings are quantized on insert. Quantized embeddings (see [`crate::embed::QuantizedEmbedder`]) are stored as-is, so they never need to be converted to floats in between.
+/// Returned embeddings are dequantized, so they are an approximation of the originally inserted embeddings.
+pub struct QuantizedInMemoryDB {
+    /// The dimensions of the contained embeddings.
+    dim: usize,
+    /// A hashmap of string keys that map to a quantized embedding and its payload.
+    entries: HashMap<MemoryId, (QuantizedVector, MemoryEntry)>,
+}
+
+impl QuantizedInMemoryDB {
+    pub fn new(dim: usize) -> Self {
+        Self {
+            dim,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// The dimensions of the contained embeddings.
+    pub fn dims(&self) -> usize {
+        self.dim
+    }
+
+    /// Whether or not an entry with the given ID exists in the store.
+    pub fn contains<S>(&self, id: S) -> bool
+    where
+        S: AsRef<str>,
+    {
+        self.entries.contains_key(id.as_ref())
+    }
+
+    fn sorted_by<K, F>(&self, limit: usize, key: F) -> Vec<SearchResult>
+    where
+        K: Ord,
+        F: Fn(&MemoryEntry) -> K,
+    {
+        let mut entries: Vec<_> = self.entries.values().collect();
+
+        entries.sort_by_key(|(_, entry)| key(entry));
+        entries.truncate(limit);
+
+        entries
+            .into_iter()
+            .map(|(embedding, entry)| SearchResult::new(embedding.dequantize(), entry.clone()))
+            .collect()
+    }
+}
+
+impl Storage for QuantizedInMemoryDB {
+    async fn insert(
+        &mut self,
+        embedding: Vec<f32>,
+        entry: MemoryEntry,
+    ) -> Result<(), crate::Error> {
+        self.insert_quantized(QuantizedVector::quantize(&embedding), entry)
+            .await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "quantized_in_memory_db.insert", skip_all, fields(memory_id = %entry.id))
+    )]
+    async fn insert_quantized(
+        &mut self,
+        embedding: QuantizedVector,
+        entry: MemoryEntry,
+    ) -> Result<(), crate::Error> {
+        if embedding.len() != self.dim {
+            Err(StorageError::mismatched_dimensions(
+                self.dim,
+                embedding.len(),
+            ))?
+        }
+
+        self.entries.insert(entry.id.clone(), (embedding, entry));
+
+        Ok(())
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "quantized_in_memory_db.search", skip_all, fields(limit = limit))
+    )]
+    async fn search(
+        &self,
+        embedding: Vec<f32>,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        let mut scored: Vec<(f32, Vec<f32>, &MemoryEntry)> = self
+            .entries
+            .values()
+            .map(|(stored, entry)| {
+                let stored = stored.dequantize();
+                (cosine_similarity(&embedding, &stored), stored, entry)
+            })
+            .collect();
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(candidates = scored.len(), "scored candidates");
+
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored.truncate(limit);
+
+        Ok(scored
+            .into_iter()
+            .map(|(score, embedding, entry)| {
+                SearchResult::new(embedding, entry.clone()).with_score(score)
+            })
+            .collect())
+    }
+
+    async fn search_quantized(
+        &self,
+        embedding: QuantizedVector,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        self.search(embedding.dequantize(), limit).await
+    }
+
+    async fn search_by_id(&self, id: MemoryId) -> Result<SearchResult, crate::Error> {
+        let Some((embedding, entry)) = self.entries.get(&id) else {
+            return Err(StorageError::embedding_not_exists(&id))?;
+        };
+
+        Ok(SearchResult::new(embedding.dequantize(), entry.clone()))
+    }
+
+    async fn get_oldest(&self, limit: usize) -> Result<Vec<SearchResult>, crate::Error> {
+        Ok(self.sorted_by(limit, |entry| (entry.created_at, entry.id.clone())))
+    }
+
+    async fn get_recent(&self, limit: usize) -> Result<Vec<SearchResult>, crate::Error> {
+        Ok(self.sorted_by(limit, |entry| std::cmp::Reverse(entry.created_at)))
+    }
+
+    async fn delete(&mut self, id: MemoryId) -> Result<(), crate::Error> {
+        if self.entries.remove(&id).is_none() {
+            return Err(StorageError::embedding_not_exists(&id))?;
+        }
+
+        Ok(())
+    }
+
+    async fn delete_batch(&mut self, ids: Vec<MemoryId>) -> Result<(), crate::Error> {
+        for id in ids {
+            self.delete(id).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn count(&self) -> Result<usize, crate::Error> {
+        Ok(self.entries.len())
+    }
+
+    fn dims(&self) -> Option<usize> {
+        Some(self.dim)
+    }
+
+    async fn health_check(&self) -> HealthStatus {
+        HealthStatus::healthy(Some(std::time::Duration::ZERO))
+    }
+
+    async fn update_payload_by_id(
+        &mut self,
+        id: MemoryId,
+        payload: MemoryEntry,
+    ) -> Result<(), crate::Error> {
+        let Some((_, entry)) = self.entries.get_mut(&id) else {
+            return Err(StorageError::embedding_not_exists(&id))?;
+        };
+
+        *entry = payload;
+
+        Ok(())
+    }
+}
@@ -23,3 +23,217 @@ pub trait WasmCompatSync {}
 
 #[cfg(all(target_arch = "wasm32", feature = "wasm"))]
 impl<T> WasmCompatSync for T {}
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+#[cfg_attr(docsrs, doc(cfg(all(target_arch = "wasm32", feature = "wasm"))))]
+pub mod fetch;
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+#[cfg_attr(docsrs, doc(cfg(all(target_arch = "wasm32", feature = "wasm"))))]
+pub mod indexed_db;
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+#[cfg_attr(docsrs, doc(cfg(all(target_arch = "wasm32", feature = "wasm"))))]
+pub mod js;
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+#[cfg_attr(docsrs, doc(cfg(all(target_arch = "wasm32", feature = "wasm"))))]
+pub mod local_storage;
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+#[cfg_attr(docsrs, doc(cfg(all(target_arch = "wasm32", feature = "wasm"))))]
+pub mod opfs;
+
+/// Checks that the TypeScript types given to JavaScript (see `wasm/types.d.ts`) match the `serde` representations of the types they describe.
+/// These run natively, so the types are checked without a WASM toolchain.
+#[cfg(test)]
+mod tests {
+    use serde::{Serialize, de::DeserializeOwned};
+
+    use crate::{
+        embed::ImageSource,
+        memory::{
+            Confidence, EmbeddingModelTag, MemoryEntry, MemoryKind, Visibility,
+            manager::{CacheMode, RetrieveOptions},
+        },
+        test_util::entry,
+    };
+
+    const TS_TYPES: &str = include_str!("wasm/types.d.ts");
+
+    /// The fields of an interface, along with their types and whether or not they're optional.
+    fn interface_fields(name: &str) -> Vec<(String, String, bool)> {
+        let start = TS_TYPES
+            .find(&format!("export interface {name} {{"))
+            .unwrap_or_else(|| panic!("`{name}` isn't declared"));
+        let body = &TS_TYPES[start..];
+        let body = &body[body.find('{').unwrap() + 1..body.find('}').unwrap()];
+
+        body.lines()
+            .filter_map(|line| line.trim().strip_suffix(';'))
+            .map(|field| {
+                let (field, ty) = field.split_once(':').unwrap();
+                let (field, optional) = match field.strip_suffix('?') {
+                    Some(field) => (field, true),
+                    None => (field, false),
+                };
+
+                (field.trim().to_string(), ty.trim().to_string(), optional)
+            })
+            .collect()
+    }
+
+    /// The type a type alias is declared as.
+    fn type_alias(name: &str) -> &'static str {
+        let prefix = format!("export type {name} = ");
+        let start = TS_TYPES
+            .find(&prefix)
+            .unwrap_or_else(|| panic!("`{name}` isn't declared"))
+            + prefix.len();
+
+        &TS_TYPES[start..start + TS_TYPES[start..].find(';').unwrap()]
+    }
+
+    /// The string literals in a union type (eg, `"Low" | "High"`).
+    fn literals(ty: &str) -> Vec<String> {
+        let mut literals: Vec<String> = ty
+            .split('|')
+            .map(|member| member.trim().trim_matches('"').to_string())
+            .collect();
+        literals.sort();
+        literals
+    }
+
+    /// The values a unit enum serializes to.
+    fn variants<T>(variants: &[T]) -> Vec<String>
+    where
+        T: Serialize,
+    {
+        let mut variants: Vec<String> = variants
+            .iter()
+            .map(|variant| {
+                serde_json::to_value(variant)
+                    .unwrap()
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        variants.sort();
+        variants
+    }
+
+    /// Checks that an interface declares exactly the fields a value serializes to, and that only fields that can be left out when deserializing are optional.
+    fn assert_matches_interface<T>(name: &str, value: &T)
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let json = serde_json::to_value(value).unwrap();
+        let object = json.as_object().unwrap();
+        let fields = interface_fields(name);
+
+        let mut declared: Vec<&str> = fields.iter().map(|(field, ..)| field.as_str()).collect();
+        let mut serialized: Vec<&str> = object.keys().map(String::as_str).collect();
+        declared.sort();
+        serialized.sort();
+        assert_eq!(
+            declared, serialized,
+            "the fields of `{name}` are out of sync"
+        );
+
+        for (field, _, optional) in &fields {
+            let mut without = object.clone();
+            without.remove(field);
+            if *optional {
+                assert!(
+                    serde_json::from_value::<T>(without.into()).is_ok(),
+                    "`{name}.{field}` is optional, but is required when deserializing"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn typescript_types_match_serde() {
+        let entry = MemoryEntry {
+            metadata: crate::metadata! { "source" => "chat" },
+            embedding_model: Some(EmbeddingModelTag {
+                model: "test".to_string(),
+                dims: 4,
+            }),
+            image: Some(ImageSource::Uri("https://example.com/cat.png".to_string())),
+            tags: vec!["imported".to_string()],
+            ..entry("mem-1", "User has a cat")
+        };
+
+        assert_matches_interface("MemoryEntry", &entry);
+        assert_matches_interface("MetadataEntry", &entry.metadata[0]);
+        assert_matches_interface("EmbeddingModelTag", entry.embedding_model.as_ref().unwrap());
+        assert_matches_interface("RetrieveOptions", &RetrieveOptions::new());
+
+        assert_eq!(
+            literals(type_alias("MemoryKind")),
+            variants(&[
+                MemoryKind::Working,
+                MemoryKind::Episodic,
+                MemoryKind::Semantic,
+                MemoryKind::Procedural
+            ])
+        );
+        assert_eq!(
+            literals(type_alias("Confidence")),
+            variants(&[Confidence::Low, Confidence::Medium, Confidence::High])
+        );
+        assert_eq!(
+            literals(type_alias("Visibility")),
+            variants(&[Visibility::Private, Visibility::Shared])
+        );
+
+        let (_, cache_mode, _) = interface_fields("RetrieveOptions")
+            .into_iter()
+            .find(|(field, ..)| field == "cache_mode")
+            .unwrap();
+        assert_eq!(
+            literals(&cache_mode),
+            variants(&[CacheMode::Default, CacheMode::Bypass, CacheMode::CacheOnly])
+        );
+
+        for image in [
+            ImageSource::Uri("https://example.com/cat.png".to_string()),
+            ImageSource::Bytes(vec![1, 2, 3]),
+        ] {
+            let json = serde_json::to_value(&image).unwrap();
+            let (variant, _) = json.as_object().unwrap().iter().next().unwrap();
+            assert!(type_alias("ImageSource").contains(&format!("{{ {variant}: ")));
+        }
+    }
+
+    /// Adding a variant to any of these enums fails to compile until it's added here (and to `wasm/types.d.ts`).
+    #[allow(dead_code)]
+    fn variants_are_exhaustive(
+        kind: MemoryKind,
+        confidence: Confidence,
+        visibility: Visibility,
+        cache_mode: CacheMode,
+        image: ImageSource,
+    ) {
+        match kind {
+            MemoryKind::Working
+            | MemoryKind::Episodic
+            | MemoryKind::Semantic
+            | MemoryKind::Procedural => {}
+        }
+        match confidence {
+            Confidence::Low | Confidence::Medium | Confidence::High => {}
+        }
+        match visibility {
+            Visibility::Private | Visibility::Shared => {}
+        }
+        match cache_mode {
+            CacheMode::Default | CacheMode::Bypass | CacheMode::CacheOnly => {}
+        }
+        match image {
+            ImageSource::Uri(_) | ImageSource::Bytes(_) => {}
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! A lightweight embedder for OpenAI-compatible embeddings APIs, using the browser's `fetch` rather than a full HTTP client.
+//!
+//! This works with OpenAI itself as well as any server exposing an OpenAI-compatible `/embeddings` endpoint (eg, Ollama, vLLM or llama.cpp).
+//! Note that an API key used from a browser is visible to the user, so for public-facing apps you'll usually want to point this at your own proxy instead.
+//!
+//! ```rust,ignore
+//! let embedder = FetchEmbedder::new("text-embedding-3-small")
+//!     .base_url("https://my-app.example/api/v1")
+//!     .dimensions(512);
+//! ```
+
+use js_sys::{Function, Promise, Reflect};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Headers, Request, RequestInit, Response};
+
+use super::js::js_error;
+
+const OPENAI_API_BASE_URL: &str = "https://api.openai.com/v1";
+
+/// A text embedder that calls an OpenAI-compatible embeddings API using `fetch`, made compliant to work with the `Embedder` trait.
+pub struct FetchEmbedder {
+    api_key: Option<String>,
+    model: String,
+    base_url: String,
+    dimensions: Option<usize>,
+}
+
+impl FetchEmbedder {
+    /// Creates a new instance of `FetchEmbedder` using a given model, calling the OpenAI API without an API key.
+    pub fn new<S>(model: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            api_key: None,
+            model: model.into(),
+            base_url: OPENAI_API_BASE_URL.to_string(),
+            dimensions: None,
+        }
+    }
+
+    /// Sets the API key, which is sent as a bearer token.
+    pub fn api_key<S>(mut self, api_key: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Sets the base URL of the API (eg, for proxies or self-hosted servers). Defaults to the OpenAI API.
+    pub fn base_url<S>(mut self, base_url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Truncates the output embeddings to a given number of dimensions. Only supported by some models (eg, `text-embedding-3` and later).
+    pub fn dimensions(mut self, dims: usize) -> Self {
+        self.dimensions = Some(dims);
+        self
+    }
+
+    async fn fetch(&self, request: &Request) -> Result<Response, crate::Error> {
+        // `fetch` is available on both windows and workers
+        let fetch: Function = Reflect::get(&js_sys::global(), &JsValue::from_str("fetch"))
+            .map_err(js_error)?
+            .dyn_into()
+            .map_err(|_| crate::Error::custom("fetch isn't available"))?;
+        let promise: Promise = fetch
+            .call1(&JsValue::NULL, request)
+            .map_err(js_error)?
+            .unchecked_into();
+
+        Ok(JsFuture::from(promise)
+            .await
+            .map_err(js_error)?
+            .unchecked_into())
+    }
+}
+
+#[derive(Serialize)]
+struct EmbeddingsRequest<'a> {
+    model: &'a str,
+    input: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dimensions: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingsResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    embedding: Vec<f32>,
+}
+
+impl crate::embed::Embedder for FetchEmbedder {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "fetch_embed_text", skip_all, fields(input_len = text.len()))
+    )]
+    async fn embed_text(&self, text: &str) -> Result<Vec<f32>, crate::Error> {
+        let body = serde_json::to_string(&EmbeddingsRequest {
+            model: &self.model,
+            input: text,
+            dimensions: self.dimensions,
+        })
+        .map_err(|err| crate::Error::wrap("Failed to serialize request", err))?;
+
+        let headers = Headers::new().map_err(js_error)?;
+        headers
+            .set("Content-Type", "application/json")
+            .map_err(js_error)?;
+        if let Some(api_key) = &self.api_key {
+            headers
+                .set("Authorization", &format!("Bearer {api_key}"))
+                .map_err(js_error)?;
+        }
+
+        let init = RequestInit::new();
+        init.set_method("POST");
+        init.set_headers(&headers);
+        init.set_body(&JsValue::from_str(&body));
+        let request =
+            Request::new_with_str_and_init(&format!("{}/embeddings", self.base_url), &init)
+                .map_err(js_error)?;
+
+        let response = self.fetch(&request).await?;
+        let text = JsFuture::from(response.text().map_err(js_error)?)
+            .await
+            .map_err(js_error)?
+            .as_string()
+            .unwrap_or_default();
+
+        if !response.ok() {
+            return Err(crate::Error::Custom(format!(
+                "Request failed with status {}: {text}",
+                response.status()
+            )));
+        }
+
+        let response: EmbeddingsResponse = serde_json::from_str(&text)
+            .map_err(|err| crate::Error::wrap("Failed to parse response", err))?;
+
+        response
+            .data
+            .into_iter()
+            .next()
+            .map(|data| data.embedding)
+            .ok_or_else(|| crate::Error::custom("The embeddings API returned no embeddings"))
+    }
+
+    fn dims(&self) -> Option<usize> {
+        self.dimensions.or(match self.model.as_str() {
+            "text-embedding-3-small" | "text-embedding-ada-002" => Some(1536),
+            "text-embedding-3-large" => Some(3072),
+            _ => None,
+        })
+    }
+
+    /// Uses the same model IDs as `OpenAIEmbedder`, so memories embedded natively can be used in the browser (and vice versa).
+    fn model_id(&self) -> Option<String> {
+        Some(match self.dimensions {
+            Some(dims) => format!("openai/{}/{dims}", self.model),
+            None => format!("openai/{}", self.model),
+        })
+    }
+}
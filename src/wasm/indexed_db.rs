@@ -0,0 +1,243 @@
+//! A [`Storage`] implementation backed by IndexedDB, so that browser-based agents keep their memories across page reloads.
+//!
+//! Every memory is written through to an IndexedDB object store (in the same format as an [`InMemoryDBSnapshot`] entry),
+//! while searches are served from an [`InMemoryDB`] that is loaded from IndexedDB when the storage is opened.
+//!
+//! ```rust,ignore
+//! let storage = IndexedDbStorage::open("braindump", 384).await?;
+//! let manager = MemoryManager::builder()
+//!     .embedder(embedder)
+//!     .storage(storage)
+//!     .build()?;
+//! ```
+
+use idb::{
+    Database, TransactionMode,
+    builder::{DatabaseBuilder, ObjectStoreBuilder},
+};
+use serde::Serialize;
+use wasm_bindgen::JsValue;
+
+use crate::{
+    embed::SparseVector,
+    memory::{MemoryEntry, MemoryId},
+    storage::{HybridFusion, MetadataFilter, SearchResult, Storage},
+    vector_store::{InMemoryDB, InMemoryDBSnapshot, SnapshotEntry},
+};
+
+/// The name of the object store that memories are kept in.
+const STORE_NAME: &str = "memories";
+
+/// The version of the IndexedDB database. This should be bumped whenever the object stores change.
+const DB_VERSION: u32 = 1;
+
+/// A storage that persists memories to IndexedDB, keeping an in-memory copy for searching.
+pub struct IndexedDbStorage {
+    db: Database,
+    inner: InMemoryDB,
+}
+
+impl IndexedDbStorage {
+    /// Opens (or creates) the IndexedDB database with the given name, loading every stored memory.
+    /// Returns an error if IndexedDB isn't available, or if the stored embeddings don't have the given dimensions.
+    pub async fn open(name: &str, dims: usize) -> Result<Self, crate::Error> {
+        let db = DatabaseBuilder::new(name)
+            .version(DB_VERSION)
+            .add_object_store(ObjectStoreBuilder::new(STORE_NAME))
+            .build()
+            .await
+            .map_err(idb_error)?;
+
+        let transaction = db
+            .transaction(&[STORE_NAME], TransactionMode::ReadOnly)
+            .map_err(idb_error)?;
+        let values = transaction
+            .object_store(STORE_NAME)
+            .map_err(idb_error)?
+            .get_all(None, None)
+            .map_err(idb_error)?
+            .await
+            .map_err(idb_error)?;
+        transaction.await.map_err(idb_error)?;
+
+        let entries = values
+            .into_iter()
+            .map(serde_wasm_bindgen::from_value)
+            .collect::<Result<Vec<SnapshotEntry>, _>>()
+            .map_err(|err| crate::Error::Custom(format!("Failed to parse stored memory: {err}")))?;
+        let inner = InMemoryDB::from_snapshot(InMemoryDBSnapshot { dims, entries })?;
+
+        Ok(Self { db, inner })
+    }
+
+    /// The name of the underlying IndexedDB database.
+    pub fn name(&self) -> String {
+        self.db.name()
+    }
+
+    /// Writes the current state of the given memories to IndexedDB, deleting any that no longer exist in memory.
+    async fn sync(&self, ids: &[MemoryId]) -> Result<(), crate::Error> {
+        let transaction = self
+            .db
+            .transaction(&[STORE_NAME], TransactionMode::ReadWrite)
+            .map_err(idb_error)?;
+        let store = transaction.object_store(STORE_NAME).map_err(idb_error)?;
+
+        for id in ids {
+            let key = JsValue::from_str(id);
+            match self.inner.snapshot_entry(id) {
+                Some(entry) => {
+                    let value = entry
+                        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+                        .map_err(|err| {
+                            crate::Error::Custom(format!("Failed to serialize memory: {err}"))
+                        })?;
+                    store
+                        .put(&value, Some(&key))
+                        .map_err(idb_error)?
+                        .await
+                        .map_err(idb_error)?;
+                }
+                None => {
+                    store
+                        .delete(key)
+                        .map_err(idb_error)?
+                        .await
+                        .map_err(idb_error)?;
+                }
+            }
+        }
+
+        let result = transaction
+            .commit()
+            .map_err(idb_error)?
+            .await
+            .map_err(idb_error)?;
+        if result.is_aborted() {
+            Err(crate::Error::custom("IndexedDB transaction was aborted"))?
+        }
+
+        Ok(())
+    }
+}
+
+fn idb_error(err: idb::Error) -> crate::Error {
+    crate::Error::Custom(format!("IndexedDB error: {err}"))
+}
+
+impl Storage for IndexedDbStorage {
+    async fn insert(
+        &mut self,
+        embedding: Vec<f32>,
+        entry: MemoryEntry,
+    ) -> Result<(), crate::Error> {
+        let id = entry.id.clone();
+        self.inner.insert(embedding, entry).await?;
+
+        self.sync(&[id]).await
+    }
+
+    async fn search(
+        &self,
+        embedding: Vec<f32>,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        self.inner.search(embedding, limit).await
+    }
+
+    async fn search_by_id(&self, id: MemoryId) -> Result<SearchResult, crate::Error> {
+        self.inner.search_by_id(id).await
+    }
+
+    async fn get_recent(&self, limit: usize) -> Result<Vec<SearchResult>, crate::Error> {
+        self.inner.get_recent(limit).await
+    }
+
+    async fn delete(&mut self, id: MemoryId) -> Result<(), crate::Error> {
+        self.inner.delete(id.clone()).await?;
+
+        self.sync(&[id]).await
+    }
+
+    async fn delete_batch(&mut self, ids: Vec<MemoryId>) -> Result<(), crate::Error> {
+        let result = self.inner.delete_batch(ids.clone()).await;
+        // Some of the memories may have been deleted before an error, so they still need to be removed from IndexedDB
+        self.sync(&ids).await?;
+
+        result
+    }
+
+    async fn get_oldest(&self, limit: usize) -> Result<Vec<SearchResult>, crate::Error> {
+        self.inner.get_oldest(limit).await
+    }
+
+    async fn get_between(&self, start: i64, end: i64) -> Result<Vec<SearchResult>, crate::Error> {
+        self.inner.get_between(start, end).await
+    }
+
+    async fn get_oldest_page(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        self.inner.get_oldest_page(offset, limit).await
+    }
+
+    async fn get_oldest_after(
+        &self,
+        created_at: i64,
+        id: MemoryId,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        self.inner.get_oldest_after(created_at, id, limit).await
+    }
+
+    async fn update_payload_by_id(
+        &mut self,
+        id: MemoryId,
+        payload: MemoryEntry,
+    ) -> Result<(), crate::Error> {
+        self.inner.update_payload_by_id(id.clone(), payload).await?;
+
+        self.sync(&[id]).await
+    }
+
+    async fn count(&self) -> Result<usize, crate::Error> {
+        self.inner.count().await
+    }
+
+    fn dims(&self) -> Option<usize> {
+        Some(self.inner.dims())
+    }
+
+    async fn insert_sparse(
+        &mut self,
+        id: MemoryId,
+        sparse: SparseVector,
+    ) -> Result<(), crate::Error> {
+        self.inner.insert_sparse(id.clone(), sparse).await?;
+
+        self.sync(&[id]).await
+    }
+
+    async fn search_hybrid(
+        &self,
+        embedding: Vec<f32>,
+        sparse: SparseVector,
+        limit: usize,
+        fusion: HybridFusion,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        self.inner
+            .search_hybrid(embedding, sparse, limit, fusion)
+            .await
+    }
+
+    async fn search_filtered(
+        &self,
+        embedding: Vec<f32>,
+        limit: usize,
+        filter: MetadataFilter,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        self.inner.search_filtered(embedding, limit, filter).await
+    }
+}
@@ -0,0 +1,208 @@
+//! A JavaScript/TypeScript API using `wasm-bindgen`, so that browser-based agents can use the memory system directly.
+//!
+//! Memories are stored in an [`InMemoryDB`], and embedded using a JavaScript function:
+//!
+//! ```ts
+//! import { MemoryManager } from "braindump";
+//!
+//! const manager = new MemoryManager(async (text) => embed(text), 384);
+//! await manager.store({ id: "mem-1", content: "The user's favourite colour is green", ... });
+//! const memories = await manager.retrieve("What colour does the user like?", 5, { cache_mode: "bypass" });
+//! ```
+//!
+//! Memory entries and retrieval options use the same JSON representation as their `serde` implementations (see the generated TypeScript types).
+
+use std::rc::Rc;
+
+use futures::lock::Mutex;
+use js_sys::{Float32Array, Function, Promise};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::{JsFuture, future_to_promise};
+
+use crate::{
+    embed::Embedder,
+    memory::{
+        MemoryEntry,
+        manager::{MemoryManager, RetrieveOptions},
+    },
+    vector_store::InMemoryDB,
+};
+
+/// The TypeScript types of the JSON values passed to and from JavaScript. These are kept in sync with the `serde` representations by the tests in [`crate::wasm`].
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPES: &'static str = include_str!("types.d.ts");
+
+/// An embedder that calls a JavaScript function, which can return an array of numbers (or a `Float32Array`), or a promise of one.
+pub struct JsEmbedder {
+    embed: Function,
+    dims: usize,
+    model_id: Option<String>,
+}
+
+impl JsEmbedder {
+    /// Creates an embedder from a function creating embeddings with the given number of dimensions.
+    pub fn new(embed: Function, dims: usize) -> Self {
+        Self {
+            embed,
+            dims,
+            model_id: None,
+        }
+    }
+
+    /// Sets the identifier of the embedding model behind the function (see [`Embedder::model_id`]).
+    pub fn with_model_id<S>(mut self, model_id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.model_id = Some(model_id.into());
+        self
+    }
+}
+
+impl Embedder for JsEmbedder {
+    async fn embed_text(&self, input: &str) -> Result<Vec<f32>, crate::Error> {
+        let mut value = self
+            .embed
+            .call1(&JsValue::NULL, &JsValue::from_str(input))
+            .map_err(js_error)?;
+        if value.is_instance_of::<Promise>() {
+            value = JsFuture::from(Promise::from(value))
+                .await
+                .map_err(js_error)?;
+        }
+
+        if value.is_instance_of::<Float32Array>() {
+            return Ok(Float32Array::from(value).to_vec());
+        }
+
+        serde_wasm_bindgen::from_value(value).map_err(|err| crate::Error::Custom(err.to_string()))
+    }
+
+    fn dims(&self) -> Option<usize> {
+        Some(self.dims)
+    }
+
+    fn model_id(&self) -> Option<String> {
+        self.model_id.clone()
+    }
+}
+
+pub(crate) fn js_error(err: JsValue) -> crate::Error {
+    crate::Error::Custom(
+        err.as_string()
+            .unwrap_or_else(|| format!("JavaScript error: {err:?}")),
+    )
+}
+
+fn to_js_error(err: crate::Error) -> JsValue {
+    js_sys::Error::new(&err.to_string()).into()
+}
+
+/// A memory manager, storing memories in memory and embedding them using a JavaScript function.
+#[wasm_bindgen(js_name = MemoryManager)]
+pub struct JsMemoryManager {
+    inner: Rc<Mutex<MemoryManager<JsEmbedder, InMemoryDB>>>,
+}
+
+#[wasm_bindgen(js_class = MemoryManager)]
+impl JsMemoryManager {
+    /// Creates a memory manager, using a function to embed memories into embeddings with the given number of dimensions.
+    /// `modelId` identifies the embedding model behind the function, so memories embedded by a different model are caught when retrieving them. If it's omitted, memories are left untagged.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        #[wasm_bindgen(unchecked_param_type = "EmbedFunction")] embed: Function,
+        dims: usize,
+        #[wasm_bindgen(js_name = modelId)] model_id: Option<String>,
+    ) -> Result<JsMemoryManager, JsValue> {
+        let mut embedder = JsEmbedder::new(embed, dims);
+        if let Some(model_id) = model_id {
+            embedder = embedder.with_model_id(model_id);
+        }
+
+        let manager = MemoryManager::builder()
+            .embedder(embedder)
+            .storage(InMemoryDB::new(dims))
+            .build()
+            .map_err(to_js_error)?;
+
+        Ok(Self {
+            inner: Rc::new(Mutex::new(manager)),
+        })
+    }
+
+    /// Stores a memory, embedding its content.
+    #[wasm_bindgen(unchecked_return_type = "Promise<void>")]
+    pub fn store(
+        &self,
+        #[wasm_bindgen(unchecked_param_type = "MemoryEntry")] entry: JsValue,
+    ) -> Result<Promise, JsValue> {
+        let entry: MemoryEntry = serde_wasm_bindgen::from_value(entry)?;
+        let inner = self.inner.clone();
+
+        Ok(future_to_promise(async move {
+            let content = entry.content.clone();
+            inner
+                .lock()
+                .await
+                .store(content, entry)
+                .await
+                .map_err(to_js_error)?;
+
+            Ok(JsValue::UNDEFINED)
+        }))
+    }
+
+    /// Retrieves the memories most relevant to a query. Defaults to 5 memories.
+    #[wasm_bindgen(unchecked_return_type = "Promise<MemoryEntry[]>")]
+    pub fn retrieve(
+        &self,
+        query: String,
+        limit: Option<usize>,
+        #[wasm_bindgen(unchecked_param_type = "RetrieveOptions | undefined")] options: JsValue,
+    ) -> Result<Promise, JsValue> {
+        let options: RetrieveOptions = if options.is_undefined() || options.is_null() {
+            RetrieveOptions::default()
+        } else {
+            serde_wasm_bindgen::from_value(options)?
+        };
+        let inner = self.inner.clone();
+
+        Ok(future_to_promise(async move {
+            let results = inner
+                .lock()
+                .await
+                .retrieve_with_options(query, limit.unwrap_or(5), options)
+                .await
+                .map_err(to_js_error)?;
+            let entries: Vec<MemoryEntry> =
+                results.iter().map(|result| result.data_owned()).collect();
+
+            Ok(entries.serialize(&serde_wasm_bindgen::Serializer::json_compatible())?)
+        }))
+    }
+
+    /// Deletes a memory.
+    #[wasm_bindgen(unchecked_return_type = "Promise<void>")]
+    pub fn delete(&self, id: String) -> Promise {
+        let inner = self.inner.clone();
+
+        future_to_promise(async move {
+            inner.lock().await.delete(&id).await.map_err(to_js_error)?;
+
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+
+    /// The number of stored memories.
+    #[wasm_bindgen(unchecked_return_type = "Promise<number>")]
+    pub fn count(&self) -> Promise {
+        let inner = self.inner.clone();
+
+        future_to_promise(async move {
+            let count = inner.lock().await.count().await.map_err(to_js_error)?;
+
+            Ok(JsValue::from(count as f64))
+        })
+    }
+}
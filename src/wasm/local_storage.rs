@@ -0,0 +1,202 @@
+//! A small [`Storage`] implementation backed by `localStorage`, for lightweight browser extensions that only need to keep a few hundred memories.
+//!
+//! The whole store is kept under a single `localStorage` key as a JSON [`InMemoryDBSnapshot`], and rewritten on every change.
+//! `localStorage` is typically limited to around 5MB per origin, so the number of entries is capped: once the cap is reached, inserting a new memory evicts the oldest one.
+
+use js_sys::Reflect;
+use wasm_bindgen::{JsCast, JsValue};
+
+use super::js::js_error;
+use crate::{
+    embed::SparseVector,
+    error::StorageError,
+    memory::{MemoryEntry, MemoryId},
+    storage::{HybridFusion, MetadataFilter, SearchResult, Storage},
+    vector_store::{InMemoryDB, InMemoryDBSnapshot},
+};
+
+/// The default maximum number of entries held by a [`LocalStorageStore`].
+pub const DEFAULT_MAX_ENTRIES: usize = 500;
+
+/// A storage that persists memories to `localStorage`, holding up to a maximum number of entries.
+pub struct LocalStorageStore {
+    storage: web_sys::Storage,
+    key: String,
+    max_entries: usize,
+    inner: InMemoryDB,
+}
+
+impl LocalStorageStore {
+    /// Opens the store kept under the given `localStorage` key (creating an empty store if there isn't one), holding up to [`DEFAULT_MAX_ENTRIES`] entries.
+    /// Returns an error if `localStorage` isn't available (eg, in a web worker), or if the stored embeddings don't have the given dimensions.
+    pub fn open(key: &str, dims: usize) -> Result<Self, crate::Error> {
+        let storage = Reflect::get(&js_sys::global(), &JsValue::from_str("localStorage"))
+            .map_err(js_error)?
+            .dyn_into::<web_sys::Storage>()
+            .map_err(|_| crate::Error::custom("localStorage isn't available"))?;
+
+        let inner = match storage.get_item(key).map_err(js_error)? {
+            Some(json) => {
+                let snapshot: InMemoryDBSnapshot = serde_json::from_str(&json)
+                    .map_err(|err| crate::Error::wrap("Failed to parse stored memories", err))?;
+                if snapshot.dims != dims {
+                    Err(StorageError::mismatched_dimensions(dims, snapshot.dims))?
+                }
+
+                InMemoryDB::from_snapshot(snapshot)?
+            }
+            None => InMemoryDB::new(dims),
+        };
+
+        Ok(Self {
+            storage,
+            key: key.to_string(),
+            max_entries: DEFAULT_MAX_ENTRIES,
+            inner,
+        })
+    }
+
+    /// Sets the maximum number of entries. If the store already holds more than this, the oldest entries are evicted on the next insert.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries.max(1);
+        self
+    }
+
+    /// The maximum number of entries held by the store.
+    pub fn max_entries(&self) -> usize {
+        self.max_entries
+    }
+
+    /// Removes the store from `localStorage`, along with every memory held in memory.
+    pub fn clear(&mut self) -> Result<(), crate::Error> {
+        self.storage.remove_item(&self.key).map_err(js_error)?;
+        self.inner = InMemoryDB::new(self.inner.dims());
+
+        Ok(())
+    }
+
+    /// Writes the whole store to `localStorage`.
+    fn persist(&self) -> Result<(), crate::Error> {
+        let json = serde_json::to_string(&self.inner.snapshot())
+            .map_err(|err| crate::Error::wrap("Failed to serialize memories", err))?;
+
+        // Fails with a `QuotaExceededError` if the origin's localStorage is full
+        self.storage.set_item(&self.key, &json).map_err(js_error)
+    }
+}
+
+impl Storage for LocalStorageStore {
+    async fn insert(
+        &mut self,
+        embedding: Vec<f32>,
+        entry: MemoryEntry,
+    ) -> Result<(), crate::Error> {
+        if !self.inner.contains(&entry.id) {
+            let count = self.inner.count().await?;
+            if count >= self.max_entries {
+                let evicted = self
+                    .inner
+                    .get_oldest(count + 1 - self.max_entries)
+                    .await?
+                    .into_iter()
+                    .map(|result| result.data().id.clone())
+                    .collect::<Vec<_>>();
+
+                #[cfg(feature = "tracing")]
+                tracing::debug!(ids = ?evicted, "evicted oldest memories from localStorage");
+
+                self.inner.delete_batch(evicted).await?;
+            }
+        }
+        self.inner.insert(embedding, entry).await?;
+
+        self.persist()
+    }
+
+    async fn search(
+        &self,
+        embedding: Vec<f32>,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        self.inner.search(embedding, limit).await
+    }
+
+    async fn search_by_id(&self, id: MemoryId) -> Result<SearchResult, crate::Error> {
+        self.inner.search_by_id(id).await
+    }
+
+    async fn get_recent(&self, limit: usize) -> Result<Vec<SearchResult>, crate::Error> {
+        self.inner.get_recent(limit).await
+    }
+
+    async fn delete(&mut self, id: MemoryId) -> Result<(), crate::Error> {
+        self.inner.delete(id).await?;
+
+        self.persist()
+    }
+
+    async fn delete_batch(&mut self, ids: Vec<MemoryId>) -> Result<(), crate::Error> {
+        let result = self.inner.delete_batch(ids).await;
+        // Some of the memories may have been deleted before an error, so the store still needs to be written
+        self.persist()?;
+
+        result
+    }
+
+    async fn get_oldest(&self, limit: usize) -> Result<Vec<SearchResult>, crate::Error> {
+        self.inner.get_oldest(limit).await
+    }
+
+    async fn get_between(&self, start: i64, end: i64) -> Result<Vec<SearchResult>, crate::Error> {
+        self.inner.get_between(start, end).await
+    }
+
+    async fn update_payload_by_id(
+        &mut self,
+        id: MemoryId,
+        payload: MemoryEntry,
+    ) -> Result<(), crate::Error> {
+        self.inner.update_payload_by_id(id, payload).await?;
+
+        self.persist()
+    }
+
+    async fn count(&self) -> Result<usize, crate::Error> {
+        self.inner.count().await
+    }
+
+    fn dims(&self) -> Option<usize> {
+        Some(self.inner.dims())
+    }
+
+    async fn insert_sparse(
+        &mut self,
+        id: MemoryId,
+        sparse: SparseVector,
+    ) -> Result<(), crate::Error> {
+        self.inner.insert_sparse(id, sparse).await?;
+
+        self.persist()
+    }
+
+    async fn search_hybrid(
+        &self,
+        embedding: Vec<f32>,
+        sparse: SparseVector,
+        limit: usize,
+        fusion: HybridFusion,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        self.inner
+            .search_hybrid(embedding, sparse, limit, fusion)
+            .await
+    }
+
+    async fn search_filtered(
+        &self,
+        embedding: Vec<f32>,
+        limit: usize,
+        filter: MetadataFilter,
+    ) -> Result<Vec<SearchResult>, crate::Error> {
+        self.inner.search_filtered(embedding, limit, filter).await
+    }
+}
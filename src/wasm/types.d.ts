@@ -0,0 +1,39 @@
+export type MemoryKind = "Working" | "Episodic" | "Semantic" | "Procedural";
+export type Confidence = "Low" | "Medium" | "High";
+export type Visibility = "Private" | "Shared";
+export type ImageSource = { Uri: string } | { Bytes: number[] };
+
+export interface MetadataEntry {
+    key: string;
+    value: string;
+}
+
+export interface EmbeddingModelTag {
+    model: string;
+    dims: number;
+}
+
+export interface MemoryEntry {
+    id: string;
+    content: string;
+    kind: MemoryKind;
+    importance: number;
+    created_at: number;
+    last_accessed: number;
+    access_count: number;
+    source_context: string;
+    confidence: Confidence;
+    metadata: MetadataEntry[];
+    agent_id: string | null;
+    visibility: Visibility;
+    embedding_model: EmbeddingModelTag | null;
+    image: ImageSource | null;
+    tags?: string[];
+    strength?: number;
+}
+
+export interface RetrieveOptions {
+    cache_mode?: "default" | "bypass" | "cache_only";
+}
+
+export type EmbedFunction = (text: string) => number[] | Float32Array | Promise<number[] | Float32Array>;
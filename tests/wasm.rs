@@ -0,0 +1,87 @@
+//! Tests for the JavaScript API, run in a JavaScript runtime using `wasm-bindgen-test`:
+//! `cargo test --target wasm32-unknown-unknown --features wasm --test wasm` (with `wasm-bindgen-test-runner` installed).
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use braindump::{
+    embed::Embedder,
+    memory::MemoryEntry,
+    wasm::js::{JsEmbedder, JsMemoryManager},
+};
+use js_sys::{Function, JSON, Promise};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+/// Embeds text by counting occurrences of a handful of letters.
+fn letter_embedder(body: &str) -> Function {
+    Function::new_with_args(
+        "text",
+        &format!("const embedding = ['a', 'e', 'i', 'o'].map((c) => text.split(c).length); {body}"),
+    )
+}
+
+#[wasm_bindgen_test]
+async fn embed_functions_can_return_arrays_or_promises() {
+    for body in [
+        "return embedding;",
+        "return new Float32Array(embedding);",
+        "return Promise.resolve(embedding);",
+    ] {
+        let embedder = JsEmbedder::new(letter_embedder(body), 4);
+
+        assert_eq!(
+            embedder.embed_text("aaa").await.unwrap(),
+            [4.0, 1.0, 1.0, 1.0]
+        );
+    }
+
+    let embedder = JsEmbedder::new(Function::new_no_args("throw new Error('offline')"), 4);
+    assert!(embedder.embed_text("aaa").await.is_err());
+}
+
+#[wasm_bindgen_test]
+async fn memories_round_trip_through_javascript() {
+    let manager = JsMemoryManager::new(letter_embedder("return embedding;"), 4, None).unwrap();
+
+    // Optional fields (`tags` and `strength`) are left out, as a JavaScript caller would
+    let entry = JSON::parse(
+        r#"{
+            "id": "mem-1",
+            "content": "aaa",
+            "kind": "Semantic",
+            "importance": 0.5,
+            "created_at": 0,
+            "last_accessed": 0,
+            "access_count": 0,
+            "source_context": "test",
+            "confidence": "High",
+            "metadata": [{"key": "source", "value": "chat"}],
+            "agent_id": null,
+            "visibility": "Private",
+            "embedding_model": null,
+            "image": null
+        }"#,
+    )
+    .unwrap();
+    resolve(manager.store(entry).unwrap()).await;
+    assert_eq!(resolve(manager.count()).await, JsValue::from(1.0));
+
+    let options = JSON::parse(r#"{"cache_mode": "bypass"}"#).unwrap();
+    let results = resolve(
+        manager
+            .retrieve("aaa".to_string(), Some(1), options)
+            .unwrap(),
+    )
+    .await;
+    let results: Vec<MemoryEntry> = serde_wasm_bindgen::from_value(results).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].id, "mem-1");
+    assert_eq!(results[0].metadata_get("source"), Some("chat"));
+
+    resolve(manager.delete("mem-1".to_string())).await;
+    assert_eq!(resolve(manager.count()).await, JsValue::from(0.0));
+}
+
+async fn resolve(promise: Promise) -> JsValue {
+    JsFuture::from(promise).await.unwrap()
+}
@@ -0,0 +1,30 @@
+//! Regenerates `src/grpc/braindump.v1.rs` from `proto/braindump/v1/memory.proto`.
+//! The protobuf definitions are compiled with `protox`, so `protoc` doesn't need to be installed.
+//!
+//! Run from anywhere with `just protogen` (or `cargo run --manifest-path tools/protogen/Cargo.toml`).
+
+use std::path::Path;
+
+const PROTO: &str = "braindump/v1/memory.proto";
+const GENERATED: &str = "braindump.v1.rs";
+const HEADER: &str = "// Generated from `proto/braindump/v1/memory.proto` by `tonic-prost-build`. Do not edit by hand.\n// Regenerate with `just protogen`.\n";
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+    let out_dir = root.join("src/grpc");
+
+    let descriptors = protox::compile([PROTO], [root.join("proto")])?;
+    tonic_prost_build::configure()
+        .build_client(true)
+        .build_server(true)
+        .out_dir(&out_dir)
+        .compile_fds(descriptors)?;
+
+    let path = out_dir.join(GENERATED);
+    let generated = std::fs::read_to_string(&path)?;
+    std::fs::write(&path, format!("{HEADER}{generated}"))?;
+
+    println!("Wrote {}", path.display());
+
+    Ok(())
+}